@@ -3,21 +3,33 @@
 pub(crate) mod decoders;
 pub mod compare;
 pub mod indexing;
+pub mod metrics;
 pub mod types;
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Display;
 use std::mem::discriminant;
+use std::str::FromStr;
 
 use log::warn;
 use nalgebra::{Matrix, Const, VecStorage, Dyn, Scalar, DMatrix};
-use num::Zero;
+use num::{Complex, Zero};
 use serde::{Serialize, Deserialize};
 
-use indexing::{IndexType, NasIndex};
+use clap::ValueEnum;
+use indexing::{
+  ComplexDof,
+  ComplexPart,
+  GridPointMode,
+  GridPointRef,
+  IndexType,
+  NasIndex
+};
 use crate::blocks::types::BlockType;
+use crate::elements::ElementType;
 use crate::flavour::Flavour;
-use crate::prelude::BlockRef;
+use crate::geometry::{Dof, SIXDOF};
+use crate::prelude::{BlockRef, OutputStep};
 
 /// This trait encapsulates the necessary properties for a scalar that can exist
 /// in the data matrices.
@@ -115,6 +127,91 @@ impl Display for F06Number {
   }
 }
 
+/// The form complex values were written in. They're always stored as real
+/// and imaginary parts, so this only records where they came from.
+#[derive(
+  Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord
+)]
+pub enum ComplexForm {
+  /// Real and imaginary parts.
+  RealImaginary,
+  /// Magnitude and phase (in degrees).
+  MagnitudePhase
+}
+
+impl Display for ComplexForm {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return match self {
+      Self::RealImaginary => write!(f, "real/imaginary"),
+      Self::MagnitudePhase => write!(f, "magnitude/phase")
+    };
+  }
+}
+
+/// Computes the plane-stress von Mises equivalent stress from the normal and
+/// shear components.
+pub fn von_mises(sx: f64, sy: f64, txy: f64) -> f64 {
+  return (sx * sx - sx * sy + sy * sy + 3.0 * txy * txy).sqrt();
+}
+
+/// What a decoder should do when a block lists the same row twice, as can
+/// happen with corrupt files or interleaved superelement output.
+#[derive(
+  Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize,
+  clap::ValueEnum
+)]
+#[clap(rename_all = "snake_case")]
+pub enum DuplicateRowPolicy {
+  /// Log a warning and keep the last occurrence.
+  #[default]
+  Warn,
+  /// Silently keep the first occurrence.
+  KeepFirst,
+  /// Silently keep the last occurrence.
+  KeepLast
+}
+
+/// How to scale eigenvector mode shapes, which solvers normalise in their
+/// own ways (by mass, by the largest component and so on).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModeNormalization {
+  /// To a unit L2 norm, with the largest component positive.
+  UnitNorm,
+  /// So that a DOF of a grid point is 1.
+  UnitDof(GridPointRef, Dof)
+}
+
+impl Display for ModeNormalization {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return match self {
+      Self::UnitNorm => write!(f, "norm"),
+      Self::UnitDof(gp, dof) => write!(f, "{}:{}", gp.gid, dof.short_name())
+    };
+  }
+}
+
+impl FromStr for ModeNormalization {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("norm") {
+      return Ok(Self::UnitNorm);
+    }
+    let bad = || {
+      return format!(
+        "bad mode normalisation \"{}\", expected \"norm\" or GRID:DOF",
+        s
+      );
+    };
+    let (gid, dof) = s.split_once(':').ok_or_else(bad)?;
+    let gid = gid.trim().parse::<usize>().map_err(|_| bad())?;
+    let dof = <Dof as ValueEnum>::from_str(dof.trim(), true)
+      .map_err(|_| bad())?;
+    return Ok(Self::UnitDof(GridPointRef { gid }, dof));
+  }
+}
+
 /// A block that contains an indexing type, some details, and a data matrix.
 /// The number of columns is fixed -- F06 data don't grow horizontally. Types:
 ///   - S: the scalar type for the data within.
@@ -130,7 +227,23 @@ pub(crate) struct RowBlock<
   /// The column indexes.
   col_indexes: BTreeMap<C, usize>,
   /// The data within.
-  data: Option<DynMatx<S, W>>
+  data: Option<DynMatx<S, W>>,
+  /// What to do with repeated rows.
+  duplicates: DuplicateRowPolicy
+}
+
+/// The settings of a RowBlock that don't depend on its types, so that
+/// decoders can share the code that applies them.
+pub(crate) trait RowBlockSettings {
+  /// Sets what to do with repeated rows.
+  fn set_duplicate_policy(&mut self, policy: DuplicateRowPolicy);
+}
+
+impl<S, R, C, const W: usize> RowBlockSettings for RowBlock<S, R, C, W>
+  where S: NasScalar, R: IndexType, C: IndexType {
+  fn set_duplicate_policy(&mut self, policy: DuplicateRowPolicy) {
+    self.duplicates = policy;
+  }
 }
 
 impl<S, R, C, const W: usize> RowBlock<S, R, C, W>
@@ -138,14 +251,27 @@ impl<S, R, C, const W: usize> RowBlock<S, R, C, W>
   /// Creates a new RowBlock with a set width and a pre-allocated size.
   pub(crate) fn new(col_indexes: BTreeMap<C, usize>) -> Self {
     let row_indexes: BTreeMap<R, usize> = BTreeMap::new();
-    return Self { row_indexes, col_indexes, data: None }
+    return Self {
+      row_indexes,
+      col_indexes,
+      data: None,
+      duplicates: DuplicateRowPolicy::default()
+    };
   }
 
+
   /// Inserts a line raw into the data matrix, without fixing indexes. Returns
   /// the row within the underlying matrixes this was put in.
   pub(crate) fn insert_raw(&mut self, row_index: R, row: &[S; W]) -> usize {
-    if self.row_indexes.contains_key(&row_index) {
-      warn!("tried to insert the same line twice! index: {:?}", row_index);
+    if let Some(existing) = self.row_indexes.get(&row_index) {
+      match self.duplicates {
+        DuplicateRowPolicy::Warn => warn!(
+          "tried to insert the same line twice! index: {:?}",
+          row_index
+        ),
+        DuplicateRowPolicy::KeepFirst => return *existing,
+        DuplicateRowPolicy::KeepLast => {}
+      };
     }
     let irow: usize;
     if let Some(mut mat) = self.data.take() {
@@ -219,6 +345,8 @@ impl<S, R, C, const W: usize> RowBlock<S, R, C, W>
       block_type,
       line_range,
       subcase,
+      step: None,
+      complex_form: None,
       row_indexes,
       col_indexes,
       data
@@ -261,18 +389,26 @@ pub enum MergeIncompatible {
   /// Matrices did not have the same type of scalar.
   ScalarMismatch,
   /// Subcases don't match.
-  SubcaseMismatch
+  SubcaseMismatch,
+  /// The blocks are for different steps of their subcase.
+  StepMismatch
 }
 
 /// Immutable view into a result block once it's finalised.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FinalBlock {
-  /// The line range, if at all known.
+  /// The first and last lines (one-based), if at all known.
   pub line_range: Option<(usize, usize)>,
   /// The block type that originated the data.
   pub block_type: BlockType,
   /// The subcase where this block appears.
   pub subcase: usize,
+  /// The step of the subcase this block is for, if the solution has them.
+  #[serde(default)]
+  pub step: Option<OutputStep>,
+  /// The form the values were written in, for blocks of complex values.
+  #[serde(default)]
+  pub complex_form: Option<ComplexForm>,
   /// The row indexes.
   pub row_indexes: BTreeMap<NasIndex, usize>,
   /// The column indexes.
@@ -303,11 +439,227 @@ impl FinalBlock {
     });
   }
 
-  /// Returns this's blocks BlockRef for adding into files.
+  /// Returns the complex value of a degree of freedom, for blocks whose
+  /// columns hold the real and imaginary parts of each.
+  pub fn get_complex<R: Into<NasIndex>>(
+    &self,
+    row: R,
+    dof: Dof
+  ) -> Option<Complex<f64>> {
+    let row = row.into();
+    let part = |part: ComplexPart| {
+      return self.get(row, ComplexDof { dof, part }).map(f64::from);
+    };
+    return Some(Complex::new(
+      part(ComplexPart::Real)?,
+      part(ComplexPart::Imaginary)?
+    ));
+  }
+
+  /// Returns the reference (subcase, block type and step) under which this
+  /// block is stored in an `F06File`. Blocks sharing it can be merged, and
+  /// it's the key used by diffs and extractions to pair blocks between files.
   pub fn block_ref(&self) -> BlockRef {
-    return BlockRef {
-      subcase: self.subcase,
-      block_type: self.block_type
+    return BlockRef::new(self.subcase.into(), self.block_type)
+      .at_step(self.step);
+  }
+
+  /// For grid point force balance blocks, sums the forces from all origins
+  /// acting on a grid point, per DOF. Equilibrium means this is near zero.
+  /// Returns None for other block types or if the grid point is absent.
+  pub fn force_balance_residual(
+    &self,
+    grid: GridPointRef
+  ) -> Option<[f64; SIXDOF]> {
+    if self.block_type != BlockType::GridPointForceBalance {
+      return None;
+    }
+    let mut residual = [0.0; SIXDOF];
+    let mut found = false;
+    for row in self.row_indexes.keys() {
+      if let NasIndex::GridPointForceOrigin(gpfo) = row {
+        if gpfo.grid_point != grid {
+          continue;
+        }
+        found = true;
+        for (total, dof) in residual.iter_mut().zip(Dof::all()) {
+          *total += f64::from(self.get(*row, *dof)?);
+        }
+      }
+    }
+    return if found { Some(residual) } else { None };
+  }
+
+  /// Appends a new real-valued column to this block, filled with the given
+  /// values per row (rows not present get zero). Returns false, changing
+  /// nothing, if the column already exists or the block isn't real-valued.
+  pub fn insert_column(
+    &mut self,
+    col: NasIndex,
+    values: &BTreeMap<NasIndex, f64>
+  ) -> bool {
+    if self.col_indexes.contains_key(&col) {
+      return false;
+    }
+    if let Some(FinalDMat::Reals(ref mut m)) = self.data {
+      let ncols = m.ncols();
+      let old = std::mem::replace(m, DMatrix::zeros(0, 0));
+      *m = old.insert_column(ncols, 0.0);
+      for (row, ri) in self.row_indexes.iter() {
+        if let Some(v) = values.get(row) {
+          m[(*ri, ncols)] = *v;
+        }
+      }
+      self.col_indexes.insert(col, ncols);
+      return true;
+    }
+    return false;
+  }
+
+  /// For plate stress blocks lacking a von Mises column, derives it from the
+  /// normal and shear components and appends it. Returns whether a column
+  /// was added.
+  ///
+  /// The plane-stress formula is used at every point and fibre:
+  /// `sqrt(sx^2 - sx*sy + sy^2 + 3*txy^2)`.
+  pub fn derive_von_mises(&mut self) -> bool {
+    use indexing::PlateStressField as PSF;
+    let plate_stresses = [BlockType::TriaStresses, BlockType::QuadStresses];
+    if !plate_stresses.contains(&self.block_type) {
+      return false;
+    }
+    let mut values: BTreeMap<NasIndex, f64> = BTreeMap::new();
+    for row in self.row_indexes.keys() {
+      let get = |c: PSF| self.get(*row, c).map(f64::from);
+      let comps = (get(PSF::NormalX), get(PSF::NormalY), get(PSF::ShearXY));
+      if let (Some(sx), Some(sy), Some(txy)) = comps {
+        values.insert(*row, von_mises(sx, sy, txy));
+      }
+    }
+    return self.insert_column(PSF::VonMises.into(), &values);
+  }
+
+  /// For blocks with rows on element sides and a fibre distance column (such
+  /// as plate stresses), relabels the sides by the sign of the fibre
+  /// distance: negative is the bottom, positive is the top. Solvers don't
+  /// always agree on the labels, so this lets their rows match. The rows of a
+  /// point are left alone if relabelling them would make two collide, and so
+  /// are rows with a zero fibre distance. Returns how many were relabelled.
+  pub fn canonicalize_sides(&mut self) -> usize {
+    use indexing::{
+      ElementPoint,
+      ElementRef,
+      ElementSide,
+      PlateStrainField,
+      PlateStressField
+    };
+    let fd_cols: [NasIndex; 2] = [
+      PlateStressField::FibreDistance.into(),
+      PlateStrainField(PlateStressField::FibreDistance).into()
+    ];
+    let fd = fd_cols.into_iter().find(|c| self.col_indexes.contains_key(c));
+    let Some(fd) = fd else {
+      return 0;
+    };
+    // (current, canonical) row pairs, by element and point
+    let mut groups: BTreeMap<
+      (ElementRef, ElementPoint),
+      Vec<(NasIndex, NasIndex)>
+    > = BTreeMap::new();
+    for row in self.row_indexes.keys() {
+      if let NasIndex::ElementSidedPoint(esp) = row {
+        let mut canon = *esp;
+        match self.get(*row, fd).map(f64::from) {
+          Some(z) if z < 0.0 => canon.side = ElementSide::Bottom,
+          Some(z) if z > 0.0 => canon.side = ElementSide::Top,
+          _ => {}
+        };
+        groups.entry((esp.element, esp.point))
+          .or_default()
+          .push((*row, canon.into()));
+      }
+    }
+    let mut relabelled: usize = 0;
+    for pairs in groups.into_values() {
+      let targets = pairs.iter().map(|p| p.1).collect::<BTreeSet<_>>();
+      if targets.len() != pairs.len() {
+        continue;
+      }
+      let moved = pairs.into_iter()
+        .filter(|(from, to)| from != to)
+        .filter_map(|(from, to)| Some((to, self.row_indexes.remove(&from)?)))
+        .collect::<Vec<_>>();
+      relabelled += moved.len();
+      self.row_indexes.extend(moved);
+    }
+    return relabelled;
+  }
+
+  /// For eigenvector blocks, scales each mode shape as asked, so that modes
+  /// normalised differently by different solvers can be compared. Modes that
+  /// can't be scaled (all zeroes, or with the chosen DOF missing or zero) are
+  /// left alone. This changes the values in the block. Returns how many
+  /// modes were scaled.
+  pub fn normalize_mode_shape(&mut self, how: ModeNormalization) -> usize {
+    if self.block_type != BlockType::Eigenvectors {
+      return 0;
+    }
+    let Some(FinalDMat::Reals(ref mut m)) = self.data else {
+      return 0;
+    };
+    // the matrix rows of each mode
+    let mut modes: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (row, ri) in self.row_indexes.iter() {
+      if let Some(mode) = row.mode() {
+        modes.entry(mode).or_default().push(*ri);
+      }
+    }
+    let mut scaled: usize = 0;
+    for (mode, ris) in modes {
+      let reference = match how {
+        ModeNormalization::UnitNorm => {
+          // the largest component sets the sign
+          let mut norm_squared: f64 = 0.0;
+          let mut largest: f64 = 0.0;
+          for ri in ris.iter() {
+            for x in m.row(*ri).iter() {
+              norm_squared += x * x;
+              if x.abs() > largest.abs() {
+                largest = *x;
+              }
+            }
+          }
+          Some(norm_squared.sqrt().copysign(largest))
+        },
+        ModeNormalization::UnitDof(grid_point, dof) => {
+          let row = NasIndex::from(GridPointMode { mode, grid_point });
+          let ri = self.row_indexes.get(&row);
+          let ci = self.col_indexes.get(&dof.into());
+          ri.zip(ci).map(|(ri, ci)| m[(*ri, *ci)])
+        }
+      };
+      if let Some(r) = reference.filter(|r| *r != 0.0 && r.is_finite()) {
+        for ri in ris {
+          m.row_mut(ri).unscale_mut(r);
+        }
+        scaled += 1;
+      }
+    }
+    return scaled;
+  }
+
+  /// Renames a column, keeping its data. Returns false, changing nothing, if
+  /// the column doesn't exist or there's already one with the new name.
+  pub fn rename_column(&mut self, from: NasIndex, to: NasIndex) -> bool {
+    if self.col_indexes.contains_key(&to) {
+      return false;
+    }
+    return match self.col_indexes.remove(&from) {
+      Some(ci) => {
+        self.col_indexes.insert(to, ci);
+        true
+      },
+      None => false
     };
   }
 
@@ -346,7 +698,7 @@ impl FinalBlock {
     let nixes: Vec<NasIndex> = self.col_indexes.keys().copied().collect();
     let mut ns: Vec<usize> = self.col_indexes.values().copied().collect();
     ns.sort();
-    for (nix, i) in nixes.into_iter().zip(ns.into_iter()) {
+    for (nix, i) in nixes.into_iter().zip(ns) {
       let nswap = self.col_indexes.iter()
         .find(|p| p.1 == &i)
         .map(|p| *(p.0))
@@ -362,7 +714,7 @@ impl FinalBlock {
     let nixes: Vec<NasIndex> = self.row_indexes.keys().copied().collect();
     let mut ns: Vec<usize> = self.row_indexes.values().copied().collect();
     ns.sort();
-    for (nix, i) in nixes.into_iter().zip(ns.into_iter()) {
+    for (nix, i) in nixes.into_iter().zip(ns) {
       let nswap = self.row_indexes.iter()
         .find(|p| p.1 == &i)
         .map(|p| *(p.0))
@@ -381,6 +733,10 @@ impl FinalBlock {
     if self.subcase != other.subcase {
       return Err(MergeIncompatible::SubcaseMismatch);
     }
+    // check for same step
+    if self.step != other.step {
+      return Err(MergeIncompatible::StepMismatch);
+    }
     // check for same columns
     let primary_col_set: BTreeSet<NasIndex> = self.col_indexes.keys()
       .copied()
@@ -416,6 +772,58 @@ impl FinalBlock {
       .collect();
   }
 
+  /// Returns a block with the element-wise difference between this one and
+  /// another (`self - other`) over the rows they have in common, so it can be
+  /// written out like any other results. The blocks must be compatible for a
+  /// merge. Natural values give integer differences, since those can go
+  /// negative, and if either block has no data, neither does the delta.
+  pub fn delta(&self, other: &Self) -> Result<FinalBlock, MergeIncompatible> {
+    self.can_merge(other)?;
+    let cols = self.col_indexes.keys().copied().collect::<Vec<_>>();
+    let rows = match (&self.data, &other.data) {
+      (Some(_), Some(_)) => self.row_conflicts(other),
+      _ => BTreeSet::new()
+    };
+    let positions = rows.iter()
+      .flat_map(|r| cols.iter().map(move |c| (*r, *c)))
+      .map(|(r, c)| (
+        (self.row_indexes[&r], self.col_indexes[&c]),
+        (other.row_indexes[&r], other.col_indexes[&c])
+      ));
+    macro_rules! delta {
+      ($a:expr, $b:expr, $conv:expr) => {
+        DMatrix::from_row_iterator(
+          rows.len(),
+          cols.len(),
+          positions.map(|(pa, pb)| $conv($a[pa]) - $conv($b[pb]))
+        )
+      };
+    }
+    let data = match (&self.data, &other.data) {
+      (Some(FinalDMat::Reals(a)), Some(FinalDMat::Reals(b))) => {
+        Some(FinalDMat::Reals(delta!(a, b, |x: f64| x)))
+      },
+      (Some(FinalDMat::Integers(a)), Some(FinalDMat::Integers(b))) => {
+        Some(FinalDMat::Integers(delta!(a, b, |x: isize| x)))
+      },
+      (Some(FinalDMat::Naturals(a)), Some(FinalDMat::Naturals(b))) => {
+        Some(FinalDMat::Integers(delta!(a, b, |x: usize| x as isize)))
+      },
+      (Some(_), Some(_)) => return Err(MergeIncompatible::ScalarMismatch),
+      _ => None
+    };
+    return Ok(Self {
+      line_range: None,
+      block_type: self.block_type,
+      subcase: self.subcase,
+      step: self.step,
+      complex_form: self.complex_form,
+      row_indexes: rows.into_iter().enumerate().map(|(i, r)| (r, i)).collect(),
+      col_indexes: cols.into_iter().enumerate().map(|(i, c)| (c, i)).collect(),
+      data
+    });
+  }
+
   /// Copies lines from another block into this one.
   pub fn try_merge(
     mut self,
@@ -494,8 +902,11 @@ impl FinalBlock {
         // un-move stuff (this is stupid)
         self.data = Some(ndp);
         other.data = Some(nds);
-        // delete line range
-        self.line_range = None;
+        // the line range now spans both
+        self.line_range = match (self.line_range, other.line_range) {
+          (Some((s1, e1)), Some((s2, e2))) => Some((s1.min(s2), e1.max(e2))),
+          _ => None
+        };
         // return accordingly
         if skipped.is_empty() {
           return Ok(MergeResult::Success { merged: self });
@@ -578,9 +989,24 @@ pub(crate) trait BlockDecoder {
   /// Called to hint about the last used index. Useful for catching paging.
   fn hint_last(&mut self, _last: NasIndex) {}
 
+  /// Called to hint about the current vibration mode, for modal results.
+  fn hint_mode(&mut self, _mode: usize) {}
+
   /// Returns the last inserted index. Useful for stateful decoders.
   fn last_row_index(&self) -> Option<NasIndex> { return None; }
 
+  /// Returns the block being filled in, so that its settings can be applied.
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings;
+
+  /// Sets what to do when the block lists the same row twice.
+  fn set_duplicate_policy(&mut self, policy: DuplicateRowPolicy) {
+    self.row_block().set_duplicate_policy(policy);
+  }
+
+  /// Replaces the element type read from the header, when an override
+  /// matches it. Only decoders that tag rows with one need to care.
+  fn override_etype(&mut self, _etype: ElementType) {}
+
   /// Consumes a line into the underlying data.
   fn consume(&mut self, line: &str) -> LineResponse;
 }
@@ -599,9 +1025,19 @@ pub trait OpaqueDecoder {
   /// Called to hint about the last used index. Useful for catching paging.
   fn hint_last(&mut self, last: NasIndex);
 
+  /// Called to hint about the current vibration mode, for modal results.
+  fn hint_mode(&mut self, mode: usize);
+
   /// Returns the last inserted index. Useful for stateful decoders.
   fn last_index(&self) -> Option<NasIndex>;
 
+  /// Sets what to do when the block lists the same row twice.
+  fn set_duplicate_policy(&mut self, policy: DuplicateRowPolicy);
+
+  /// Replaces the element type read from the header, when an override
+  /// matches it. Does nothing by default.
+  fn override_etype(&mut self, _etype: ElementType) {}
+
   /// Extracts the data within.
   fn finalise(
     self: Box<Self>,
@@ -620,10 +1056,22 @@ impl<T> OpaqueDecoder for T
     return BlockDecoder::hint_last(self, last);
   }
 
+  fn hint_mode(&mut self, mode: usize) {
+    BlockDecoder::hint_mode(self, mode);
+  }
+
   fn last_index(&self) -> Option<NasIndex> {
     return BlockDecoder::last_row_index(self);
   }
 
+  fn set_duplicate_policy(&mut self, policy: DuplicateRowPolicy) {
+    BlockDecoder::set_duplicate_policy(self, policy);
+  }
+
+  fn override_etype(&mut self, etype: ElementType) {
+    BlockDecoder::override_etype(self, etype);
+  }
+
   fn finalise(
     self: Box<Self>,
     subcase: usize,
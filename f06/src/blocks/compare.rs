@@ -145,6 +145,101 @@ impl DisjunctionBehaviour {
   }
 }
 
+/// How to handle blocks whose column sets differ (e.g. when one solver writes
+/// out principal stresses and the other doesn't).
+#[derive(
+  Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq
+)]
+pub enum CompareMode {
+  /// The blocks must have the same columns to be compared at all.
+  #[default]
+  Exact,
+  /// Only the columns in both blocks are compared; the others are dropped.
+  Intersection
+}
+
+impl Display for CompareMode {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return write!(f, "{}", self.small_lc_name());
+  }
+}
+
+impl ValueEnum for CompareMode {
+  fn value_variants<'a>() -> &'a [Self] {
+    return Self::all();
+  }
+
+  fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+    return Some(self.small_lc_name().into());
+  }
+}
+
+/// The columns dropped from a pair of blocks before comparing them.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DroppedColumns {
+  /// Columns only in the first block.
+  pub only_in_a: BTreeSet<NasIndex>,
+  /// Columns only in the second block.
+  pub only_in_b: BTreeSet<NasIndex>
+}
+
+impl DroppedColumns {
+  /// Returns true if no columns were dropped.
+  pub fn is_empty(&self) -> bool {
+    return self.only_in_a.is_empty() && self.only_in_b.is_empty();
+  }
+}
+
+impl CompareMode {
+  /// Returns all variants.
+  pub const fn all() -> &'static [Self] {
+    return &[Self::Exact, Self::Intersection];
+  }
+
+  /// Returns a small name for the variant (lower-case).
+  pub const fn small_lc_name(&self) -> &'static str {
+    return match self {
+      Self::Exact => "exact",
+      Self::Intersection => "intersection",
+    };
+  }
+
+  /// Gets a pair of blocks ready for comparison, returning the columns that
+  /// were dropped from them. In intersection mode, columns not in both
+  /// blocks are dropped, unless they have none in common (so they're still
+  /// reported as incompatible).
+  pub fn restrict(
+    &self,
+    a: &mut FinalBlock,
+    b: &mut FinalBlock
+  ) -> DroppedColumns {
+    if *self == Self::Exact {
+      return DroppedColumns::default();
+    }
+    let aci = a.col_indexes.keys().copied().collect::<BTreeSet<_>>();
+    let bci = b.col_indexes.keys().copied().collect::<BTreeSet<_>>();
+    if aci.is_disjoint(&bci) {
+      return DroppedColumns::default();
+    }
+    a.col_indexes.retain(|c, _| bci.contains(c));
+    b.col_indexes.retain(|c, _| aci.contains(c));
+    return DroppedColumns {
+      only_in_a: &aci - &bci,
+      only_in_b: &bci - &aci
+    };
+  }
+}
+
+/// The value Nastran writes out for undefined or unbounded quantities, such
+/// as the margins of safety of unloaded elements.
+pub const UNSET_SENTINEL: f64 = 1.0e37;
+
+/// Returns whether a value is to be taken as unset, given a threshold.
+/// Infinities are never unset, so they can still be flagged as such.
+pub fn is_unset(x: f64, threshold: Option<f64>) -> bool {
+  return x.is_finite() && threshold.is_some_and(|t| x.abs() >= t);
+}
+
 /// Value testing/comparison criteria.
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Args)]
 pub struct Criteria {
@@ -154,6 +249,10 @@ pub struct Criteria {
   /// Test a big-to-small ratio?
   #[arg(long, short = 'r')]
   pub ratio: Option<f64>,
+  /// Below this magnitude, pairs are compared by difference instead of ratio.
+  #[arg(long, default_value_t = 0.0)]
+  #[serde(default)]
+  pub ratio_floor: f64,
   /// Check for NaNs?
   #[arg(long)]
   pub nan: bool,
@@ -162,7 +261,31 @@ pub struct Criteria {
   pub inf: bool,
   /// Check for differing signs?
   #[arg(long)]
-  pub sig: bool
+  pub sig: bool,
+  /// Skip pairs where a value is at least this large in magnitude, taking it
+  /// as unset. If given without a value, 1.0E+37 (Nastran's own) is used.
+  #[arg(
+    long = "unset-threshold",
+    require_equals = true,
+    num_args = 0..=1,
+    default_missing_value = "1.0E+37"
+  )]
+  #[serde(default)]
+  pub unset_threshold: Option<f64>,
+  /// Test the phase difference of complex values, in degrees? Complex
+  /// values are then compared by magnitude and phase instead of by real and
+  /// imaginary part. Phases are compared modulo 360, so 359 and 1 are 2
+  /// apart.
+  #[arg(long = "phase-tolerance")]
+  #[serde(default)]
+  pub phase_tolerance_deg: Option<f64>
+}
+
+/// Returns the difference between two phase angles in degrees, going the
+/// short way around the circle, so it's always between 0 and 180 (or NaN).
+pub fn phase_difference_deg(a: f64, b: f64) -> f64 {
+  let d = (a - b).rem_euclid(360.0);
+  return d.min(360.0 - d);
 }
 
 impl Default for Criteria {
@@ -170,9 +293,12 @@ impl Default for Criteria {
     return Self {
       difference: None,
       ratio: None,
+      ratio_floor: 0.0,
       nan: true,
       inf: true,
-      sig: false
+      sig: false,
+      unset_threshold: None,
+      phase_tolerance_deg: None
     };
   }
 }
@@ -180,11 +306,15 @@ impl Default for Criteria {
 impl Criteria {
   /// Checks a pair of values against this set of criteria.
   pub fn check(&self, a: f64, b: f64) -> Option<FlagReason> {
+    // unset values are missing, not numbers
+    if is_unset(a, self.unset_threshold) || is_unset(b, self.unset_threshold) {
+      return None;
+    }
     // check for NaNs
     if self.nan && (a.is_nan() || b.is_nan()) {
       return Some(FlagReason::NaN);
     }
-    // check for infinities
+    // check for infinities; unchecked, they still make a huge difference
     if self.inf && (a.is_infinite() || b.is_infinite()) {
       return Some(FlagReason::Infinity);
     }
@@ -204,6 +334,18 @@ impl Criteria {
     }
     // check ratio
     if let Some(max_ratio) = self.ratio {
+      // near-zero values make the ratio blow up, so compare those by their
+      // absolute difference against the floor instead
+      if a.abs().min(b.abs()) < self.ratio_floor {
+        let diff = (a-b).abs();
+        if diff > self.ratio_floor {
+          return Some(FlagReason::Difference {
+            abs_difference: diff,
+            max_epsilon: self.ratio_floor
+          });
+        }
+        return None;
+      }
       let (big, small) = if a >= b { (a, b) } else { (b, a) };
       let rat = (big/small).abs();
       if rat > max_ratio {
@@ -213,6 +355,162 @@ impl Criteria {
     // nothing? no flag
     return None;
   }
+
+  /// Checks a pair of complex values, given as magnitudes and phases in
+  /// degrees. The magnitudes are checked like any other pair of values, and
+  /// the phases against the phase tolerance, if there is one, going the
+  /// short way around the circle (see `phase_difference_deg`).
+  pub fn check_polar(
+    &self,
+    (mag_a, phase_a): (f64, f64),
+    (mag_b, phase_b): (f64, f64)
+  ) -> Option<FlagReason> {
+    if let Some(reason) = self.check(mag_a, mag_b) {
+      return Some(reason);
+    }
+    let tol = self.phase_tolerance_deg?;
+    if phase_a.is_nan() || phase_b.is_nan() {
+      return if self.nan { Some(FlagReason::NaN) } else { None };
+    }
+    let diff = phase_difference_deg(phase_a, phase_b);
+    if diff > tol {
+      return Some(FlagReason::Phase {
+        abs_difference_deg: diff,
+        max_tolerance_deg: tol
+      });
+    }
+    return None;
+  }
+}
+
+/// Named sets of criteria with conventional tolerances for a kind of result,
+/// so there's a sensible starting point instead of a guess.
+#[derive(
+  Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord
+)]
+pub enum CriteriaPreset {
+  /// For displacements (see `Criteria::preset_displacement`).
+  Displacement,
+  /// For forces (see `Criteria::preset_force`).
+  Force,
+  /// For stresses (see `Criteria::preset_stress`).
+  Stress,
+  /// For strains (see `Criteria::preset_strain`).
+  Strain
+}
+
+impl CriteriaPreset {
+  /// Returns all variants.
+  pub const fn all() -> &'static [Self] {
+    return &[Self::Displacement, Self::Force, Self::Stress, Self::Strain];
+  }
+
+  /// Returns a small name for the variant (lower-case).
+  pub const fn small_lc_name(&self) -> &'static str {
+    return match self {
+      Self::Displacement => "displacement",
+      Self::Force => "force",
+      Self::Stress => "stress",
+      Self::Strain => "strain",
+    };
+  }
+
+  /// Returns the criteria for this preset.
+  pub fn criteria(&self) -> Criteria {
+    return match self {
+      Self::Displacement => Criteria::preset_displacement(),
+      Self::Force => Criteria::preset_force(),
+      Self::Stress => Criteria::preset_stress(),
+      Self::Strain => Criteria::preset_strain(),
+    };
+  }
+}
+
+impl Display for CriteriaPreset {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return write!(f, "{}", self.small_lc_name());
+  }
+}
+
+impl ValueEnum for CriteriaPreset {
+  fn value_variants<'a>() -> &'a [Self] {
+    return Self::all();
+  }
+
+  fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+    return Some(self.small_lc_name().into());
+  }
+}
+
+impl Criteria {
+  /// Criteria for displacements: they're what the solver actually solves
+  /// for, so two sound runs should agree to within 1%. Below 1.0E-9 they're
+  /// numerical noise, and only compared by difference.
+  pub fn preset_displacement() -> Self {
+    return Self {
+      ratio: Some(1.01),
+      ratio_floor: 1.0e-9,
+      ..Self::default()
+    };
+  }
+
+  /// Criteria for forces (element forces, SPC forces and the like): they're
+  /// recovered from the displacements through the stiffness, which adds some
+  /// round-off, so 2% is allowed, and values below 1.0E-6 count as zero.
+  pub fn preset_force() -> Self {
+    return Self {
+      ratio: Some(1.02),
+      ratio_floor: 1.0e-6,
+      ..Self::default()
+    };
+  }
+
+  /// Criteria for stresses: they come from derivatives of the displacements,
+  /// recovered at points that differ between element formulations, so 5% is
+  /// allowed. Values below 1.0E-3 count as zero, and margins of safety left
+  /// unset by Nastran are skipped.
+  pub fn preset_stress() -> Self {
+    return Self {
+      ratio: Some(1.05),
+      ratio_floor: 1.0e-3,
+      unset_threshold: Some(UNSET_SENTINEL),
+      ..Self::default()
+    };
+  }
+
+  /// Criteria for strains: as loose as stresses for the same reasons, but
+  /// strains are small and dimensionless, so the zero floor is much lower,
+  /// at 1.0E-9.
+  pub fn preset_strain() -> Self {
+    return Self {
+      ratio: Some(1.05),
+      ratio_floor: 1.0e-9,
+      unset_threshold: Some(UNSET_SENTINEL),
+      ..Self::default()
+    };
+  }
+
+  /// Fills in whatever wasn't set in these criteria (no difference, ratio
+  /// or unset threshold, or a zero ratio floor) from other criteria, such as
+  /// a preset. The NaN, infinity and sign checks can't be told apart from
+  /// unset ones, so they're kept as they are here.
+  pub fn or_from(self, other: &Self) -> Self {
+    return Self {
+      difference: self.difference.or(other.difference),
+      ratio: self.ratio.or(other.ratio),
+      ratio_floor: if self.ratio_floor == 0.0 {
+        other.ratio_floor
+      } else {
+        self.ratio_floor
+      },
+      nan: self.nan,
+      inf: self.inf,
+      sig: self.sig,
+      unset_threshold: self.unset_threshold.or(other.unset_threshold),
+      phase_tolerance_deg: self.phase_tolerance_deg
+        .or(other.phase_tolerance_deg)
+    };
+  }
 }
 
 /// Holds a found value in two data blocks.
@@ -251,6 +549,13 @@ pub enum FlagReason {
   Infinity,
   /// Signs differ!
   Signs,
+  /// Flagged due to a phase difference, in complex values.
+  Phase {
+    /// The phase difference, in degrees, between 0 and 180.
+    abs_difference_deg: f64,
+    /// The exceeded phase tolerance, in degrees.
+    max_tolerance_deg: f64
+  },
   /// Row is misisng in one of the blocks.
   Disjunction
 }
@@ -263,6 +568,7 @@ impl Display for FlagReason {
       FlagReason::NaN => "NaN detected",
       FlagReason::Infinity => "infinity detected",
       FlagReason::Signs => "signs differ",
+      FlagReason::Phase { .. } => "maximum phase difference exceeded",
       FlagReason::Disjunction => "value absent in one of the files",
     });
   }
@@ -277,6 +583,32 @@ pub struct FlaggedPosition {
   pub reason: FlagReason
 }
 
+impl FlaggedPosition {
+  /// A severity score for ranking flags. For differences and ratios, it's how
+  /// many times the limit was exceeded; for differing signs, it's the relative
+  /// error (between one and two). Flags without a finite measure (NaNs,
+  /// infinities and disjunctions) rank as infinitely severe.
+  pub fn severity(&self) -> f64 {
+    return match self.reason {
+      FlagReason::Difference { abs_difference, max_epsilon } => {
+        abs_difference / max_epsilon
+      },
+      FlagReason::Ratio { big_to_small, max_ratio } => big_to_small / max_ratio,
+      FlagReason::Phase { abs_difference_deg, max_tolerance_deg } => {
+        abs_difference_deg / max_tolerance_deg
+      },
+      FlagReason::Signs => {
+        let a = f64::from(self.values.val_a);
+        let b = f64::from(self.values.val_b);
+        (a - b).abs() / a.abs().max(b.abs())
+      },
+      FlagReason::NaN | FlagReason::Infinity | FlagReason::Disjunction => {
+        f64::INFINITY
+      }
+    };
+  }
+}
+
 /// This structure holds the necessary data to diff data blocks. It could be
 /// made parallel, but there's been no need to make this parallel... for now.
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
@@ -296,7 +628,10 @@ impl DataDiffer {
     return Self { criteria, dxn_behaviour };
   }
 
-  /// Diff two data blocks and return flagged positions.
+  /// Diff two data blocks and return flagged positions. With a phase
+  /// tolerance, the real and imaginary columns of complex values are checked
+  /// together, in polar form, and flags go on the real column with the
+  /// magnitudes as the values.
   pub fn compare<'a>(
     &'a self,
     a: &'a FinalBlock,
@@ -324,6 +659,11 @@ impl DataDiffer {
         }
       }
     };
+    // magnitude and phase in degrees, a missing row being zero
+    let polar = |s: &FinalBlock, r: &NasIndex, dof: Dof| -> (f64, f64) {
+      let (mag, phase) = s.get_complex(*r, dof).unwrap_or_default().to_polar();
+      return (mag, phase.to_degrees());
+    };
     let row_indexes = a.row_indexes.keys().chain(b.row_indexes.keys())
       .copied()
       .collect::<BTreeSet<_>>();
@@ -331,6 +671,14 @@ impl DataDiffer {
     return Ok(
       row_indexes.into_iter().cartesian_product(col_indexes)
         .filter_map(move |(r, c)| {
+          let complex_dof = match c {
+            NasIndex::ComplexDof(cd) => Some(cd),
+            _ => None
+          }.filter(|_| self.criteria.phase_tolerance_deg.is_some());
+          // the imaginary part is checked along with the real one
+          if complex_dof.is_some_and(|cd| cd.part == ComplexPart::Imaginary) {
+            return None;
+          }
           let mut fv = FoundValues {
             row: r,
             col: c,
@@ -340,6 +688,13 @@ impl DataDiffer {
           match (get(a, &r, &c), get(b, &r, &c)) {
             // got both values
             (Ok(Some(x)), Ok(Some(y))) => {
+              if let Some(cd) = complex_dof {
+                let (pa, pb) = (polar(a, &r, cd.dof), polar(b, &r, cd.dof));
+                fv.val_a = pa.0.into();
+                fv.val_b = pb.0.into();
+                return self.criteria.check_polar(pa, pb)
+                  .map(|fr| FlaggedPosition { values: fv, reason: fr });
+              }
               fv.val_a = x.into();
               fv.val_b = y.into();
               return self.criteria.check(x, y)
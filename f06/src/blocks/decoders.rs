@@ -1,6 +1,7 @@
 //! This module implements the specific decoders for known data block types.
 
 use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
 
 use log::*;
 
@@ -17,6 +18,19 @@ fn dof_cols() -> BTreeMap<Dof, usize> {
     .collect();
 }
 
+/// Reals in the first line of a plate stress/strain row (MYSTRAN might add the
+/// transverse shears at the end).
+const PLATE_LEAD_REALS: RangeInclusive<usize> = 8..=10;
+
+/// Reals in the second line of a plate stress/strain row.
+const PLATE_CONT_REALS: RangeInclusive<usize> = 8..=8;
+
+/// Reals in the first line of a bar stress/strain row.
+const BAR_LEAD_REALS: RangeInclusive<usize> = 7..=8;
+
+/// Reals in the second line of a bar stress/strain row.
+const BAR_CONT_REALS: RangeInclusive<usize> = 6..=7;
+
 /// Creates a decoder that performs pure conversions from an inner decoder.
 macro_rules! converting_decoder {
   (
@@ -24,7 +38,8 @@ macro_rules! converting_decoder {
     $outer_desc:literal,
     // name of the outer decoder
     $outer_type:ident,
-    // inner decoder
+    // inner decoder (must not be a converting decoder itself, since its header
+    // check would reject ours)
     $inner_type:ty,
     // scalar type of both
     $scalar_type:ty,
@@ -35,7 +50,9 @@ macro_rules! converting_decoder {
     // block type of the outer
     $block_type:expr,
     // matwidth of both
-    $matwidth:literal
+    $matwidth:literal,
+    // a word the outer's headers must contain
+    $header_word:literal
   ) => {
     #[doc = $outer_desc]
     pub(crate) struct $outer_type {
@@ -56,6 +73,11 @@ macro_rules! converting_decoder {
       }
 
       fn good_header(&mut self, header: &str) -> bool {
+        // the inner decoder reads the same layout, so make sure we're not
+        // mislabelling its kind of table as ours
+        if !header.contains($header_word) {
+          return false;
+        }
         return BlockDecoder::good_header(&mut self.inner, header);
       }
 
@@ -67,6 +89,14 @@ macro_rules! converting_decoder {
         return BlockDecoder::last_row_index(&self.inner);
       }
 
+      fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+        return BlockDecoder::row_block(&mut self.inner);
+      }
+
+      fn override_etype(&mut self, etype: ElementType) {
+        BlockDecoder::override_etype(&mut self.inner, etype);
+      }
+
       fn unwrap(
         self,
         subcase: usize,
@@ -126,6 +156,10 @@ impl BlockDecoder for DisplacementsDecoder {
     };
   }
 
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
   fn unwrap(
     self,
     subcase: usize,
@@ -148,6 +182,147 @@ impl BlockDecoder for DisplacementsDecoder {
   }
 }
 
+/// This decodes a complex displacements block, from frequency response
+/// solutions. Each grid point takes two lines: the first has its ID and the
+/// real parts (or magnitudes), the second the imaginary parts (or phases, in
+/// degrees). Magnitudes and phases are turned into real and imaginary parts.
+pub(crate) struct ComplexDisplacementsDecoder {
+  /// The form of the values, from the line after the header.
+  form: ComplexForm,
+  /// The grid point whose first line was just read, and its values.
+  pending: Option<(GridPointRef, [f64; SIXDOF])>,
+  /// The displacement data.
+  data: RowBlock<f64, GridPointRef, ComplexDof, { Self::MATWIDTH }>
+}
+
+impl BlockDecoder for ComplexDisplacementsDecoder {
+  type MatScalar = f64;
+  type RowIndex = GridPointRef;
+  type ColumnIndex = ComplexDof;
+  const MATWIDTH: usize = 2 * SIXDOF;
+  const BLOCK_TYPE: BlockType = BlockType::ComplexDisplacements;
+
+  fn new(_flavour: Flavour) -> Self {
+    return Self {
+      form: ComplexForm::RealImaginary,
+      pending: None,
+      data: RowBlock::new(ComplexDof::canonical_cols())
+    };
+  }
+
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
+  fn unwrap(
+    self,
+    subcase: usize,
+    line_range: Option<(usize, usize)>
+  ) -> FinalBlock {
+    let mut block = self.data.finalise(Self::BLOCK_TYPE, subcase, line_range);
+    block.complex_form = Some(self.form);
+    return block;
+  }
+
+  fn consume(&mut self, line: &str) -> LineResponse {
+    if line.contains("REAL/IMAGINARY") {
+      self.form = ComplexForm::RealImaginary;
+      return LineResponse::Metadata;
+    } else if line.contains("MAGNITUDE/PHASE") {
+      self.form = ComplexForm::MagnitudePhase;
+      return LineResponse::Metadata;
+    }
+    let Some(vals) = extract_reals::<SIXDOF>(line) else {
+      return LineResponse::Useless;
+    };
+    if let Some(gid) = row_id(line).and_then(|i| usize::try_from(i).ok()) {
+      if self.pending.is_some() {
+        warn!("complex displacement line with no second line before it!");
+      }
+      self.pending = Some((gid.into(), vals));
+      return LineResponse::Data;
+    }
+    let Some((gid, first)) = self.pending.take() else {
+      warn!("complex displacement continuation with no grid point!");
+      return LineResponse::MissingMetadata;
+    };
+    let mut row = [0.0; Self::MATWIDTH];
+    for (i, (a, b)) in first.into_iter().zip(vals).enumerate() {
+      let (re, im) = match self.form {
+        ComplexForm::RealImaginary => (a, b),
+        ComplexForm::MagnitudePhase => {
+          let phase = b.to_radians();
+          (a * phase.cos(), a * phase.sin())
+        }
+      };
+      row[2 * i] = re;
+      row[2 * i + 1] = im;
+    }
+    self.data.insert_raw(gid, &row);
+    return LineResponse::Data;
+  }
+}
+
+/// This decodes a real eigenvectors block, which holds the shapes of one or
+/// more vibration modes.
+pub(crate) struct EigenvectorsDecoder {
+  /// The flavour of F06 file we're decoding eigenvectors for.
+  flavour: Flavour,
+  /// The mode the rows belong to, as hinted by the parser.
+  mode: Option<usize>,
+  /// The eigenvector data.
+  data: RowBlock<f64, GridPointMode, Dof, { Self::MATWIDTH }>
+}
+
+impl BlockDecoder for EigenvectorsDecoder {
+  type MatScalar = f64;
+  type RowIndex = GridPointMode;
+  type ColumnIndex = Dof;
+  const MATWIDTH: usize = SIXDOF;
+  const BLOCK_TYPE: BlockType = BlockType::Eigenvectors;
+
+  fn new(flavour: Flavour) -> Self {
+    return Self {
+      flavour,
+      mode: None,
+      data: RowBlock::new(dof_cols())
+    };
+  }
+
+  fn hint_mode(&mut self, mode: usize) {
+    self.mode = Some(mode);
+  }
+
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
+  fn unwrap(
+    self,
+    subcase: usize,
+    line_range: Option<(usize, usize)>
+  ) -> FinalBlock {
+    return self.data.finalise(Self::BLOCK_TYPE, subcase, line_range);
+  }
+
+  fn consume(&mut self, line: &str) -> LineResponse {
+    let dofs: [f64; SIXDOF] = if let Some(arr) = extract_reals(line) {
+      arr
+    } else {
+      return LineResponse::Useless;
+    };
+    let Some(mode) = self.mode else {
+      return LineResponse::MissingMetadata;
+    };
+    if let Some(gid) = nth_integer(line, 0) {
+      let ri = GridPointMode { mode, grid_point: (gid as usize).into() };
+      self.data.insert_raw(ri, &dofs);
+      return LineResponse::Data;
+    }
+    return LineResponse::Useless;
+  }
+}
+
 /// The decoder for grid point force balance blocks.
 pub(crate) struct GridPointForceBalanceDecoder {
   /// The flavour of F06 file we're decoding displacements for.
@@ -173,6 +348,10 @@ impl BlockDecoder for GridPointForceBalanceDecoder {
     };
   }
 
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
   fn unwrap(
     self,
     subcase: usize,
@@ -286,6 +465,10 @@ impl BlockDecoder for SpcForcesDecoder {
     };
   }
 
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
   fn unwrap(
     self,
     subcase: usize,
@@ -330,6 +513,10 @@ impl BlockDecoder for AppliedForcesDecoder {
     };
   }
 
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
   fn unwrap(
     self,
     subcase: usize,
@@ -352,6 +539,19 @@ impl BlockDecoder for AppliedForcesDecoder {
   }
 }
 
+/// Decides whether the n-th grid point row (zero-based) of a plate element
+/// is at a corner or at a midside node, based on the element type.
+fn plate_grid_point(
+  etype: Option<ElementType>,
+  nth: usize,
+  gid: usize
+) -> ElementPoint {
+  return match etype.and_then(|et| et.midside_offset()) {
+    Some(corners) if nth >= corners => ElementPoint::Midpoint(gid.into()),
+    _ => ElementPoint::Corner(gid.into())
+  };
+}
+
 /// A decoder for the "stresses in quad elements" table.
 pub(crate) struct QuadStressesDecoder {
   /// The flavour of solver we're decoding for.
@@ -361,7 +561,11 @@ pub(crate) struct QuadStressesDecoder {
   /// Current row reference.
   cur_row: Option<<Self as BlockDecoder>::RowIndex>,
   /// Element type, hinted by the header.
-  etype: Option<ElementType>
+  etype: Option<ElementType>,
+  /// How many grid point rows the current element has had so far.
+  grid_rows: usize,
+  /// Does this block hold grid-IDs (i.e. corner output)?
+  has_grid_id: bool
 }
 
 impl BlockDecoder for QuadStressesDecoder {
@@ -376,10 +580,16 @@ impl BlockDecoder for QuadStressesDecoder {
       flavour,
       data: RowBlock::new(PlateStressField::canonical_cols()),
       cur_row: None,
-      etype: None
+      etype: None,
+      grid_rows: 0,
+      has_grid_id: false
     };
   }
 
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
   fn unwrap(
     self,
     subcase: usize,
@@ -388,6 +598,10 @@ impl BlockDecoder for QuadStressesDecoder {
     return self.data.finalise(Self::BLOCK_TYPE, subcase, line_range);
   }
 
+  fn override_etype(&mut self, etype: ElementType) {
+    self.etype = Some(etype);
+  }
+
   fn good_header(&mut self, header: &str) -> bool {
     self.etype = nth_etype(header, 0);
     if header.contains("THERMAL") || header.contains("ELASTIC") {
@@ -398,6 +612,13 @@ impl BlockDecoder for QuadStressesDecoder {
 
   fn hint_last(&mut self, last: NasIndex) {
     if let NasIndex::ElementSidedPoint(esp) = last {
+      // the exact count is lost across pages, but past a midside node, all
+      // that's left are midside nodes.
+      if let ElementPoint::Midpoint(_) = esp.point {
+        self.grid_rows = esp.element.etype
+          .and_then(|et| et.midside_offset())
+          .unwrap_or(0);
+      }
       self.cur_row = Some(esp);
     }
   }
@@ -407,18 +628,23 @@ impl BlockDecoder for QuadStressesDecoder {
   }
 
   fn consume(&mut self, line: &str) -> LineResponse {
+    if line.contains("GRID-ID") {
+      self.has_grid_id = true;
+      return LineResponse::Metadata;
+    }
     // first, take eight floats. if there aren't any, we're toast.
     let cols: [f64; Self::MATWIDTH] = if let Some(arr) = lax_reals(line) {
       arr
     } else {
       return LineResponse::Useless;
     };
-    // okay, now we get the sided point.
+    // okay, now we get the sided point. no IDs means a continuation line.
     let fields = line_breakdown(line).collect::<Vec<_>>();
-    let ints = fields.iter()
-      .filter_map(|lf| {
-        if let LineField::Integer(i) = lf { Some(i) } else { None }
-      }).copied().collect::<Vec<_>>();
+    let ints = match row_line(line, PLATE_LEAD_REALS, PLATE_CONT_REALS) {
+      RowLine::Leading(ids) => ids,
+      RowLine::Continuation => Vec::new(),
+      RowLine::NotData => return LineResponse::Useless
+    };
     match self.flavour.solver {
       Some(Solver::Mystran) => {
         if ints.is_empty() {
@@ -434,7 +660,7 @@ impl BlockDecoder for QuadStressesDecoder {
           let point = if line.contains("CENTER") {
             ElementPoint::Centroid
           } else if let Some(gid) = ints.last() {
-            ElementPoint::Corner((*gid as usize).into())
+            ElementPoint::Corner((*gid).into())
           } else {
             warn!("no point at {}", line);
             return LineResponse::Abort;
@@ -466,17 +692,23 @@ impl BlockDecoder for QuadStressesDecoder {
           }
         } else {
           // line has row info
-          let point = if line.contains("CEN/4") {
+          if ints.len() > 1 {
+            self.grid_rows = 0;
+          }
+          // without corner output, the only ID is the element's.
+          let point = if line.contains("CEN/") || !self.has_grid_id {
             ElementPoint::Centroid
           } else if let Some(gid) = ints.last() {
-            ElementPoint::Corner((*gid as usize).into())
+            let nth = self.grid_rows;
+            self.grid_rows += 1;
+            plate_grid_point(self.etype, nth, *gid)
           } else {
             warn!("no point at {}", line);
             return LineResponse::Abort;
           };
           let side = ElementSide::Bottom;
           let eid = if let Some(x) = ints.get(1) {
-            *x as usize
+            *x
           } else if let Some(ri) = self.cur_row {
             ri.element.eid
           } else {
@@ -510,7 +742,8 @@ converting_decoder!(
   (ElementSidedPoint, ElementSidedPoint),
   (PlateStrainField, PlateStressField),
   BlockType::QuadStrains,
-  8
+  8,
+  "STRAIN"
 );
 
 /// Decoder for quad element engineering forces.
@@ -544,6 +777,14 @@ impl BlockDecoder for QuadForcesDecoder {
     };
   }
 
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
+  fn override_etype(&mut self, etype: ElementType) {
+    self.etype = Some(etype);
+  }
+
   fn good_header(&mut self, header: &str) -> bool {
     self.etype = nth_etype(header, 0);
     return true;
@@ -652,7 +893,7 @@ pub(crate) struct TriaForcesDecoder {
   /// The inner block of data.
   data: RowBlock<f64, ElementRef, PlateForceField, { Self::MATWIDTH }>,
   /// Element type, hinted by the header.
-  etype: Option<ElementType>
+  etype: Option<ElementType>,
 }
 
 impl BlockDecoder for TriaForcesDecoder {
@@ -666,10 +907,18 @@ impl BlockDecoder for TriaForcesDecoder {
     return Self {
       flavour,
       data: RowBlock::new(PlateForceField::canonical_cols()),
-      etype: None
+      etype: None,
     };
   }
 
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
+  fn override_etype(&mut self, etype: ElementType) {
+    self.etype = Some(etype);
+  }
+
   fn good_header(&mut self, header: &str) -> bool {
     self.etype = nth_etype(header, 0);
     return true;
@@ -717,6 +966,10 @@ impl BlockDecoder for RodForcesDecoder {
     return Self { data: RowBlock::new(RodForceField::canonical_cols()) };
   }
 
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
   fn unwrap(
     self,
     subcase: usize,
@@ -773,6 +1026,10 @@ impl BlockDecoder for BarForcesDecoder {
     };
   }
 
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
   fn unwrap(
     self,
     subcase: usize,
@@ -801,6 +1058,120 @@ impl BlockDecoder for BarForcesDecoder {
   }
 }
 
+/// Decoder for beam engineering forces table.
+///
+/// MYSTRAN writes one line per element, laid out like the bar forces, which
+/// becomes a row for each end (it computes no warping torque, so that's zero).
+/// Simcenter writes the element ID on a line of its own, followed by a line
+/// per station that starts with its grid ID (zero for intermediate ones).
+pub(crate) struct BeamForcesDecoder {
+  /// The flavour of the F06 file.
+  flavour: Flavour,
+  /// The last row inserted, so stations can follow their element's line.
+  cur_row: Option<ElementStation>,
+  /// The inner block of data.
+  data: RowBlock<f64, ElementStation, BeamForceField, { Self::MATWIDTH }>
+}
+
+impl BlockDecoder for BeamForcesDecoder {
+  type MatScalar = f64;
+  type RowIndex = ElementStation;
+  type ColumnIndex = BeamForceField;
+  const MATWIDTH: usize = 8;
+  const BLOCK_TYPE: BlockType = BlockType::BeamForces;
+
+  fn new(flavour: Flavour) -> Self {
+    return Self {
+      flavour,
+      cur_row: None,
+      data: RowBlock::new(BeamForceField::canonical_cols()),
+    };
+  }
+
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
+  fn hint_last(&mut self, last: NasIndex) {
+    if let NasIndex::ElementStation(es) = last {
+      self.cur_row = Some(es);
+    }
+  }
+
+  fn last_row_index(&self) -> Option<NasIndex> {
+    return self.cur_row.map(|es| es.into());
+  }
+
+  fn unwrap(
+    self,
+    subcase: usize,
+    line_range: Option<(usize, usize)>
+  ) -> FinalBlock {
+    return self.data.finalise(Self::BLOCK_TYPE, subcase, line_range);
+  }
+
+  fn consume(&mut self, line: &str) -> LineResponse {
+    let etype = Some(ElementType::Beam);
+    let Some(cols) = extract_reals::<{ Self::MATWIDTH }>(line) else {
+      // a lone ID is the element line of a Simcenter table.
+      if self.flavour.solver == Some(Solver::Mystran) {
+        return LineResponse::Useless;
+      }
+      let fields = line_breakdown(line).collect::<Vec<_>>();
+      let ints_only = fields.iter()
+        .all(|f| matches!(f, LineField::Integer(_)));
+      return match fields.last() {
+        Some(LineField::Integer(eid)) if ints_only && *eid > 0 => {
+          let element = ElementRef { eid: *eid as usize, etype };
+          self.cur_row = Some(ElementStation {
+            element,
+            station: BeamStation::EndA
+          });
+          LineResponse::Metadata
+        },
+        _ => LineResponse::Useless
+      };
+    };
+    let Some(id) = row_id(line).and_then(|i| usize::try_from(i).ok()) else {
+      warn!("no ID on beam force data line!");
+      return LineResponse::Abort;
+    };
+    if self.flavour.solver == Some(Solver::Mystran) {
+      let element = ElementRef { eid: id, etype };
+      let [ma1, ma2, mb1, mb2, v1, v2, fx, t] = cols;
+      let ends = [
+        (BeamStation::EndA, [0.0, ma1, ma2, v1, v2, fx, t, 0.0]),
+        (BeamStation::EndB, [1.0, mb1, mb2, v1, v2, fx, t, 0.0])
+      ];
+      for (station, row) in ends {
+        let es = ElementStation { element, station };
+        self.data.insert_raw(es, &row);
+        self.cur_row = Some(es);
+      }
+      return LineResponse::Data;
+    }
+    // simcenter: the ID is the station's grid, and the element came before.
+    let Some(prev) = self.cur_row else {
+      warn!("beam force station line with no element before it!");
+      return LineResponse::MissingMetadata;
+    };
+    let dist = cols[0];
+    let station = if dist <= 0.0 {
+      BeamStation::EndA
+    } else if dist >= 1.0 {
+      BeamStation::EndB
+    } else if let BeamStation::Intermediate(n) = prev.station {
+      BeamStation::Intermediate(n.saturating_add(1))
+    } else {
+      BeamStation::Intermediate(1)
+    };
+    let es = ElementStation { element: prev.element, station };
+    self.data.insert_raw(es, &cols);
+    self.cur_row = Some(es);
+    return LineResponse::Data;
+  }
+}
+
 /// Decoder for ELAS1 engineering force blocks.
 pub(crate) struct Elas1ForcesDecoder {
   /// The inner data block.
@@ -818,6 +1189,10 @@ impl BlockDecoder for Elas1ForcesDecoder {
     return Self { data: RowBlock::new(SingleForce::canonical_cols()) };
   }
 
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
   fn unwrap(
     self,
     subcase: usize,
@@ -860,7 +1235,11 @@ pub(crate) struct TriaStressesDecoder {
   /// The current element ID.
   eid: Option<usize>,
   /// The element type (gleaned from the header).
-  etype: Option<ElementType>
+  etype: Option<ElementType>,
+  /// The current point, for higher-order elements.
+  point: ElementPoint,
+  /// How many grid point rows the current element has had so far.
+  grid_rows: usize
 }
 
 impl BlockDecoder for TriaStressesDecoder {
@@ -876,9 +1255,19 @@ impl BlockDecoder for TriaStressesDecoder {
       data: RowBlock::new(PlateStressField::canonical_cols()),
       eid: None,
       etype: None,
+      point: ElementPoint::Anywhere,
+      grid_rows: 0
     }
   }
 
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
+  fn override_etype(&mut self, etype: ElementType) {
+    self.etype = Some(etype);
+  }
+
   fn good_header(&mut self, header: &str) -> bool {
     self.etype = nth_etype(header, 0);
     return true;
@@ -888,11 +1277,26 @@ impl BlockDecoder for TriaStressesDecoder {
     if let NasIndex::ElementSidedPoint(esp) = last {
       self.etype = esp.element.etype;
       self.eid = Some(esp.element.eid);
+      self.point = esp.point;
+      if let ElementPoint::Midpoint(_) = esp.point {
+        self.grid_rows = self.etype
+          .and_then(|et| et.midside_offset())
+          .unwrap_or(0);
+      }
     } else {
       panic!("bad header passed to hint_last");
     }
   }
 
+  fn last_row_index(&self) -> Option<NasIndex> {
+    let element = ElementRef { eid: self.eid?, etype: self.etype };
+    return Some(ElementSidedPoint {
+      element,
+      point: self.point,
+      side: ElementSide::Bottom
+    }.into());
+  }
+
   fn unwrap(
     self,
     subcase: usize,
@@ -907,8 +1311,13 @@ impl BlockDecoder for TriaStressesDecoder {
     } else {
       return LineResponse::Useless;
     };
-    let i0 = nth_natural(line, 0);
-    let i1 = nth_natural(line, 1);
+    let (ids, side) = match row_line(line, PLATE_LEAD_REALS, PLATE_CONT_REALS) {
+      RowLine::Leading(ids) => (ids, ElementSide::Bottom),
+      RowLine::Continuation => (Vec::new(), ElementSide::Top),
+      RowLine::NotData => return LineResponse::Useless
+    };
+    let i0 = ids.first().copied();
+    let i1 = ids.get(1).copied();
     self.eid = match (self.flavour.solver, self.eid) {
       (Some(Solver::Mystran), None) => i0,
       (Some(Solver::Mystran), Some(_)) => i0.or(self.eid),
@@ -916,14 +1325,23 @@ impl BlockDecoder for TriaStressesDecoder {
       (Some(Solver::Simcenter), Some(_)) => i1.or(self.eid),
       (None, _) => return LineResponse::BadFlavour,
     };
+    // higher-order elements get per-point rows in simcenter.
+    let higher = self.etype.and_then(|et| et.midside_offset()).is_some();
+    if higher && self.flavour.solver == Some(Solver::Simcenter) {
+      if ids.len() > 1 {
+        self.grid_rows = 0;
+      }
+      if line.contains("CEN/") {
+        self.point = ElementPoint::Centroid;
+      } else if let Some(gid) = ids.last() {
+        let nth = self.grid_rows;
+        self.grid_rows += 1;
+        self.point = plate_grid_point(self.etype, nth, *gid);
+      }
+    }
     let esp = if let Some(eid) = self.eid {
       let element = ElementRef { eid, etype: self.etype };
-      let side = if nth_natural(line, 0).is_none() {
-        ElementSide::Top
-      } else {
-        ElementSide::Bottom
-      };
-      let point = ElementPoint::Anywhere;
+      let point = self.point;
       ElementSidedPoint { element, point, side }
     } else {
       warn!("no eid on data line on {}", line);
@@ -942,7 +1360,8 @@ converting_decoder!(
   (ElementSidedPoint, ElementSidedPoint),
   (PlateStrainField, PlateStressField),
   BlockType::TriaStrains,
-  8
+  8,
+  "STRAIN"
 );
 
 /// Decoder for "stresses in rod elements" tables.
@@ -967,6 +1386,10 @@ impl BlockDecoder for RodStressesDecoder {
     };
   }
 
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
   fn unwrap(
     self,
     subcase: usize,
@@ -1012,7 +1435,8 @@ converting_decoder!(
   (ElementRef, ElementRef),
   (RodStrainField, RodStressField),
   BlockType::RodStrains,
-  4
+  4,
+  "STRAIN"
 );
 
 /// Decoder for "stresses in bar elements" tables.
@@ -1040,6 +1464,10 @@ impl BlockDecoder for BarStressesDecoder {
     };
   }
 
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
   fn unwrap(
     self,
     subcase: usize,
@@ -1070,79 +1498,82 @@ impl BlockDecoder for BarStressesDecoder {
       BarStressField::MinAt(BarEnd::EndB),
       BarStressField::SafetyMargin(NormalStressDirection::Compression)
     ];
-    let i0 = nth_natural(line, 0);
-    let i1 = nth_natural(line, 1);
-    if let Some(ui0) = i0 {
-      // eid line
-      let eid = match self.flavour.solver {
-        Some(Solver::Mystran) => ui0,
-        Some(Solver::Simcenter) => match i1 {
-          Some(ui1) => ui1,
-          None => {
-            warn!("missing uid on data line {}", line);
-            return LineResponse::Abort;
-          }
-        },
-        None => return LineResponse::BadFlavour
-      };
-      // get data
-      let vals: [f64; 8] = if let Some(arr) = extract_reals(line) {
-        arr
-      } else if let Some(arr7) = extract_reals::<7>(line) {
-        [
-          arr7[0],
-          arr7[1],
-          arr7[2],
-          arr7[3],
-          arr7[4],
-          arr7[5],
-          arr7[6],
-          0.0
-        ]
-      } else {
-        return LineResponse::Useless;
-      };
-      let cols: BTreeMap<BarStressField, f64> = ORDER_L1.iter().copied()
-        .zip(vals)
-        .collect();
-      self.curr = Some((eid, cols));
-      return LineResponse::Data;
-    } else if let Some((eid, mut cols)) = self.curr.take() {
-      // non-eid line. get some floats.
-      let vals: [f64; 7] = if let Some(arr) = extract_reals(line) {
-        arr
-      } else if let Some(arr6) = extract_reals::<6>(line) {
-        [
-          arr6[0],
-          arr6[1],
-          arr6[2],
-          arr6[3],
-          arr6[4],
-          arr6[5],
-          0.0
-        ]
-      } else {
-        warn!("non-data line whilst having an eid");
-        return LineResponse::Abort;
-      };
-      ORDER_L2.iter().copied().zip(vals)
-        .for_each(|(k, v)| { cols.insert(k, v); });
-      if cols.len() == Self::MATWIDTH {
-        let eref = ElementRef { eid, etype: Some(ElementType::Bar) };
-        self.data.insert_row(eref, &cols);
+    match row_line(line, BAR_LEAD_REALS, BAR_CONT_REALS) {
+      RowLine::Leading(ids) => {
+        // eid line
+        let eid = match self.flavour.solver {
+          Some(Solver::Mystran) => ids[0],
+          Some(Solver::Simcenter) => match ids.get(1) {
+            Some(ui1) => *ui1,
+            None => {
+              warn!("missing uid on data line {}", line);
+              return LineResponse::Abort;
+            }
+          },
+          None => return LineResponse::BadFlavour
+        };
+        // get data
+        let vals: [f64; 8] = if let Some(arr) = extract_reals(line) {
+          arr
+        } else if let Some(arr7) = extract_reals::<7>(line) {
+          [
+            arr7[0],
+            arr7[1],
+            arr7[2],
+            arr7[3],
+            arr7[4],
+            arr7[5],
+            arr7[6],
+            0.0
+          ]
+        } else {
+          return LineResponse::Useless;
+        };
+        let cols: BTreeMap<BarStressField, f64> = ORDER_L1.iter().copied()
+          .zip(vals)
+          .collect();
+        self.curr = Some((eid, cols));
         return LineResponse::Data;
-      } else {
-        warn!("bad number of items in val map ({})", cols.len());
-        return LineResponse::Abort;
-      }
-    } else if extract_reals::<7>(line).is_some() {
-      // line has floats but no current line
-      warn!("found second row without ever seeing a first, at {}", line);
-      return LineResponse::Abort;
-    } else {
+      },
+      RowLine::Continuation => {
+        let (eid, mut cols) = if let Some(curr) = self.curr.take() {
+          curr
+        } else {
+          // line has floats but no current line
+          warn!("found second row without ever seeing a first, at {}", line);
+          return LineResponse::Abort;
+        };
+        // non-eid line. get some floats.
+        let vals: [f64; 7] = if let Some(arr) = extract_reals(line) {
+          arr
+        } else if let Some(arr6) = extract_reals::<6>(line) {
+          [
+            arr6[0],
+            arr6[1],
+            arr6[2],
+            arr6[3],
+            arr6[4],
+            arr6[5],
+            0.0
+          ]
+        } else {
+          warn!("non-data line whilst having an eid");
+          return LineResponse::Abort;
+        };
+        ORDER_L2.iter().copied().zip(vals)
+          .for_each(|(k, v)| { cols.insert(k, v); });
+        if cols.len() == Self::MATWIDTH {
+          let eref = ElementRef { eid, etype: Some(ElementType::Bar) };
+          self.data.insert_row(eref, &cols);
+          return LineResponse::Data;
+        } else {
+          warn!("bad number of items in val map ({})", cols.len());
+          return LineResponse::Abort;
+        }
+      },
       // non-eid line with no data.
-      return LineResponse::Useless;
-    }
+      RowLine::NotData => return LineResponse::Useless
+    };
   }
 }
 
@@ -1154,7 +1585,8 @@ converting_decoder!(
   (ElementRef, ElementRef),
   (BarStrainField, BarStressField),
   BlockType::BarStrains,
-  15
+  15,
+  "STRAIN"
 );
 
 /// Decoder for ELAS1 element stresses.
@@ -1176,6 +1608,10 @@ impl BlockDecoder for Elas1StressesDecoder {
     };
   }
 
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
   fn unwrap(
     self,
     subcase: usize,
@@ -1218,7 +1654,8 @@ converting_decoder!(
   (ElementRef, ElementRef),
   (SingleStrain, SingleStress),
   BlockType::Elas1Strains,
-  1
+  1,
+  "STRAIN"
 );
 
 /// This decodes a bush forces block.
@@ -1243,6 +1680,10 @@ impl BlockDecoder for BushForcesDecoder {
     };
   }
 
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
   fn unwrap(
     self,
     subcase: usize,
@@ -1257,7 +1698,7 @@ impl BlockDecoder for BushForcesDecoder {
     } else {
       return LineResponse::Useless;
     };
-    if let Some(eid) = last_natural(line) {
+    if let Some(eid) = row_id(line).and_then(|i| usize::try_from(i).ok()) {
       let eref = ElementRef { eid, etype: Some(ElementType::Bush) };
       self.data.insert_raw(eref, &dofs);
       return LineResponse::Data;
@@ -1276,16 +1717,385 @@ converting_decoder!(
   (ElementRef, ElementRef),
   (Dof, Dof),
   BlockType::BushStresses,
-  6
+  6,
+  "STRESS"
 );
 
 converting_decoder!(
   "Decoder for BUSH strains",
   BushStrainsDecoder,
-  BushStressesDecoder,
+  BushForcesDecoder,
   f64,
   (ElementRef, ElementRef),
   (Dof, Dof),
   BlockType::BushStrains,
-  6
+  6,
+  "STRAIN"
 );
+
+/// Decoder for CWELD connector engineering force tables.
+pub(crate) struct ConnectorForcesDecoder {
+  /// The element type to tag rows with.
+  etype: ElementType,
+  /// The force data.
+  data: RowBlock<f64, ElementRef, ConnectorForceField, { Self::MATWIDTH }>
+}
+
+impl BlockDecoder for ConnectorForcesDecoder {
+  type MatScalar = f64;
+  type RowIndex = ElementRef;
+  type ColumnIndex = ConnectorForceField;
+  const MATWIDTH: usize = 8;
+  const BLOCK_TYPE: BlockType = BlockType::ConnectorForces;
+
+  fn new(_flavour: Flavour) -> Self {
+    return Self {
+      etype: ElementType::Weld,
+      data: RowBlock::new(ConnectorForceField::canonical_cols())
+    };
+  }
+
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
+  fn override_etype(&mut self, etype: ElementType) {
+    self.etype = etype;
+  }
+
+  fn unwrap(
+    self,
+    subcase: usize,
+    line_range: Option<(usize, usize)>
+  ) -> FinalBlock {
+    return self.data.finalise(Self::BLOCK_TYPE, subcase, line_range);
+  }
+
+  fn consume(&mut self, line: &str) -> LineResponse {
+    // CWELD tables have the same layout as CBAR ones.
+    let cols: [f64; 8] = if let Some(arr) = extract_reals(line) {
+      arr
+    } else {
+      return LineResponse::Useless;
+    };
+    if let Some(eid) = row_id(line).and_then(|i| usize::try_from(i).ok()) {
+      let eref = ElementRef { eid, etype: Some(self.etype) };
+      self.data.insert_raw(eref, &cols);
+      return LineResponse::Data;
+    } else {
+      warn!("no eid on {} force data line!", self.etype);
+      return LineResponse::Abort;
+    }
+  }
+}
+
+/// Decoder for CFAST fastener engineering force tables. These carry a single
+/// set of forces and moments in the element system, with x along the
+/// fastener, so they get one column per DOF instead of the CWELD ends.
+pub(crate) struct FastenerForcesDecoder {
+  /// The element type to tag rows with.
+  etype: ElementType,
+  /// The force data.
+  data: RowBlock<f64, ElementRef, Dof, { Self::MATWIDTH }>
+}
+
+impl BlockDecoder for FastenerForcesDecoder {
+  type MatScalar = f64;
+  type RowIndex = ElementRef;
+  type ColumnIndex = Dof;
+  const MATWIDTH: usize = SIXDOF;
+  const BLOCK_TYPE: BlockType = BlockType::FastenerForces;
+
+  fn new(_flavour: Flavour) -> Self {
+    return Self {
+      etype: ElementType::Fast,
+      data: RowBlock::new(dof_cols())
+    };
+  }
+
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
+  fn override_etype(&mut self, etype: ElementType) {
+    self.etype = etype;
+  }
+
+  fn unwrap(
+    self,
+    subcase: usize,
+    line_range: Option<(usize, usize)>
+  ) -> FinalBlock {
+    return self.data.finalise(Self::BLOCK_TYPE, subcase, line_range);
+  }
+
+  fn consume(&mut self, line: &str) -> LineResponse {
+    let dofs: [f64; SIXDOF] = if let Some(arr) = extract_reals(line) {
+      arr
+    } else {
+      return LineResponse::Useless;
+    };
+    if let Some(eid) = row_id(line).and_then(|i| usize::try_from(i).ok()) {
+      let eref = ElementRef { eid, etype: Some(self.etype) };
+      self.data.insert_raw(eref, &dofs);
+      return LineResponse::Data;
+    } else {
+      warn!("no eid on {} force data line!", self.etype);
+      return LineResponse::Abort;
+    }
+  }
+}
+
+/// Splits a field into the numbers glued together in it, as happens with the
+/// fixed-width direction cosines of solid stresses (e.g. "0.00-0.71").
+fn unglue_reals(field: &str) -> impl Iterator<Item = &str> {
+  let mut starts = vec![0];
+  let bytes = field.as_bytes();
+  for i in 1..bytes.len() {
+    if bytes[i] == b'-' && !matches!(bytes[i - 1], b'E' | b'e') {
+      starts.push(i);
+    }
+  }
+  starts.push(field.len());
+  return (0..starts.len() - 1).map(move |i| &field[starts[i]..starts[i + 1]]);
+}
+
+/// Decoder for solid element stresses (HEXA, PENTA, TETRA).
+///
+/// Each element starts with a line holding its ID and "GRID CS", followed by
+/// three lines per point (the centre, then each corner), one per axis. The
+/// first of them names the point and also holds the mean pressure and the
+/// von Mises stress.
+pub(crate) struct SolidStressesDecoder {
+  /// The kind of solid in this table, gotten from the header.
+  etype: ElementType,
+  /// The element the current lines are for.
+  element: Option<ElementRef>,
+  /// The last point inserted.
+  last_row: Option<PointInElement>,
+  /// The point being read, its values so far and which axes were read.
+  pending: Option<(PointInElement, [f64; Self::MATWIDTH], [bool; 3])>,
+  /// The stress data.
+  data: RowBlock<f64, PointInElement, SolidStressField, { Self::MATWIDTH }>
+}
+
+impl SolidStressesDecoder {
+  /// Reads the values in an axis line, starting at the axis name. Returns the
+  /// axis index, the normal, shear and principal stresses, the cosines, and
+  /// whatever else follows them.
+  fn read_axis(fields: &[&str]) -> Option<(usize, [f64; 6], Vec<f64>)> {
+    let axis = match fields.first() {
+      Some(&"X") => 0,
+      Some(&"Y") => 1,
+      Some(&"Z") => 2,
+      _ => return None
+    };
+    let labels = [["XY", "A", "LX"], ["YZ", "B", "LY"], ["ZX", "C", "LZ"]];
+    let [shear, principal, cosine] = labels[axis];
+    if fields.get(2) != Some(&shear)
+      || fields.get(4) != Some(&principal)
+      || fields.get(6) != Some(&cosine) {
+      return None;
+    }
+    let real = |i: usize| fields.get(i).and_then(|f| f.parse::<f64>().ok());
+    let mut nums = fields[7..].iter()
+      .flat_map(|f| unglue_reals(f))
+      .map(|f| f.parse::<f64>().ok());
+    let mut vals = [real(1)?, real(3)?, real(5)?, 0.0, 0.0, 0.0];
+    for v in vals[3..].iter_mut() {
+      *v = nums.next()??;
+    }
+    return Some((axis, vals, nums.collect::<Option<Vec<f64>>>()?));
+  }
+}
+
+impl BlockDecoder for SolidStressesDecoder {
+  type MatScalar = f64;
+  type RowIndex = PointInElement;
+  type ColumnIndex = SolidStressField;
+  const MATWIDTH: usize = 20;
+  const BLOCK_TYPE: BlockType = BlockType::SolidStresses;
+
+  fn new(_flavour: Flavour) -> Self {
+    return Self {
+      etype: ElementType::Hexa,
+      element: None,
+      last_row: None,
+      pending: None,
+      data: RowBlock::new(SolidStressField::canonical_cols())
+    };
+  }
+
+  fn good_header(&mut self, header: &str) -> bool {
+    let solids = [
+      ("HEXAHEDRON", ElementType::Hexa),
+      ("PENTAHEDRON", ElementType::Penta),
+      ("TETRAHEDRON", ElementType::Tetra)
+    ];
+    return match solids.iter().find(|(name, _)| header.contains(name)) {
+      Some((_, et)) => {
+        self.etype = *et;
+        true
+      },
+      None => false
+    };
+  }
+
+  fn hint_last(&mut self, last: NasIndex) {
+    if let NasIndex::PointInElement(pie) = last {
+      self.element = Some(pie.element);
+      self.last_row = Some(pie);
+    }
+  }
+
+  fn last_row_index(&self) -> Option<NasIndex> {
+    return self.last_row.map(|pie| pie.into());
+  }
+
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
+  fn unwrap(
+    self,
+    subcase: usize,
+    line_range: Option<(usize, usize)>
+  ) -> FinalBlock {
+    return self.data.finalise(Self::BLOCK_TYPE, subcase, line_range);
+  }
+
+  fn consume(&mut self, line: &str) -> LineResponse {
+    let fields = line.split_whitespace().collect::<Vec<_>>();
+    // element lines have the ID before the coordinate system.
+    if let Some(g) = fields.iter().position(|f| f.ends_with("GRID")) {
+      let eid = fields[..g].iter()
+        .filter_map(|f| f.parse::<usize>().ok())
+        .find(|eid| *eid > 0);
+      if let Some(eid) = eid {
+        self.element = Some(ElementRef { eid, etype: Some(self.etype) });
+        self.pending = None;
+        return LineResponse::Metadata;
+      }
+      return LineResponse::Useless;
+    }
+    let Some(a) = fields.iter().position(|f| matches!(*f, "X" | "Y" | "Z"))
+    else {
+      return LineResponse::Useless;
+    };
+    let Some((axis, vals, rest)) = Self::read_axis(&fields[a..]) else {
+      return LineResponse::Useless;
+    };
+    if axis == 0 {
+      // the first line of a point says which one it is.
+      let Some(element) = self.element else {
+        warn!("solid stress line with no element before it!");
+        return LineResponse::MissingMetadata;
+      };
+      let point = match a.checked_sub(1).map(|i| fields[i]) {
+        Some("CENTER") => ElementPoint::Centroid,
+        Some(f) => match f.parse::<usize>() {
+          Ok(gid) => ElementPoint::Corner(gid.into()),
+          Err(_) => return LineResponse::Useless
+        },
+        None => return LineResponse::Useless
+      };
+      let [pressure, von_mises] = rest[..] else {
+        return LineResponse::Useless;
+      };
+      let mut row = [0.0; Self::MATWIDTH];
+      row[18] = pressure;
+      row[19] = von_mises;
+      self.pending = Some((PointInElement { element, point }, row, [false; 3]));
+    }
+    let Some((pie, row, seen)) = self.pending.as_mut() else {
+      return LineResponse::MissingMetadata;
+    };
+    let [normal, shear, principal, ca, cb, cc] = vals;
+    row[axis] = normal;
+    row[3 + axis] = shear;
+    row[6 + axis] = principal;
+    row[9 + axis] = ca;
+    row[12 + axis] = cb;
+    row[15 + axis] = cc;
+    seen[axis] = true;
+    if seen.iter().all(|s| *s) {
+      let pie = *pie;
+      self.data.insert_raw(pie, row);
+      self.last_row = Some(pie);
+      self.pending = None;
+    }
+    return LineResponse::Data;
+  }
+}
+
+/// Value flagging a DOF found singular in the grid point singularity table.
+pub(crate) const SINGULAR: usize = 1;
+
+/// Value flagging a singular DOF that was also constrained automatically (the
+/// asterisk at the end of the singularity table's line).
+pub(crate) const AUTO_CONSTRAINED: usize = 2;
+
+/// This decodes the grid point singularity table. Each line is a single DOF
+/// of a grid point, so they're accumulated per grid point before unwrapping.
+pub(crate) struct SingularitiesDecoder {
+  /// The singularity flags per grid point, accumulated across lines.
+  flags: BTreeMap<GridPointRef, [usize; SIXDOF]>,
+  /// The singularity data.
+  data: RowBlock<usize, GridPointRef, Dof, { Self::MATWIDTH }>
+}
+
+impl BlockDecoder for SingularitiesDecoder {
+  type MatScalar = usize;
+  type RowIndex = GridPointRef;
+  type ColumnIndex = Dof;
+  const MATWIDTH: usize = SIXDOF;
+  const BLOCK_TYPE: BlockType = BlockType::Singularities;
+
+  fn new(_flavour: Flavour) -> Self {
+    return Self {
+      flags: BTreeMap::new(),
+      data: RowBlock::new(dof_cols())
+    };
+  }
+
+  fn row_block(&mut self) -> &mut dyn RowBlockSettings {
+    return &mut self.data;
+  }
+
+  fn unwrap(
+    mut self,
+    subcase: usize,
+    line_range: Option<(usize, usize)>
+  ) -> FinalBlock {
+    for (gpref, row) in self.flags.iter() {
+      self.data.insert_raw(*gpref, row);
+    }
+    return self.data.finalise(Self::BLOCK_TYPE, subcase, line_range);
+  }
+
+  fn consume(&mut self, line: &str) -> LineResponse {
+    // lines go like "<grid id> G <failed direction> <stiffness ratio> ...",
+    // with a trailing asterisk if the DOF got constrained
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    let found = words.windows(3).find_map(|w| {
+      if w[1] != "G" {
+        return None;
+      }
+      let gid = w[0].parse::<usize>().ok()?;
+      let dof = Dof::try_from(w[2].parse::<usize>().ok()?).ok()?;
+      return Some((gid, dof));
+    });
+    let (gid, dof) = match found {
+      Some(x) => x,
+      None => return LineResponse::Useless
+    };
+    let value = if words.last() == Some(&"*") {
+      AUTO_CONSTRAINED
+    } else {
+      SINGULAR
+    };
+    let row = self.flags.entry(gid.into()).or_insert([0; SIXDOF]);
+    row[*self.data.col_indexes().get(&dof).unwrap()] = value;
+    return LineResponse::Data;
+  }
+}
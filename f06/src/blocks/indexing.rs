@@ -146,6 +146,7 @@ impl NasIndex {
         }
       },
       NasIndex::GridPointForceOrigin(gpfo) => gpfo.grid_point,
+      NasIndex::GridPointMode(gpm) => gpm.grid_point,
       NasIndex::ElementSidedPoint(esp) => {
         match esp.point {
           ElementPoint::Corner(g) => g,
@@ -169,30 +170,54 @@ impl NasIndex {
         }
       },
       NasIndex::ElementSidedPoint(esp) => esp.element,
+      NasIndex::ElementStation(es) => es.element,
       _ => return None
     });
   }
+
+  /// Returns the mode this index refers to, if any.
+  pub fn mode(&self) -> Option<usize> {
+    return match self {
+      NasIndex::GridPointMode(gpm) => Some(gpm.mode),
+      _ => None
+    };
+  }
+
+  /// Returns the point within an element this index refers to, if any.
+  pub fn element_point(&self) -> Option<ElementPoint> {
+    return match self {
+      NasIndex::PointInElement(pie) => Some(pie.point),
+      NasIndex::ElementSidedPoint(esp) => Some(esp.point),
+      _ => None
+    };
+  }
 }
 
 gen_nasindex!(
   Dof,
+  ComplexDof,
   GridPointRef,
   ElementRef,
   PointInElement,
   GridPointForceOrigin,
   ElementSidedPoint,
+  ElementStation,
   SingleForce,
   SingleStress,
   SingleStrain,
   BarForceField,
   BarStressField,
   BarStrainField,
+  ConnectorForceField,
+  BeamForceField,
   RodForceField,
   RodStressField,
   RodStrainField,
   PlateForceField,
   PlateStressField,
   PlateStrainField,
+  SolidStressField,
+  GridPointMode,
 );
 
 /// All field indexing types must implement this trait.
@@ -206,6 +231,11 @@ impl IndexType for Dof {
 }
 
 /// The possible origins for a force.
+///
+/// These are ordered as grid point force balances are usually read: the
+/// applied load, then the single- and multi-point constraints, then the
+/// elements by ID. This is the order rows come in for each grid point, in
+/// blocks and in CSV output alike, so keep the variants in it.
 #[derive(
   Copy, Clone, Debug, Serialize, Deserialize, PartialOrd, Ord, PartialEq, Eq,
   derive_more::From
@@ -213,15 +243,15 @@ impl IndexType for Dof {
 pub enum ForceOrigin {
   /// The force was applied by a load.
   Load,
+  /// The force was applied by a single-point constraint.
+  SinglePointConstraint,
+  /// The force was applied by a multi-point constraint.
+  MultiPointConstraint,
   /// The force was applied by another element.
   Element {
     /// A reference to the element.
     elem: ElementRef
-  },
-  /// The force was applied by a single-point constraint.
-  SinglePointConstraint,
-  /// The force was applied by a multi-point constraint.
-  MultiPointConstraint
+  }
 }
 
 impl Display for ForceOrigin {
@@ -326,6 +356,28 @@ impl IndexType for GridPointForceOrigin {
   const INDEX_NAME: &'static str = "GRID POINT FORCE ORIGIN";
 }
 
+/// A grid point in the shape of a vibration mode.
+#[derive(
+  Copy, Clone, Debug, Serialize, Deserialize, PartialOrd, Ord, PartialEq, Eq,
+  derive_more::From
+)]
+pub struct GridPointMode {
+  /// The mode number, as printed by the solver.
+  pub mode: usize,
+  /// A reference to the grid point.
+  pub grid_point: GridPointRef
+}
+
+impl Display for GridPointMode {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return write!(f, "{} IN MODE {}", self.grid_point, self.mode);
+  }
+}
+
+impl IndexType for GridPointMode {
+  const INDEX_NAME: &'static str = "GRID POINT AND MODE";
+}
+
 /// A point within an element.
 #[derive(
   Copy, Clone, Debug, Serialize, Deserialize, PartialOrd, Ord, PartialEq, Eq
@@ -440,6 +492,101 @@ impl ElementSidedPoint {
   }
 }
 
+/// A station along a BEAM element, where its forces are recovered.
+#[derive(
+  Copy, Clone, Debug, Serialize, Deserialize, PartialOrd, Ord, PartialEq, Eq
+)]
+pub enum BeamStation {
+  /// End A of the beam.
+  EndA,
+  /// An intermediate station, numbered from end A starting at 1.
+  Intermediate(u8),
+  /// End B of the beam.
+  EndB
+}
+
+impl Display for BeamStation {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return match self {
+      Self::EndA => write!(f, "END-A"),
+      Self::Intermediate(n) => write!(f, "STATION {}", n),
+      Self::EndB => write!(f, "END-B")
+    };
+  }
+}
+
+/// An element and a station along it, for BEAM results.
+#[derive(
+  Copy, Clone, Debug, Serialize, Deserialize, PartialOrd, Ord, PartialEq, Eq,
+  derive_more::From
+)]
+pub struct ElementStation {
+  /// A reference to the element.
+  pub element: ElementRef,
+  /// The station along the element.
+  pub station: BeamStation
+}
+
+impl Display for ElementStation {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return write!(f, "{}, {}", self.element, self.station);
+  }
+}
+
+impl IndexType for ElementStation {
+  const INDEX_NAME: &'static str = "ELEMENT AND STATION";
+}
+
+from_enum!(
+  "A part of a complex value.",
+  ComplexPart,
+  [
+    (Real, "REAL"),
+    (Imaginary, "IMAGINARY"),
+  ]
+);
+
+/// A part of the complex value of a degree of freedom, for frequency
+/// response results.
+#[derive(
+  Copy, Clone, Debug, Serialize, Deserialize, PartialOrd, Ord, PartialEq, Eq,
+  derive_more::From
+)]
+pub struct ComplexDof {
+  /// The degree of freedom.
+  pub dof: Dof,
+  /// The part of its value.
+  pub part: ComplexPart
+}
+
+impl Display for ComplexDof {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return write!(f, "{} ({})", self.dof, self.part);
+  }
+}
+
+impl IndexType for ComplexDof {
+  const INDEX_NAME: &'static str = "COMPLEX DOF";
+}
+
+impl ComplexDof {
+  /// Returns all the fields, each degree of freedom's real part before its
+  /// imaginary one.
+  pub fn all() -> impl Iterator<Item = Self> {
+    return Dof::all().iter().flat_map(|dof| {
+      return ComplexPart::all().iter().map(|part| Self {
+        dof: *dof,
+        part: *part
+      });
+    });
+  }
+
+  /// Returns a col index map for ease of use in decoders.
+  pub fn canonical_cols() -> BTreeMap<Self, usize> {
+    return Self::all().enumerate().map(|(a, b)| (b, a)).collect();
+  }
+}
+
 from_enum!(
   "The columns for the stresses table for plate elements.",
   PlateStressField,
@@ -593,6 +740,75 @@ impl BarForceField {
   }
 }
 
+from_enum!(
+  "A column of a CWELD connector engineering force table.",
+  ConnectorForceField,
+  [
+    (MomentA1, "BEND-MOMENT END-A, PLANE 1"),
+    (MomentA2, "BEND-MOMENT END-A, PLANE 2"),
+    (MomentB1, "BEND-MOMENT END-B, PLANE 1"),
+    (MomentB2, "BEND-MOMENT END-B, PLANE 2"),
+    (Shear1, "SHEAR PLANE 1"),
+    (Shear2, "SHEAR PLANE 2"),
+    (AxialForce, "AXIAL FORCE"),
+    (Torque, "TORQUE"),
+  ]
+);
+
+impl IndexType for ConnectorForceField {
+  const INDEX_NAME: &'static str = "CONNECTOR FORCE FIELD";
+}
+
+from_enum!(
+  "A column of a BEAM engineering force table, at a station along the beam.",
+  BeamForceField,
+  [
+    (StationDistance, "STATION DISTANCE/LENGTH"),
+    (BendMoment1, "BEND-MOMENT PLANE 1"),
+    (BendMoment2, "BEND-MOMENT PLANE 2"),
+    (Shear1, "WEB SHEAR PLANE 1"),
+    (Shear2, "WEB SHEAR PLANE 2"),
+    (AxialForce, "AXIAL FORCE"),
+    (TotalTorque, "TOTAL TORQUE"),
+    (WarpingTorque, "WARPING TORQUE"),
+  ]
+);
+
+impl IndexType for BeamForceField {
+  const INDEX_NAME: &'static str = "BEAM FORCE FIELD";
+}
+
+from_enum!(
+  "The columns for the stresses table for solid elements.",
+  SolidStressField,
+  [
+    (NormalX, "NORMAL-X"),
+    (NormalY, "NORMAL-Y"),
+    (NormalZ, "NORMAL-Z"),
+    (ShearXY, "SHEAR-XY"),
+    (ShearYZ, "SHEAR-YZ"),
+    (ShearZX, "SHEAR-ZX"),
+    (PrincipalA, "PRINCIPAL A"),
+    (PrincipalB, "PRINCIPAL B"),
+    (PrincipalC, "PRINCIPAL C"),
+    (CosineAX, "COSINE A-X"),
+    (CosineAY, "COSINE A-Y"),
+    (CosineAZ, "COSINE A-Z"),
+    (CosineBX, "COSINE B-X"),
+    (CosineBY, "COSINE B-Y"),
+    (CosineBZ, "COSINE B-Z"),
+    (CosineCX, "COSINE C-X"),
+    (CosineCY, "COSINE C-Y"),
+    (CosineCZ, "COSINE C-Z"),
+    (MeanPressure, "MEAN PRESSURE"),
+    (VonMises, "VON MISES"),
+  ]
+);
+
+impl IndexType for SolidStressField {
+  const INDEX_NAME: &'static str = "SOLID STRESS FIELD";
+}
+
 from_enum!(
   "Generic single-force field.",
   SingleForce,
@@ -0,0 +1,374 @@
+//! This submodule implements metrics computed over the columns of blocks, such
+//! as their extrema and averages, or how a column differs between two blocks.
+
+use std::fmt::Display;
+
+use serde::{Serialize, Deserialize};
+
+use crate::prelude::*;
+
+/// Options for how values are taken in by metrics.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct MetricOptions {
+  /// Values smaller in magnitude than this are taken as zero, so numerical
+  /// noise doesn't skew metrics.
+  pub epsilon: f64,
+  /// Values at least this large in magnitude are taken as unset and skipped
+  /// (see `UNSET_SENTINEL`).
+  #[serde(default)]
+  pub unset_threshold: Option<f64>
+}
+
+impl MetricOptions {
+  /// Takes in a value, returning None if it's unset and snapping it to zero
+  /// if it's below epsilon.
+  fn take(&self, x: f64) -> Option<f64> {
+    if is_unset(x, self.unset_threshold) {
+      return None;
+    }
+    return Some(if x.abs() < self.epsilon { 0.0 } else { x });
+  }
+}
+
+/// Single-column metrics (such as min, max, mean).
+#[derive(
+  Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord
+)]
+pub enum SingleColumnMetric {
+  /// Minimum of a column.
+  Mininum,
+  /// Maximum of a column.
+  Maximum,
+  /// Average value of a column.
+  Average,
+  /// Standard deviation of a column.
+  StandardDeviation
+}
+
+impl SingleColumnMetric {
+  /// Returns all the currently-implemented single-column metrics.
+  pub const fn all() -> &'static [Self] {
+    return &[
+      Self::Mininum,
+      Self::Maximum,
+      Self::Average,
+      Self::StandardDeviation,
+    ];
+  }
+
+  /// Returns a short name for this metric.
+  pub const fn short_name(&self) -> &'static str {
+    return match self {
+      Self::Mininum => "min",
+      Self::Maximum => "max",
+      Self::Average => "avg",
+      Self::StandardDeviation => "sd",
+    };
+  }
+
+  /// Returns a long name for this metric.
+  pub const fn long_name(&self) -> &'static str {
+    return match self {
+      Self::Mininum => "minimum",
+      Self::Maximum => "maximum",
+      Self::Average => "average",
+      Self::StandardDeviation => "standard deviation",
+    };
+  }
+
+  /// Computes this metric over a block and columns.
+  pub fn compute(
+    &self,
+    block: &FinalBlock,
+    col: NasIndex
+  ) -> Option<f64> {
+    return self.compute_with_epsilon(block, col, 0.0);
+  }
+
+  /// Computes this metric over a block and columns, taking values smaller in
+  /// magnitude than epsilon as zero.
+  pub fn compute_with_epsilon(
+    &self,
+    block: &FinalBlock,
+    col: NasIndex,
+    epsilon: f64
+  ) -> Option<f64> {
+    let opts = MetricOptions { epsilon, ..Default::default() };
+    return self.compute_with(block, col, &opts);
+  }
+
+  /// Computes this metric over a block and columns, with options.
+  pub fn compute_with(
+    &self,
+    block: &FinalBlock,
+    col: NasIndex,
+    opts: &MetricOptions
+  ) -> Option<f64> {
+    let nums = block.row_indexes.keys()
+      .filter_map(|r| block.get(*r, col))
+      .filter_map(|x| opts.take(f64::from(x)));
+    match self {
+      Self::Mininum => {
+        return nums.min_by(|a, b| a.total_cmp(b));
+      },
+      Self::Maximum => {
+        return nums.max_by(|a, b| a.total_cmp(b));
+      },
+      Self::Average => {
+        let mut count: usize = 0;
+        let mut total: f64 = 0.0;
+        for num in nums {
+          count += 1;
+          total += num;
+        }
+        if count > 0 {
+          return Some(total/count as f64);
+        } else {
+          return None;
+        }
+      },
+      Self::StandardDeviation => {
+        let avg = Self::Average.compute_with(block, col, opts)?;
+        let mut count: usize = 0;
+        let mut total_qm: f64 = 0.0;
+        for num in nums {
+          count += 1;
+          total_qm += (avg - num).powi(2);
+        }
+        if count > 0 {
+          return Some(f64::sqrt(total_qm/count as f64));
+        } else {
+          return None;
+        }
+      },
+    }
+  }
+}
+
+impl Display for SingleColumnMetric {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return write!(f, "{}", self.long_name());
+  }
+}
+
+/// Column-compare metrics (like the RMSD).
+#[derive(
+  Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord
+)]
+pub enum ColumnCompareMetric {
+  /// Max absolute deviation.
+  MaximumAbsoluteDifference,
+  /// Average absolute deviation.
+  AverageAbsoluteDifference,
+  /// Root mean square deviation.
+  RootMeanSquareDeviation
+}
+
+impl ColumnCompareMetric {
+  /// Returns all the currently-implemented column-compare metrics.
+  pub const fn all() -> &'static [Self] {
+    return &[
+      Self::MaximumAbsoluteDifference,
+      Self::AverageAbsoluteDifference,
+      Self::RootMeanSquareDeviation
+    ];
+  }
+
+  /// Returns a short name for this metric.
+  pub const fn short_name(&self) -> &'static str {
+    return match self {
+      Self::MaximumAbsoluteDifference => "max-abs-diff",
+      Self::AverageAbsoluteDifference => "avg-abs-diff",
+      Self::RootMeanSquareDeviation => "rmsd"
+    };
+  }
+
+  /// Returns a long name for this metric.
+  pub const fn long_name(&self) -> &'static str {
+    return match self {
+      Self::MaximumAbsoluteDifference => "maximum absolute deviation",
+      Self::AverageAbsoluteDifference => "average absolute deviation",
+      Self::RootMeanSquareDeviation => "root mean square deviation",
+    };
+  }
+
+  /// Computes this metric between a column in a reference block and the same
+  /// column in a block under test. Only rows present in both count.
+  ///
+  /// ```
+  /// use std::collections::BTreeMap;
+  /// use f06::prelude::*;
+  /// use nalgebra::DMatrix;
+  ///
+  /// // a one-column (Tx) displacements block with three grid points
+  /// let tx: NasIndex = Dof::try_from(1).unwrap().into();
+  /// let block = |vals: &[f64]| {
+  ///   let row_indexes: BTreeMap<NasIndex, usize> = (0..vals.len())
+  ///     .map(|i| (GridPointRef { gid: i + 1 }.into(), i))
+  ///     .collect();
+  ///   let col_indexes: BTreeMap<NasIndex, usize> = [(tx, 0)].into();
+  ///   return FinalBlock {
+  ///     line_range: None,
+  ///     block_type: BlockType::Displacements,
+  ///     subcase: 1,
+  ///     step: None,
+  ///     complex_form: None,
+  ///     row_indexes,
+  ///     col_indexes,
+  ///     data: Some(DMatrix::from_column_slice(vals.len(), 1, vals).into())
+  ///   };
+  /// };
+  /// let reference = block(&[1.0, 2.0, 3.0]);
+  /// let testing = block(&[1.0, 2.0, 5.0]);
+  /// let rmsd = ColumnCompareMetric::RootMeanSquareDeviation
+  ///   .compute(&reference, &testing, tx)
+  ///   .unwrap();
+  /// assert!((rmsd - f64::sqrt(4.0/3.0)).abs() < 1e-12);
+  /// ```
+  pub fn compute(
+    &self,
+    ref_block: &FinalBlock,
+    test_block: &FinalBlock,
+    col: NasIndex
+  ) -> Option<f64> {
+    return self.compute_with_epsilon(ref_block, test_block, col, 0.0);
+  }
+
+  /// Computes this metric like `compute`, but taking values smaller in
+  /// magnitude than epsilon as zero in both blocks.
+  pub fn compute_with_epsilon(
+    &self,
+    ref_block: &FinalBlock,
+    test_block: &FinalBlock,
+    col: NasIndex,
+    epsilon: f64
+  ) -> Option<f64> {
+    let opts = MetricOptions { epsilon, ..Default::default() };
+    return self.compute_with(ref_block, test_block, col, &opts);
+  }
+
+  /// Returns the rows in both blocks where both values are set, along with
+  /// the values (reference first), with options applied.
+  fn value_pairs<'a>(
+    ref_block: &'a FinalBlock,
+    test_block: &'a FinalBlock,
+    col: NasIndex,
+    opts: &'a MetricOptions
+  ) -> impl Iterator<Item = (NasIndex, f64, f64)> + 'a {
+    return ref_block.row_indexes.keys().filter_map(move |r| {
+      let rval = opts.take(ref_block.get(*r, col)?.into())?;
+      let tval = opts.take(test_block.get(*r, col)?.into())?;
+      return Some((*r, rval, tval));
+    });
+  }
+
+  /// Computes this metric like `compute`, with options. Rows where either
+  /// value is unset are skipped.
+  pub fn compute_with(
+    &self,
+    ref_block: &FinalBlock,
+    test_block: &FinalBlock,
+    col: NasIndex,
+    opts: &MetricOptions
+  ) -> Option<f64> {
+    let nums = Self::value_pairs(ref_block, test_block, col, opts)
+      .map(|(_, r, t)| (r, t));
+    match self {
+      Self::MaximumAbsoluteDifference => {
+        return nums.map(|(r, t)| (r - t).abs())
+          .max_by(|a, b| a.total_cmp(b));
+      },
+      Self::AverageAbsoluteDifference => {
+        let mut count: usize = 0;
+        let mut total: f64 = 0.0;
+        for (r, t) in nums {
+          count += 1;
+          total += (r - t).abs();
+        }
+        if count > 0 {
+          return Some(total/count as f64);
+        } else {
+          return None;
+        }
+      },
+      Self::RootMeanSquareDeviation => {
+        let mut count: usize = 0;
+        let mut total: f64 = 0.0;
+        for (r, t) in nums {
+          count += 1;
+          total += (r - t).powi(2);
+        }
+        if count > 0 {
+          return Some(f64::sqrt(total/count as f64));
+        } else {
+          return None;
+        }
+      },
+    }
+  }
+
+  /// Computes this metric like `compute`, along with the row where the
+  /// values differ the most, so the largest deviation can be looked at. For
+  /// the maximum absolute difference, that's the row it comes from; for the
+  /// others, it's the row contributing the most.
+  ///
+  /// ```
+  /// use f06::prelude::*;
+  /// use nalgebra::DMatrix;
+  ///
+  /// // grid point 2 is the outlier
+  /// let tx: NasIndex = Dof::try_from(1).unwrap().into();
+  /// let block = |vals: &[f64]| FinalBlock {
+  ///   line_range: None,
+  ///   block_type: BlockType::Displacements,
+  ///   subcase: 1,
+  ///   step: None,
+  ///   complex_form: None,
+  ///   row_indexes: (0..vals.len())
+  ///     .map(|i| (GridPointRef { gid: i + 1 }.into(), i))
+  ///     .collect(),
+  ///   col_indexes: [(tx, 0)].into(),
+  ///   data: Some(DMatrix::from_column_slice(vals.len(), 1, vals).into())
+  /// };
+  /// let reference = block(&[1.0, 2.0, 3.0]);
+  /// let testing = block(&[1.0, 4.0, 3.5]);
+  /// let (x, row) = ColumnCompareMetric::MaximumAbsoluteDifference
+  ///   .compute_located(&reference, &testing, tx)
+  ///   .unwrap();
+  /// assert_eq!(x, 2.0);
+  /// assert_eq!(row, GridPointRef { gid: 2 }.into());
+  /// ```
+  pub fn compute_located(
+    &self,
+    ref_block: &FinalBlock,
+    test_block: &FinalBlock,
+    col: NasIndex
+  ) -> Option<(f64, NasIndex)> {
+    let opts = MetricOptions::default();
+    return self.compute_located_with(ref_block, test_block, col, &opts);
+  }
+
+  /// Computes this metric like `compute_located`, with options.
+  pub fn compute_located_with(
+    &self,
+    ref_block: &FinalBlock,
+    test_block: &FinalBlock,
+    col: NasIndex,
+    opts: &MetricOptions
+  ) -> Option<(f64, NasIndex)> {
+    let (worst, row) = Self::value_pairs(ref_block, test_block, col, opts)
+      .map(|(row, r, t)| ((r - t).abs(), row))
+      .max_by(|a, b| a.0.total_cmp(&b.0))?;
+    let value = match self {
+      Self::MaximumAbsoluteDifference => worst,
+      _ => self.compute_with(ref_block, test_block, col, opts)?
+    };
+    return Some((value, row));
+  }
+}
+
+impl Display for ColumnCompareMetric {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return write!(f, "{}", self.long_name());
+  }
+}
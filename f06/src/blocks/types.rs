@@ -3,6 +3,7 @@
 
 use std::fmt::Display;
 
+use clap::ValueEnum;
 use serde::{Serialize, Deserialize};
 use convert_case::{Case, Casing};
 
@@ -99,6 +100,14 @@ gen_block_types!(
     None,
     ["DISPLACEMENTS", "DISPLACEMENT VECTOR"]
   },
+  // complex displacements
+  {
+    "Grid point complex displacements",
+    ComplexDisplacements,
+    ComplexDisplacementsDecoder,
+    None,
+    ["COMPLEX DISPLACEMENT VECTOR"]
+  },
   // grid point force balance
   {
     "Grid point force balance",
@@ -240,6 +249,17 @@ gen_block_types!(
       )
     ]
   },
+  // beam forces
+  {
+    "Engineering forces in beam elements",
+    BeamForces,
+    BeamForcesDecoder,
+    Some(ElementType::Beam),
+    [
+      "FORCES IN BEAM ELEMENTS (CBEAM)",
+      "ELEMENT ENGINEERING FORCES FOR ELEMENT TYPE BEAM"
+    ]
+  },
   // tria forces
   {
     "Engineering forces in triangular elements",
@@ -259,6 +279,7 @@ gen_block_types!(
     Some(ElementType::Tria3),
     [
       "STRESSES IN TRIANGULAR ELEMENTS (CTRIA3)",
+      "STRESSES IN TRIANGULAR ELEMENTS (TRIA6)",
       concat!(
         "ELEMENT STRESSES IN LOCAL ELEMENT COORDINATE SYSTEM ",
         "FOR ELEMENT TYPE TRIA3"
@@ -273,6 +294,7 @@ gen_block_types!(
     Some(ElementType::Tria3),
     [
       "STRAINS IN TRIANGULAR ELEMENTS (CTRIA3)",
+      "STRAINS IN TRIANGULAR ELEMENTS (TRIA6)",
       concat!(
         "ELEMENT STRAINS IN LOCAL ELEMENT COORDINATE SYSTEM ",
         "FOR ELEMENT TYPE TRIA3"
@@ -298,6 +320,7 @@ gen_block_types!(
     Some(ElementType::Quad4),
     [
       "STRESSES IN QUADRILATERAL ELEMENTS (QUAD4)",
+      "STRESSES IN QUADRILATERAL ELEMENTS (QUAD8)",
       concat!(
         "ELEMENT STRESSES IN LOCAL ELEMENT COORDINATE SYSTEM ",
         "FOR ELEMENT TYPE QUAD4"
@@ -312,6 +335,7 @@ gen_block_types!(
     Some(ElementType::Quad4),
     [
       "STRAINS IN QUADRILATERAL ELEMENTS (QUAD4)",
+      "STRAINS IN QUADRILATERAL ELEMENTS (QUAD8)",
       concat!(
         "ELEMENT STRAINS IN LOCAL ELEMENT COORDINATE SYSTEM ",
         "FOR ELEMENT TYPE QUAD4"
@@ -357,6 +381,50 @@ gen_block_types!(
       )
     ]
   },
+  // solid stresses
+  {
+    "Stresses in solid elements",
+    SolidStresses,
+    SolidStressesDecoder,
+    None,
+    [
+      "STRESSES IN HEXAHEDRON SOLID ELEMENTS",
+      "STRESSES IN PENTAHEDRON SOLID ELEMENTS",
+      "STRESSES IN TETRAHEDRON SOLID ELEMENTS"
+    ]
+  },
+  // connector forces
+  {
+    "Engineering forces in CWELD connector elements",
+    ConnectorForces,
+    ConnectorForcesDecoder,
+    Some(ElementType::Weld),
+    ["FORCES IN WELD ELEMENTS (CWELD)"]
+  },
+  // fastener forces
+  {
+    "Engineering forces in CFAST fastener elements",
+    FastenerForces,
+    FastenerForcesDecoder,
+    Some(ElementType::Fast),
+    ["FORCES IN FASTENER ELEMENTS (CFAST)"]
+  },
+  // grid point singularities
+  {
+    "Grid point singularities",
+    Singularities,
+    SingularitiesDecoder,
+    None,
+    ["GRID POINT SINGULARITY TABLE"]
+  },
+  // eigenvectors
+  {
+    "Real eigenvectors",
+    Eigenvectors,
+    EigenvectorsDecoder,
+    None,
+    ["EIGENVECTOR"]
+  },
 );
 
 impl Display for BlockType {
@@ -364,3 +432,14 @@ impl Display for BlockType {
     return write!(f, "{}", self.desc());
   }
 }
+
+// this impl allows passing block types as CLI arguments
+impl ValueEnum for BlockType {
+  fn value_variants<'a>() -> &'a [Self] {
+    return Self::all();
+  }
+
+  fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+    return Some(self.short_name().into());
+  }
+}
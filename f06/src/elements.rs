@@ -19,6 +19,8 @@ pub enum ElementCategory {
   ScalarSpring,
   /// Bushing elements, like BUSH.
   Bushing,
+  /// Connector elements, like WELD.
+  Connector,
   /// One-dimensional elastic elements, like ROD.
   OneDimensionalElastic,
   /// Two-dimensional elastic elements, like QUAD4.
@@ -98,6 +100,9 @@ gen_elems!(
   (Elas4, "ELAS4", ScalarSpring),
   // bushing
   (Bush, "BUSH", Bushing),
+  // connectors
+  (Weld, "WELD", Connector),
+  (Fast, "FAST", Connector),
   // 1D elastic
   (Bar, "BAR", OneDimensionalElastic),
   (Rod, "ROD", OneDimensionalElastic),
@@ -119,6 +124,19 @@ gen_elems!(
   (Hexa, "HEXA", ThreeDimensionalElastic),
 );
 
+impl ElementType {
+  /// For higher-order plate elements, returns how many corner nodes precede
+  /// the midside nodes, both in their connectivity and in their output. For
+  /// elements without midside nodes, returns None.
+  pub const fn midside_offset(&self) -> Option<usize> {
+    return match self {
+      Self::Quad8 => Some(4),
+      Self::Tria6 => Some(3),
+      _ => None
+    };
+  }
+}
+
 impl PartialOrd for ElementType {
   fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
     Some(self.cmp(other))
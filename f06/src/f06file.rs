@@ -1,10 +1,16 @@
 //! This module implements the general structure of an F06 file as we interpret
 //! it, and its submodules are responsible for specific parsing subroutines.
 
+pub mod checks;
 pub mod diff;
+pub mod dump;
 pub mod extraction;
+pub mod fingerprint;
 
 use std::collections::{BTreeSet, BTreeMap};
+use std::fmt::Display;
+use std::num::ParseIntError;
+use std::str::FromStr;
 
 use log::debug;
 use serde::{Serialize, Deserialize};
@@ -12,8 +18,134 @@ use serde::{Serialize, Deserialize};
 use crate::prelude::*;
 use crate::util::*;
 
-/// This type stores a reference to a specific subcase and type (generally
-/// used to refer to a specific block).
+/// A subcase ID. Subcases are plain numbers in the F06, and so are mode
+/// numbers, element IDs and so on; this keeps them from getting mixed up. It
+/// (de)serialises as the bare number.
+#[derive(
+  Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord,
+  Hash
+)]
+#[serde(transparent)]
+pub struct Subcase(pub usize);
+
+impl From<usize> for Subcase {
+  fn from(value: usize) -> Self {
+    return Self(value);
+  }
+}
+
+impl From<Subcase> for usize {
+  fn from(value: Subcase) -> Self {
+    return value.0;
+  }
+}
+
+impl Display for Subcase {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return write!(f, "{}", self.0);
+  }
+}
+
+impl FromStr for Subcase {
+  type Err = ParseIntError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    return s.trim().parse::<usize>().map(Self);
+  }
+}
+
+/// A label to give a subcase, as passed on the command line, in the form
+/// "SUBCASE=LABEL".
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SubcaseRelabel {
+  /// The subcase to label.
+  pub subcase: Subcase,
+  /// Its new label.
+  pub label: String
+}
+
+impl FromStr for SubcaseRelabel {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let bad = || {
+      return format!("bad subcase label \"{}\", expected SUBCASE=LABEL", s);
+    };
+    let (sc, label) = s.split_once('=').ok_or_else(bad)?;
+    let subcase = sc.parse::<Subcase>().map_err(|_| bad())?;
+    let label = label.trim();
+    if label.is_empty() {
+      return Err(bad());
+    }
+    return Ok(Self { subcase, label: label.to_owned() });
+  }
+}
+
+/// A step within a subcase that some output is for, in solutions that write
+/// more than one set of results per subcase. Steps are ordered by kind, then
+/// by value, so that they can key blocks.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum OutputStep {
+  /// A load step, in nonlinear solutions.
+  LoadStep(f64),
+  /// A time, in transient solutions.
+  Time(f64),
+  /// A frequency, in frequency response solutions.
+  Frequency(f64)
+}
+
+impl OutputStep {
+  /// Returns the load step, time or frequency.
+  pub const fn value(&self) -> f64 {
+    return match self {
+      Self::LoadStep(x) | Self::Time(x) | Self::Frequency(x) => *x
+    };
+  }
+
+  /// Returns a number for the kind of step, for ordering.
+  const fn kind(&self) -> u8 {
+    return match self {
+      Self::LoadStep(_) => 0,
+      Self::Time(_) => 1,
+      Self::Frequency(_) => 2
+    };
+  }
+}
+
+impl PartialEq for OutputStep {
+  fn eq(&self, other: &Self) -> bool {
+    return self.cmp(other).is_eq();
+  }
+}
+
+impl Eq for OutputStep {}
+
+impl PartialOrd for OutputStep {
+  fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    return Some(self.cmp(other));
+  }
+}
+
+impl Ord for OutputStep {
+  fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    return self.kind()
+      .cmp(&other.kind())
+      .then(self.value().total_cmp(&other.value()));
+  }
+}
+
+impl Display for OutputStep {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return match self {
+      Self::LoadStep(x) => write!(f, "load step {}", x),
+      Self::Time(x) => write!(f, "time {}", x),
+      Self::Frequency(x) => write!(f, "frequency {}", x)
+    };
+  }
+}
+
+/// This type stores a reference to a specific subcase, type and step
+/// (generally used to refer to a specific block).
 #[derive(
   Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord,
   derive_more::From
@@ -21,9 +153,139 @@ use crate::util::*;
 pub struct BlockRef {
   /// The subcase. A value of 1 is pre-set for when the output file doesn't
   /// ever mention subcases.
-  pub subcase: usize,
+  pub subcase: Subcase,
   /// The type of the block (or blocks).
-  pub block_type: BlockType
+  pub block_type: BlockType,
+  /// The step of the subcase the block is for, in solutions that have them.
+  #[serde(default)]
+  pub step: Option<OutputStep>
+}
+
+impl BlockRef {
+  /// Creates a reference to the blocks of a type in a subcase, without a
+  /// step.
+  pub const fn new(subcase: Subcase, block_type: BlockType) -> Self {
+    return Self { subcase, block_type, step: None };
+  }
+
+  /// Returns this reference, but for a step of the subcase.
+  pub const fn at_step(self, step: Option<OutputStep>) -> Self {
+    return Self { step, ..self };
+  }
+}
+
+impl Display for BlockRef {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    if let Some(step) = self.step {
+      return write!(
+        f,
+        "Subcase {} at {}, {}",
+        self.subcase,
+        step,
+        self.block_type
+      );
+    }
+    return write!(f, "Subcase {}, {}", self.subcase, self.block_type);
+  }
+}
+
+/// What an unsupported potential header seems to be about.
+#[derive(
+  Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord
+)]
+pub enum UnsupportedKind {
+  /// Mentions a known element type, so a decoder could be written for it.
+  ElementResults(ElementType),
+  /// A table we can't make anything of.
+  Unknown
+}
+
+impl UnsupportedKind {
+  /// Categorises the text of a potential header. If more than one element
+  /// type name is in it, the longest one wins (so QUAD4K beats QUAD4).
+  pub fn of_header(text: &str) -> Self {
+    return ElementType::all().iter()
+      .filter(|et| text.contains(et.name()))
+      .max_by_key(|et| et.name().len())
+      .map(|et| Self::ElementResults(*et))
+      .unwrap_or(Self::Unknown);
+  }
+}
+
+impl Display for UnsupportedKind {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return match self {
+      Self::ElementResults(et) => write!(f, "results for {} elements", et),
+      Self::Unknown => write!(f, "unknown tables"),
+    };
+  }
+}
+
+/// A value that was dropped when merging two blocks, because the primary block
+/// already had its row and held a different value.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MergeConflict {
+  /// The row of the value.
+  pub row: NasIndex,
+  /// The column of the value.
+  pub col: NasIndex,
+  /// The value in the primary block, which was kept.
+  pub kept: Option<F06Number>,
+  /// The value in the secondary block, which was dropped.
+  pub dropped: Option<F06Number>
+}
+
+/// What happened when a pair of blocks was merged.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MergeReportEntry {
+  /// The subcase and type of the blocks.
+  pub block_ref: BlockRef,
+  /// The line range of the primary block, if known.
+  pub primary_lines: Option<(usize, usize)>,
+  /// The line range of the secondary block, if known.
+  pub secondary_lines: Option<(usize, usize)>,
+  /// Rows copied from the secondary block into the primary.
+  pub merged: BTreeSet<NasIndex>,
+  /// Rows skipped because the primary block already had them.
+  pub skipped: BTreeSet<NasIndex>,
+  /// Values in skipped rows that differed between the blocks.
+  pub conflicts: Vec<MergeConflict>
+}
+
+/// The lines of a table that wasn't decoded, kept verbatim after its
+/// potential header so they can be looked at later. Only kept if the parser
+/// is told to, since they can take up a lot of memory.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RawBlock {
+  /// The unspaced header text, as in the potential header.
+  pub header: String,
+  /// The lines after the header, as written.
+  pub lines: Vec<String>,
+  /// The subcase where the table appears.
+  pub subcase: usize,
+  /// The first and last lines, header included.
+  pub line_range: (usize, usize)
+}
+
+/// The loads applied in a subcase, as selected in the case control section.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LoadInfo {
+  /// The load set ID.
+  pub load_set: usize,
+  /// The overall scale factor, if the load set is a load combination (LOAD
+  /// card) echoed in the bulk data.
+  pub scale: Option<f64>
+}
+
+impl Display for LoadInfo {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return match self.scale {
+      Some(scale) => {
+        write!(f, "load set {} (scaled by {})", self.load_set, scale)
+      },
+      None => write!(f, "load set {}", self.load_set),
+    };
+  }
 }
 
 /// This is the output of an F06 parser.
@@ -33,14 +295,45 @@ pub struct F06File {
   pub filename: Option<String>,
   /// The flavour of file.
   pub flavour: Flavour,
-  /// The detected blocks.
+  /// The detected blocks, by type and subcase. Each list holds blocks in the
+  /// order they were inserted, and may have several of them (such as the
+  /// pages of a long table) until they're merged; see `insert_block` and
+  /// `merge_blocks`.
   pub blocks: BTreeMap<BlockRef, Vec<FinalBlock>>,
   /// The line numbers for warning messages.
   pub warnings: BTreeMap<usize, String>,
   /// The line numbers for fatal error messages.
   pub fatal_errors: BTreeMap<usize, String>,
   /// Lines with potential, unknown headers, and their line ranges.
-  pub potential_headers: BTreeSet<PotentialHeader>
+  pub potential_headers: BTreeSet<PotentialHeader>,
+  /// The date (and time, if the solver prints it) of the run, as written.
+  #[serde(default)]
+  pub run_timestamp: Option<String>,
+  /// The solver version string, as written.
+  #[serde(default)]
+  pub solver_version: Option<String>,
+  /// What happened in each block merge done so far.
+  #[serde(default)]
+  pub merge_log: Vec<MergeReportEntry>,
+  /// Element connectivity (element ID to grid IDs), read from the bulk data
+  /// echo if the file has one.
+  #[serde(default)]
+  pub connectivity: BTreeMap<usize, Vec<usize>>,
+  /// Element property IDs (element ID to property ID), read from the bulk
+  /// data echo if the file has one.
+  #[serde(default)]
+  pub properties: BTreeMap<usize, usize>,
+  /// The label of each subcase (subcase ID to label), as set in the case
+  /// control section.
+  #[serde(default)]
+  pub subcase_labels: BTreeMap<Subcase, String>,
+  /// The loads applied in each subcase (subcase ID to load info), as set in
+  /// the case control section.
+  #[serde(default)]
+  pub load_info: BTreeMap<Subcase, LoadInfo>,
+  /// The tables that weren't decoded, if the parser was told to keep them.
+  #[serde(default)]
+  pub raw_blocks: Vec<RawBlock>
 }
 
 impl Default for F06File {
@@ -58,11 +351,46 @@ impl F06File {
       blocks: BTreeMap::new(),
       warnings: BTreeMap::new(),
       fatal_errors: BTreeMap::new(),
-      potential_headers: BTreeSet::new()
+      potential_headers: BTreeSet::new(),
+      run_timestamp: None,
+      solver_version: None,
+      merge_log: Vec::new(),
+      connectivity: BTreeMap::new(),
+      properties: BTreeMap::new(),
+      subcase_labels: BTreeMap::new(),
+      load_info: BTreeMap::new(),
+      raw_blocks: Vec::new()
     };
   }
 
-  /// Inserts a new block into the file.
+  /// Inserts a new block into the file, after any others of the same type and
+  /// subcase. It's never merged with those on insertion, so callers building
+  /// files block by block should call `merge_blocks` once they're done.
+  ///
+  /// ```
+  /// use f06::prelude::*;
+  /// use nalgebra::DMatrix;
+  ///
+  /// // two halves of a one-column (Tx) displacements table
+  /// let tx: NasIndex = Dof::try_from(1).unwrap().into();
+  /// let half = |gid: usize| FinalBlock {
+  ///   line_range: None,
+  ///   block_type: BlockType::Displacements,
+  ///   subcase: 1,
+  ///   step: None,
+  ///   complex_form: None,
+  ///   row_indexes: [(GridPointRef { gid }.into(), 0)].into(),
+  ///   col_indexes: [(tx, 0)].into(),
+  ///   data: Some(DMatrix::from_element(1, 1, gid as f64).into())
+  /// };
+  /// let mut f06 = F06File::new();
+  /// f06.insert_block(half(1));
+  /// f06.insert_block(half(2));
+  /// let br = BlockRef::new(Subcase(1), BlockType::Displacements);
+  /// assert_eq!(f06.blocks[&br].len(), 2);
+  /// assert_eq!(f06.merge_blocks(true), 1);
+  /// assert_eq!(f06.blocks[&br][0].row_indexes.len(), 2);
+  /// ```
   pub fn insert_block(&mut self, block: FinalBlock) {
     let br = block.block_ref();
     if let Some(ref mut vec) = self.blocks.get_mut(&br) {
@@ -92,7 +420,12 @@ impl F06File {
   }
 
   /// Merges a vector of blocks having only a mutable reference to that vector.
-  fn merge_block_vec(vec: &mut Vec<FinalBlock>, clean: bool) -> usize {
+  /// Each merge done is recorded in the log.
+  fn merge_block_vec(
+    vec: &mut Vec<FinalBlock>,
+    clean: bool,
+    log: &mut Vec<MergeReportEntry>
+  ) -> usize {
     let mut num_merges = 0;
     let mut new_vec: Vec<FinalBlock> = Vec::new();
     while let Some(primary) = vec.pop() {
@@ -113,14 +446,36 @@ impl F06File {
       if let Some(si) = sio {
         // at least one to merge
         let secondary = vec.remove(si);
+        let skipped = primary.row_conflicts(&secondary);
+        let conflicts = skipped.iter()
+          .flat_map(|r| primary.col_indexes.keys().map(move |c| (*r, *c)))
+          .filter_map(|(row, col)| {
+            let kept = primary.get(row, col);
+            let dropped = secondary.get(row, col);
+            if kept == dropped {
+              return None;
+            }
+            return Some(MergeConflict { row, col, kept, dropped });
+          }).collect::<Vec<_>>();
+        let entry = MergeReportEntry {
+          block_ref: primary.block_ref(),
+          primary_lines: primary.line_range,
+          secondary_lines: secondary.line_range,
+          merged: secondary.row_indexes.keys()
+            .filter(|r| !skipped.contains(r))
+            .copied()
+            .collect(),
+          skipped,
+          conflicts
+        };
         let res = primary.try_merge(secondary);
         let merged = match res {
           Ok(MergeResult::Success { merged }) => merged,
-          Ok(MergeResult::Partial { .. }) => {
-            panic!("partial merge not implemented yet!")
-          },
+          // the primary's rows win, the residue is what got dropped
+          Ok(MergeResult::Partial { merged, .. }) => merged,
           Err(x) => panic!("pre-merge check failed: {:#?}", x)
         };
+        log.push(entry);
         num_merges += 1;
         // put it back since it could have other potential merges
         vec.push(merged);
@@ -129,20 +484,44 @@ impl F06File {
         new_vec.push(primary);
       }
     }
+    // they were popped from the back, so restore the insertion order
+    new_vec.reverse();
     std::mem::swap(&mut new_vec, vec);
     return num_merges;
   }
 
   /// Locates blocks that can be merged and merges them. Returns the number of
-  /// done merges. Clean merges mean no row conflicts.
+  /// done merges. Clean merges mean no row conflicts; otherwise, rows already
+  /// in the primary block win. Each merge is recorded in the merge report.
+  ///
+  /// Only blocks of the same type and subcase, with the same columns and
+  /// scalar type, are merged. The primary block is the one inserted last, so
+  /// in unclean merges, the values inserted last win. Blocks that can't be
+  /// merged are left as they are, in insertion order.
   pub fn merge_blocks(&mut self, clean: bool) -> usize {
-    return self.blocks.values_mut()
-      .map(|v| Self::merge_block_vec(v, clean))
+    let mut log: Vec<MergeReportEntry> = Vec::new();
+    let num_merges = self.blocks.values_mut()
+      .map(|v| Self::merge_block_vec(v, clean, &mut log))
       .sum();
+    self.merge_log.extend(log);
+    return num_merges;
+  }
+
+  /// Returns what happened in each block merge done so far: which rows were
+  /// merged, which were skipped because of conflicts, and the values that
+  /// differed in those.
+  pub fn merge_report(&self) -> &[MergeReportEntry] {
+    return &self.merge_log;
   }
 
   /// Merges the potential headers. Returns the number of merges.
   pub fn merge_potential_headers(&mut self) -> usize {
+    return self.merge_potential_headers_within(0);
+  }
+
+  /// Merges the potential headers, bridging up to a number of lines between
+  /// them. Returns the number of merges.
+  pub fn merge_potential_headers_within(&mut self, max_gap: usize) -> usize {
     let mut new_phs: BTreeSet<PotentialHeader> = BTreeSet::new();
     let mut num_merges: usize = 0;
     while !self.potential_headers.is_empty() {
@@ -151,7 +530,7 @@ impl F06File {
       // take another
       if let Some(second) = self.potential_headers.pop_first() {
         // is the next one compatible?
-        match first.try_merge(second) {
+        match first.try_merge_within(second, max_gap) {
           Ok(merged) => {
             // merged, put it back, continue.
             self.potential_headers.insert(merged);
@@ -181,14 +560,83 @@ impl F06File {
     }
   }
 
-  /// Returns all the subcases.
-  pub fn subcases(&self) -> impl Iterator<Item = usize> {
+  /// Returns all the subcases, in order.
+  pub fn subcases(&self) -> impl Iterator<Item = Subcase> {
     return self.blocks.keys()
-      .map(|k| k.subcase)
-      .collect::<BTreeSet<usize>>()
+      .map(|br| br.subcase)
+      .collect::<BTreeSet<Subcase>>()
       .into_iter();
   }
 
+  /// Returns the subcases whose labels contain some text, ignoring case. Only
+  /// subcases that have blocks are returned.
+  pub fn subcases_labelled(&self, label: &str) -> Vec<Subcase> {
+    let label = label.to_uppercase();
+    return self.subcases()
+      .filter(|sc| {
+        return self.subcase_labels.get(sc)
+          .is_some_and(|l| l.to_uppercase().contains(&label));
+      })
+      .collect();
+  }
+
+  /// Returns the label of a subcase, if it has one.
+  pub fn subcase_label(&self, subcase: Subcase) -> Option<&str> {
+    return self.subcase_labels.get(&subcase).map(String::as_str);
+  }
+
+  /// Sets the label of a subcase, replacing the one read from the file (if
+  /// any), which is returned. Labels set this way are shown by the tools in
+  /// the same way as the ones in the case control section.
+  pub fn set_subcase_label<S: Into<String>>(
+    &mut self,
+    subcase: Subcase,
+    label: S
+  ) -> Option<String> {
+    return self.subcase_labels.insert(subcase, label.into());
+  }
+
+  /// Applies a bunch of subcase labels, in order.
+  pub fn relabel_subcases<'a, I>(&mut self, labels: I)
+  where
+    I: IntoIterator<Item = &'a SubcaseRelabel>
+  {
+    for rl in labels {
+      self.set_subcase_label(rl.subcase, rl.label.as_str());
+    }
+  }
+
+  /// Returns the blocks in a subcase, of all types.
+  pub fn blocks_in(
+    &self,
+    subcase: Subcase,
+    unique: bool
+  ) -> impl Iterator<Item = &FinalBlock> {
+    return self.blocks.iter()
+      .filter(move |(br, v)| br.subcase == subcase && (v.len() == 1 || !unique))
+      .flat_map(|(_, v)| v);
+  }
+
+  /// Returns the blocks of a type in a subcase, outside of any step (none if
+  /// it has no such blocks). Blocks for a step are under their own
+  /// `BlockRef` in `blocks`.
+  pub fn blocks_of(&self, subcase: Subcase, bt: BlockType) -> &[FinalBlock] {
+    return self.blocks.get(&BlockRef::new(subcase, bt))
+      .map_or(&[], Vec::as_slice);
+  }
+
+  /// Returns the block of a type in a subcase, if there's exactly one.
+  pub fn unique_block(
+    &self,
+    subcase: Subcase,
+    bt: BlockType
+  ) -> Option<&FinalBlock> {
+    return match self.blocks_of(subcase, bt) {
+      [block] => Some(block),
+      _ => None
+    };
+  }
+
   /// Returns all the block types.
   pub fn block_types(&self) -> impl Iterator<Item = BlockType> {
     return self.blocks.keys()
@@ -197,15 +645,132 @@ impl F06File {
       .into_iter();
   }
 
+  /// Returns the distinct texts of the potential headers. Call this after
+  /// merging them, so that headers split across lines are whole.
+  pub fn potential_header_texts(&self) -> BTreeSet<&str> {
+    return self.potential_headers.iter()
+      .map(|ph| ph.text.trim())
+      .collect();
+  }
+
+  /// Returns the potential headers for unsupported blocks, categorised by
+  /// whether they look like results for a known element type.
+  pub fn unsupported_report(
+    &self
+  ) -> BTreeMap<UnsupportedKind, Vec<&PotentialHeader>> {
+    let mut report: BTreeMap<UnsupportedKind, Vec<&PotentialHeader>> =
+      BTreeMap::new();
+    for ph in self.potential_headers.iter() {
+      report.entry(UnsupportedKind::of_header(&ph.text))
+        .or_default()
+        .push(ph);
+    }
+    return report;
+  }
+
+  /// Returns the line range of the block holding a row, if it's known. After
+  /// merging, it spans all the pages the block was split across.
+  pub fn row_line_range(
+    &self,
+    br: &BlockRef,
+    row: &NasIndex
+  ) -> Option<(usize, usize)> {
+    return self.blocks.get(br)?
+      .iter()
+      .find(|b| b.row_indexes.contains_key(row))
+      .and_then(|b| b.line_range);
+  }
+
   /// Searches blocks filtering by subcase and/or type.
   pub fn block_search(
     &self,
     type_filter: Option<BlockType>,
-    subcase_filter: Option<usize>,
+    subcase_filter: Option<Subcase>,
     unique: bool
   ) -> impl Iterator<Item = &'_ FinalBlock> {
     return self.all_blocks(unique)
       .filter(move |b| type_filter.map(|t| b.block_type == t).unwrap_or(true))
-      .filter(move |b| subcase_filter.map(|s| b.subcase == s).unwrap_or(true));
+      .filter(move |b| subcase_filter.is_none_or(|s| s == b.subcase.into()));
+  }
+
+  /// Returns the row indexes of all blocks, optionally only in a subcase.
+  fn all_row_indexes(
+    &self,
+    subcase_filter: Option<Subcase>
+  ) -> impl Iterator<Item = &NasIndex> {
+    return self.blocks.iter()
+      .filter(move |(br, _)| subcase_filter.is_none_or(|s| br.subcase == s))
+      .flat_map(|(_, v)| v)
+      .flat_map(|b| b.row_indexes.keys());
+  }
+
+  /// Returns every grid point that appears in a row of any block.
+  pub fn grid_point_ids(&self) -> BTreeSet<GridPointRef> {
+    return self.all_row_indexes(None)
+      .filter_map(NasIndex::grid_point_id)
+      .collect();
+  }
+
+  /// Returns every grid point that appears in a row of any block in a
+  /// subcase.
+  pub fn grid_point_ids_in(&self, subcase: Subcase) -> BTreeSet<GridPointRef> {
+    return self.all_row_indexes(Some(subcase))
+      .filter_map(NasIndex::grid_point_id)
+      .collect();
+  }
+
+  /// Returns every element that appears in a row of any block. An element
+  /// can show up more than once if its type is only known in some blocks.
+  pub fn element_ids(&self) -> BTreeSet<ElementRef> {
+    return self.all_row_indexes(None)
+      .filter_map(NasIndex::element_id)
+      .collect();
+  }
+
+  /// Returns every element that appears in a row of any block in a subcase.
+  pub fn element_ids_in(&self, subcase: Subcase) -> BTreeSet<ElementRef> {
+    return self.all_row_indexes(Some(subcase))
+      .filter_map(NasIndex::element_id)
+      .collect();
+  }
+
+  /// Returns the IDs of the elements that appear in a row of any block, by
+  /// element type. Elements of unknown type are left out.
+  pub fn element_ids_by_type(&self) -> BTreeMap<ElementType, BTreeSet<usize>> {
+    let mut by_type: BTreeMap<ElementType, BTreeSet<usize>> = BTreeMap::new();
+    for eref in self.element_ids() {
+      if let Some(etype) = eref.etype {
+        by_type.entry(etype).or_default().insert(eref.eid);
+      }
+    }
+    return by_type;
+  }
+
+  /// Finds, for each row in the blocks of a type, the subcase where a column
+  /// is largest in magnitude. Returns that subcase and the (signed) value by
+  /// row. Ties go to the lowest subcase, and NaNs are ignored.
+  pub fn envelope(
+    &self,
+    bt: BlockType,
+    col: NasIndex
+  ) -> BTreeMap<NasIndex, (usize, f64)> {
+    let mut env: BTreeMap<NasIndex, (usize, f64)> = BTreeMap::new();
+    for block in self.block_search(Some(bt), None, false) {
+      for row in block.row_indexes.keys() {
+        let Some(x) = block.get(*row, col).map(f64::from) else {
+          continue;
+        };
+        if x.is_nan() {
+          continue;
+        }
+        let governs = env.get(row).is_none_or(|(sc, y)| {
+          x.abs() > y.abs() || (x.abs() == y.abs() && block.subcase < *sc)
+        });
+        if governs {
+          env.insert(*row, (block.subcase, x));
+        }
+      }
+    }
+    return env;
   }
 }
@@ -0,0 +1,140 @@
+//! This module implements physical consistency checks on the results of an
+//! F06 file, meant to flag runs that are obviously wrong.
+
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+use serde::{Serialize, Deserialize};
+
+use crate::prelude::*;
+
+/// Default relative tolerance for the consistency checks.
+pub const DEFAULT_CHECK_TOLERANCE: f64 = 1e-3;
+
+/// The built-in consistency checks.
+#[derive(
+  Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord
+)]
+pub enum CheckKind {
+  /// The applied loads and the single-point constraint forces must cancel
+  /// out, per translational DOF. This assumes both are output in the same
+  /// coordinate system, and moments are not checked since they'd need the
+  /// grid point locations.
+  LoadReactionBalance,
+  /// The forces from all origins acting on each grid point in a grid point
+  /// force balance must sum to zero, per DOF.
+  ForceBalanceResidual
+}
+
+impl Display for CheckKind {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return write!(f, "{}", match self {
+      Self::LoadReactionBalance => "applied loads vs. SPC forces",
+      Self::ForceBalanceResidual => "grid point force balance residual",
+    });
+  }
+}
+
+/// The outcome of a consistency check in a subcase.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CheckResult {
+  /// The subcase checked.
+  pub subcase: Subcase,
+  /// The check that was run.
+  pub kind: CheckKind,
+  /// The largest residual found, in absolute terms.
+  pub residual: f64,
+  /// The magnitude the residual is compared against, which is the largest
+  /// absolute value involved in the check.
+  pub scale: f64,
+  /// Whether the residual was within tolerance.
+  pub passed: bool
+}
+
+impl CheckResult {
+  /// Creates a result, deciding whether it passed with a relative tolerance.
+  fn new(
+    subcase: Subcase,
+    kind: CheckKind,
+    residual: f64,
+    scale: f64,
+    tolerance: f64
+  ) -> Self {
+    let passed = residual <= tolerance * scale.max(f64::MIN_POSITIVE);
+    return Self { subcase, kind, residual, scale, passed };
+  }
+
+  /// Returns the residual relative to the scale.
+  pub fn relative(&self) -> f64 {
+    return if self.scale > 0.0 { self.residual / self.scale } else { 0.0 };
+  }
+}
+
+/// Sums the translational DOF columns of a block over all its rows. Returns
+/// the sums and the largest absolute value summed.
+fn translational_sums(block: &FinalBlock) -> ([f64; 3], f64) {
+  let mut sums = [0.0; 3];
+  let mut largest: f64 = 0.0;
+  for row in block.row_indexes.keys() {
+    for (sum, dof) in sums.iter_mut().zip([DOF_TX, DOF_TY, DOF_TZ]) {
+      if let Some(x) = block.get(*row, dof) {
+        let x = f64::from(x);
+        *sum += x;
+        largest = largest.max(x.abs());
+      }
+    }
+  }
+  return (sums, largest);
+}
+
+impl F06File {
+  /// Runs the built-in consistency checks on every subcase that has the
+  /// blocks they need. Only unique blocks are checked, so merge them first.
+  pub fn consistency_checks(&self, tolerance: f64) -> Vec<CheckResult> {
+    let mut results = Vec::new();
+    for subcase in self.subcases() {
+      // loads vs. reactions
+      let loads = self.unique_block(subcase, BlockType::AppliedForces);
+      let spcs = self.unique_block(subcase, BlockType::SpcForces);
+      if let (Some(loads), Some(spcs)) = (loads, spcs) {
+        let (ls, lmax) = translational_sums(loads);
+        let (ss, smax) = translational_sums(spcs);
+        let residual = ls.iter()
+          .zip(ss.iter())
+          .map(|(l, s)| (l + s).abs())
+          .fold(0.0, f64::max);
+        results.push(CheckResult::new(
+          subcase,
+          CheckKind::LoadReactionBalance,
+          residual,
+          lmax.max(smax),
+          tolerance
+        ));
+      }
+      // grid point force balance
+      let gpfb = self.unique_block(subcase, BlockType::GridPointForceBalance);
+      if let Some(gpfb) = gpfb {
+        let grids = gpfb.row_indexes.keys()
+          .filter_map(NasIndex::grid_point_id)
+          .collect::<BTreeSet<_>>();
+        let residual = grids.into_iter()
+          .filter_map(|g| gpfb.force_balance_residual(g))
+          .flat_map(|r| r.map(f64::abs))
+          .fold(0.0, f64::max);
+        let scale = gpfb.row_indexes.keys()
+          .flat_map(|r| Dof::all().iter().map(|d| gpfb.get(*r, *d)))
+          .flatten()
+          .map(|x| f64::from(x).abs())
+          .fold(0.0, f64::max);
+        results.push(CheckResult::new(
+          subcase,
+          CheckKind::ForceBalanceResidual,
+          residual,
+          scale,
+          tolerance
+        ));
+      }
+    }
+    return results;
+  }
+}
@@ -3,8 +3,10 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Display;
+use std::str::FromStr;
 
 use clap::Args;
+use log::warn;
 use serde::{Serialize, Deserialize};
 
 use crate::prelude::*;
@@ -49,20 +51,246 @@ impl Display for NonCompareReason {
   }
 }
 
+/// How the subcases in the second file of a diff correspond to those in the
+/// first.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SubcaseMap {
+  /// Subcases are matched by number.
+  #[default]
+  Identity,
+  /// Subcases are matched by their order in each file.
+  ByOrder,
+  /// Explicit pairs (first file subcase to second file subcase). Subcases not
+  /// listed are matched by number, unless a pair already took their number,
+  /// in which case they are left out of the comparison.
+  Explicit(BTreeMap<Subcase, Subcase>)
+}
+
+impl SubcaseMap {
+  /// Resolves this into explicit pairs (first file subcase to second file
+  /// subcase) for two files. Unlisted subcases whose numbers were taken by a
+  /// pair are warned about, since they won't be compared.
+  pub fn resolve(
+    &self,
+    a: &F06File,
+    b: &F06File
+  ) -> BTreeMap<Subcase, Subcase> {
+    return match self {
+      Self::Identity => BTreeMap::new(),
+      Self::ByOrder => a.subcases().zip(b.subcases()).collect(),
+      Self::Explicit(pairs) => {
+        let targets = pairs.values().copied().collect::<BTreeSet<_>>();
+        for sc in a.subcases() {
+          if targets.contains(&sc) && !pairs.contains_key(&sc) {
+            warn!(
+              "Subcase {} of the first file is not mapped, but another one \
+              is mapped onto it, so it will not be compared!",
+              sc
+            );
+          }
+        }
+        for sc in b.subcases() {
+          if pairs.contains_key(&sc) && !targets.contains(&sc) {
+            warn!(
+              "Subcase {} of the second file is not mapped onto, but another \
+              one is mapped as it, so it will not be compared!",
+              sc
+            );
+          }
+        }
+        pairs.clone()
+      },
+    };
+  }
+}
+
+impl FromStr for SubcaseMap {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim() {
+      "" | "identity" => return Ok(Self::Identity),
+      "order" => return Ok(Self::ByOrder),
+      _ => {}
+    }
+    let mut pairs: BTreeMap<Subcase, Subcase> = BTreeMap::new();
+    for pair in s.split(',') {
+      let bad = || format!("bad subcase pair \"{}\", expected A=B", pair);
+      let (a, b) = pair.split_once('=').ok_or_else(bad)?;
+      let a = a.parse::<Subcase>().map_err(|_| bad())?;
+      let b = b.parse::<Subcase>().map_err(|_| bad())?;
+      if pairs.values().any(|t| *t == b) {
+        return Err(format!("subcase {} is mapped onto more than once", b));
+      }
+      if pairs.insert(a, b).is_some() {
+        return Err(format!("subcase {} is mapped more than once", a));
+      }
+    }
+    return Ok(Self::Explicit(pairs));
+  }
+}
+
+/// A column alias, for solvers that name the same quantity differently. It
+/// renames columns named like `from` to the column named like `to`, going by
+/// their displayed names and ignoring case.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ColumnAlias {
+  /// The name to rename from.
+  pub from: String,
+  /// The name to rename to, which must be a column in one of the blocks.
+  pub to: String
+}
+
+impl ColumnAlias {
+  /// Applies this alias to a pair of blocks about to be compared. The target
+  /// column is looked up in both blocks, and then renamed to in whichever
+  /// has a column to rename (and doesn't have the target already). Returns
+  /// how many columns were renamed.
+  pub fn apply(&self, a: &mut FinalBlock, b: &mut FinalBlock) -> usize {
+    let named = |block: &FinalBlock, name: &str| block.col_indexes.keys()
+      .find(|c| c.to_string().eq_ignore_ascii_case(name))
+      .copied();
+    let Some(to) = named(a, &self.to).or_else(|| named(b, &self.to)) else {
+      return 0;
+    };
+    let mut renamed = 0;
+    for block in [a, b] {
+      if let Some(from) = named(block, &self.from) {
+        if block.rename_column(from, to) {
+          renamed += 1;
+        }
+      }
+    }
+    return renamed;
+  }
+}
+
+impl FromStr for ColumnAlias {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let bad = || format!("bad column alias \"{}\", expected FROM=TO", s);
+    let (from, to) = s.split_once('=').ok_or_else(bad)?;
+    let (from, to) = (from.trim(), to.trim());
+    if from.is_empty() || to.is_empty() {
+      return Err(bad());
+    }
+    return Ok(Self { from: from.to_owned(), to: to.to_owned() });
+  }
+}
+
 /// This contains the settings for when you need to compare two files.
-#[derive(Copy, Clone, Debug, Serialize, Deserialize, Args)]
+#[derive(Clone, Debug, Serialize, Deserialize, Args)]
 pub struct DiffSettings {
   /// The criteria for comparing numbers.
   #[command(flatten)]
   pub criteria: Criteria,
-  /// What to do with line disjunctions?
-  #[arg(short = 'x')]
-  #[clap(default_value = "zero")]
+  /// What to do with rows found in only one of the files.
+  ///
+  /// "skip" leaves them out, so they never count as flagged; "zero" compares
+  /// them against zeroes, flagging only the values that fail the criteria;
+  /// "flag" flags every value in them as a disjunction, counting towards the
+  /// flag limit. Defaults to "skip" if the files are known to come from
+  /// different solvers (whose element sets often differ), and "zero"
+  /// otherwise.
+  #[arg(short = 'x', long = "disjunction")]
   pub dxn_behaviour: Option<DisjunctionBehaviour>,
   /// Limit for the number of flagged values per block (0 for no limit)
   #[clap(default_value = "0")]
   #[arg(short = 'F')]
-  pub max_flags: Option<usize>
+  pub max_flags: Option<usize>,
+  /// Only compare blocks of this type.
+  #[arg(long = "only-block", ignore_case = true)]
+  pub only_block: Option<BlockType>,
+  /// Only compare blocks in this subcase.
+  #[arg(long = "subcase")]
+  pub only_subcase: Option<Subcase>,
+  /// How subcases in the second file map to those in the first.
+  ///
+  /// Either "order" to match them by order, or pairs like "1=10,2=20". Blocks
+  /// are reported with the first file's subcase numbers.
+  #[arg(long = "subcase-map")]
+  pub subcase_map: Option<SubcaseMap>,
+  /// Derive a von Mises column for plate stress blocks lacking one, so that
+  /// files from solvers that omit it can still be compared. Opt-in.
+  #[arg(long = "derive-von-mises")]
+  #[serde(default)]
+  pub derive_von_mises: bool,
+  /// Relabel the top and bottom sides of plate stress and strain rows by the
+  /// sign of their fibre distance before matching rows, for solvers that
+  /// label them differently. Opt-in.
+  #[arg(long = "canonicalize-sides")]
+  #[serde(default)]
+  pub canonicalize_sides: bool,
+  /// Scale eigenvector mode shapes before comparing them, as solvers
+  /// normalise them differently.
+  ///
+  /// Either "norm" for a unit L2 norm (with the largest component positive),
+  /// or "GRID:DOF" like "7:t3" for that DOF of that grid point to be 1. This
+  /// changes the values compared, so it's opt-in.
+  #[arg(long = "normalize-modes")]
+  #[serde(default)]
+  pub normalize_modes: Option<ModeNormalization>,
+  /// Rename columns before comparing blocks, for solvers that name the same
+  /// quantity differently.
+  ///
+  /// Takes the form "FROM=TO", by column names as displayed (ignoring case),
+  /// like "OCTAHEDRAL=VON MISES". Can be specified more than once.
+  #[arg(long = "col-alias")]
+  #[serde(default)]
+  pub column_aliases: Vec<ColumnAlias>,
+  /// How to handle blocks whose column sets differ.
+  ///
+  /// "exact" doesn't compare them at all; "intersection" compares only the
+  /// columns they have in common, and reports the others as dropped.
+  #[arg(long = "columns", default_value_t)]
+  #[serde(default)]
+  pub compare_mode: CompareMode
+}
+
+impl Default for DiffSettings {
+  fn default() -> Self {
+    return Self {
+      criteria: Criteria::default(),
+      dxn_behaviour: Some(DisjunctionBehaviour::default()),
+      max_flags: Some(0),
+      only_block: None,
+      only_subcase: None,
+      subcase_map: None,
+      derive_von_mises: false,
+      canonicalize_sides: false,
+      normalize_modes: None,
+      column_aliases: Vec::new(),
+      compare_mode: CompareMode::default()
+    };
+  }
+}
+
+impl DiffSettings {
+  /// Fills in the disjunction behaviour if it's unset: skip for files known
+  /// to come from different solvers, and the default otherwise. Returns it.
+  pub fn resolve_dxn_behaviour(
+    &mut self,
+    a: &F06File,
+    b: &F06File
+  ) -> DisjunctionBehaviour {
+    let cross_solver = match (a.flavour.solver, b.flavour.solver) {
+      (Some(sa), Some(sb)) => sa != sb,
+      _ => false
+    };
+    return *self.dxn_behaviour.get_or_insert(if cross_solver {
+      DisjunctionBehaviour::Skip
+    } else {
+      DisjunctionBehaviour::default()
+    });
+  }
+
+  /// Returns whether a block reference passes the narrowing filters.
+  pub fn wants(&self, br: &BlockRef) -> bool {
+    let type_ok = self.only_block.map(|t| t == br.block_type);
+    let subcase_ok = self.only_subcase.map(|s| s == br.subcase);
+    return type_ok.unwrap_or(true) && subcase_ok.unwrap_or(true);
+  }
 }
 
 impl From<DiffSettings> for DataDiffer {
@@ -74,30 +302,133 @@ impl From<DiffSettings> for DataDiffer {
   }
 }
 
-/// This structure holds the differences found between two F06Files.
+/// A subcase whose loads differ between the files of a diff, which usually
+/// means the runs weren't set up the same way.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LoadMismatch {
+  /// The subcase, as numbered in the first file.
+  pub subcase: Subcase,
+  /// The loads in the first file.
+  pub first: LoadInfo,
+  /// The loads in the second file.
+  pub second: LoadInfo
+}
+
+impl LoadMismatch {
+  /// Compares the loads of a subcase in both files, returning a mismatch if
+  /// their load sets differ, or their scales are known for both and differ.
+  pub fn check(
+    subcase: Subcase,
+    first: LoadInfo,
+    second: LoadInfo
+  ) -> Option<Self> {
+    let scales_differ = match (first.scale, second.scale) {
+      (Some(sa), Some(sb)) => sa != sb,
+      _ => false
+    };
+    if first.load_set == second.load_set && !scales_differ {
+      return None;
+    }
+    return Some(Self { subcase, first, second });
+  }
+}
+
+/// This structure holds the differences found between two F06Files. It's
+/// what `f06diff` reports on, and other tools can render it their own way.
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use f06::prelude::*;
+/// use nalgebra::DMatrix;
+///
+/// // a file with a one-column (Tx) displacements block
+/// let tx: NasIndex = Dof::try_from(1).unwrap().into();
+/// let file = |vals: &[f64]| {
+///   let row_indexes: BTreeMap<NasIndex, usize> = (0..vals.len())
+///     .map(|i| (GridPointRef { gid: i + 1 }.into(), i))
+///     .collect();
+///   let mut f06 = F06File::new();
+///   f06.insert_block(FinalBlock {
+///     line_range: None,
+///     block_type: BlockType::Displacements,
+///     subcase: 1,
+///     step: None,
+///     complex_form: None,
+///     row_indexes,
+///     col_indexes: [(tx, 0)].into(),
+///     data: Some(DMatrix::from_column_slice(vals.len(), 1, vals).into())
+///   });
+///   return f06;
+/// };
+/// let a = file(&[1.0, 2.0, 3.0]);
+/// let b = file(&[1.0, 2.0, 3.5]);
+/// let settings = DiffSettings {
+///   criteria: Criteria { difference: Some(0.1), ..Default::default() },
+///   ..Default::default()
+/// };
+/// let diff = F06Diff::compare(&settings, &a, &b);
+/// assert!(diff.not_compared.is_empty());
+/// let br = BlockRef::new(Subcase(1), BlockType::Displacements);
+/// let flags = &diff.compared[&br];
+/// assert_eq!(flags.len(), 1);
+/// assert_eq!(flags[0].values.row, GridPointRef { gid: 3 }.into());
+/// ```
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct F06Diff {
   /// Blocks that were compared and the positions that were flagged.
   pub compared: BTreeMap<BlockRef, Vec<FlaggedPosition>>,
   /// Blocks that were not compared due to their being incompatible.
   pub not_compared: BTreeMap<BlockRef, NonCompareReason>,
+  /// Columns left out of compared blocks, for blocks that had any (see
+  /// `CompareMode::Intersection`).
+  #[serde(default)]
+  pub dropped_columns: BTreeMap<BlockRef, DroppedColumns>,
+  /// Subcases whose loads differ between the files.
+  #[serde(default)]
+  pub load_mismatches: Vec<LoadMismatch>
 }
 
 impl F06Diff {
-  /// Diffs two `F06File`s.
+  /// Diffs two `F06File`s. Only blocks unique in each file are compared, so
+  /// merge (and sort) their blocks first.
   pub fn compare(settings: &DiffSettings, a: &F06File, b: &F06File) -> Self {
     // init inners
     let mut compared: BTreeMap<BlockRef, Vec<FlaggedPosition>>;
     let mut not_compared: BTreeMap<BlockRef, NonCompareReason>;
+    let mut dropped_columns: BTreeMap<BlockRef, DroppedColumns>;
     compared = BTreeMap::new();
     not_compared = BTreeMap::new();
-    let differ: DataDiffer = (*settings).into();
-    let brs = a.blocks.keys().chain(b.blocks.keys()).collect::<BTreeSet<_>>();
+    dropped_columns = BTreeMap::new();
+    let differ: DataDiffer = settings.clone().into();
+    // re-key the second file's blocks with the first file's subcases, leaving
+    // out the ones whose number a pair took
+    let pairs = settings.subcase_map.clone()
+      .unwrap_or_default()
+      .resolve(a, b);
+    let rev = pairs.iter()
+      .map(|(sa, sb)| (*sb, *sa))
+      .collect::<BTreeMap<Subcase, Subcase>>();
+    let mut b_blocks: BTreeMap<BlockRef, Vec<&FinalBlock>> = BTreeMap::new();
+    for (br, v) in b.blocks.iter() {
+      let subcase = match rev.get(&br.subcase) {
+        Some(sa) => *sa,
+        None if pairs.contains_key(&br.subcase) => continue,
+        None => br.subcase
+      };
+      let mapped = BlockRef { subcase, ..*br };
+      b_blocks.entry(mapped).or_default().extend(v.iter());
+    }
+    let brs = a.blocks.keys()
+      .chain(b_blocks.keys())
+      .filter(|br| settings.wants(br))
+      .collect::<BTreeSet<_>>();
     for br in brs {
-      let ta: Vec<FinalBlock> = Vec::new();
-      let tb: Vec<FinalBlock> = Vec::new();
-      let va = a.blocks.get(br).unwrap_or(&ta);
-      let vb = b.blocks.get(br).unwrap_or(&tb);
+      let va: Vec<&FinalBlock> = a.blocks.get(br)
+        .map(|v| v.iter().collect())
+        .unwrap_or_default();
+      let vb: Vec<&FinalBlock> = b_blocks.get(br)
+        .cloned()
+        .unwrap_or_default();
       let afn = a.filename.clone();
       let bfn = b.filename.clone();
       match (va.len(), vb.len()) {
@@ -115,16 +446,45 @@ impl F06Diff {
           );
         },
         (1, 1) => {
-          let block_a = va.first().unwrap();
-          let block_b = vb.first().unwrap();
-          if let Ok(flags) = differ.compare(block_a, block_b) {
-            let mf = settings.max_flags.unwrap_or(0);
-            if mf == 0 {
-              compared.insert(*br, flags.collect());
-            } else {
-              compared.insert(*br, flags.take(mf).collect());
-            }
+          let mut block_a = (*va.first().unwrap()).clone();
+          let mut block_b = (*vb.first().unwrap()).clone();
+          block_b.subcase = block_a.subcase;
+          if settings.derive_von_mises {
+            block_a.derive_von_mises();
+            block_b.derive_von_mises();
           }
+          if settings.canonicalize_sides {
+            block_a.canonicalize_sides();
+            block_b.canonicalize_sides();
+          }
+          if let Some(how) = settings.normalize_modes {
+            block_a.normalize_mode_shape(how);
+            block_b.normalize_mode_shape(how);
+          }
+          for alias in settings.column_aliases.iter() {
+            alias.apply(&mut block_a, &mut block_b);
+          }
+          let dropped = settings.compare_mode
+            .restrict(&mut block_a, &mut block_b);
+          match differ.compare(&block_a, &block_b) {
+            Ok(flags) => {
+              let mf = settings.max_flags.unwrap_or(0);
+              if mf == 0 {
+                compared.insert(*br, flags.collect());
+              } else {
+                compared.insert(*br, flags.take(mf).collect());
+              }
+              if !dropped.is_empty() {
+                dropped_columns.insert(*br, dropped);
+              }
+            },
+            Err(reason) => {
+              not_compared.insert(
+                *br,
+                NonCompareReason::NotCompatible(reason)
+              );
+            }
+          };
         },
         (_, 1) => {
           not_compared.insert(
@@ -146,6 +506,30 @@ impl F06Diff {
         },
       };
     }
-    return Self { compared, not_compared };
+    // check that the same loads were applied
+    let load_mismatches = a.load_info.iter()
+      .filter(|(sa, _)| settings.only_subcase.is_none_or(|s| s == **sa))
+      .filter_map(|(sa, la)| {
+        let sb = pairs.get(sa).copied().unwrap_or(*sa);
+        let lb = b.load_info.get(&sb)?;
+        return LoadMismatch::check(*sa, *la, *lb);
+      })
+      .collect();
+    return Self { compared, not_compared, dropped_columns, load_mismatches };
+  }
+
+  /// Returns all flagged positions across all blocks, most severe first (see
+  /// `FlaggedPosition::severity`). Ties are broken by absolute difference.
+  pub fn ranked(&self) -> Vec<(BlockRef, &FlaggedPosition)> {
+    let mut flags = self.compared.iter()
+      .flat_map(|(br, v)| v.iter().map(|fp| (*br, fp)))
+      .collect::<Vec<_>>();
+    let absdiff = |fp: &FlaggedPosition| {
+      (f64::from(fp.values.val_a) - f64::from(fp.values.val_b)).abs()
+    };
+    flags.sort_by(|a, b| b.1.severity().total_cmp(&a.1.severity())
+      .then(absdiff(b.1).total_cmp(&absdiff(a.1)))
+    );
+    return flags;
   }
 }
@@ -0,0 +1,128 @@
+//! This module implements a plain, self-describing dump of a whole F06 file,
+//! meant to be serialized (to JSON, for example) for use outside of Rust.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Serialize, Deserialize};
+
+use crate::prelude::*;
+use crate::util::PotentialHeader;
+
+/// The values of a block, row-major: the outer vector has one entry per row,
+/// in the same order as the block's row labels, and each of those has one
+/// value per column, in the order of the column labels.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum RowMajorValues {
+  /// Real values.
+  Reals(Vec<Vec<f64>>),
+  /// Integer values.
+  Integers(Vec<Vec<isize>>),
+  /// Natural values.
+  Naturals(Vec<Vec<usize>>)
+}
+
+/// A block, with its data laid out row-major alongside the row and column
+/// labels.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BlockDump {
+  /// The block type.
+  pub block_type: BlockType,
+  /// The subcase where the block appears.
+  pub subcase: usize,
+  /// The line range, if known.
+  pub line_range: Option<(usize, usize)>,
+  /// The row labels, sorted.
+  pub rows: Vec<NasIndex>,
+  /// The column labels, sorted.
+  pub cols: Vec<NasIndex>,
+  /// The values, if the block has any.
+  pub values: Option<RowMajorValues>
+}
+
+impl From<&FinalBlock> for BlockDump {
+  fn from(block: &FinalBlock) -> Self {
+    let rows = block.row_indexes.keys().copied().collect::<Vec<_>>();
+    let cols = block.col_indexes.keys().copied().collect::<Vec<_>>();
+    let ris = block.row_indexes.values().copied().collect::<Vec<_>>();
+    let cis = block.col_indexes.values().copied().collect::<Vec<_>>();
+    macro_rules! row_major {
+      ($m:expr) => {
+        ris.iter()
+          .map(|ri| cis.iter().map(|ci| $m[(*ri, *ci)]).collect())
+          .collect()
+      };
+    }
+    let values = block.data.as_ref().map(|data| match data {
+      FinalDMat::Reals(m) => RowMajorValues::Reals(row_major!(m)),
+      FinalDMat::Integers(m) => RowMajorValues::Integers(row_major!(m)),
+      FinalDMat::Naturals(m) => RowMajorValues::Naturals(row_major!(m)),
+    });
+    return Self {
+      block_type: block.block_type,
+      subcase: block.subcase,
+      line_range: block.line_range,
+      rows,
+      cols,
+      values
+    };
+  }
+}
+
+/// Everything in an F06 file, in a deterministic order: blocks are sorted by
+/// subcase and type, and everything else is kept in sorted maps and sets.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct F06Dump {
+  /// Original name of the file, if known.
+  pub filename: Option<String>,
+  /// The flavour of file.
+  pub flavour: Flavour,
+  /// The solver version string, as written.
+  pub solver_version: Option<String>,
+  /// The date (and time, if the solver prints it) of the run, as written.
+  pub run_timestamp: Option<String>,
+  /// The warning messages, by line number.
+  pub warnings: BTreeMap<usize, String>,
+  /// The fatal error messages, by line number.
+  pub fatal_errors: BTreeMap<usize, String>,
+  /// Lines with potential, unknown headers.
+  pub potential_headers: BTreeSet<PotentialHeader>,
+  /// Element connectivity, from the bulk data echo.
+  pub connectivity: BTreeMap<usize, Vec<usize>>,
+  /// Element property IDs, from the bulk data echo.
+  #[serde(default)]
+  pub properties: BTreeMap<usize, usize>,
+  /// What happened in each block merge done so far.
+  pub merge_log: Vec<MergeReportEntry>,
+  /// The subcase labels, by subcase ID.
+  #[serde(default)]
+  pub subcase_labels: BTreeMap<Subcase, String>,
+  /// The loads applied in each subcase, by subcase ID.
+  #[serde(default)]
+  pub load_info: BTreeMap<Subcase, LoadInfo>,
+  /// The tables that weren't decoded, if kept.
+  #[serde(default)]
+  pub raw_blocks: Vec<RawBlock>,
+  /// The blocks.
+  pub blocks: Vec<BlockDump>
+}
+
+impl From<&F06File> for F06Dump {
+  fn from(file: &F06File) -> Self {
+    return Self {
+      filename: file.filename.clone(),
+      flavour: file.flavour,
+      solver_version: file.solver_version.clone(),
+      run_timestamp: file.run_timestamp.clone(),
+      warnings: file.warnings.clone(),
+      fatal_errors: file.fatal_errors.clone(),
+      potential_headers: file.potential_headers.clone(),
+      connectivity: file.connectivity.clone(),
+      properties: file.properties.clone(),
+      merge_log: file.merge_log.clone(),
+      subcase_labels: file.subcase_labels.clone(),
+      load_info: file.load_info.clone(),
+      raw_blocks: file.raw_blocks.clone(),
+      blocks: file.all_blocks(false).map(BlockDump::from).collect()
+    };
+  }
+}
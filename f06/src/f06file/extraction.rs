@@ -1,5 +1,6 @@
 //! This module implements data structures to specify ways to extract data
-//! subsets from F06 files.
+//! subsets from F06 files. It's what `nastester` uses to pick the data under
+//! test, and it's meant for use by other tools as well.
 
 use std::error::Error;
 use std::fmt::Display;
@@ -248,12 +249,38 @@ impl Error for ExtractionError {}
 
 /// This structure represents a way to extract a subset of the data from an F06
 /// so one can apply comparison criteria to it.
+///
+/// `lookup` gives the index of every datum it selects, and `blockify` gives
+/// the selected data as trimmed-down copies of the blocks.
+///
+/// ```
+/// use std::path::PathBuf;
+/// use f06::prelude::*;
+///
+/// let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+///   .join("../examples/mystran/SB-BUSH-01-OFFSET-2b.F06");
+/// let mut file = OnePassParser::parse_file(path).unwrap();
+/// file.merge_blocks(true);
+/// file.sort_all_blocks();
+/// // all six displacements of grid point 2
+/// let extraction = Extraction {
+///   block_types: Specifier::List(vec![BlockType::Displacements]),
+///   rows: Specifier::List(vec![GridPointRef { gid: 2 }.into()]),
+///   ..Default::default()
+/// };
+/// let indexes = extraction.lookup(&file).collect::<Vec<DatumIndex>>();
+/// assert_eq!(indexes.len(), 6);
+/// assert!(indexes.iter().all(|ix| ix.get_from(&file).is_ok()));
+/// let blocks = extraction.blockify(&file);
+/// assert_eq!(blocks.len(), 1);
+/// assert_eq!(blocks[0].row_indexes.len(), 1);
+/// ```
 #[derive(
-  Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default
+  Debug, Clone, Serialize, Deserialize, PartialEq, Default
 )]
 pub struct Extraction {
   /// Subcases to get data from.
-  pub subcases: Specifier<usize>,
+  pub subcases: Specifier<Subcase>,
   /// Block types to get data from.
   pub block_types: Specifier<BlockType>,
   /// Grid point filter (filters out grid points if present).
@@ -266,11 +293,74 @@ pub struct Extraction {
   pub cols: Specifier<NasIndex>,
   /// Raw column filter (for ease of separation).
   pub raw_cols: Specifier<usize>,
+  /// Column type filter, by the `IndexType::INDEX_NAME` of the columns (e.g.
+  /// "DOF"), so a whole family of columns can be picked across block types.
+  #[serde(default)]
+  pub col_types: Specifier<String>,
+  /// Grid adjacency filter: element rows and columns are kept only if the
+  /// element is connected to a grid point this accepts, going by the file's
+  /// connectivity (so elements missing from it are dropped unless this is
+  /// `All`). Indexes without an element are not affected.
+  #[serde(default)]
+  pub adjacent_grids: Specifier<GridPointRef>,
+  /// Property filter, by property ID: element rows and columns are kept only
+  /// if the element's property is accepted, going by the file's property IDs
+  /// (so elements missing from them are dropped unless this is `All`).
+  /// Indexes without an element are not affected.
+  #[serde(default)]
+  pub properties: Specifier<usize>,
+  /// Magnitude threshold: if set, rows are kept only if at least one of the
+  /// selected columns has a value whose magnitude exceeds it. Handy to pick
+  /// the most stressed elements without listing their IDs.
+  #[serde(default)]
+  pub value_threshold: Option<f64>,
   /// What to do in case of disjunctions.
   pub dxn: DisjunctionBehaviour
 }
 
 impl Extraction {
+  /// Applies the grid adjacency filter to an index.
+  fn adjacency_filter(&self, file: &F06File, ix: &NasIndex) -> bool {
+    if matches!(self.adjacent_grids, Specifier::All) {
+      return true;
+    }
+    return match ix.element_id() {
+      Some(eref) => file.connectivity.get(&eref.eid).is_some_and(
+        |gids| gids.iter().any(
+          |gid| self.adjacent_grids.filter_fn(&GridPointRef { gid: *gid })
+        )
+      ),
+      None => true,
+    };
+  }
+
+  /// Applies the property filter to an index.
+  fn property_filter(&self, file: &F06File, ix: &NasIndex) -> bool {
+    return match ix.element_id() {
+      Some(eref) => self.properties.lax_filter(
+        &file.properties.get(&eref.eid).copied()
+      ),
+      None => true,
+    };
+  }
+
+  /// Applies the magnitude threshold to a row of a block, going by the
+  /// selected columns.
+  fn threshold_filter(
+    &self,
+    block: &FinalBlock,
+    row: &NasIndex,
+    cols: &[NasIndex]
+  ) -> bool {
+    return match self.value_threshold {
+      Some(threshold) => cols.iter().any(|ci| {
+        block.get(*row, *ci)
+          .is_some_and(|v| f64::from(v).abs() > threshold)
+      }),
+      None => true,
+    };
+  }
+
   /// Produces an iterator over the indices resulting from applying an
   /// extraction to a file. This assumes the file has already had its blocks
   /// sorted and merged.
@@ -279,28 +369,33 @@ impl Extraction {
     file: &'f F06File
   ) -> impl Iterator<Item = DatumIndex> + 'f {
     return file.all_blocks(true)
-      .filter(|b| self.subcases.filter_fn(&b.subcase))
+      .filter(|b| self.subcases.filter_fn(&b.block_ref().subcase))
       .filter(|b| self.block_types.filter_fn(&b.block_type))
       .flat_map(|b| {
-        let rows = b.row_indexes.keys()
-          .filter(|ri| self.rows.filter_fn(ri))
-          .filter(|ri| self.grid_points.lax_filter(&ri.grid_point_id()))
-          .filter(|ri| self.elements.lax_filter(&ri.element_id()));
         let cols = b.col_indexes.keys()
           .filter(|ci| self.cols.filter_fn(ci))
           .filter(|ci| self.grid_points.lax_filter(&ci.grid_point_id()))
           .filter(|ci| self.elements.lax_filter(&ci.element_id()))
           .filter(
             |ci| self.raw_cols.filter_fn(b.col_indexes.get(ci).unwrap())
-          );
-        return rows.cartesian_product(cols).map(|(ri, ci)| DatumIndex {
-          block_ref: BlockRef {
-            subcase: b.subcase,
-            block_type: b.block_type
-          },
-          row: *ri,
-          col: *ci
-        })
+          )
+          .filter(|ci| self.col_types.filter_fn(&ci.type_name().to_owned()))
+          .filter(|ci| self.adjacency_filter(file, ci))
+          .filter(|ci| self.property_filter(file, ci))
+          .copied()
+          .collect::<Vec<_>>();
+        let rows = b.row_indexes.keys()
+          .filter(|ri| self.rows.filter_fn(ri))
+          .filter(|ri| self.grid_points.lax_filter(&ri.grid_point_id()))
+          .filter(|ri| self.elements.lax_filter(&ri.element_id()))
+          .filter(|ri| self.adjacency_filter(file, ri))
+          .filter(|ri| self.property_filter(file, ri))
+          .filter(|ri| self.threshold_filter(b, ri, &cols))
+          .copied()
+          .collect::<Vec<_>>();
+        return rows.into_iter().cartesian_product(cols).map(|(row, col)| {
+          return DatumIndex { block_ref: b.block_ref(), row, col };
+        });
       })
   }
 
@@ -308,16 +403,10 @@ impl Extraction {
   pub fn blockify(&self, file: &F06File) -> Vec<FinalBlock> {
     let mut subs: Vec<FinalBlock> = Vec::new();
     let compatible_blocks = file.all_blocks(true)
-      .filter(|b| self.subcases.filter_fn(&b.subcase))
+      .filter(|b| self.subcases.filter_fn(&b.block_ref().subcase))
       .filter(|b| self.block_types.filter_fn(&b.block_type));
     for block in compatible_blocks {
       let mut clone = block.clone();
-      let rows: Vec<NasIndex> = clone.row_indexes.keys()
-        .filter(|ri| self.rows.filter_fn(ri))
-        .filter(|ri| self.grid_points.lax_filter(&ri.grid_point_id()))
-        .filter(|ri| self.elements.lax_filter(&ri.element_id()))
-        .copied()
-        .collect();
       let cols: Vec<NasIndex> = clone.col_indexes.keys()
         .filter(|ci| self.cols.filter_fn(ci))
         .filter(|ci| self.grid_points.lax_filter(&ci.grid_point_id()))
@@ -325,6 +414,18 @@ impl Extraction {
         .filter(
           |ci| self.raw_cols.filter_fn(clone.col_indexes.get(ci).unwrap())
         )
+        .filter(|ci| self.col_types.filter_fn(&ci.type_name().to_owned()))
+        .filter(|ci| self.adjacency_filter(file, ci))
+        .filter(|ci| self.property_filter(file, ci))
+        .copied()
+        .collect();
+      let rows: Vec<NasIndex> = clone.row_indexes.keys()
+        .filter(|ri| self.rows.filter_fn(ri))
+        .filter(|ri| self.grid_points.lax_filter(&ri.grid_point_id()))
+        .filter(|ri| self.elements.lax_filter(&ri.element_id()))
+        .filter(|ri| self.adjacency_filter(file, ri))
+        .filter(|ri| self.property_filter(file, ri))
+        .filter(|ri| self.threshold_filter(block, ri, &cols))
         .copied()
         .collect();
       clone.row_indexes.retain(|ri, _| rows.contains(ri));
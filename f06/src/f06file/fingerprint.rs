@@ -0,0 +1,131 @@
+//! This module implements content fingerprints of F06 files: hashes of their
+//! decoded results that don't depend on where they came from, so two runs
+//! that produced the same results can be told apart from ones that didn't
+//! without diffing them.
+
+use crate::prelude::*;
+
+/// The number of significant digits reals are rounded to before hashing.
+pub const FINGERPRINT_DIGITS: usize = 10;
+
+/// A 64-bit FNV-1a hasher. It's used instead of the standard library's
+/// hashers since those aren't guaranteed to stay the same across releases.
+struct Fnv64(u64);
+
+impl Fnv64 {
+  /// The FNV-1a offset basis.
+  const OFFSET: u64 = 0xcbf29ce484222325;
+  /// The FNV-1a prime.
+  const PRIME: u64 = 0x00000100000001b3;
+
+  /// Starts a new hash.
+  fn new() -> Self {
+    return Self(Self::OFFSET);
+  }
+
+  /// Feeds bytes into the hash.
+  fn bytes(&mut self, bytes: &[u8]) {
+    for b in bytes {
+      self.0 ^= u64::from(*b);
+      self.0 = self.0.wrapping_mul(Self::PRIME);
+    }
+  }
+
+  /// Feeds a string into the hash, terminated so that consecutive strings
+  /// can't run into each other.
+  fn str(&mut self, s: &str) {
+    self.bytes(s.as_bytes());
+    self.bytes(&[0]);
+  }
+
+  /// Feeds a number into the hash.
+  fn u64(&mut self, x: u64) {
+    self.bytes(&x.to_le_bytes());
+  }
+}
+
+/// Writes a value the way it's hashed: reals are rounded to
+/// `FINGERPRINT_DIGITS` significant digits in scientific notation, with
+/// negative zero as zero, and integers are written as they are.
+fn fingerprint_value(value: F06Number) -> String {
+  return match value {
+    // this matches negative zero too
+    F06Number::Real(0.0) => "0".to_owned(),
+    F06Number::Real(x) => format!("{:.*e}", FINGERPRINT_DIGITS - 1, x),
+    F06Number::Integer(i) => i.to_string(),
+    F06Number::Natural(n) => n.to_string(),
+  };
+}
+
+/// Writes an index the way it's hashed: the name of its type, then the way
+/// it's displayed. Unlike the Debug form, both are part of the interface, so
+/// they're kept stable.
+fn fingerprint_index(index: NasIndex) -> String {
+  return format!("{}: {}", index.type_name(), index);
+}
+
+/// Writes a step the way it's hashed: the number of its kind, then its value
+/// as any other real.
+fn fingerprint_step(step: OutputStep) -> String {
+  let value = fingerprint_value(step.value().into());
+  return format!("step {}: {}", step.kind(), value);
+}
+
+impl FinalBlock {
+  /// Returns a fingerprint of the block's type, subcase, step, indexes and
+  /// values (see `F06File::content_fingerprint`).
+  pub fn content_fingerprint(&self) -> u64 {
+    let mut h = Fnv64::new();
+    h.str(self.block_type.short_name());
+    h.u64(self.subcase as u64);
+    // blocks without a step hash as they did before steps existed
+    if let Some(step) = self.step {
+      h.str(&fingerprint_step(step));
+    }
+    // the index maps are sorted, so the matrix layout doesn't matter
+    for col in self.col_indexes.keys() {
+      h.str(&fingerprint_index(*col));
+    }
+    h.str("");
+    for row in self.row_indexes.keys() {
+      h.str(&fingerprint_index(*row));
+      for col in self.col_indexes.keys() {
+        match self.get(*row, *col) {
+          Some(v) => h.str(&fingerprint_value(v)),
+          None => h.str("-"),
+        };
+      }
+    }
+    return h.0;
+  }
+}
+
+impl F06File {
+  /// Returns a fingerprint of the decoded results: the type, subcase, step,
+  /// indexes and values of every block. Anything else, like the file name,
+  /// line numbers, warnings and the order of blocks, rows and columns, is
+  /// left out. Reals are rounded to `FINGERPRINT_DIGITS` significant digits
+  /// first, so noise in their last bits doesn't change it.
+  ///
+  /// Equal fingerprints mean the results are almost certainly the same. The
+  /// hash is 64-bit FNV-1a, which doesn't change across builds or platforms,
+  /// so fingerprints can be stored and compared later. Blocks are hashed as
+  /// they are, so merge them first if they might have been split differently.
+  pub fn content_fingerprint(&self) -> u64 {
+    let mut h = Fnv64::new();
+    for (br, blocks) in self.blocks.iter() {
+      if let Some(step) = br.step {
+        h.str(&fingerprint_step(step));
+      }
+      let mut fps = blocks.iter()
+        .map(|b| b.content_fingerprint())
+        .collect::<Vec<_>>();
+      fps.sort();
+      h.u64(fps.len() as u64);
+      for fp in fps {
+        h.u64(fp);
+      }
+    }
+    return h.0;
+  }
+}
@@ -3,6 +3,8 @@
 
 use std::fmt::Display;
 
+use clap::builder::PossibleValue;
+use clap::{Args, ValueEnum};
 use serde::{Serialize, Deserialize};
 
 use crate::prelude::BlockType;
@@ -37,6 +39,14 @@ impl Solver {
     };
   }
 
+  /// Returns a short, lowercase name for the solver, used in the CLI.
+  pub const fn short_name(&self) -> &'static str {
+    return match self {
+      Solver::Mystran => "mystran",
+      Solver::Simcenter => "simcenter"
+    };
+  }
+
   /// Returns an array of "block ending" strings tht we should test for.
   pub const fn block_enders(&self) -> &'static [&'static str] {
     return match self {
@@ -59,6 +69,20 @@ impl Solver {
   }
 }
 
+impl ValueEnum for Solver {
+  fn value_variants<'a>() -> &'a [Self] {
+    return Self::all();
+  }
+
+  fn to_possible_value(&self) -> Option<PossibleValue> {
+    let pv = PossibleValue::new(self.short_name());
+    return Some(match self {
+      Solver::Mystran => pv,
+      Solver::Simcenter => pv.aliases(["nx", "nastran"]),
+    });
+  }
+}
+
 /// The known solution types.
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[non_exhaustive]
@@ -107,7 +131,51 @@ impl Display for SolType {
   }
 }
 
+impl ValueEnum for SolType {
+  fn value_variants<'a>() -> &'a [Self] {
+    return Self::all();
+  }
+
+  fn to_possible_value(&self) -> Option<PossibleValue> {
+    let pv = PossibleValue::new(self.short_name()).aliases(self.aliases());
+    return Some(pv);
+  }
+}
+
 impl SolType {
+  /// Returns all known solution types.
+  pub const fn all() -> &'static [Self] {
+    return &[
+      Self::LinearStatic,
+      Self::Eigenvalue,
+      Self::LinearStaticDiffStiff,
+      Self::LinearBuckling,
+      Self::NonLinearStatic
+    ];
+  }
+
+  /// Returns a short, lowercase name for the solution, used in the CLI.
+  pub const fn short_name(&self) -> &'static str {
+    return match self {
+      SolType::LinearStatic => "static",
+      SolType::Eigenvalue => "modes",
+      SolType::LinearStaticDiffStiff => "diffstiff",
+      SolType::LinearBuckling => "buckling",
+      SolType::NonLinearStatic => "nlstatic",
+    };
+  }
+
+  /// Returns the hidden aliases (the SOL numbers) for the solution.
+  pub const fn aliases(&self) -> &'static [&'static str] {
+    return match self {
+      SolType::LinearStatic => &["1", "101"],
+      SolType::Eigenvalue => &["3", "103"],
+      SolType::LinearStaticDiffStiff => &["4", "104"],
+      SolType::LinearBuckling => &["5", "105"],
+      SolType::NonLinearStatic => &["106"],
+    };
+  }
+
   /// Returns a user-friendly display name for the solution.
   pub const fn name(&self) -> &'static str {
     return match self {
@@ -124,10 +192,17 @@ impl SolType {
 
 /// This structure encapsulates what we currently take to be the "flavour" of
 /// F06 file.
-#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+///
+/// When used as CLI arguments, it's a hint for files whose banner is missing,
+/// such as fragments piped through standard input.
+#[derive(
+  Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default, Args
+)]
 pub struct Flavour {
   /// The solver that produced the file, if known.
+  #[arg(long = "solver", ignore_case = true)]
   pub solver: Option<Solver>,
   /// The solution type that resulted in the file, if known.
+  #[arg(long = "soltype", ignore_case = true)]
   pub soltype: Option<SolType>
 }
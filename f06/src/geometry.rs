@@ -1,6 +1,8 @@
 //! This module defines basic geometric concepts to understand Nastran output.
 
 use std::fmt::Display;
+use clap::builder::PossibleValue;
+use clap::ValueEnum;
 use nalgebra::{Vector3, Scalar};
 use serde::{Deserialize, Serialize};
 
@@ -206,6 +208,42 @@ impl Dof {
   pub const fn name(&self) -> [char; 2] {
     return [self.dof_type.letter(), self.axis.letter()];
   }
+
+  /// Returns the numbered short name for the DOF, like t1 or r3.
+  pub const fn short_name(&self) -> &'static str {
+    return match (self.dof_type, self.axis) {
+      (DofType::Translational, Axis::X) => "t1",
+      (DofType::Translational, Axis::Y) => "t2",
+      (DofType::Translational, Axis::Z) => "t3",
+      (DofType::Rotational, Axis::X) => "r1",
+      (DofType::Rotational, Axis::Y) => "r2",
+      (DofType::Rotational, Axis::Z) => "r3",
+    };
+  }
+
+  /// Returns the lettered short name for the DOF, like tx or rz.
+  pub const fn letter_name(&self) -> &'static str {
+    return match (self.dof_type, self.axis) {
+      (DofType::Translational, Axis::X) => "tx",
+      (DofType::Translational, Axis::Y) => "ty",
+      (DofType::Translational, Axis::Z) => "tz",
+      (DofType::Rotational, Axis::X) => "rx",
+      (DofType::Rotational, Axis::Y) => "ry",
+      (DofType::Rotational, Axis::Z) => "rz",
+    };
+  }
+}
+
+impl ValueEnum for Dof {
+  fn value_variants<'a>() -> &'a [Self] {
+    return Self::all();
+  }
+
+  fn to_possible_value(&self) -> Option<PossibleValue> {
+    return Some(
+      PossibleValue::new(self.short_name()).alias(self.letter_name())
+    );
+  }
 }
 
 /// Holds some kind of data for every degree of freedom.
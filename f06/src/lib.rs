@@ -20,6 +20,10 @@ pub mod f06file;
 pub mod flavour;
 pub mod geometry;
 pub mod parser;
+pub mod punch;
+pub mod selfcheck;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod util;
 
 /// Prelude module; includes commonly-used public exports.
@@ -27,14 +31,20 @@ pub mod prelude {
   pub use crate::blocks::*;
   pub use crate::blocks::compare::*;
   pub use crate::blocks::indexing::*;
+  pub use crate::blocks::metrics::*;
   pub use crate::blocks::types::*;
   pub use crate::elements::*;
   pub use crate::f06file::*;
+  pub use crate::f06file::checks::*;
   pub use crate::f06file::extraction::*;
   pub use crate::f06file::diff::*;
+  pub use crate::f06file::dump::*;
+  pub use crate::f06file::fingerprint::*;
   pub use crate::flavour::*;
   pub use crate::geometry::*;
   pub use crate::parser::*;
+  pub use crate::punch::*;
+  pub use crate::selfcheck::*;
 }
 
 #[cfg(test)]
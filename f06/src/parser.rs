@@ -1,17 +1,109 @@
 //! This module implements the generic parser for F06 files, and associated
 //! structures and enums.
 
+use std::borrow::Cow;
 use std::collections::{BTreeSet, BTreeMap};
+use std::error::Error;
+use std::fmt::Display;
 use std::fs::File;
 use std::io::{self, BufReader, BufRead};
+use std::panic::{self, AssertUnwindSafe};
 use std::path::Path;
+use std::str::FromStr;
 
+use clap::Args;
 use log::{debug, error, warn};
+use memmap2::Mmap;
 use serde::{Serialize, Deserialize};
 
 use crate::prelude::*;
 use crate::util::*;
 
+/// A sensible number of lines between progress reports when parsing with
+/// `parse_bufread_with_progress` and friends.
+pub const PROGRESS_INTERVAL: usize = 1_000_000;
+
+/// This is the kind of error that can be returned when parsing a whole file.
+/// Problems within the file that still let it be parsed are kept in the
+/// `F06File` instead, as warnings, potential headers and so on.
+#[derive(Debug)]
+pub enum ParseError {
+  /// The input could not be opened or read.
+  Io(io::Error),
+  /// The input doesn't look like text (it has NUL bytes at this line).
+  Decode {
+    /// The line with the bad bytes.
+    line: usize
+  },
+  /// The input ended before a single line, e.g. a solver that crashed
+  /// before writing anything.
+  Truncated,
+  /// The input was read, but its solver could not be detected, and none was
+  /// hinted.
+  UnknownSolver,
+  /// The input is a self-check CSV, and it couldn't be loaded.
+  SelfCheck(SelfCheckError)
+}
+
+impl Display for ParseError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return match self {
+      Self::Io(e) => write!(f, "I/O error: {}", e),
+      Self::Decode { line } => write!(f, "not a text file (line {})", line),
+      Self::Truncated => write!(f, "input is empty"),
+      Self::UnknownSolver => write!(f, "could not detect the solver"),
+      Self::SelfCheck(e) => write!(f, "bad self-check file: {}", e),
+    };
+  }
+}
+
+impl ParseError {
+  /// Returns a hint on how to get past the error, for command-line tools to
+  /// show after it, if there's one.
+  pub fn hint(&self) -> Option<&'static str> {
+    return match self {
+      Self::UnknownSolver => Some(
+        "If the file lacks its solver banner, hint it with --solver."
+      ),
+      _ => None
+    };
+  }
+}
+
+impl Error for ParseError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    return match self {
+      Self::Io(e) => Some(e),
+      Self::SelfCheck(e) => Some(e),
+      _ => None
+    };
+  }
+}
+
+impl From<SelfCheckError> for ParseError {
+  fn from(value: SelfCheckError) -> Self {
+    return Self::SelfCheck(value);
+  }
+}
+
+impl From<io::Error> for ParseError {
+  fn from(value: io::Error) -> Self {
+    return Self::Io(value);
+  }
+}
+
+impl From<ParseError> for io::Error {
+  fn from(value: ParseError) -> Self {
+    return match value {
+      ParseError::Io(e) => e,
+      ParseError::Decode { .. } => {
+        io::Error::new(io::ErrorKind::InvalidData, value)
+      },
+      _ => io::Error::other(value),
+    };
+  }
+}
+
 /// A parser might respond this when successfully decoding a line.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[non_exhaustive]
@@ -35,11 +127,129 @@ pub enum ParserResponse {
   BeginBlock(BlockType),
   /// The line was passed to a block decoder.
   PassedToDecoder(BlockType, LineResponse),
+  /// The line echoed an element's connectivity. Contains the element ID.
+  Connectivity(usize),
   /// The line was a block beginning, but I didn't instantiate a block decoder
   /// because we don't even know the solver yet!
   BeginningWithoutSolver,
   /// This line indicates the beginning of a block we don't even know yet.
-  PotentialHeader
+  PotentialHeader,
+  /// The line started with a comment prefix, so it was skipped.
+  Comment,
+  /// This line told us the vibration mode of the modal results that follow.
+  Mode(usize),
+  /// This line set the label of the current subcase.
+  Label,
+  /// This line set the load set of the current subcase.
+  LoadSet(usize),
+  /// This line told us the step of the current subcase that the results
+  /// that follow are for.
+  Step(OutputStep)
+}
+
+/// What an "OUTPUT FOR ..." line (or a page header) says the output that
+/// follows is for.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum OutputContext {
+  /// A subcase.
+  Subcase(usize),
+  /// A vibration mode, within modal results.
+  Mode(usize),
+  /// A step of the current subcase.
+  Step(OutputStep)
+}
+
+/// Settings that change how the parser behaves. When used as CLI arguments,
+/// they're flattened into the tool's own.
+#[derive(
+  Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default, Args
+)]
+pub struct ParserSettings {
+  /// Stop decoding blocks at the first fatal error, since results after it
+  /// are meaningless. Warnings and fatals are still recorded.
+  #[arg(long = "stop-on-fatal")]
+  #[serde(default)]
+  pub stop_on_fatal: bool,
+  /// Skip lines starting with this prefix, such as comments injected by a
+  /// wrapper script. They still count towards line numbers. Can be
+  /// specified more than once.
+  #[arg(long = "comment-prefix")]
+  #[serde(default)]
+  pub comment_prefixes: Vec<String>,
+  /// Keep the lines after each potential header verbatim, as raw blocks, so
+  /// tables that weren't decoded can be looked at. Off by default, since
+  /// they can take up a lot of memory.
+  #[arg(long = "keep-raw-blocks")]
+  #[serde(default)]
+  pub keep_raw_blocks: bool,
+  /// Tag the elements of blocks whose header contains some text, ignoring
+  /// case, with an element type, for headers that have it wrong or missing.
+  /// Takes the form TEXT=ETYPE, like "(QUAD8)=QUAD4". Can be specified more
+  /// than once; the first override that matches a header wins.
+  #[arg(long = "etype-override", value_name = "TEXT=ETYPE")]
+  #[serde(default)]
+  pub etype_overrides: Vec<EtypeOverride>
+}
+
+impl ParserSettings {
+  /// Returns true if a line starts with one of the comment prefixes.
+  fn is_comment(&self, line: &str) -> bool {
+    return self.comment_prefixes.iter()
+      .any(|p| !p.is_empty() && line.starts_with(p.as_str()));
+  }
+}
+
+/// An element type override: blocks whose header contains the text, ignoring
+/// case, have their elements tagged with the element type, instead of the one
+/// read from the header (if any).
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EtypeOverride {
+  /// The text to look for in headers.
+  pub text: String,
+  /// The element type to tag with.
+  pub etype: ElementType
+}
+
+impl EtypeOverride {
+  /// Returns the element type of the first override whose text is in a
+  /// header, if any.
+  pub fn find(overrides: &[Self], header: &str) -> Option<ElementType> {
+    let header = header.to_uppercase();
+    return overrides.iter()
+      .find(|o| header.contains(&o.text.to_uppercase()))
+      .map(|o| o.etype);
+  }
+}
+
+impl FromStr for EtypeOverride {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let bad = || format!(
+      "bad element type override \"{}\", expected TEXT=ETYPE",
+      s
+    );
+    let (text, etype) = s.rsplit_once('=').ok_or_else(bad)?;
+    if text.trim().is_empty() {
+      return Err(bad());
+    }
+    let etype = ElementType::from_str(&etype.trim().to_uppercase())
+      .map_err(|_| format!("unknown element type \"{}\"", etype.trim()))?;
+    return Ok(Self { text: text.trim().to_owned(), etype });
+  }
+}
+
+/// Runs a call into a decoder, catching any panic so that one bad block can't
+/// take the whole parse down with it. Returns the panic message, if any.
+fn catch_decoder_panic<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+  return panic::catch_unwind(AssertUnwindSafe(f)).map_err(|e| {
+    if let Some(s) = e.downcast_ref::<&str>() {
+      return s.to_string();
+    }
+    return e.downcast_ref::<String>()
+      .cloned()
+      .unwrap_or("unknown panic".to_owned());
+  });
 }
 
 /// This is the F06 parser -- it doesn't care how lines are fed into it.
@@ -57,8 +267,23 @@ pub struct OnePassParser {
   last_block_start: usize,
   /// Accumulator of block header strings.
   header_accumulator: Vec<String>,
-  /// Stores last indexes per block type.
-  last_indexes: BTreeMap<BlockType, NasIndex>
+  /// Stores last indexes per block type, within the current subcase and mode,
+  /// so tables continued across pages pick up where they left off.
+  last_indexes: BTreeMap<BlockType, NasIndex>,
+  /// What decoders should do with repeated rows.
+  duplicate_policy: DuplicateRowPolicy,
+  /// Whether we're within the bulk data echo.
+  in_bulk_echo: bool,
+  /// The settings in use.
+  settings: ParserSettings,
+  /// Whether block decoding was stopped by a fatal error.
+  stopped: bool,
+  /// The current vibration mode, within modal results.
+  mode: Option<usize>,
+  /// The current step of the subcase, in solutions that have them.
+  step: Option<OutputStep>,
+  /// The raw block being filled in, if keeping them.
+  raw_block: Option<RawBlock>
 }
 
 impl Default for OnePassParser {
@@ -77,7 +302,14 @@ impl OnePassParser {
       total_lines: 0,
       last_block_start: 0,
       header_accumulator: Vec::new(),
-      last_indexes: BTreeMap::new()
+      last_indexes: BTreeMap::new(),
+      duplicate_policy: DuplicateRowPolicy::default(),
+      in_bulk_echo: false,
+      settings: ParserSettings::default(),
+      stopped: false,
+      mode: None,
+      step: None,
+      raw_block: None
     };
   }
 
@@ -87,6 +319,17 @@ impl OnePassParser {
     self.file.flavour.soltype = self.file.flavour.soltype.or(flavour.soltype);
   }
 
+  /// Sets how the parser behaves.
+  pub fn apply_settings(&mut self, settings: ParserSettings) {
+    self.settings = settings;
+  }
+
+  /// Sets what to do when a block lists the same row twice. By default, a
+  /// warning is logged and the last occurrence is kept.
+  pub fn set_duplicate_policy(&mut self, policy: DuplicateRowPolicy) {
+    self.duplicate_policy = policy;
+  }
+
   /// Tries to update the solver in based on a line.
   fn detect_solver(&self, line: &str) -> Option<Solver> {
     if self.file.flavour.solver.is_none() {
@@ -99,27 +342,195 @@ impl OnePassParser {
     return None;
   }
 
-  /// Tries to detect a change in subcase.
-  fn detect_subcase(&self, line: &str) -> Option<usize> {
-    let bd: Vec<_> = line_breakdown(line).collect();
-    if line.contains("OUTPUT FOR SUBCASE") {
-      return bd.into_iter()
-        .filter_map(|field| {
-          if let LineField::Integer(x) = field {
-            return Some(x as usize)
-          } else {
-            None
-          }
-      }).nth(0);
+  /// Keeps track of the bulk data echo and reads element connectivity and
+  /// property IDs from it. Returns the element ID if the line was a
+  /// connectivity card.
+  ///
+  /// The echo starts after "BEGIN BULK" (or Simcenter's echo title, which is
+  /// repeated on every page of it) and ends at ENDDATA, at a page header or
+  /// at anything that doesn't look like a card, since solvers don't always
+  /// echo the bulk data.
+  fn detect_connectivity(&mut self, line: &str) -> Option<usize> {
+    if !self.in_bulk_echo {
+      if line.contains("BULK") || line.contains("B U L K") {
+        let compact = line.split_whitespace().collect::<String>();
+        self.in_bulk_echo = compact.ends_with("BEGINBULK")
+          || compact.contains("BULKDATAECHO");
+      }
+      return None;
+    }
+    if line.starts_with('1') || !bulk_echo_line(line) {
+      self.in_bulk_echo = false;
+      return None;
+    }
+    if line.contains("ENDDATA") {
+      self.in_bulk_echo = false;
+      return None;
+    }
+    if let Some((eid, pid)) = property_card(line) {
+      self.file.properties.insert(eid, pid);
+    }
+    if let Some((sid, scale)) = load_card(line) {
+      self.file.load_info.values_mut()
+        .filter(|li| li.load_set == sid)
+        .for_each(|li| li.scale = Some(scale));
+    }
+    let (eid, grids) = connectivity_card(line)?;
+    self.file.connectivity.insert(eid, grids);
+    return Some(eid);
+  }
+
+  /// Tries to pick up the solver version and the run's timestamp from a line.
+  /// These are kept as written, since each solver formats them differently.
+  fn detect_provenance(&mut self, line: &str) {
+    /// Month names, as in Simcenter's page headers.
+    const MONTHS: &[&str] = &[
+      "JANUARY", "FEBRUARY", "MARCH", "APRIL", "MAY", "JUNE", "JULY",
+      "AUGUST", "SEPTEMBER", "OCTOBER", "NOVEMBER", "DECEMBER"
+    ];
+    /// Collapses runs of whitespace into single spaces.
+    fn collapse(s: &str) -> String {
+      return s.split_whitespace().collect::<Vec<_>>().join(" ");
+    }
+    if self.file.solver_version.is_none() {
+      // MYSTRAN: " MYSTRAN Version 15.1.4   Dec 20 2023 ..."
+      // Simcenter: "* *     Simcenter Nastran 2021.1    * *"
+      let after = ["MYSTRAN Version ", "Simcenter Nastran "].iter()
+        .find_map(|p| line.find(p).map(|i| &line[i+p.len()..]));
+      let version = after.and_then(|rest| rest.split_whitespace().next())
+        .filter(|v| v.starts_with(|c: char| c.is_ascii_digit()));
+      if let Some(v) = version {
+        debug!("Line {} told us the version is {}.", self.total_lines, v);
+        self.file.solver_version = Some(v.to_owned());
+      }
+    }
+    if self.file.run_timestamp.is_none() {
+      // MYSTRAN: " >> MYSTRAN BEGIN  : 12/31/2023 at 15:22:40.469 The ..."
+      if line.contains("MYSTRAN BEGIN") {
+        if let Some((_, rest)) = line.split_once(':') {
+          let stamp = rest.split("The input").next().unwrap_or(rest);
+          self.file.run_timestamp = Some(collapse(stamp));
+        }
+      }
+      // Simcenter page headers: "... JANUARY  22, 2024  SIMCENTER NASTRAN"
+      if let Some(i) = line.find("SIMCENTER NASTRAN") {
+        let before = &line[..i];
+        let start = MONTHS.iter().filter_map(|m| before.rfind(m)).max();
+        if let Some(start) = start {
+          self.file.run_timestamp = Some(collapse(&before[start..]));
+        }
+      }
+    }
+  }
+
+  /// Tries to detect what the output that follows is for, from the "OUTPUT
+  /// FOR ..." lines MYSTRAN writes, the "FREQUENCY = x" lines above
+  /// Simcenter's frequency response tables and the "SUBCASE n" that ends
+  /// Simcenter's page headers.
+  fn detect_output_context(line: &str) -> Option<OutputContext> {
+    // this runs on every line, so don't tokenise the ones that can't matter
+    let cues = ["FREQUENCY", "OUTPUT FOR", "SUBCASE"];
+    if !cues.iter().any(|c| line.contains(c)) {
+      return None;
+    }
+    if let Some(rest) = line.trim_start().strip_prefix("FREQUENCY =") {
+      let fields = line_breakdown(rest).collect::<Vec<_>>();
+      if let [LineField::Real(x)] = fields[..] {
+        return Some(OutputContext::Step(OutputStep::Frequency(x)));
+      }
+    }
+    if let Some((_, rest)) = line.split_once("OUTPUT FOR ") {
+      let rest = rest.trim_start();
+      let natural = || line_breakdown(rest).find_map(|field| {
+        if let LineField::Integer(x) = field {
+          return usize::try_from(x).ok();
+        } else {
+          return None;
+        }
+      });
+      let number = || line_breakdown(rest).find_map(|field| {
+        return match field {
+          LineField::Integer(x) => Some(x as f64),
+          LineField::Real(x) => Some(x),
+          _ => None
+        };
+      });
+      if rest.starts_with("SUBCASE") {
+        return natural().map(OutputContext::Subcase);
+      } else if rest.starts_with("EIGENVECTOR") {
+        return natural().map(OutputContext::Mode);
+      } else if rest.starts_with("LOAD STEP") {
+        return number().map(|x| OutputContext::Step(OutputStep::LoadStep(x)));
+      } else if rest.starts_with("TIME") {
+        return number().map(|x| OutputContext::Step(OutputStep::Time(x)));
+      } else if rest.starts_with("FREQUENCY") {
+        return number().map(|x| OutputContext::Step(OutputStep::Frequency(x)));
+      }
     }
+    if !line.contains("SUBCASE") {
+      return None;
+    }
+    let bd: Vec<_> = line_breakdown(line).collect();
     if let Some(LineField::Integer(sc)) = bd.last() {
       if let Some(LineField::NoIdea("SUBCASE")) = bd.iter().rev().nth(1) {
-        return Some(*sc as usize);
+        return Some(OutputContext::Subcase(*sc as usize));
       }
     }
     return None;
   }
 
+  /// Tries to detect a subcase label in the case control echo, which looks
+  /// like "LABEL = TEXT", with a line number before it in Simcenter's echo.
+  /// Returns the label, if it's not empty.
+  fn detect_label(line: &str) -> Option<&str> {
+    let rest = line.trim_start()
+      .trim_start_matches(|c: char| c.is_ascii_digit())
+      .trim_start()
+      .strip_prefix("LABEL")?
+      .trim_start()
+      .strip_prefix('=')?
+      .trim();
+    return Some(rest).filter(|l| !l.is_empty());
+  }
+
+  /// Tries to detect a subcase's load set in the case control echo, which
+  /// looks like "LOAD = ID", with a line number before it in Simcenter's
+  /// echo. Returns the load set ID.
+  fn detect_load_set(line: &str) -> Option<usize> {
+    return line.trim_start()
+      .trim_start_matches(|c: char| c.is_ascii_digit())
+      .trim_start()
+      .strip_prefix("LOAD")?
+      .trim_start()
+      .strip_prefix('=')?
+      .trim()
+      .parse::<usize>()
+      .ok();
+  }
+
+  /// Tries to detect the vibration mode of the modal results that follow.
+  /// MYSTRAN writes "OUTPUT FOR EIGENVECTOR n" before each one, and Simcenter
+  /// a spaced-out "R E A L   E I G E N V E C T O R   N O .   n" title. Returns
+  /// the mode and whether the line was such a title. Takes the line's output
+  /// context, as already detected.
+  fn detect_mode(
+    line: &str,
+    context: Option<OutputContext>
+  ) -> Option<(usize, bool)> {
+    if let Some(OutputContext::Mode(mode)) = context {
+      return Some((mode, false));
+    }
+    if !line.contains("E I G E N") && !line.contains("EIGENVECTOR") {
+      return None;
+    }
+    let compact = line.split_whitespace().collect::<String>();
+    let (_, rest) = compact.split_once("EIGENVECTORNO.")?;
+    let digits = rest.chars()
+      .take_while(char::is_ascii_digit)
+      .collect::<String>();
+    return digits.parse().ok().map(|mode| (mode, true));
+  }
+
   /// Flushes the current block decoder into the file.
   fn flush_decoder(&mut self) {
     if let Some(dec) = self.current_decoder.take() {
@@ -129,16 +540,99 @@ impl OnePassParser {
         self.total_lines
       );
       let line_range = Some((self.last_block_start, self.total_lines+1));
+      let bt = dec.block_type();
       if let Some(li) = dec.last_index() {
-        self.last_indexes.insert(dec.block_type(), li);
+        self.last_indexes.insert(bt, li);
       }
-      let fb = dec.finalise(self.subcase, line_range);
-      if !fb.row_indexes.is_empty() {
-        self.file.insert_block(fb);
+      match catch_decoder_panic(|| dec.finalise(self.subcase, line_range)) {
+        Ok(mut fb) if !fb.row_indexes.is_empty() => {
+          fb.step = self.step;
+          self.file.insert_block(fb);
+        },
+        Ok(_) => {},
+        Err(msg) => self.decoder_panicked(bt, msg)
+      };
+    }
+  }
+
+  /// Records a potential header and, if keeping raw blocks, starts one
+  /// after it. The current line is the first one after the header.
+  fn push_potential_header(
+    &mut self,
+    text: String,
+    num_lines: usize,
+    line: &str
+  ) {
+    let start = self.total_lines - num_lines;
+    if self.settings.keep_raw_blocks {
+      self.raw_block = Some(RawBlock {
+        header: text.clone(),
+        lines: Vec::new(),
+        subcase: self.subcase,
+        line_range: (start, self.total_lines - 1)
+      });
+      self.push_raw_line(line);
+    }
+    self.file.potential_headers.insert(PotentialHeader {
+      start,
+      span: num_lines,
+      text,
+    });
+    debug!(
+      "Found a potential header ending in line {}! Flushing.",
+      self.total_lines
+    );
+  }
+
+  /// Adds a line to the current raw block, if any. Blank lines don't count
+  /// towards its line range, since they're dropped from the end.
+  fn push_raw_line(&mut self, line: &str) {
+    if let Some(ref mut raw) = self.raw_block {
+      if !line.trim().is_empty() {
+        raw.line_range.1 = self.total_lines;
       }
+      raw.lines.push(line.to_owned());
     }
   }
 
+  /// Finishes up the current raw block, if any, dropping trailing blank
+  /// lines.
+  fn flush_raw_block(&mut self) {
+    if let Some(mut raw) = self.raw_block.take() {
+      while raw.lines.last().is_some_and(|l| l.trim().is_empty()) {
+        raw.lines.pop();
+      }
+      self.file.raw_blocks.push(raw);
+    }
+  }
+
+  /// Drops the current decoder after it panicked, and notes that down as a
+  /// warning in the current line.
+  fn decoder_panicked(&mut self, bt: BlockType, msg: String) {
+    self.current_decoder = None;
+    let text = format!("decoding {} failed ({}), block skipped", bt, msg);
+    warn!("In line {}, {}!", self.total_lines, text);
+    self.push_warning(text);
+  }
+
+  /// Records a warning in the current line, after any already there.
+  fn push_warning(&mut self, text: String) {
+    self.file.warnings.entry(self.total_lines)
+      .and_modify(|w| {
+        w.push_str("; ");
+        w.push_str(&text);
+      })
+      .or_insert(text);
+  }
+
+  /// Starts a block with some decoder, as if its header had just been read.
+  #[cfg(test)]
+  pub(crate) fn start_decoder(&mut self, dec: Box<dyn OpaqueDecoder>) {
+    self.flush_decoder();
+    self.last_block_start = self.total_lines;
+    self.current_decoder = Some(dec);
+  }
+
   /// Flushes the current block header accumulator.
   fn flush_header(&mut self) -> Option<(String, usize)> {
     if self.header_accumulator.is_empty() {
@@ -154,14 +648,29 @@ impl OnePassParser {
   /// Consumes a line into the parser.
   pub fn consume(&mut self, line: &str) -> ParserResponse {
     self.total_lines += 1;
+    if self.settings.is_comment(line) {
+      return ParserResponse::Comment;
+    }
+    // some files break pages with form feeds, which stick to the first word.
+    let line: Cow<str> = if line.contains(FORM_FEED) {
+      Cow::Owned(line.replace(FORM_FEED, ""))
+    } else {
+      Cow::Borrowed(line)
+    };
+    let line: &str = &line;
     // first, try and enhance our knowledge of the flavour from the line.
+    self.detect_provenance(line);
+    if let Some(eid) = self.detect_connectivity(line) {
+      return ParserResponse::Connectivity(eid);
+    }
     if let Some(solver) = self.detect_solver(line) {
       self.file.flavour.solver = Some(solver);
       debug!("Line {} told us the solver is {}!", self.total_lines, solver);
       return ParserResponse::Solver(solver);
     }
     // check for a subcase change
-    if let Some(subcase) = self.detect_subcase(line) {
+    let context = Self::detect_output_context(line);
+    if let Some(OutputContext::Subcase(subcase)) = context {
       if self.subcase != subcase {
         // a subcase change definitely means we should stop the block
         self.flush_decoder();
@@ -171,15 +680,55 @@ impl OnePassParser {
           subcase,
           self.total_lines
         );
+        self.flush_raw_block();
         self.subcase = subcase;
+        self.mode = None;
+        self.step = None;
+        // tables never continue into another subcase
+        self.last_indexes.clear();
       }
       self.flush_header();
       return ParserResponse::Subcase(subcase);
     }
+    // check for a step change, within a subcase
+    if let Some(OutputContext::Step(step)) = context {
+      if self.step != Some(step) {
+        self.flush_decoder();
+        self.flush_raw_block();
+        self.last_indexes.clear();
+        debug!("Output for {} starts on line {}.", step, self.total_lines);
+      }
+      self.step = Some(step);
+      self.flush_header();
+      return ParserResponse::Step(step);
+    }
+    // check for the current subcase's label
+    if !self.in_bulk_echo {
+      if let Some(label) = Self::detect_label(line) {
+        debug!("Line {} labels subcase {}.", self.total_lines, self.subcase);
+        self.file.subcase_labels
+          .entry(self.subcase.into())
+          .or_insert_with(|| label.to_owned());
+        self.flush_header();
+        return ParserResponse::Label;
+      }
+      if let Some(load_set) = Self::detect_load_set(line) {
+        debug!(
+          "Line {} sets the loads of subcase {}.",
+          self.total_lines,
+          self.subcase
+        );
+        self.file.load_info
+          .entry(self.subcase.into())
+          .or_insert(LoadInfo { load_set, scale: None });
+        self.flush_header();
+        return ParserResponse::LoadSet(load_set);
+      }
+    }
     // check for warning
     if line.contains("WARNING") {
       debug!("Found warning on line {}: {}", self.total_lines, line);
-      self.file.warnings.insert(self.total_lines, line.to_string());
+      self.push_warning(line.to_string());
       self.flush_header();
       return ParserResponse::Warning;
     }
@@ -188,21 +737,55 @@ impl OnePassParser {
       debug!("Found fatal on line {}: {}", self.total_lines, line);
       self.file.fatal_errors.insert(self.total_lines, line.to_string());
       self.flush_header();
+      if self.settings.stop_on_fatal && !self.stopped {
+        // keep what was decoded up to here, but nothing after
+        self.flush_decoder();
+        self.stopped = true;
+        debug!("Stopped decoding blocks at line {}.", self.total_lines);
+      }
       return ParserResponse::Fatal;
     }
+    if self.stopped {
+      return ParserResponse::Useless;
+    }
+    // check for a mode change, in modal results
+    if let Some((mode, title)) = Self::detect_mode(line, context) {
+      if self.mode != Some(mode) {
+        self.flush_decoder();
+        self.last_indexes.clear();
+        debug!("Mode {} starts on line {}.", mode, self.total_lines);
+      }
+      self.mode = Some(mode);
+      self.flush_header();
+      if title {
+        // the title isn't spaced out like the others, so push it ourselves
+        self.header_accumulator.push(format!("REAL EIGENVECTOR NO. {}", mode));
+        return ParserResponse::BlockHeader;
+      }
+      return ParserResponse::Mode(mode);
+    }
     // check for a block header part.
     if let Some(unspaced) = check_header(line) {
       self.header_accumulator.push(unspaced);
       return ParserResponse::BlockHeader;
     } else if let Some((full_name, num_lines)) = self.flush_header() {
       // not a block header, but we were accumulating one.
-      // first, flush the current decoder.
+      // first, flush the current decoder and raw block.
       self.flush_decoder();
+      self.flush_raw_block();
       // is it the header of a known block?
+      // when a header contains another (as "COMPLEX DISPLACEMENT VECTOR"
+      // contains "DISPLACEMENT VECTOR"), the longest one wins.
+      let matched = |bt: &BlockType| bt.headers()
+        .iter()
+        .filter(|s| full_name.contains(*s))
+        .map(|s| s.len())
+        .max();
+      let longest = BlockType::all().iter().filter_map(matched).max();
       let mut candidates = BlockType::all()
         .iter()
         .copied()
-        .filter(|bt| bt.headers().iter().any(|s| full_name.contains(s)))
+        .filter(|bt| longest.is_some() && matched(bt) == longest)
         .collect::<BTreeSet<_>>();
       match candidates.len() {
         0 => {
@@ -211,19 +794,15 @@ impl OnePassParser {
           if BAD_WORDS.iter().any(|w| full_name.contains(w)) {
             return ParserResponse::Useless;
           }
-          self.file.potential_headers.insert(PotentialHeader {
-            start: self.total_lines-num_lines,
-            span: num_lines,
-            text: full_name,
-          });
-          debug!(
-            "Found a potential header ending in line {}! Flushing.",
-            self.total_lines
-          );
+          self.push_potential_header(full_name, num_lines, line);
           return ParserResponse::PotentialHeader;
         },
         1 => {
-          let bt = candidates.pop_first().unwrap();
+          let mut bt = candidates.pop_first().unwrap();
+          // displacements within modal results are mode shapes
+          if bt == BlockType::Displacements && self.mode.is_some() {
+            bt = BlockType::Eigenvectors;
+          }
           // do we know the solver?
           if self.file.flavour.solver.is_none() {
             // nope
@@ -235,24 +814,34 @@ impl OnePassParser {
           } else {
             // ok, begin the block then.
             let mut dec = bt.init_decoder(self.file.flavour);
+            dec.set_duplicate_policy(self.duplicate_policy);
             if dec.good_header(&full_name) {
-              debug!("Started a \"{}\" block on line {}!", bt, self.total_lines);
-              if let Some(li) = self.last_indexes.remove(&dec.block_type()) {
-                dec.hint_last(li);
+              let overrides = &self.settings.etype_overrides;
+              if let Some(etype) = EtypeOverride::find(overrides, &full_name) {
+                dec.override_etype(etype);
               }
+              debug!("Started a \"{}\" block on line {}!", bt, self.total_lines);
+              // keep the hint around in case this page has no rows, so the
+              // next one still gets it
+              let last = self.last_indexes.get(&dec.block_type()).copied();
+              let mode = self.mode;
+              let hinted = catch_decoder_panic(|| {
+                if let Some(li) = last {
+                  dec.hint_last(li);
+                }
+                if let Some(mode) = mode {
+                  dec.hint_mode(mode);
+                }
+              });
               self.last_block_start = self.total_lines;
               self.current_decoder = Some(dec);
+              if let Err(msg) = hinted {
+                self.decoder_panicked(bt, msg);
+                return ParserResponse::PassedToDecoder(bt, LineResponse::Abort);
+              }
             } else if !BAD_WORDS.iter().any(|w| full_name.contains(w)) {
               // bad header, whoops.
-              self.file.potential_headers.insert(PotentialHeader {
-                start: self.total_lines-num_lines,
-                span: num_lines,
-                text: full_name,
-              });
-              debug!(
-                "Found a potential header ending in line {}! Flushing.",
-                self.total_lines
-              );
+              self.push_potential_header(full_name, num_lines, line);
               return ParserResponse::PotentialHeader;
             }
           }
@@ -275,7 +864,14 @@ impl OnePassParser {
           LineResponse::Done
         } else {
           // no block ender, pass to decoder
-          dec.consume(line)
+          match catch_decoder_panic(|| dec.consume(line)) {
+            Ok(resp) => resp,
+            Err(msg) => {
+              let bt = dec.block_type();
+              self.decoder_panicked(bt, msg);
+              return ParserResponse::PassedToDecoder(bt, LineResponse::Abort);
+            }
+          }
         }
       } else {
         // no solver but we're in a block?!
@@ -288,6 +884,16 @@ impl OnePassParser {
       }
       return ParserResponse::PassedToDecoder(bt, resp);
     }
+    // otherwise, it may be part of a table we can't decode.
+    if self.raw_block.is_some() {
+      let ender = self.file.flavour.solver
+        .is_some_and(|s| s.block_enders().iter().any(|e| line.contains(e)));
+      if ender {
+        self.flush_raw_block();
+      } else {
+        self.push_raw_line(line);
+      }
+    }
     // well, the line was useless then.
     return ParserResponse::Useless;
   }
@@ -295,41 +901,189 @@ impl OnePassParser {
   /// Finishes up and returns the file struct.
   pub fn finish(mut self) -> F06File {
     self.flush_decoder();
+    self.flush_raw_block();
     return self.file;
   }
 
   /// Parses from a BufRead instance.
-  pub fn parse_bufread<R: BufRead>(mut reader: R) -> io::Result<F06File> {
+  pub fn parse_bufread<R: BufRead>(reader: R) -> Result<F06File, ParseError> {
+    return Self::parse_bufread_hinted(reader, Flavour::default());
+  }
+
+  /// Parses from a BufRead instance, hinting the flavour before any lines are
+  /// consumed. Useful for fragments without the solver's banner.
+  pub fn parse_bufread_hinted<R: BufRead>(
+    reader: R,
+    hint: Flavour
+  ) -> Result<F06File, ParseError> {
+    return Self::parse_bufread_with(reader, hint, ParserSettings::default());
+  }
+
+  /// Parses from a BufRead instance, hinting the flavour and with some
+  /// settings applied.
+  pub fn parse_bufread_with<R: BufRead>(
+    reader: R,
+    hint: Flavour,
+    settings: ParserSettings
+  ) -> Result<F06File, ParseError> {
+    return Self::parse_bufread_with_progress(reader, hint, settings, 0, |_| {});
+  }
+
+  /// Same as `parse_bufread_with`, but calls `on_progress` with the number
+  /// of lines read so far every `every` lines (e.g. `PROGRESS_INTERVAL`), so
+  /// long parses can show progress. It's never called if `every` is zero.
+  pub fn parse_bufread_with_progress<R: BufRead, F: FnMut(usize)>(
+    mut reader: R,
+    hint: Flavour,
+    settings: ParserSettings,
+    every: usize,
+    mut on_progress: F
+  ) -> Result<F06File, ParseError> {
     let mut parser = Self::new();
+    parser.hint_flavour(hint);
+    parser.apply_settings(settings);
     let mut buf = vec![];
-    while reader.read_until(b'\n', &mut buf).is_ok() {
+    loop {
+      reader.read_until(b'\n', &mut buf)?;
       if buf.is_empty() {
         break;
       }
       buf.pop();
-      let line = String::from_utf8_lossy(&buf);
-      match parser.consume(&line) {
-        ParserResponse::PassedToDecoder(bt, lr) if lr.abnormal() => warn!(
-          "Got abnormal response {:?} from {} while parsing line {}!",
-          lr,
-          bt,
-          parser.total_lines
-        ),
-        ParserResponse::BeginningWithoutSolver => warn!(
-          "Found block beginning in line {} before detecting the solver!",
-          parser.total_lines
-        ),
-        _ => {}
-      }
+      parser.consume_bytes(&buf)?;
       buf.clear();
+      if every > 0 && parser.total_lines.is_multiple_of(every) {
+        on_progress(parser.total_lines);
+      }
+    }
+    return parser.finish_checked();
+  }
+
+  /// Consumes a line given as raw bytes, without its line break, logging
+  /// abnormal responses. Valid UTF-8 is consumed without copying.
+  fn consume_bytes(&mut self, bytes: &[u8]) -> Result<(), ParseError> {
+    if bytes.contains(&0) {
+      return Err(ParseError::Decode { line: self.total_lines + 1 });
+    }
+    let line = String::from_utf8_lossy(bytes);
+    match self.consume(&line) {
+      ParserResponse::PassedToDecoder(bt, lr) if lr.abnormal() => warn!(
+        "Got abnormal response {:?} from {} while parsing line {}!",
+        lr,
+        bt,
+        self.total_lines
+      ),
+      ParserResponse::BeginningWithoutSolver => warn!(
+        "Found block beginning in line {} before detecting the solver!",
+        self.total_lines
+      ),
+      _ => {}
+    }
+    return Ok(());
+  }
+
+  /// Finishes up, returning the file struct if anything useful was read.
+  fn finish_checked(self) -> Result<F06File, ParseError> {
+    if self.total_lines == 0 {
+      return Err(ParseError::Truncated);
+    }
+    let file = self.finish();
+    if file.flavour.solver.is_none() {
+      return Err(ParseError::UnknownSolver);
     }
-    return Ok(parser.finish());
+    return Ok(file);
+  }
+
+  /// Reads and parses a file by memory-mapping it, which avoids copying
+  /// every line into a buffer. Meant for very large files; results are the
+  /// same as those of `parse_file`.
+  ///
+  /// The file must not be modified while it's being parsed.
+  pub fn parse_mmap<S: AsRef<Path>>(p: S) -> Result<F06File, ParseError> {
+    return Self::parse_mmap_hinted(p, Flavour::default());
+  }
+
+  /// Reads and parses a file by memory-mapping it, hinting the flavour.
+  pub fn parse_mmap_hinted<S: AsRef<Path>>(
+    p: S,
+    hint: Flavour
+  ) -> Result<F06File, ParseError> {
+    return Self::parse_mmap_with(p, hint, ParserSettings::default());
+  }
+
+  /// Reads and parses a file by memory-mapping it, hinting the flavour and
+  /// with some settings applied.
+  pub fn parse_mmap_with<S: AsRef<Path>>(
+    p: S,
+    hint: Flavour,
+    settings: ParserSettings
+  ) -> Result<F06File, ParseError> {
+    let file = File::open(p.as_ref())?;
+    // an empty file can't be mapped on every platform
+    if file.metadata()?.len() == 0 {
+      return Err(ParseError::Truncated);
+    }
+    // SAFETY: the map is read-only, and the caller is told not to modify the
+    // file while it's being parsed.
+    let map = unsafe { Mmap::map(&file)? };
+    #[cfg(unix)]
+    map.advise(memmap2::Advice::Sequential)?;
+    let mut parser = Self::new();
+    parser.hint_flavour(hint);
+    parser.apply_settings(settings);
+    let bytes: &[u8] = &map;
+    // a trailing line break doesn't start another line
+    let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+    for line in bytes.split(|b| *b == b'\n') {
+      parser.consume_bytes(line)?;
+    }
+    let mut f06 = parser.finish_checked()?;
+    f06.filename = p.as_ref().file_name()
+      .and_then(|s| s.to_str())
+      .map(String::from);
+    return Ok(f06);
   }
 
   /// Utility method -- reads and parses a file.
-  pub fn parse_file<S: AsRef<Path>>(p: S) -> io::Result<F06File> {
+  pub fn parse_file<S: AsRef<Path>>(p: S) -> Result<F06File, ParseError> {
+    return Self::parse_file_hinted(p, Flavour::default());
+  }
+
+  /// Utility method -- reads and parses a file, hinting the flavour.
+  pub fn parse_file_hinted<S: AsRef<Path>>(
+    p: S,
+    hint: Flavour
+  ) -> Result<F06File, ParseError> {
+    return Self::parse_file_with(p, hint, ParserSettings::default());
+  }
+
+  /// Utility method -- reads and parses a file, hinting the flavour and with
+  /// some settings applied.
+  pub fn parse_file_with<S: AsRef<Path>>(
+    p: S,
+    hint: Flavour,
+    settings: ParserSettings
+  ) -> Result<F06File, ParseError> {
+    return Self::parse_file_with_progress(p, hint, settings, 0, |_| {});
+  }
+
+  /// Same as `parse_file_with`, but reports progress like
+  /// `parse_bufread_with_progress`.
+  pub fn parse_file_with_progress<S: AsRef<Path>, F: FnMut(usize)>(
+    p: S,
+    hint: Flavour,
+    settings: ParserSettings,
+    every: usize,
+    on_progress: F
+  ) -> Result<F06File, ParseError> {
     let file = File::open(p.as_ref())?;
-    let mut f06 = Self::parse_bufread(BufReader::new(file))?;
+    let reader = BufReader::new(file);
+    let mut f06 = Self::parse_bufread_with_progress(
+      reader,
+      hint,
+      settings,
+      every,
+      on_progress
+    )?;
     f06.filename = p.as_ref().file_name()
       .and_then(|s| s.to_str())
       .map(String::from);
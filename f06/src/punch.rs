@@ -0,0 +1,427 @@
+//! This module implements a parser for punch (.pch) files, which hold the
+//! same results as an F06 in a fixed-field format. It produces the same
+//! `F06File` and `FinalBlock` structures, so everything downstream works the
+//! same for either kind of file.
+//!
+//! Each punch record is an ID in columns 1-10, an optional text field in
+//! columns 11-18, and up to three values of 18 columns each; records with
+//! more values carry on in "-CONT-" lines. Columns 73-80 hold a line counter
+//! and are ignored. Tables are introduced by a series of "$" lines naming the
+//! table, the subcase, and (for element results) the element type.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use log::warn;
+
+use crate::blocks::RowBlock;
+use crate::prelude::*;
+use crate::util::*;
+
+/// The file extension of punch files.
+pub const PUNCH_EXTENSION: &str = "pch";
+
+/// Width of the ID and text fields that start a record.
+const ID_WIDTH: usize = 18;
+
+/// Width of the text field, right after the ID.
+const TEXT_WIDTH: usize = 8;
+
+/// Width of a value field.
+const VALUE_WIDTH: usize = 18;
+
+/// Number of columns with data; the rest is the line counter.
+const DATA_WIDTH: usize = 72;
+
+/// Marker for continuation lines.
+const CONT_MARKER: &str = "-CONT-";
+
+/// Number of values in a plate stress record (eight per side).
+const PLATE_STRESS_VALUES: usize = 16;
+
+/// Returns true if a path looks like a punch file, going by its extension.
+pub fn is_punch_path<P: AsRef<Path>>(p: P) -> bool {
+  return p.as_ref()
+    .extension()
+    .and_then(|e| e.to_str())
+    .is_some_and(|e| e.eq_ignore_ascii_case(PUNCH_EXTENSION));
+}
+
+/// Reads and parses a results file, as a punch file or a self-check CSV if
+/// its extension says so, or as an F06 otherwise. The parser settings only
+/// apply to F06 files.
+pub fn parse_any_file<S: AsRef<Path>>(
+  p: S,
+  hint: Flavour,
+  settings: ParserSettings
+) -> Result<F06File, ParseError> {
+  return parse_any_file_with_progress(p, hint, settings, 0, |_| {});
+}
+
+/// Same as `parse_any_file`, but reports progress on F06 files like
+/// `OnePassParser::parse_bufread_with_progress`.
+pub fn parse_any_file_with_progress<S: AsRef<Path>, F: FnMut(usize)>(
+  p: S,
+  hint: Flavour,
+  settings: ParserSettings,
+  every: usize,
+  on_progress: F
+) -> Result<F06File, ParseError> {
+  return if is_punch_path(&p) {
+    PunchParser::parse_file_hinted(p, hint)
+  } else if is_self_check_path(&p) {
+    Ok(load_self_check_file(p)?)
+  } else {
+    OnePassParser::parse_file_with_progress(
+      p,
+      hint,
+      settings,
+      every,
+      on_progress
+    )
+  };
+}
+
+/// Splits a string after its first n characters.
+fn split_chars(s: &str, n: usize) -> (&str, &str) {
+  return match s.char_indices().nth(n) {
+    Some((i, _)) => s.split_at(i),
+    None => (s, "")
+  };
+}
+
+/// The tables the punch parser can decode.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum PunchTable {
+  /// Displacements.
+  Displacements,
+  /// Element stresses, for the element type given later.
+  ElementStresses,
+  /// Some table that isn't supported (yet).
+  Unsupported
+}
+
+/// The block being filled in from a table.
+enum PunchBlock {
+  /// A displacements block.
+  Displacements(RowBlock<f64, GridPointRef, Dof, SIXDOF>),
+  /// A plate stresses block (quad or tria, centroid only).
+  PlateStresses(
+    BlockType,
+    ElementType,
+    RowBlock<f64, ElementSidedPoint, PlateStressField, 8>
+  )
+}
+
+/// A record being read, possibly across continuation lines.
+struct PunchRecord {
+  /// The line the record starts at.
+  line: usize,
+  /// The ID in the first field.
+  id: usize,
+  /// The text field right after the ID (like "G" for grid points).
+  text: String,
+  /// The values read so far.
+  values: Vec<f64>
+}
+
+/// This parser reads punch files into the same structures as F06 files.
+/// Displacements and quad/tria centroid stresses are supported; other
+/// tables are recorded as potential headers.
+pub struct PunchParser {
+  /// The file being built.
+  file: F06File,
+  /// The table we're in, if any.
+  table: Option<PunchTable>,
+  /// Whether the table has real values.
+  real: bool,
+  /// The current subcase.
+  subcase: usize,
+  /// The last label seen, which comes before the subcase ID it belongs to.
+  label: Option<String>,
+  /// The current element type code, for element tables.
+  etype: Option<usize>,
+  /// The block being filled in.
+  block: Option<PunchBlock>,
+  /// The line the current block started at.
+  block_start: usize,
+  /// The last line with data.
+  last_data: usize,
+  /// The record being read.
+  record: Option<PunchRecord>,
+  /// The total number of consumed lines.
+  total_lines: usize
+}
+
+impl Default for PunchParser {
+  fn default() -> Self {
+    return Self::new();
+  }
+}
+
+impl PunchParser {
+  /// Instantiates a new parser.
+  pub fn new() -> Self {
+    return Self {
+      file: F06File::new(),
+      table: None,
+      real: true,
+      subcase: 1,
+      label: None,
+      etype: None,
+      block: None,
+      block_start: 0,
+      last_data: 0,
+      record: None,
+      total_lines: 0
+    };
+  }
+
+  /// Sets the flavour, since punch files don't say which solver wrote them.
+  pub fn hint_flavour(&mut self, flavour: Flavour) {
+    self.file.flavour.solver = self.file.flavour.solver.or(flavour.solver);
+    self.file.flavour.soltype = self.file.flavour.soltype.or(flavour.soltype);
+  }
+
+  /// Stores the current record into the current block, if it fits.
+  fn flush_record(&mut self) {
+    let Some(rec) = self.record.take() else { return };
+    if self.block.is_none() {
+      self.block = self.new_block();
+      self.block_start = rec.line;
+    }
+    match self.block {
+      Some(PunchBlock::Displacements(ref mut rb)) => {
+        // scalar points only have one value, skip them
+        if let Ok(dofs) = <[f64; SIXDOF]>::try_from(rec.values.as_slice()) {
+          rb.insert_raw(rec.id.into(), &dofs);
+        } else if rec.text != "S" {
+          warn!("Punch displacement for {} has the wrong size!", rec.id);
+        }
+      },
+      Some(PunchBlock::PlateStresses(_, etype, ref mut rb)) => {
+        if rec.values.len() != PLATE_STRESS_VALUES {
+          warn!("Punch stresses for element {} have the wrong size!", rec.id);
+          return;
+        }
+        let element = ElementRef { eid: rec.id, etype: Some(etype) };
+        let sides = [ElementSide::Bottom, ElementSide::Top];
+        for (side, vals) in sides.into_iter().zip(rec.values.chunks(8)) {
+          let esp = ElementSidedPoint {
+            element,
+            point: ElementPoint::Centroid,
+            side
+          };
+          if let Ok(row) = <[f64; 8]>::try_from(vals) {
+            rb.insert_raw(esp, &row);
+          }
+        }
+      },
+      None => {}
+    }
+  }
+
+  /// Creates the block for the current table, if it's supported.
+  fn new_block(&self) -> Option<PunchBlock> {
+    if !self.real {
+      return None;
+    }
+    return match self.table? {
+      PunchTable::Displacements => Some(PunchBlock::Displacements(
+        RowBlock::new(
+          Dof::all().iter().enumerate().map(|(i, d)| (*d, i)).collect()
+        )
+      )),
+      PunchTable::ElementStresses => {
+        let (bt, et) = match self.etype? {
+          33 => (BlockType::QuadStresses, ElementType::Quad4),
+          74 => (BlockType::TriaStresses, ElementType::Tria3),
+          _ => return None
+        };
+        Some(PunchBlock::PlateStresses(
+          bt,
+          et,
+          RowBlock::new(PlateStressField::canonical_cols())
+        ))
+      },
+      PunchTable::Unsupported => None
+    };
+  }
+
+  /// Finishes the current block, inserting it into the file.
+  fn flush_block(&mut self) {
+    self.flush_record();
+    let lr = Some((self.block_start, self.last_data));
+    let block = match self.block.take() {
+      Some(PunchBlock::Displacements(rb)) => {
+        rb.finalise(BlockType::Displacements, self.subcase, lr)
+      },
+      Some(PunchBlock::PlateStresses(bt, _, rb)) => {
+        rb.finalise(bt, self.subcase, lr)
+      },
+      None => return
+    };
+    self.file.insert_block(block);
+  }
+
+  /// Handles a "$" line, which describes the table that follows.
+  fn consume_dollar(&mut self, line: &str) {
+    self.flush_block();
+    let text = line.trim_start_matches('$').trim();
+    let (key, value) = match text.split_once('=') {
+      Some((k, v)) => (k.trim(), v.trim()),
+      None => (text, "")
+    };
+    match key {
+      "TITLE" | "SUBTITLE" => {},
+      "LABEL" => {
+        self.label = Some(value.to_owned()).filter(|l| !l.is_empty());
+      },
+      "SUBCASE ID" => {
+        if let Ok(sc) = value.parse() {
+          self.subcase = sc;
+          if let Some(label) = self.label.take() {
+            self.file.subcase_labels.entry(sc.into()).or_insert(label);
+          }
+        }
+      },
+      "ELEMENT TYPE" => {
+        self.etype = value.split_whitespace()
+          .next()
+          .and_then(|c| c.parse().ok());
+        if self.table == Some(PunchTable::ElementStresses) {
+          self.note_unsupported(&format!("ELEMENT STRESSES, {}", text));
+        }
+      },
+      "REAL OUTPUT" => self.real = true,
+      "REAL-IMAGINARY OUTPUT" | "MAGNITUDE-PHASE OUTPUT" => {
+        self.real = false;
+      },
+      "DISPLACEMENTS" => self.begin_table(PunchTable::Displacements),
+      "ELEMENT STRESSES" => self.begin_table(PunchTable::ElementStresses),
+      _ if value.is_empty() => {
+        self.begin_table(PunchTable::Unsupported);
+        self.note_unsupported(text);
+      },
+      _ => {}
+    }
+  }
+
+  /// Starts a new table, resetting what the previous one set.
+  fn begin_table(&mut self, table: PunchTable) {
+    self.table = Some(table);
+    self.real = true;
+    self.etype = None;
+  }
+
+  /// Records a table (or element type) we can't decode as a potential header.
+  fn note_unsupported(&mut self, text: &str) {
+    if self.new_block().is_some() || self.table.is_none() {
+      return;
+    }
+    self.file.potential_headers.insert(PotentialHeader {
+      start: self.total_lines,
+      span: 1,
+      text: text.to_owned()
+    });
+  }
+
+  /// Consumes a line (without its line break).
+  pub fn consume(&mut self, line: &str) {
+    self.total_lines += 1;
+    let line = line.trim_end_matches('\r');
+    let (data, _) = split_chars(line, DATA_WIDTH);
+    if data.starts_with('$') {
+      self.consume_dollar(data);
+      return;
+    }
+    if self.table.is_none() || data.trim().is_empty() {
+      return;
+    }
+    let (head, rest) = split_chars(data, ID_WIDTH);
+    let values = rest.chars()
+      .collect::<Vec<_>>()
+      .chunks(VALUE_WIDTH)
+      .map(|c| c.iter().collect::<String>())
+      .filter(|s| !s.trim().is_empty())
+      .map(|s| decode_nasfloat(s.trim()))
+      .collect::<Option<Vec<f64>>>();
+    let Some(values) = values else {
+      warn!("Could not read the values in punch line {}!", self.total_lines);
+      return;
+    };
+    self.last_data = self.total_lines;
+    if head.starts_with(CONT_MARKER) {
+      if let Some(ref mut rec) = self.record {
+        rec.values.extend(values);
+      }
+      return;
+    }
+    self.flush_record();
+    let (id, text) = split_chars(head, ID_WIDTH - TEXT_WIDTH);
+    if let Ok(id) = id.trim().parse() {
+      self.record = Some(PunchRecord {
+        line: self.total_lines,
+        id,
+        text: text.trim().to_owned(),
+        values
+      });
+    }
+  }
+
+  /// Finishes up and returns the file struct.
+  pub fn finish(mut self) -> F06File {
+    self.flush_block();
+    return self.file;
+  }
+
+  /// Parses from a BufRead instance.
+  pub fn parse_bufread<R: BufRead>(reader: R) -> Result<F06File, ParseError> {
+    return Self::parse_bufread_hinted(reader, Flavour::default());
+  }
+
+  /// Parses from a BufRead instance, hinting the flavour.
+  pub fn parse_bufread_hinted<R: BufRead>(
+    mut reader: R,
+    hint: Flavour
+  ) -> Result<F06File, ParseError> {
+    let mut parser = Self::new();
+    parser.hint_flavour(hint);
+    let mut buf = vec![];
+    loop {
+      reader.read_until(b'\n', &mut buf)?;
+      if buf.is_empty() {
+        break;
+      }
+      if buf.contains(&0) {
+        return Err(ParseError::Decode { line: parser.total_lines + 1 });
+      }
+      buf.pop();
+      parser.consume(&String::from_utf8_lossy(&buf));
+      buf.clear();
+    }
+    if parser.total_lines == 0 {
+      return Err(ParseError::Truncated);
+    }
+    return Ok(parser.finish());
+  }
+
+  /// Utility method -- reads and parses a file.
+  pub fn parse_file<S: AsRef<Path>>(p: S) -> Result<F06File, ParseError> {
+    return Self::parse_file_hinted(p, Flavour::default());
+  }
+
+  /// Utility method -- reads and parses a file, hinting the flavour.
+  pub fn parse_file_hinted<S: AsRef<Path>>(
+    p: S,
+    hint: Flavour
+  ) -> Result<F06File, ParseError> {
+    let file = File::open(p.as_ref())?;
+    let mut f06 = Self::parse_bufread_hinted(BufReader::new(file), hint)?;
+    f06.filename = p.as_ref().file_name()
+      .and_then(|s| s.to_str())
+      .map(String::from);
+    return Ok(f06);
+  }
+}
@@ -0,0 +1,223 @@
+//! This module implements a loader for MYSTRAN's CSV self-check output, which
+//! lists the solver's own view of the grid point results. It's loaded into an
+//! `F06File`, so it can be diffed against the values read from the F06 of the
+//! same run to check that they agree.
+//!
+//! The CSV must start with a header row. Columns are found by name, ignoring
+//! case, and may come in any order; unknown columns are ignored. They are:
+//!
+//! - `SUBCASE`: the subcase ID;
+//! - `TABLE`: the block type, by its short name, which must be one of
+//!   `Displacements`, `SpcForces` and `AppliedForces`;
+//! - `GRID`: the grid point ID;
+//! - `T1`, `T2`, `T3`, `R1`, `R2`, `R3`: the values, of which at least one
+//!   must be present; the blocks only get the columns that are.
+//!
+//! Values may be written either as usual or in Nastran's own notation (like
+//! `1.5-3`). Rows for other tables are skipped with a warning.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use log::warn;
+use nalgebra::DMatrix;
+
+use crate::prelude::*;
+use crate::util::decode_nasfloat;
+
+/// The file extension of self-check files.
+pub const SELF_CHECK_EXTENSION: &str = "csv";
+
+/// The names of the DOF columns, in the same order as `Dof::all()`.
+const DOF_COLUMNS: [&str; SIXDOF] = ["T1", "T2", "T3", "R1", "R2", "R3"];
+
+/// The block types a self-check file can hold.
+const SELF_CHECK_BLOCKS: &[BlockType] = &[
+  BlockType::Displacements,
+  BlockType::SpcForces,
+  BlockType::AppliedForces
+];
+
+/// An error that can come up when loading a self-check file.
+#[derive(Debug)]
+pub enum SelfCheckError {
+  /// The CSV itself could not be read.
+  Csv(csv::Error),
+  /// A required column is missing from the header row.
+  MissingColumn(&'static str),
+  /// None of the DOF columns is in the header row.
+  NoValueColumns,
+  /// A field could not be read.
+  BadField {
+    /// The line the field is in.
+    line: u64,
+    /// The name of the column.
+    column: &'static str,
+    /// The contents of the field.
+    value: String
+  }
+}
+
+impl Display for SelfCheckError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return match self {
+      Self::Csv(e) => write!(f, "CSV error: {}", e),
+      Self::MissingColumn(c) => write!(f, "missing the {} column", c),
+      Self::NoValueColumns => write!(f, "no T1..R3 columns"),
+      Self::BadField { line, column, value } => write!(
+        f,
+        "bad {} \"{}\" in line {}",
+        column,
+        value,
+        line
+      ),
+    };
+  }
+}
+
+impl Error for SelfCheckError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    return match self {
+      Self::Csv(e) => Some(e),
+      _ => None
+    };
+  }
+}
+
+impl From<csv::Error> for SelfCheckError {
+  fn from(value: csv::Error) -> Self {
+    return Self::Csv(value);
+  }
+}
+
+/// Returns true if a path looks like a self-check file, going by its
+/// extension.
+pub fn is_self_check_path<P: AsRef<Path>>(p: P) -> bool {
+  return p.as_ref()
+    .extension()
+    .and_then(|e| e.to_str())
+    .is_some_and(|e| e.eq_ignore_ascii_case(SELF_CHECK_EXTENSION));
+}
+
+/// The rows of a block being loaded, by grid point ID, along with the lines
+/// they were read from.
+#[derive(Default)]
+struct SelfCheckBlock {
+  /// The values in each row.
+  rows: BTreeMap<usize, Vec<f64>>,
+  /// The first and last lines.
+  lines: Option<(usize, usize)>
+}
+
+/// Loads a self-check CSV from a reader. The flavour is set to MYSTRAN, since
+/// that's who writes these.
+pub fn load_self_check<R: Read>(
+  reader: R
+) -> Result<F06File, SelfCheckError> {
+  let mut rdr = csv::ReaderBuilder::new()
+    .trim(csv::Trim::All)
+    .flexible(true)
+    .from_reader(reader);
+  let headers = rdr.headers()?.clone();
+  let find = |name: &str| headers.iter()
+    .position(|h| h.eq_ignore_ascii_case(name));
+  let required = |name: &'static str| find(name)
+    .ok_or(SelfCheckError::MissingColumn(name));
+  let isc = required("SUBCASE")?;
+  let itable = required("TABLE")?;
+  let igrid = required("GRID")?;
+  let dofs = DOF_COLUMNS.iter()
+    .zip(Dof::all())
+    .filter_map(|(name, dof)| find(name).map(|i| (*name, *dof, i)))
+    .collect::<Vec<_>>();
+  if dofs.is_empty() {
+    return Err(SelfCheckError::NoValueColumns);
+  }
+  let mut blocks: BTreeMap<(usize, BlockType), SelfCheckBlock> =
+    BTreeMap::new();
+  for record in rdr.records() {
+    let record = record?;
+    let line = record.position().map_or(0, |p| p.line());
+    let field = |column: &'static str, i: usize| {
+      let value = record.get(i).unwrap_or_default();
+      return (value, move || SelfCheckError::BadField {
+        line,
+        column,
+        value: value.to_owned()
+      });
+    };
+    let (table, _) = field("TABLE", itable);
+    let block_type = SELF_CHECK_BLOCKS.iter()
+      .find(|bt| bt.short_name().eq_ignore_ascii_case(table));
+    let Some(block_type) = block_type else {
+      warn!("Skipping unsupported self-check table \"{}\".", table);
+      continue;
+    };
+    let (sc, bad_sc) = field("SUBCASE", isc);
+    let subcase = sc.parse::<usize>().map_err(|_| bad_sc())?;
+    let (gid, bad_gid) = field("GRID", igrid);
+    let gid = gid.parse::<usize>().map_err(|_| bad_gid())?;
+    let values = dofs.iter()
+      .map(|(name, _, i)| {
+        let (value, bad) = field(name, *i);
+        return value.parse::<f64>()
+          .ok()
+          .or_else(|| decode_nasfloat(value))
+          .ok_or_else(bad);
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    let block = blocks.entry((subcase, *block_type)).or_default();
+    if block.rows.insert(gid, values).is_some() {
+      warn!("Grid point {} is repeated in line {}, keeping that.", gid, line);
+    }
+    let line = line as usize;
+    block.lines = Some(match block.lines {
+      Some((first, last)) => (first.min(line), last.max(line)),
+      None => (line, line)
+    });
+  }
+  let mut file = F06File::new();
+  file.flavour.solver = Some(Solver::Mystran);
+  for ((subcase, block_type), block) in blocks {
+    let row_indexes = block.rows.keys()
+      .enumerate()
+      .map(|(i, gid)| (GridPointRef { gid: *gid }.into(), i))
+      .collect();
+    let col_indexes = dofs.iter()
+      .enumerate()
+      .map(|(i, (_, dof, _))| ((*dof).into(), i))
+      .collect();
+    let data = DMatrix::from_row_iterator(
+      block.rows.len(),
+      dofs.len(),
+      block.rows.into_values().flatten()
+    );
+    file.insert_block(FinalBlock {
+      line_range: block.lines,
+      block_type,
+      subcase,
+      step: None,
+      complex_form: None,
+      row_indexes,
+      col_indexes,
+      data: Some(data.into())
+    });
+  }
+  return Ok(file);
+}
+
+/// Utility function -- loads a self-check CSV from a file.
+pub fn load_self_check_file<P: AsRef<Path>>(
+  p: P
+) -> Result<F06File, SelfCheckError> {
+  let file = File::open(p.as_ref()).map_err(csv::Error::from)?;
+  let mut f06 = load_self_check(BufReader::new(file))?;
+  f06.filename = p.as_ref().file_name()
+    .and_then(|s| s.to_str())
+    .map(String::from);
+  return Ok(f06);
+}
@@ -0,0 +1,132 @@
+//! This module implements assert-style helpers for verification tests, so a
+//! solver's test suite can check its output against a reference F06 in a
+//! single call. It's only built with the `testing` feature.
+
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use crate::prelude::*;
+
+/// Something that kept two F06 files from matching.
+#[derive(Debug)]
+pub enum Flag {
+  /// One of the files couldn't be parsed.
+  Unparseable {
+    /// The path of the file.
+    path: PathBuf,
+    /// Why it couldn't be parsed.
+    error: ParseError
+  },
+  /// A block wasn't compared, e.g. because it's missing from one file.
+  NotCompared {
+    /// The block.
+    block: BlockRef,
+    /// Why it wasn't compared.
+    reason: NonCompareReason
+  },
+  /// A value failed the criteria.
+  Value {
+    /// The block the value is in.
+    block: BlockRef,
+    /// The flagged position.
+    position: FlaggedPosition
+  },
+  /// The loads of a subcase differ between the files.
+  Loads(LoadMismatch)
+}
+
+impl Display for Flag {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return match self {
+      Self::Unparseable { path, error } => {
+        write!(f, "could not parse {}: {}", path.display(), error)
+      },
+      Self::NotCompared { block, reason } => {
+        write!(f, "{} not compared: {}", block, reason)
+      },
+      Self::Value { block, position } => {
+        let vals = &position.values;
+        write!(
+          f,
+          "{}, row {}, column {}: {} vs {} ({})",
+          block,
+          vals.row,
+          vals.col,
+          vals.val_a,
+          vals.val_b,
+          position.reason
+        )
+      },
+      Self::Loads(lm) => {
+        write!(f, "subcase {} has different loads", lm.subcase)
+      }
+    };
+  }
+}
+
+/// Parses a file the way `f06diff` does, tidying its blocks up.
+fn load(path: &Path) -> Result<F06File, ParseError> {
+  let mut f06 = OnePassParser::parse_file(path)?;
+  f06.merge_blocks(true);
+  f06.merge_potential_headers();
+  f06.sort_all_blocks();
+  return Ok(f06);
+}
+
+/// Parses a reference and a test F06 and compares them, returning every
+/// flag found, if any. Meant to be called from `#[test]` functions.
+///
+/// Blocks that couldn't be compared (e.g. missing from the test file) and
+/// subcases with differing loads are flagged too, alongside the values that
+/// fail the criteria.
+pub fn assert_f06_matches<P: AsRef<Path>, Q: AsRef<Path>>(
+  reference: P,
+  test: Q,
+  criteria: &Criteria
+) -> Result<(), Vec<Flag>> {
+  let settings = DiffSettings {
+    criteria: *criteria,
+    ..Default::default()
+  };
+  return assert_f06_matches_with(reference, test, &settings);
+}
+
+/// Same as `assert_f06_matches`, but with full diff settings, so that blocks
+/// and subcases can be narrowed down, columns aliased and so on.
+pub fn assert_f06_matches_with<P: AsRef<Path>, Q: AsRef<Path>>(
+  reference: P,
+  test: Q,
+  settings: &DiffSettings
+) -> Result<(), Vec<Flag>> {
+  let paths = [reference.as_ref(), test.as_ref()];
+  let (a, b) = match paths.map(load) {
+    [Ok(a), Ok(b)] => (a, b),
+    loaded => {
+      return Err(
+        paths.into_iter()
+          .zip(loaded)
+          .filter_map(|(p, r)| r.err().map(|error| (p.to_owned(), error)))
+          .map(|(path, error)| Flag::Unparseable { path, error })
+          .collect()
+      );
+    }
+  };
+  let mut settings = settings.clone();
+  settings.resolve_dxn_behaviour(&a, &b);
+  let diff = F06Diff::compare(&settings, &a, &b);
+  let mut flags: Vec<Flag> = Vec::new();
+  flags.extend(diff.not_compared.into_iter().map(|(block, reason)| {
+    return Flag::NotCompared { block, reason };
+  }));
+  flags.extend(diff.compared.into_iter().flat_map(|(block, v)| {
+    return v.into_iter().map(move |position| {
+      return Flag::Value { block, position };
+    });
+  }));
+  flags.extend(diff.load_mismatches.into_iter().map(Flag::Loads));
+  if flags.is_empty() {
+    return Ok(());
+  } else {
+    return Err(flags);
+  }
+}
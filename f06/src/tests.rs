@@ -1,5 +1,87 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use nalgebra::DMatrix;
+
+use crate::prelude::*;
+use crate::blocks::indexing::PlateStressField;
 use crate::util::decode_nasfloat;
 
+/// Returns the directory with the example files.
+fn examples_dir() -> PathBuf {
+  return PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..").join("examples");
+}
+
+/// Returns the path to an example file, given its solver's directory.
+fn example_path(solver: &str, name: &str) -> PathBuf {
+  return examples_dir().join(solver).join(name);
+}
+
+/// Builds a displacements block in subcase 1, with a row per grid point in
+/// the order given, a column per DOF, and the values row by row.
+fn grid_block(gids: &[usize], dofs: &[Dof], vals: &[f64]) -> FinalBlock {
+  let row_indexes: BTreeMap<NasIndex, usize> = gids.iter()
+    .enumerate()
+    .map(|(i, gid)| (GridPointRef { gid: *gid }.into(), i))
+    .collect();
+  let col_indexes: BTreeMap<NasIndex, usize> = dofs.iter()
+    .enumerate()
+    .map(|(i, d)| (NasIndex::from(*d), i))
+    .collect();
+  return FinalBlock {
+    line_range: None,
+    block_type: BlockType::Displacements,
+    subcase: 1,
+    step: None,
+    complex_form: None,
+    row_indexes,
+    col_indexes,
+    data: Some(
+      DMatrix::from_row_slice(gids.len(), dofs.len(), vals).into()
+    )
+  };
+}
+
+/// Builds a complex displacements block in subcase 1, with grid point 7's
+/// T1 given as real and imaginary parts.
+fn complex_block(re: f64, im: f64) -> FinalBlock {
+  let tx = Dof::try_from(1).unwrap();
+  let part = |part| NasIndex::from(ComplexDof { dof: tx, part });
+  return FinalBlock {
+    block_type: BlockType::ComplexDisplacements,
+    complex_form: Some(ComplexForm::RealImaginary),
+    col_indexes: [
+      (part(ComplexPart::Real), 0),
+      (part(ComplexPart::Imaginary), 1)
+    ].into(),
+    data: Some(DMatrix::from_row_slice(1, 2, &[re, im]).into()),
+    ..grid_block(&[7], &[tx], &[0.0])
+  };
+}
+
+/// Builds a quad stresses block in subcase 1, with a single row for the
+/// bottom of the centroid of QUAD4 1, and the given columns and values.
+fn quad_block(cols: &[PlateStressField], vals: &[f64]) -> FinalBlock {
+  let esp = ElementSidedPoint {
+    element: ElementRef { eid: 1, etype: Some(ElementType::Quad4) },
+    point: ElementPoint::Centroid,
+    side: ElementSide::Bottom
+  };
+  return FinalBlock {
+    line_range: None,
+    block_type: BlockType::QuadStresses,
+    subcase: 1,
+    step: None,
+    complex_form: None,
+    row_indexes: [(esp.into(), 0)].into(),
+    col_indexes: cols.iter()
+      .enumerate()
+      .map(|(i, c)| (NasIndex::from(*c), i))
+      .collect(),
+    data: Some(DMatrix::from_row_slice(1, cols.len(), vals).into())
+  };
+}
+
 #[test]
 fn test_decode_nasfloat() {
   let epsilon = 1e-6_f64;
@@ -56,3 +138,2709 @@ fn test_decode_nasfloat() {
   must_fail(".e");
   must_fail(".E");
 }
+
+#[test]
+fn test_force_balance_residual() {
+  let gp = GridPointRef { gid: 1 };
+  let origins = [
+    ForceOrigin::Load,
+    ForceOrigin::SinglePointConstraint,
+    ForceOrigin::Element {
+      elem: ElementRef { eid: 10, etype: Some(ElementType::Quad4) }
+    },
+  ];
+  let row_indexes: BTreeMap<NasIndex, usize> = origins.iter()
+    .enumerate()
+    .map(|(i, fo)| {
+      let gpfo = GridPointForceOrigin { grid_point: gp, force_origin: *fo };
+      return (gpfo.into(), i);
+    }).collect();
+  let col_indexes: BTreeMap<NasIndex, usize> = Dof::all().iter()
+    .enumerate()
+    .map(|(i, d)| (NasIndex::from(*d), i))
+    .collect();
+  let data = DMatrix::from_row_slice(3, SIXDOF, &[
+    1.0, 2.0, 3.0, 0.0, 0.0, 4.0,
+    -1.0, -2.0, -1.0, 0.0, 0.0, -4.0,
+    0.0, 0.0, -1.5, 0.0, 0.0, 0.0,
+  ]);
+  let mut block = FinalBlock {
+    line_range: None,
+    block_type: BlockType::GridPointForceBalance,
+    subcase: 1,
+    step: None,
+    complex_form: None,
+    row_indexes,
+    col_indexes,
+    data: Some(data.into())
+  };
+  let residual = block.force_balance_residual(gp).unwrap();
+  let expected = [0.0, 0.0, 0.5, 0.0, 0.0, 0.0];
+  for (r, e) in residual.iter().zip(expected.iter()) {
+    assert!((r - e).abs() < 1e-12);
+  }
+  assert_eq!(block.force_balance_residual(GridPointRef { gid: 2 }), None);
+  block.block_type = BlockType::Displacements;
+  assert_eq!(block.force_balance_residual(gp), None);
+}
+
+#[test]
+fn test_row_line() {
+  use crate::util::{row_line, RowLine};
+  let plate = |s: &str| row_line(s, 8..=10, 8..=8);
+  let bar = |s: &str| row_line(s, 7..=8, 6..=7);
+  // carriage control and wide spacing on a leading line
+  assert_eq!(
+    plate(concat!(
+      "0      11   -6.250000E-02      3.784484E+01   3.618887E+02",
+      "   2.579625E+01    85.4768    3.639294E+02    3.580412E+01",
+      "   3.474138E+02"
+    )),
+    RowLine::Leading(vec![0, 11])
+  );
+  // MYSTRAN leading line with the transverse shears
+  assert_eq!(
+    plate(concat!(
+      "       11  CENTER      -6.250E-02  7.26577E+01  3.56113E+02",
+      "  2.14067E+01   85.71  3.57721E+02  7.10502E+01  3.28018E+02",
+      "  1.09962E-01 -1.86138E-02"
+    )),
+    RowLine::Leading(vec![11])
+  );
+  // continuation with odd leading text
+  assert_eq!(
+    plate(concat!(
+      "             in elem     6.25000E-02 -1.26104E+00  2.08971E+02",
+      "  2.57883E+00   89.297  2.09003E+02 -1.29267E+00  2.09652E+02"
+    )),
+    RowLine::Continuation
+  );
+  // totals have no IDs but aren't continuations either
+  assert_eq!(
+    plate(concat!(
+      " MAX* :                            7.26577E+01  3.56113E+02",
+      "  2.14067E+01          3.57721E+02  7.10502E+01  3.28018E+02",
+      "  1.09962E-01 -1.86138E-02  0.0E+00"
+    )),
+    RowLine::NotData
+  );
+  // page headers have integers but no data
+  assert_eq!(
+    plate("1    TEST OF ALL ELEMENTS    JANUARY  12, 2024    PAGE    25"),
+    RowLine::NotData
+  );
+  // integers amongst the data don't count as IDs
+  assert_eq!(
+    bar(concat!(
+      "            -1.355250E+02  -1.322937E+02   1.355250E+02",
+      "   1.322937E+02     1.355250E+02  -1.355250E+02  73"
+    )),
+    RowLine::Continuation
+  );
+  assert_eq!(
+    bar(concat!(
+      "0    1112    2.710500E+02   2.645874E+02  -2.710500E+02",
+      "  -2.645874E+02   0.0            2.710500E+02  -2.710500E+02  7.3E+01"
+    )),
+    RowLine::Leading(vec![0, 1112])
+  );
+  // negative IDs aren't wrapped around into huge ones
+  assert_eq!(
+    bar(concat!(
+      "0   -1112    2.710500E+02   2.645874E+02  -2.710500E+02",
+      "  -2.645874E+02   0.0            2.710500E+02  -2.710500E+02  7.3E+01"
+    )),
+    RowLine::NotData
+  );
+}
+
+#[test]
+fn test_row_id() {
+  use crate::util::row_id;
+  // MYSTRAN, EID first
+  assert_eq!(
+    row_id(concat!(
+      "                       1  1.000000E+02  2.000000E+02  3.000000E+02",
+      "  3.000000E+02 -2.300000E+03  2.500000E+03"
+    )),
+    Some(1)
+  );
+  // Simcenter, carriage control then EID
+  assert_eq!(
+    row_id(concat!(
+      "0                          1      1.000000E+02  2.000000E+02",
+      "  3.000000E+02  3.000000E+02 -2.300000E+03  2.500000E+03"
+    )),
+    Some(1)
+  );
+  // EID last
+  assert_eq!(
+    row_id(concat!(
+      "      1.000000E+02  2.000000E+02  3.000000E+02  3.000000E+02",
+      " -2.300000E+03  2.500000E+03      42"
+    )),
+    Some(42)
+  );
+  // totals
+  assert_eq!(
+    row_id(concat!(
+      "                MAX* :    1.000000E+02  2.000000E+02  3.000000E+02",
+      "  3.000000E+02 -2.300000E+03  2.500000E+03"
+    )),
+    None
+  );
+}
+
+#[test]
+fn test_bush_forces() {
+  let examples = examples_dir();
+  let files = [
+    examples.join("mystran").join("SB-BUSH-01-OFFSET-2b.F06"),
+    examples.join("scnastran").join("SB-BUSH-01-OFFSET-2b_scnas_postexport.f06")
+  ];
+  let expected = [1.0e2, 2.0e2, 3.0e2, 3.0e2, -2.3e3, 2.5e3];
+  for path in files {
+    let mut f06 = OnePassParser::parse_file(&path).unwrap();
+    f06.merge_blocks(true);
+    let block = f06.all_blocks(true)
+      .find(|b| b.block_type == BlockType::BushForces)
+      .expect("no bush forces block");
+    assert_eq!(block.row_indexes.len(), 1);
+    let eref = ElementRef { eid: 1, etype: Some(ElementType::Bush) };
+    for (dof, val) in Dof::all().iter().zip(expected) {
+      let got = f64::from(block.get(eref, *dof).expect("missing value"));
+      assert!((got - val).abs() < 1e-9);
+    }
+  }
+}
+
+#[test]
+fn test_strain_decoder_rejects_stress_header() {
+  let flavour = Flavour::default();
+  let stress = "STRESSES IN QUADRILATERAL ELEMENTS (QUAD4)";
+  let strain = "STRAINS IN QUADRILATERAL ELEMENTS (QUAD4)";
+  let mut dec = BlockType::QuadStrains.init_decoder(flavour);
+  assert!(!dec.good_header(stress));
+  assert!(dec.good_header(strain));
+  let mut dec = BlockType::BushStresses.init_decoder(flavour);
+  assert!(!dec.good_header("STRAINS IN BUSH ELEMENTS (CBUSH)"));
+  assert!(dec.good_header("STRESSES IN BUSH ELEMENTS (CBUSH)"));
+}
+
+#[test]
+fn test_provenance() {
+  let parse = |s: &str| OnePassParser::parse_bufread(s.as_bytes()).unwrap();
+  let mystran = parse(concat!(
+    " MYSTRAN Version 15.1.4   Dec 20 2023 MYSTRAN developed by Dr Bill Case\n",
+    " >> MYSTRAN BEGIN  : 12/31/2023 at 15:22:40.469 The input file is A.DAT\n"
+  ));
+  assert_eq!(mystran.solver_version.as_deref(), Some("15.1.4"));
+  assert_eq!(
+    mystran.run_timestamp.as_deref(),
+    Some("12/31/2023 at 15:22:40.469")
+  );
+  let simcenter = parse(concat!(
+    "                * *     Simcenter Nastran 2021.1    * *\n",
+    "1    TITLE       JANUARY  22, 2024  SIMCENTER NASTRAN  2/11/21   PAGE 3\n"
+  ));
+  assert_eq!(simcenter.solver_version.as_deref(), Some("2021.1"));
+  assert_eq!(simcenter.run_timestamp.as_deref(), Some("JANUARY 22, 2024"));
+}
+
+#[test]
+fn test_merge_report() {
+  let block = |rows: &[(usize, f64)], line: usize| {
+    let gids = rows.iter().map(|r| r.0).collect::<Vec<_>>();
+    let vals = rows.iter().map(|r| r.1).collect::<Vec<_>>();
+    return FinalBlock {
+      line_range: Some((line, line + rows.len())),
+      ..grid_block(&gids, &[DOF_TX], &vals)
+    };
+  };
+  let mut file = F06File::new();
+  file.insert_block(block(&[(1, 1.0), (2, 2.0)], 10));
+  file.insert_block(block(&[(2, 2.5), (3, 3.0)], 20));
+  // clean merges refuse conflicting rows
+  assert_eq!(file.clone().merge_blocks(true), 0);
+  assert_eq!(file.merge_blocks(false), 1);
+  let report = file.merge_report();
+  assert_eq!(report.len(), 1);
+  let gp = |gid: usize| NasIndex::from(GridPointRef { gid });
+  // the last block is the primary
+  assert_eq!(report[0].merged, [gp(1)].into());
+  assert_eq!(report[0].skipped, [gp(2)].into());
+  assert_eq!(report[0].conflicts.len(), 1);
+  let conflict = &report[0].conflicts[0];
+  assert_eq!(conflict.kept, Some(F06Number::Real(2.5)));
+  assert_eq!(conflict.dropped, Some(F06Number::Real(2.0)));
+  let merged = file.all_blocks(true).next().unwrap();
+  assert_eq!(merged.row_indexes.len(), 3);
+}
+
+#[test]
+fn test_derive_von_mises() {
+  let path = example_path("mystran", "SB-ALL-ELEM-TEST.F06");
+  let mut f06 = OnePassParser::parse_file(&path).unwrap();
+  f06.merge_blocks(true);
+  let block = f06.all_blocks(true)
+    .find(|b| b.block_type == BlockType::QuadStresses)
+    .expect("no quad stresses block");
+  // the solver already gives von mises, so it must not be derived again
+  let mut derived = block.clone();
+  assert!(!derived.derive_von_mises());
+  // the formula must agree with the solver's own values
+  for row in block.row_indexes.keys() {
+    let get = |c: PlateStressField| f64::from(block.get(*row, c).unwrap());
+    let vm = von_mises(
+      get(PlateStressField::NormalX),
+      get(PlateStressField::NormalY),
+      get(PlateStressField::ShearXY)
+    );
+    let given = get(PlateStressField::VonMises);
+    assert!((vm - given).abs() <= 1e-3 * given.abs().max(1.0));
+  }
+  // with the column gone, it must be derived
+  let mut stripped = block.clone();
+  let vm_col = NasIndex::from(PlateStressField::VonMises);
+  stripped.col_indexes.remove(&vm_col);
+  assert!(stripped.derive_von_mises());
+  for row in block.row_indexes.keys() {
+    let a = f64::from(block.get(*row, vm_col).unwrap());
+    let b = f64::from(stripped.get(*row, vm_col).unwrap());
+    assert!((a - b).abs() <= 1e-3 * a.abs().max(1.0));
+  }
+}
+
+#[test]
+fn test_duplicate_row_policy() {
+  let path = example_path("mystran", "SB-BUSH-01-OFFSET-2b.F06");
+  let text = std::fs::read_to_string(path).unwrap();
+  // repeat the first displacement line for grid 2, with other values
+  let original = text.lines()
+    .find(|l| l.trim_start().starts_with("2        0  3.190000E+00"))
+    .expect("no displacement line for grid 2");
+  let repeated = original.replacen("3.190000E+00", "9.990000E+00", 1);
+  let lines = text.lines()
+    .flat_map(|l| {
+      if l == original { vec![l, repeated.as_str()] } else { vec![l] }
+    })
+    .collect::<Vec<_>>();
+  let tx_of = |policy: DuplicateRowPolicy| {
+    let mut parser = OnePassParser::new();
+    parser.set_duplicate_policy(policy);
+    for line in lines.iter() {
+      parser.consume(line);
+    }
+    let f06 = parser.finish();
+    let block = f06.all_blocks(true)
+      .find(|b| b.block_type == BlockType::Displacements)
+      .expect("no displacements block");
+    let grid: GridPointRef = 2.into();
+    return f64::from(block.get(grid, Dof::try_from(1).unwrap()).unwrap());
+  };
+  assert_eq!(tx_of(DuplicateRowPolicy::KeepFirst), 3.19);
+  assert_eq!(tx_of(DuplicateRowPolicy::KeepLast), 9.99);
+  assert_eq!(tx_of(DuplicateRowPolicy::Warn), 9.99);
+}
+
+#[test]
+fn test_connectivity_card() {
+  use crate::util::{connectivity_card, property_card};
+  // MYSTRAN echoes the deck as written
+  assert_eq!(
+    connectivity_card("CQUAD4  11      91      1011    1012    1022    1021"),
+    Some((11, vec![1011, 1012, 1022, 1021]))
+  );
+  // Simcenter echoes it sorted and spaced out
+  assert_eq!(
+    connectivity_card(concat!(
+      "                              CBAR        1112      97    1011    1012",
+      "      0.      0.      1."
+    )),
+    Some((1112, vec![1011, 1012]))
+  );
+  // free-field, with a scalar spring to ground
+  assert_eq!(
+    connectivity_card("CELAS1,58,75,1022,3,0,0"),
+    Some((58, vec![1022]))
+  );
+  assert_eq!(connectivity_card("GRID    1011              0.      0."), None);
+  // property IDs come from the same cards, except for those without one
+  assert_eq!(
+    property_card("   12 CQUAD4  11      91      1011    1012    1022    1021"),
+    Some((11, 91))
+  );
+  assert_eq!(property_card("CELAS1,58,75,1022,3,0,0"), Some((58, 75)));
+  assert_eq!(property_card("CONROD  5       1011    1012    3"), None);
+}
+
+#[test]
+fn test_singularities() {
+  use crate::blocks::decoders::{AUTO_CONSTRAINED, SINGULAR};
+  let lines = [
+    concat!(
+      "                                          G R I D   P O I N T   ",
+      "S I N G U L A R I T Y   T A B L E"
+    ),
+    concat!(
+      "                              POINT    TYPE   FAILED      STIFFNESS",
+      "       OLD USET           NEW USET"
+    ),
+    concat!(
+      "                               ID            DIRECTION      RATIO  ",
+      "   EXCLUSIVE  UNION   EXCLUSIVE  UNION"
+    ),
+    concat!(
+      "                                10        G      4         0.00E+00",
+      "          B        F         SB       S    *"
+    ),
+    concat!(
+      "                                10        G      5         0.00E+00",
+      "          B        F         SB       S    *"
+    ),
+    concat!(
+      "                                20        G      6         1.00E-09",
+      "          B        F          B       F"
+    ),
+    "1    SOME TITLE                    JANUARY  12, 2024  SIMCENTER NASTRAN",
+  ];
+  let mut parser = OnePassParser::new();
+  parser.hint_flavour(Flavour {
+    solver: Some(Solver::Simcenter),
+    soltype: None
+  });
+  for line in lines {
+    parser.consume(line);
+  }
+  let f06 = parser.finish();
+  let block = f06.all_blocks(true)
+    .find(|b| b.block_type == BlockType::Singularities)
+    .expect("no singularities block");
+  assert_eq!(block.row_indexes.len(), 2);
+  let flag = |gid: usize, dof: usize| {
+    let grid: GridPointRef = gid.into();
+    return block.get(grid, Dof::try_from(dof).unwrap());
+  };
+  assert_eq!(flag(10, 4), Some(F06Number::Natural(AUTO_CONSTRAINED)));
+  assert_eq!(flag(10, 5), Some(F06Number::Natural(AUTO_CONSTRAINED)));
+  assert_eq!(flag(10, 6), Some(F06Number::Natural(0)));
+  assert_eq!(flag(20, 6), Some(F06Number::Natural(SINGULAR)));
+}
+
+#[test]
+fn test_quad8_midside_rows() {
+  let reals = concat!(
+    "  -5.000000E-01   1.000000E+00   2.000000E+00   3.000000E-01",
+    "    10.0000    2.000000E+00   1.000000E+00   1.700000E+00"
+  );
+  let mut lines = vec![
+    concat!(
+      "                         S T R E S S E S   I N   Q U A D R I L A T",
+      " E R A L   E L E M E N T S   ( Q U A D 8 )"
+    ).to_string(),
+    concat!(
+      "  ELEMENT              FIBER            STRESSES IN ELEMENT COORD ",
+      "SYSTEM         PRINCIPAL STRESSES (ZERO SHEAR)"
+    ).to_string(),
+    concat!(
+      "    ID      GRID-ID   DISTANCE        NORMAL-X      NORMAL-Y     ",
+      "SHEAR-XY      ANGLE        MAJOR         MINOR       VON MISES"
+    ).to_string(),
+    format!("0       7    CEN/8 {}", reals),
+    format!("                  {}", reals),
+  ];
+  for gid in [101, 102, 103, 104, 105, 106, 107, 108] {
+    lines.push(format!("              {:>4} {}", gid, reals));
+    lines.push(format!("                  {}", reals));
+  }
+  lines.push(
+    "1    SOME TITLE                    JANUARY  12, 2024  SIMCENTER NASTRAN"
+      .to_string()
+  );
+  let mut parser = OnePassParser::new();
+  parser.hint_flavour(Flavour {
+    solver: Some(Solver::Simcenter),
+    soltype: None
+  });
+  for line in lines.iter() {
+    parser.consume(line);
+  }
+  let f06 = parser.finish();
+  let block = f06.all_blocks(true)
+    .find(|b| b.block_type == BlockType::QuadStresses)
+    .expect("no quad stresses block");
+  assert_eq!(block.row_indexes.len(), 18);
+  let points = block.row_indexes.keys()
+    .filter_map(|ri| match ri {
+      NasIndex::ElementSidedPoint(esp) => Some(esp),
+      _ => None
+    })
+    .filter(|esp| esp.side == ElementSide::Bottom)
+    .map(|esp| {
+      assert_eq!(esp.element.eid, 7);
+      assert_eq!(esp.element.etype, Some(ElementType::Quad8));
+      esp.point
+    })
+    .collect::<Vec<_>>();
+  let corner = |gid: usize| ElementPoint::Corner(gid.into());
+  let midpoint = |gid: usize| ElementPoint::Midpoint(gid.into());
+  assert_eq!(points, vec![
+    ElementPoint::Centroid,
+    corner(101), corner(102), corner(103), corner(104),
+    midpoint(105), midpoint(106), midpoint(107), midpoint(108),
+  ]);
+}
+
+#[test]
+fn test_unset_sentinel() {
+  // criteria skip pairs with an unset value only when asked to
+  let mut crit = Criteria { difference: Some(1.0), ..Default::default() };
+  assert!(crit.check(1.0, UNSET_SENTINEL).is_some());
+  crit.unset_threshold = Some(UNSET_SENTINEL);
+  assert_eq!(crit.check(1.0, UNSET_SENTINEL), None);
+  assert_eq!(crit.check(-UNSET_SENTINEL, UNSET_SENTINEL), None);
+  assert!(crit.check(1.0, 3.0).is_some());
+  // metrics leave them out too
+  let tx: NasIndex = DOF_TX.into();
+  let block = |vals: &[f64]| {
+    let gids = (1..=vals.len()).collect::<Vec<_>>();
+    return grid_block(&gids, &[DOF_TX], vals);
+  };
+  let reference = block(&[1.0, 2.0, UNSET_SENTINEL]);
+  let testing = block(&[1.0, 4.0, 3.0]);
+  let opts = MetricOptions {
+    epsilon: 0.0,
+    unset_threshold: Some(UNSET_SENTINEL)
+  };
+  let max = SingleColumnMetric::Maximum.compute_with(&reference, tx, &opts);
+  assert_eq!(max, Some(2.0));
+  let rmsd = ColumnCompareMetric::RootMeanSquareDeviation
+    .compute_with(&reference, &testing, tx, &opts)
+    .unwrap();
+  assert!((rmsd - f64::sqrt(2.0)).abs() < 1e-12);
+}
+
+#[test]
+fn test_form_feed_pages() {
+  let banner = concat!(
+    "1    SOME TITLE                              JANUARY  22, 2024  ",
+    "SIMCENTER NASTRAN  2/11/21   PAGE     4"
+  );
+  let header = "\x0c                D I S P L A C E M E N T   V E C T O R";
+  let cols = concat!(
+    "      POINT ID.   TYPE          T1             T2             T3",
+    "             R1             R2             R3"
+  );
+  let lines = [
+    format!("\x0c{}", banner),
+    " ".to_string(),
+    header.to_string(),
+    " ".to_string(),
+    cols.to_string(),
+    concat!(
+      "             1      G      1.000000E+00   0.0            0.0",
+      "            0.0            0.0            0.0"
+    ).to_string(),
+    format!("\x0c{}", banner),
+    " ".to_string(),
+    header.to_string(),
+    " ".to_string(),
+    cols.to_string(),
+    concat!(
+      "\x0c            2      G      2.000000E+00   0.0            0.0",
+      "            0.0            0.0            0.0"
+    ).to_string(),
+    banner.to_string(),
+  ];
+  let mut parser = OnePassParser::new();
+  parser.hint_flavour(Flavour {
+    solver: Some(Solver::Simcenter),
+    soltype: None
+  });
+  for line in lines.iter() {
+    parser.consume(line);
+  }
+  let mut f06 = parser.finish();
+  f06.merge_blocks(true);
+  let block = f06.all_blocks(true)
+    .find(|b| b.block_type == BlockType::Displacements)
+    .expect("no displacements block");
+  assert_eq!(block.row_indexes.len(), 2);
+  let tx = Dof::try_from(1).unwrap();
+  let grid: GridPointRef = 2.into();
+  assert_eq!(block.get(grid, tx), Some(F06Number::Real(2.0)));
+}
+
+#[test]
+fn test_extraction_col_types() {
+  use std::collections::BTreeSet;
+  let path = example_path("mystran", "SB-BUSH-01-OFFSET-2b.F06");
+  let mut f06 = OnePassParser::parse_file(&path).unwrap();
+  f06.merge_blocks(true);
+  f06.sort_all_blocks();
+  let extraction = Extraction {
+    col_types: Specifier::List(vec![Dof::INDEX_NAME.to_owned()]),
+    ..Default::default()
+  };
+  let indexes = extraction.lookup(&f06).collect::<Vec<_>>();
+  assert!(indexes.iter().all(|ix| matches!(ix.col, NasIndex::Dof(_))));
+  // every DOF column of every block, and nothing else
+  let expected = f06.all_blocks(true)
+    .filter(|b| b.col_indexes.keys().all(|c| matches!(c, NasIndex::Dof(_))))
+    .map(|b| b.row_indexes.len() * b.col_indexes.len())
+    .sum::<usize>();
+  assert_eq!(indexes.len(), expected);
+  let types = indexes.iter()
+    .map(|ix| ix.block_ref.block_type)
+    .collect::<BTreeSet<_>>();
+  assert!(types.contains(&BlockType::Displacements));
+  assert!(types.contains(&BlockType::SpcForces));
+  assert!(types.contains(&BlockType::AppliedForces));
+  // excluding them leaves nothing, as this file has no other columns
+  let others = Extraction {
+    col_types: Specifier::AllExcept(vec![Dof::INDEX_NAME.to_owned()]),
+    ..Default::default()
+  };
+  assert_eq!(others.lookup(&f06).count(), 0);
+  // blockify agrees
+  let blocks = extraction.blockify(&f06);
+  let cells = blocks.iter()
+    .map(|b| b.row_indexes.len() * b.col_indexes.len())
+    .sum::<usize>();
+  assert_eq!(cells, expected);
+}
+
+#[test]
+fn test_extraction_adjacency() {
+  use std::collections::BTreeSet;
+  let path = example_path("mystran", "SB-ALL-ELEM-TEST.F06");
+  let mut f06 = OnePassParser::parse_file(&path).unwrap();
+  f06.merge_blocks(true);
+  f06.sort_all_blocks();
+  let grid = GridPointRef { gid: 1011 };
+  let touching = f06.connectivity.iter()
+    .filter(|(_, gids)| gids.contains(&grid.gid))
+    .map(|(eid, _)| *eid)
+    .collect::<BTreeSet<_>>();
+  assert_eq!(touching, BTreeSet::from([11, 1112, 1121, 1141]));
+  let extraction = Extraction {
+    adjacent_grids: Specifier::List(vec![grid]),
+    ..Default::default()
+  };
+  let indexes = extraction.lookup(&f06).collect::<Vec<_>>();
+  let found = indexes.iter()
+    .filter_map(|ix| ix.row.element_id())
+    .map(|e| e.eid)
+    .collect::<BTreeSet<_>>();
+  assert_eq!(found, touching);
+  // grid point rows are left alone
+  assert!(indexes.iter().any(
+    |ix| ix.block_ref.block_type == BlockType::Displacements
+  ));
+  // blockify agrees
+  let cells = extraction.blockify(&f06).iter()
+    .map(|b| b.row_indexes.len() * b.col_indexes.len())
+    .sum::<usize>();
+  assert_eq!(cells, indexes.len());
+  // the exclusion list drops elements touching only excluded grids
+  let all_but = Extraction {
+    adjacent_grids: Specifier::AllExcept(vec![
+      GridPointRef { gid: 1011 },
+      GridPointRef { gid: 1012 }
+    ]),
+    ..Default::default()
+  };
+  assert!(!all_but.lookup(&f06).any(
+    |ix| ix.row.element_id().is_some_and(|e| e.eid == 1112)
+  ));
+  assert!(all_but.lookup(&f06).any(
+    |ix| ix.row.element_id().is_some_and(|e| e.eid == 11)
+  ));
+}
+
+#[test]
+fn test_parse_errors() {
+  use std::io;
+  let parse = |s: &[u8]| OnePassParser::parse_bufread(s);
+  assert!(matches!(parse(b""), Err(ParseError::Truncated)));
+  assert!(matches!(parse(b"hello\n"), Err(ParseError::UnknownSolver)));
+  // only some errors come with a hint
+  assert!(parse(b"hello\n").unwrap_err().hint().is_some());
+  assert!(parse(b"").unwrap_err().hint().is_none());
+  assert!(matches!(
+    parse(b" MYSTRAN\n\x00\x01\x02\n"),
+    Err(ParseError::Decode { line: 2 })
+  ));
+  assert!(parse(b" MYSTRAN Version 15.1.4\n").is_ok());
+  let missing = OnePassParser::parse_file("/nonexistent/file.f06");
+  let err: io::Error = missing.unwrap_err().into();
+  assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn test_consistency_checks() {
+  let dof_block = |block_type: BlockType, subcase: usize, rows: &[f64]| {
+    let gids = (1..=rows.len() / SIXDOF).collect::<Vec<_>>();
+    return FinalBlock {
+      block_type,
+      subcase,
+      ..grid_block(&gids, Dof::all(), rows)
+    };
+  };
+  let mut f06 = F06File::new();
+  // subcase 1 balances, moments are ignored
+  f06.insert_block(dof_block(BlockType::AppliedForces, 1, &[
+    0.0, 0.0, -100.0, 5.0, 0.0, 0.0,
+  ]));
+  f06.insert_block(dof_block(BlockType::SpcForces, 1, &[
+    0.0, 0.0, 60.0, 0.0, 0.0, 0.0,
+    0.0, 0.0, 40.0, 0.0, 0.0, 0.0,
+  ]));
+  // subcase 2 loses ten percent of the load
+  f06.insert_block(dof_block(BlockType::AppliedForces, 2, &[
+    0.0, 0.0, -100.0, 0.0, 0.0, 0.0,
+  ]));
+  f06.insert_block(dof_block(BlockType::SpcForces, 2, &[
+    0.0, 0.0, 90.0, 0.0, 0.0, 0.0,
+  ]));
+  let results = f06.consistency_checks(DEFAULT_CHECK_TOLERANCE);
+  assert_eq!(results.len(), 2);
+  assert!(results.iter().all(|r| r.kind == CheckKind::LoadReactionBalance));
+  assert!(results[0].passed);
+  assert!(!results[1].passed);
+  assert!((results[1].residual - 10.0).abs() < 1e-12);
+  assert!((results[1].relative() - 0.1).abs() < 1e-12);
+  // a looser tolerance lets it through
+  assert!(f06.consistency_checks(0.2).iter().all(|r| r.passed));
+}
+
+#[test]
+fn test_dump_row_major() {
+  // rows stored out of order in the matrix
+  let mut f06 = F06File::new();
+  f06.insert_block(FinalBlock {
+    line_range: Some((10, 20)),
+    ..grid_block(&[2, 1], &[DOF_TX, DOF_TY], &[2.1, 2.2, 1.1, 1.2])
+  });
+  let dump = F06Dump::from(&f06);
+  assert_eq!(dump.blocks.len(), 1);
+  let block = &dump.blocks[0];
+  assert_eq!(block.rows, vec![
+    NasIndex::from(GridPointRef { gid: 1 }),
+    NasIndex::from(GridPointRef { gid: 2 })
+  ]);
+  assert_eq!(block.cols, vec![NasIndex::from(DOF_TX), DOF_TY.into()]);
+  assert_eq!(
+    block.values,
+    Some(RowMajorValues::Reals(vec![vec![1.1, 1.2], vec![2.1, 2.2]]))
+  );
+}
+
+#[test]
+fn test_parse_mmap() {
+  let examples = examples_dir();
+  for solver in ["mystran", "scnastran"] {
+    for entry in std::fs::read_dir(examples.join(solver)).unwrap() {
+      let path = entry.unwrap().path();
+      let buffered = OnePassParser::parse_file(&path).expect("parse failed");
+      let mapped = OnePassParser::parse_mmap(&path).expect("mmap failed");
+      let (a, b) = (F06Dump::from(&buffered), F06Dump::from(&mapped));
+      assert_eq!(a.filename, b.filename);
+      assert_eq!(a.warnings, b.warnings);
+      assert_eq!(a.fatal_errors, b.fatal_errors);
+      assert_eq!(
+        buffered.potential_header_texts(),
+        mapped.potential_header_texts()
+      );
+      assert_eq!(a.blocks, b.blocks, "blocks differ in {:?}", path);
+    }
+  }
+  // settings apply to mapped files too
+  let path = example_path("scnastran", "SB-ALL-ELEM-TEST_scnas_postexport.f06");
+  let settings = ParserSettings {
+    keep_raw_blocks: true,
+    ..Default::default()
+  };
+  let mapped = OnePassParser::parse_mmap_with(
+    &path,
+    Flavour::default(),
+    settings
+  ).expect("mmap failed");
+  assert!(!mapped.raw_blocks.is_empty());
+  assert_eq!(mapped.raw_blocks.len(), mapped.potential_headers.len());
+}
+
+#[test]
+fn test_punch_parser() {
+  // the "$" lines that start a table
+  let table = |name: &str, etype: Option<&str>| {
+    let mut v = vec![
+      "$TITLE   = PUNCH TEST".to_owned(),
+      format!("${}", name),
+      "$REAL OUTPUT".to_owned(),
+      "$SUBCASE ID =           2".to_owned()
+    ];
+    v.extend(etype.map(|et| format!("$ELEMENT TYPE = {}", et)));
+    return v;
+  };
+  // a record, continued every three values
+  let record = |id: usize, text: &str, vals: &[f64]| {
+    return vals.chunks(3).enumerate().map(|(j, chunk)| {
+      let head = if j == 0 {
+        format!("{:>10}{:<8}", id, text)
+      } else {
+        format!("{:<18}", "-CONT-")
+      };
+      return head + &chunk.iter()
+        .map(|x| format!("{:>18}", format!("{:.6E}", x)))
+        .collect::<String>();
+    }).collect::<Vec<_>>();
+  };
+  let disp = [0.0, 0.0, -1.5, 0.0, 2.5e-3, 0.0];
+  let disp2 = disp.map(|x| x * 2.0);
+  let quad = [
+    -0.05, 100.0, -20.0, 5.0, 2.5, 100.5, -20.5, 110.0,
+    0.05, -100.0, 20.0, -5.0, -2.5, 20.5, -100.5, 110.0
+  ];
+  let lines = [
+    table("DISPLACEMENTS", None),
+    record(1, "G", &disp),
+    record(2, "G", &disp2),
+    table("ELEMENT STRESSES", Some("         33  QUAD4")),
+    record(10, "", &quad),
+    table("SPCF", None),
+    record(1, "G", &disp)
+  ].concat();
+  // with the line counters in columns 73-80
+  let text = lines.iter()
+    .enumerate()
+    .map(|(i, l)| format!("{:<72}{:>8}\n", l, i + 1))
+    .collect::<String>();
+  let f06 = PunchParser::parse_bufread(text.as_bytes()).expect("parse failed");
+  // displacements
+  let br = BlockRef::new(Subcase(2), BlockType::Displacements);
+  let blocks = f06.blocks.get(&br).expect("no displacements");
+  assert_eq!(blocks.len(), 1);
+  let gp = |gid: usize| GridPointRef { gid };
+  assert_eq!(blocks[0].row_indexes.len(), 2);
+  assert_eq!(blocks[0].get(gp(1), DOF_TZ), Some(F06Number::Real(-1.5)));
+  assert_eq!(blocks[0].get(gp(2), DOF_RY), Some(F06Number::Real(5e-3)));
+  // quad stresses, one row per side
+  let br = BlockRef::new(Subcase(2), BlockType::QuadStresses);
+  let block = &f06.blocks.get(&br).expect("no stresses")[0];
+  let esp = |side: ElementSide| ElementSidedPoint {
+    element: ElementRef { eid: 10, etype: Some(ElementType::Quad4) },
+    point: ElementPoint::Centroid,
+    side
+  };
+  let top = esp(ElementSide::Top);
+  let bottom = esp(ElementSide::Bottom);
+  let sx = PlateStressField::NormalX;
+  assert_eq!(block.get(bottom, sx), Some(F06Number::Real(100.0)));
+  assert_eq!(block.get(top, sx), Some(F06Number::Real(-100.0)));
+  let vm = block.get(top, PlateStressField::VonMises).map(f64::from);
+  assert!(vm.is_some_and(|x| (x - 110.0).abs() < 1e-9));
+  // unsupported tables are noted, not decoded
+  assert_eq!(f06.blocks.len(), 2);
+  assert!(f06.potential_header_texts().contains("SPCF"));
+}
+
+#[test]
+fn test_stop_on_fatal() {
+  let path = example_path("mystran", "SB-BUSH-01-OFFSET-2b.F06");
+  let text = std::fs::read_to_string(path).unwrap();
+  // put a fatal between the output blocks
+  let fatal_line = 155;
+  let mut lines = text.lines().collect::<Vec<_>>();
+  lines.insert(fatal_line - 1, " *** USER FATAL MESSAGE 1234 (TEST)");
+  let parse = |stop_on_fatal: bool| {
+    let mut parser = OnePassParser::new();
+    parser.apply_settings(
+      ParserSettings { stop_on_fatal, ..Default::default() }
+    );
+    for line in lines.iter() {
+      parser.consume(line);
+    }
+    return parser.finish();
+  };
+  let everything = parse(false);
+  let stopped = parse(true);
+  for f06 in [&everything, &stopped] {
+    let fatals = f06.fatal_errors.keys().copied().collect::<Vec<_>>();
+    assert_eq!(fatals, [fatal_line]);
+  }
+  let after = |f06: &F06File| f06.all_blocks(false)
+    .filter(|b| b.line_range.is_some_and(|(_, end)| end > fatal_line))
+    .count();
+  assert!(after(&everything) > 0);
+  assert_eq!(after(&stopped), 0);
+  assert!(stopped.all_blocks(false).count() > 0);
+}
+
+#[test]
+fn test_comment_prefixes() {
+  let path = example_path("mystran", "SB-BUSH-01-OFFSET-2b.F06");
+  let text = std::fs::read_to_string(path).unwrap();
+  // interleave comments with the displacement rows (lines 136 and 137)
+  let mut lines = text.lines().collect::<Vec<_>>();
+  lines.insert(137, "$ 3 0 9.0 9.0 9.0 9.0 9.0 9.0");
+  lines.insert(136, "$ injected by a wrapper");
+  lines.insert(135, "$$ another one");
+  let settings = ParserSettings {
+    comment_prefixes: vec!["$".to_owned()],
+    ..Default::default()
+  };
+  let mut parser = OnePassParser::new();
+  parser.apply_settings(settings);
+  let mut comments = 0;
+  for line in lines.iter() {
+    if matches!(parser.consume(line), ParserResponse::Comment) {
+      comments += 1;
+    }
+  }
+  let mut commented = parser.finish();
+  // the echoed input deck has its own comments
+  let echoed = text.lines().filter(|l| l.starts_with('$')).count();
+  assert_eq!(comments, echoed + 3);
+  let mut original = OnePassParser::parse_bufread(text.as_bytes()).unwrap();
+  for f06 in [&mut original, &mut commented] {
+    f06.merge_blocks(true);
+    f06.sort_all_blocks();
+  }
+  let disp = |f06: &F06File| f06
+    .block_search(Some(BlockType::Displacements), Some(Subcase(1)), false)
+    .next()
+    .cloned()
+    .unwrap();
+  let (a, b) = (disp(&original), disp(&commented));
+  assert_eq!(a.row_indexes, b.row_indexes);
+  assert_eq!(a.col_indexes, b.col_indexes);
+  for row in a.row_indexes.keys() {
+    for col in a.col_indexes.keys() {
+      assert_eq!(a.get(*row, *col), b.get(*row, *col));
+    }
+  }
+  // the comments still count towards line numbers
+  let (start, _) = b.line_range.unwrap();
+  assert_eq!(start, a.line_range.unwrap().0);
+  assert_eq!(b.line_range.unwrap().1, a.line_range.unwrap().1 + 3);
+}
+
+#[test]
+fn test_criteria_presets() {
+  for preset in CriteriaPreset::all() {
+    let crit = preset.criteria();
+    assert!(crit.ratio.is_some_and(|r| r > 1.0));
+    assert!(crit.ratio_floor > 0.0);
+  }
+  let stress = Criteria::preset_stress();
+  // within 5%, and near-zero noise is ignored
+  assert!(stress.check(100.0, 104.0).is_none());
+  assert!(stress.check(100.0, 106.0).is_some());
+  assert!(stress.check(1.0e-4, -2.0e-4).is_none());
+  assert!(stress.check(UNSET_SENTINEL, 1.0).is_none());
+  // explicit criteria take precedence over the preset's
+  let explicit = Criteria {
+    ratio: Some(1.5),
+    difference: Some(10.0),
+    ..Default::default()
+  };
+  let merged = explicit.or_from(&stress);
+  assert_eq!(merged.ratio, Some(1.5));
+  assert_eq!(merged.difference, Some(10.0));
+  assert_eq!(merged.ratio_floor, stress.ratio_floor);
+  assert_eq!(merged.unset_threshold, stress.unset_threshold);
+  // but whether non-finite values are flagged isn't taken from it
+  let quiet = Criteria { nan: false, inf: false, ..explicit };
+  let merged = quiet.or_from(&stress);
+  assert!(merged.check(f64::NAN, 1.0).is_none());
+  // unchecked infinities are still caught by the difference
+  assert!(matches!(
+    merged.check(1.0, f64::INFINITY),
+    Some(FlagReason::Difference { .. })
+  ));
+  let merged = Criteria { nan: true, inf: true, ..quiet }.or_from(&stress);
+  assert_eq!(merged.check(f64::NAN, 1.0), Some(FlagReason::NaN));
+  assert_eq!(merged.check(1.0, f64::NEG_INFINITY), Some(FlagReason::Infinity));
+}
+
+#[test]
+fn test_canonicalize_sides() {
+  let path = example_path("mystran", "SB-ALL-ELEM-TEST.F06");
+  let mut f06 = OnePassParser::parse_file(&path).unwrap();
+  f06.merge_blocks(true);
+  let block = f06.all_blocks(true)
+    .find(|b| b.block_type == BlockType::QuadStresses)
+    .expect("no quad stresses block")
+    .clone();
+  // mystran already puts the negative fibre on the bottom
+  let mut canon = block.clone();
+  assert_eq!(canon.canonicalize_sides(), 0);
+  assert_eq!(canon.row_indexes, block.row_indexes);
+  // a solver that labels them the other way around
+  let mut flipped = block.clone();
+  flipped.row_indexes = block.row_indexes.iter()
+    .map(|(row, i)| match row {
+      NasIndex::ElementSidedPoint(esp) => {
+        let mut esp = *esp;
+        esp.flip_side();
+        (esp.into(), *i)
+      },
+      _ => (*row, *i)
+    })
+    .collect::<BTreeMap<_, _>>();
+  let differ = DataDiffer {
+    criteria: Criteria { ratio: Some(1.001), ..Default::default() },
+    dxn_behaviour: DisjunctionBehaviour::Flag
+  };
+  let flags = |b: &FinalBlock| differ.compare(&block, b).unwrap().count();
+  assert!(flags(&flipped) > 0);
+  assert_eq!(flipped.canonicalize_sides(), block.row_indexes.len());
+  assert_eq!(flipped.row_indexes, block.row_indexes);
+  assert_eq!(flags(&flipped), 0);
+}
+
+#[test]
+fn test_eigenvector_modes() {
+  use std::collections::BTreeSet;
+  let examples = examples_dir();
+  let modes_in = |f06: &F06File| {
+    let block = f06.block_search(Some(BlockType::Eigenvectors), None, true)
+      .next()
+      .expect("no unique eigenvectors block");
+    return block.row_indexes.keys()
+      .filter_map(NasIndex::mode)
+      .collect::<BTreeSet<_>>();
+  };
+  // simcenter: a spaced-out title per mode, all in the same subcase
+  let path = examples.join("scnastran")
+    .join("SB-BUSH-01-OFFSET-2b_scnas_postexport.f06");
+  let text = std::fs::read_to_string(path).unwrap();
+  // keep everything up to the displacements, then write three modes
+  let mut lines = text.lines()
+    .take_while(|l| !l.contains("D I S P L A C E M E N T"))
+    .map(str::to_owned)
+    .collect::<Vec<_>>();
+  for mode in 1..=3 {
+    lines.extend([
+      format!("1    MODES    JANUARY  22, 2024  SIMCENTER NASTRAN  {}", mode),
+      format!("0{}SUBCASE 1", " ".repeat(60)),
+      "      EIGENVALUE =  3.947842E+01".to_owned(),
+      format!(
+        "          CYCLES =  1.000000E+00         {}          {}",
+        "R E A L   E I G E N V E C T O R   N O .",
+        mode
+      ),
+      " ".to_owned(),
+      "      POINT ID.   TYPE          T1             T2             T3"
+        .to_owned(),
+      "             1      G      0.0   0.0   0.0   0.0   0.0   0.0".to_owned(),
+      format!(
+        "             2      G      {}.0   0.0   0.0   0.0   0.0   0.0",
+        mode
+      )
+    ]);
+  }
+  let mut f06 = OnePassParser::parse_bufread(
+    lines.join("\n").as_bytes()
+  ).unwrap();
+  f06.merge_blocks(true);
+  assert_eq!(f06.subcases().collect::<Vec<_>>(), [Subcase(1)]);
+  assert_eq!(modes_in(&f06), BTreeSet::from([1, 2, 3]));
+  let block = f06.block_search(Some(BlockType::Eigenvectors), None, true)
+    .next()
+    .unwrap();
+  assert_eq!(block.row_indexes.len(), 6);
+  let row = GridPointMode { mode: 3, grid_point: GridPointRef { gid: 2 } };
+  assert_eq!(block.get(row, DOF_TX), Some(F06Number::Real(3.0)));
+  // mystran: a line before each mode, then a displacements table
+  let path = examples.join("mystran").join("SB-BUSH-01-OFFSET-2b.F06");
+  let text = std::fs::read_to_string(path).unwrap();
+  let lines = text.lines().collect::<Vec<_>>();
+  // lines 132 to 137 are the displacements table
+  let table = &lines[131..137];
+  let mut modal = lines[..131].to_vec();
+  modal.push(" OUTPUT FOR EIGENVECTOR        1");
+  modal.extend(table);
+  modal.push(" OUTPUT FOR EIGENVECTOR        2");
+  modal.extend(table);
+  modal.extend(&lines[137..]);
+  let mut f06 = OnePassParser::parse_bufread(
+    modal.join("\n").as_bytes()
+  ).unwrap();
+  f06.merge_blocks(true);
+  assert_eq!(f06.subcases().collect::<Vec<_>>(), [Subcase(1)]);
+  assert_eq!(modes_in(&f06), BTreeSet::from([1, 2]));
+  assert!(!f06.block_types().any(|bt| bt == BlockType::Displacements));
+}
+
+#[test]
+fn test_normalize_mode_shape() {
+  // two modes over grid points 1 and 2, in Tx and Ty
+  let block = |vals: &[f64]| {
+    let row = |mode: usize, gid: usize| {
+      return NasIndex::from(GridPointMode { mode, grid_point: gid.into() });
+    };
+    return FinalBlock {
+      block_type: BlockType::Eigenvectors,
+      row_indexes: [
+        (row(1, 1), 0), (row(1, 2), 1), (row(2, 1), 2), (row(2, 2), 3)
+      ].into(),
+      ..grid_block(&[1, 2, 3, 4], &[DOF_TX, DOF_TY], vals)
+    };
+  };
+  let get = |b: &FinalBlock, mode: usize, gid: usize, dof: Dof| {
+    let row = GridPointMode { mode, grid_point: gid.into() };
+    return b.get(row, dof).map(f64::from).unwrap();
+  };
+  let original = block(&[3.0, 0.0, 0.0, 4.0, 0.0, 0.0, -2.0, 0.0]);
+  // unit norm, with the largest component positive
+  let mut unit = original.clone();
+  assert_eq!(unit.normalize_mode_shape(ModeNormalization::UnitNorm), 2);
+  assert!((get(&unit, 1, 1, DOF_TX) - 0.6).abs() < 1e-12);
+  assert!((get(&unit, 1, 2, DOF_TY) - 0.8).abs() < 1e-12);
+  assert_eq!(get(&unit, 2, 2, DOF_TX), 1.0);
+  // a chosen DOF, leaving modes where it's zero alone
+  let ty2: ModeNormalization = "2:t2".parse().unwrap();
+  assert_eq!(ty2, ModeNormalization::UnitDof(2.into(), DOF_TY));
+  let mut by_dof = original.clone();
+  assert_eq!(by_dof.normalize_mode_shape(ty2), 1);
+  assert_eq!(get(&by_dof, 1, 1, DOF_TX), 0.75);
+  assert_eq!(get(&by_dof, 1, 2, DOF_TY), 1.0);
+  assert_eq!(get(&by_dof, 2, 2, DOF_TX), -2.0);
+  assert!("2:t9".parse::<ModeNormalization>().is_err());
+  assert_eq!("NORM".parse(), Ok(ModeNormalization::UnitNorm));
+  // other blocks are left alone
+  let mut disp = grid_block(&[1], &[DOF_TX], &[5.0]);
+  assert_eq!(disp.normalize_mode_shape(ModeNormalization::UnitNorm), 0);
+  // modes scaled differently only match once normalised, and only if asked
+  let file = |b: FinalBlock| {
+    let mut f06 = F06File::new();
+    f06.insert_block(b);
+    return f06;
+  };
+  let scaled = block(&[-9.0, 0.0, 0.0, -12.0, 0.0, 0.0, 0.5, 0.0]);
+  let (a, b) = (file(original), file(scaled));
+  let settings = DiffSettings {
+    criteria: Criteria { difference: Some(1e-9), ..Default::default() },
+    ..Default::default()
+  };
+  let flags = |s: &DiffSettings| {
+    return F06Diff::compare(s, &a, &b).compared.values().flatten().count();
+  };
+  assert!(flags(&settings) > 0);
+  let normalized = DiffSettings {
+    normalize_modes: Some(ModeNormalization::UnitNorm),
+    ..settings.clone()
+  };
+  assert_eq!(flags(&normalized), 0);
+}
+
+#[test]
+fn test_potential_header_gap() {
+  let text = [
+    " MYSTRAN Version 15.1.4",
+    "                    F O O B A R   S T R E S S E S",
+    "",
+    "                    I N   W I D G E T   E L E M E N T S",
+    "      1      2.0      3.0",
+  ].join("\n");
+  let parse = || OnePassParser::parse_bufread(text.as_bytes()).unwrap();
+  // without a gap, the blank line splits the header
+  let mut f06 = parse();
+  assert_eq!(f06.merge_potential_headers(), 0);
+  assert_eq!(f06.potential_headers.len(), 2);
+  // bridging it, they're glued back together
+  let mut f06 = parse();
+  assert_eq!(f06.merge_potential_headers_within(1), 1);
+  let merged = f06.potential_headers.first().unwrap();
+  assert_eq!(merged.text, "FOOBAR STRESSES IN WIDGET ELEMENTS");
+  assert_eq!((merged.start, merged.span), (2, 3));
+}
+
+
+#[test]
+fn test_scanners_fuzz() {
+  use crate::util::*;
+  // a small xorshift, so runs are reproducible without extra dependencies
+  let mut state: u64 = 0x9e3779b97f4a7c15;
+  let mut next = move |n: usize| {
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    return (state % n as u64) as usize;
+  };
+  // garbage made of the characters that matter, plus some multi-byte ones
+  let alphabet = "0123456789.+-eEdD ()*éµ²\t".chars().collect::<Vec<_>>();
+  for _ in 0..20000 {
+    let len = next(24);
+    let s = (0..len)
+      .map(|_| alphabet[next(alphabet.len())])
+      .collect::<String>();
+    // none of these may panic
+    let _ = decode_nasfloat(&s);
+    let _ = extract_reals::<3>(&s);
+    let _ = lax_reals::<2>(&s);
+    let _ = int_pattern(&s);
+    let _ = line_breakdown(&s).count();
+    let _ = check_header(&s);
+  }
+  // well-formed nastran floats must decode, with or without the E
+  for _ in 0..20000 {
+    let mantissa = 1.0 + next(9_000_000) as f64 / 1_000_000.0;
+    let exp = next(61) as i32 - 30;
+    let sign = if next(2) == 0 { "" } else { "-" };
+    let esign = if exp < 0 { "-" } else { "+" };
+    let sep = ["", "E", "e"][next(3)];
+    let s = format!("{}{:.6}{}{}{:02}", sign, mantissa, sep, esign, exp.abs());
+    let expected = format!("{}{:.6}e{}", sign, mantissa, exp)
+      .parse::<f64>()
+      .unwrap();
+    let decoded = decode_nasfloat(&s).expect("failed to decode");
+    assert!((decoded - expected).abs() <= 1e-9 * expected.abs(), "{}", s);
+  }
+  // and IDs followed by reals must come back as they went in
+  for _ in 0..2000 {
+    let rows = (0..1 + next(4))
+      .map(|i| {
+        let vals = (0..next(4))
+          .map(|_| (next(2_000_000) as f64 - 1_000_000.0) / 1000.0)
+          .collect::<Vec<_>>();
+        return (10 * i + next(10), vals);
+      })
+      .collect::<Vec<_>>();
+    let line = rows.iter()
+      .map(|(id, vals)| {
+        let vals = vals.iter().map(|v| format!("{:.6E}", v));
+        return std::iter::once(id.to_string())
+          .chain(vals)
+          .collect::<Vec<_>>()
+          .join("   ");
+      })
+      .collect::<Vec<_>>()
+      .join("   ");
+    let found = int_pattern(&line);
+    assert_eq!(found.len(), rows.len(), "{}", line);
+    for (id, vals) in rows.iter() {
+      let got = &found[id];
+      assert_eq!(got.len(), vals.len(), "{}", line);
+      for (a, b) in got.iter().zip(vals.iter()) {
+        assert!((a - b).abs() <= 1e-6 * b.abs().max(1.0), "{}", line);
+      }
+    }
+  }
+}
+
+#[test]
+fn test_self_check() {
+  let examples = examples_dir();
+  let mut f06 = parse_any_file(
+    examples.join("mystran").join("SB-BUSH-01-OFFSET-2b.F06"),
+    Flavour::default(),
+    ParserSettings::default()
+  ).expect("parse failed");
+  let mut csv = parse_any_file(
+    examples.join("selfcheck").join("SB-BUSH-01-OFFSET-2b.csv"),
+    Flavour::default(),
+    ParserSettings::default()
+  ).expect("self-check load failed");
+  assert_eq!(csv.flavour.solver, Some(Solver::Mystran));
+  assert_eq!(csv.blocks.len(), 3);
+  for file in [&mut f06, &mut csv] {
+    file.merge_blocks(true);
+    file.sort_all_blocks();
+  }
+  // the solver agrees with itself
+  let settings = DiffSettings {
+    criteria: Criteria { difference: Some(1e-9), ..Default::default() },
+    ..Default::default()
+  };
+  let diff = F06Diff::compare(&settings, &f06, &csv);
+  for bt in [
+    BlockType::Displacements,
+    BlockType::AppliedForces,
+    BlockType::SpcForces
+  ] {
+    let flags = diff.compared.get(&BlockRef::new(Subcase(1), bt));
+    assert_eq!(flags.map(Vec::len), Some(0), "{} not compared", bt);
+  }
+  // columns can be partial and out of order, and reals in Nastran notation
+  let text = "grid,r3,table,t1,subcase\n\
+    7, 1.5-3 ,SpcForces,2.0,4\n\
+    8,0.0,Modes,1.0,4\n";
+  let loaded = load_self_check(text.as_bytes()).expect("load failed");
+  let spcf = Some(BlockType::SpcForces);
+  let block = loaded.block_search(spcf, Some(Subcase(4)), false)
+    .next()
+    .expect("no block");
+  let g7 = GridPointRef { gid: 7 };
+  assert_eq!(block.col_indexes.len(), 2);
+  assert_eq!(block.get(g7, Dof::try_from(6).unwrap()), Some(1.5e-3.into()));
+  assert_eq!(block.get(g7, Dof::try_from(1).unwrap()), Some(2.0.into()));
+  assert_eq!(block.row_indexes.len(), 1);
+  // and bad ones are reported
+  let bad = load_self_check("subcase,table,grid\n".as_bytes());
+  assert!(matches!(bad, Err(SelfCheckError::NoValueColumns)));
+  let bad = load_self_check("subcase,grid,t1\n".as_bytes());
+  assert!(matches!(bad, Err(SelfCheckError::MissingColumn("TABLE"))));
+  let bad = load_self_check(
+    "subcase,table,grid,t1\n1,Displacements,x,0.0\n".as_bytes()
+  );
+  assert!(matches!(bad, Err(SelfCheckError::BadField { line: 2, .. })));
+}
+
+#[test]
+fn test_column_aliases() {
+  use crate::blocks::indexing::PlateStressField as PSF;
+  // two one-row quad stress files, one naming the last column differently
+  let file = |last: PSF| {
+    let mut f06 = F06File::new();
+    f06.insert_block(quad_block(&[PSF::NormalX, last], &[1.0, 2.0]));
+    return f06;
+  };
+  let (a, b) = (file(PSF::VonMises), file(PSF::Major));
+  let br = BlockRef::new(Subcase(1), BlockType::QuadStresses);
+  let mut settings = DiffSettings::default();
+  assert!(!F06Diff::compare(&settings, &a, &b).compared.contains_key(&br));
+  let alias = "major = Von Mises".parse::<ColumnAlias>().unwrap();
+  assert_eq!(alias.from, "major");
+  assert_eq!(alias.to, "Von Mises");
+  settings.column_aliases.push(alias);
+  let diff = F06Diff::compare(&settings, &a, &b);
+  assert_eq!(diff.compared.get(&br).map(Vec::len), Some(0));
+  // renaming onto an existing column doesn't happen
+  let mut block = a.blocks[&br][0].clone();
+  assert!(!block.rename_column(PSF::NormalX.into(), PSF::VonMises.into()));
+  assert!(block.rename_column(PSF::NormalX.into(), PSF::Minor.into()));
+  let row = *block.row_indexes.keys().next().unwrap();
+  assert_eq!(block.get(row, PSF::Minor), Some(1.0.into()));
+  assert!("major".parse::<ColumnAlias>().is_err());
+  assert!("=x".parse::<ColumnAlias>().is_err());
+}
+
+#[test]
+fn test_envelope() {
+  use crate::blocks::indexing::PlateStressField as PSF;
+  let path = example_path("scnastran", "SB-ALL-ELEM-TEST_scnas_postexport.f06");
+  let mut f06 = OnePassParser::parse_file(path).expect("parse failed");
+  f06.merge_blocks(true);
+  let bt = BlockType::QuadStresses;
+  let col: NasIndex = PSF::VonMises.into();
+  let env = f06.envelope(bt, col);
+  assert!(!env.is_empty());
+  let blocks = f06.block_search(Some(bt), None, true).collect::<Vec<_>>();
+  assert!(blocks.len() > 1);
+  for (row, (subcase, x)) in env.iter() {
+    // the value is the one in the governing subcase...
+    let governing = blocks.iter().find(|b| b.subcase == *subcase).unwrap();
+    assert_eq!(governing.get(*row, col), Some((*x).into()));
+    // ...and none of the others is any larger
+    for block in blocks.iter() {
+      if let Some(y) = block.get(*row, col).map(f64::from) {
+        assert!(y.abs() <= x.abs());
+      }
+    }
+  }
+  // no such column, no envelope
+  assert!(f06.envelope(bt, Dof::try_from(1).unwrap().into()).is_empty());
+}
+
+#[test]
+fn test_decoder_panic() {
+  use crate::blocks::OpaqueDecoder;
+  /// A decoder that panics on lines that say so.
+  struct PanickyDecoder;
+  impl OpaqueDecoder for PanickyDecoder {
+    fn block_type(&self) -> BlockType {
+      return BlockType::Displacements;
+    }
+    fn consume(&mut self, line: &str) -> LineResponse {
+      if line.contains("PANIC") {
+        panic!("told to panic");
+      }
+      return LineResponse::Useless;
+    }
+    fn good_header(&mut self, _header: &str) -> bool {
+      return true;
+    }
+    fn hint_last(&mut self, _last: NasIndex) {}
+    fn hint_mode(&mut self, _mode: usize) {}
+    fn last_index(&self) -> Option<NasIndex> {
+      return None;
+    }
+    fn set_duplicate_policy(&mut self, _policy: DuplicateRowPolicy) {}
+    fn finalise(
+      self: Box<Self>,
+      _subcase: usize,
+      _line_range: Option<(usize, usize)>
+    ) -> FinalBlock {
+      panic!("should have been dropped");
+    }
+  }
+  let path = example_path("mystran", "SB-BUSH-01-OFFSET-2b.F06");
+  let text = std::fs::read_to_string(path).unwrap();
+  let mut parser = OnePassParser::new();
+  parser.hint_flavour(Flavour { solver: Some(Solver::Mystran), soltype: None });
+  parser.start_decoder(Box::new(PanickyDecoder));
+  parser.consume("  nothing to see here");
+  let resp = parser.consume("  PANIC");
+  assert!(matches!(
+    resp,
+    ParserResponse::PassedToDecoder(_, LineResponse::Abort)
+  ));
+  // the rest of the file still gets parsed
+  for line in text.lines() {
+    parser.consume(line);
+  }
+  let f06 = parser.finish();
+  assert!(f06.warnings.get(&2).is_some_and(|w| w.contains("told to panic")));
+  let reference = OnePassParser::parse_bufread(text.as_bytes()).unwrap();
+  assert_eq!(f06.blocks.len(), reference.blocks.len());
+  // a warning in the same line as a panic doesn't replace it
+  let mut parser = OnePassParser::new();
+  parser.hint_flavour(Flavour { solver: Some(Solver::Mystran), soltype: None });
+  parser.start_decoder(Box::new(PanickyDecoder));
+  parser.consume(" *** USER WARNING MESSAGE 1234");
+  let f06 = parser.finish();
+  let warning = f06.warnings.get(&1).expect("no warning");
+  assert!(warning.contains("should have been dropped"));
+  assert!(warning.contains("USER WARNING MESSAGE 1234"));
+}
+
+#[test]
+fn test_subcase_labels() {
+  let examples = examples_dir();
+  let paths = [
+    examples.join("mystran").join("SB-ALL-ELEM-TEST.F06"),
+    examples.join("scnastran").join("SB-ALL-ELEM-TEST_scnas_postexport.f06")
+  ];
+  for path in paths {
+    let f06 = OnePassParser::parse_file(path).expect("parse failed");
+    assert_eq!(
+      f06.subcase_labels.get(&Subcase(91)).map(String::as_str),
+      Some("PRESSURE LOAD ON PLATE ELEMENTS")
+    );
+    assert_eq!(
+      f06.subcase_labels.get(&Subcase(92)).map(String::as_str),
+      Some("END LOADS ON TRUSS")
+    );
+    assert_eq!(f06.subcases_labelled("truss"), vec![Subcase(92)]);
+    assert_eq!(
+      f06.subcases_labelled("LOAD"),
+      vec![Subcase(91), Subcase(92)]
+    );
+    assert!(f06.subcases_labelled("landing").is_empty());
+    // labels can be overridden after parsing
+    let mut f06 = f06;
+    let relabel = "92 = LANDING".parse::<SubcaseRelabel>().unwrap();
+    f06.relabel_subcases([&relabel]);
+    assert_eq!(f06.subcase_label(Subcase(92)), Some("LANDING"));
+    assert_eq!(f06.subcases_labelled("landing"), vec![Subcase(92)]);
+    let old = f06.set_subcase_label(Subcase(91), "TAKEOFF");
+    assert_eq!(old.as_deref(), Some("PRESSURE LOAD ON PLATE ELEMENTS"));
+    assert_eq!(f06.subcase_label(Subcase(91)), Some("TAKEOFF"));
+  }
+  assert!("92".parse::<SubcaseRelabel>().is_err());
+  assert!("x=LANDING".parse::<SubcaseRelabel>().is_err());
+  assert!("92=".parse::<SubcaseRelabel>().is_err());
+  // punch files set the label before the subcase ID
+  let text = "\
+$LABEL   = LANDING                                                       1
+$DISPLACEMENTS                                                           2
+$REAL OUTPUT                                                             3
+$SUBCASE ID =           7                                                4
+";
+  let f06 = PunchParser::parse_bufread(text.as_bytes()).expect("parse failed");
+  assert_eq!(
+    f06.subcase_labels.get(&Subcase(7)).map(String::as_str),
+    Some("LANDING")
+  );
+}
+
+#[test]
+fn test_extraction_properties() {
+  use std::collections::BTreeSet;
+  let path = example_path("mystran", "SB-ALL-ELEM-TEST.F06");
+  let mut f06 = OnePassParser::parse_file(&path).unwrap();
+  f06.merge_blocks(true);
+  f06.sort_all_blocks();
+  assert_eq!(f06.properties.get(&11), Some(&91));
+  assert_eq!(f06.properties.get(&1121), Some(&98));
+  let bars = f06.properties.iter()
+    .filter(|(_, pid)| **pid == 98)
+    .map(|(eid, _)| *eid)
+    .collect::<BTreeSet<_>>();
+  let extraction = Extraction {
+    properties: Specifier::List(vec![98]),
+    ..Default::default()
+  };
+  let indexes = extraction.lookup(&f06).collect::<Vec<_>>();
+  let found = indexes.iter()
+    .filter_map(|ix| ix.row.element_id())
+    .map(|e| e.eid)
+    .collect::<BTreeSet<_>>();
+  assert_eq!(found, bars);
+  // grid point rows are left alone
+  assert!(indexes.iter().any(
+    |ix| ix.block_ref.block_type == BlockType::Displacements
+  ));
+  // blockify agrees
+  let cells = extraction.blockify(&f06).iter()
+    .map(|b| b.row_indexes.len() * b.col_indexes.len())
+    .sum::<usize>();
+  assert_eq!(cells, indexes.len());
+}
+
+#[test]
+fn test_block_delta() {
+  let tx: NasIndex = Dof::try_from(1).unwrap().into();
+  let ty: NasIndex = Dof::try_from(2).unwrap().into();
+  let gp = |gid: usize| NasIndex::from(GridPointRef { gid });
+  // rows are given out of order, to make sure the indexes are followed
+  let block = |rows: &[(usize, [f64; 2])]| {
+    let gids = rows.iter().map(|r| r.0).collect::<Vec<_>>();
+    let vals = rows.iter().flat_map(|r| r.1).collect::<Vec<_>>();
+    return grid_block(&gids, &[DOF_TX, DOF_TY], &vals);
+  };
+  let a = block(&[(2, [5.0, -1.0]), (1, [1.0, 1.0])]);
+  let b = block(&[(3, [9.0, 9.0]), (2, [2.0, 1.5])]);
+  // only the common row is there, and the sign is a - b
+  let d = a.delta(&b).expect("compatible blocks");
+  assert_eq!(d.row_indexes.keys().copied().collect::<Vec<_>>(), vec![gp(2)]);
+  assert_eq!(d.get(gp(2), tx), Some(F06Number::Real(3.0)));
+  assert_eq!(d.get(gp(2), ty), Some(F06Number::Real(-2.5)));
+  let d = b.delta(&a).expect("compatible blocks");
+  assert_eq!(d.get(gp(2), tx), Some(F06Number::Real(-3.0)));
+  // no common rows, no values
+  let c = block(&[(4, [0.0, 0.0])]);
+  assert!(a.delta(&c).expect("compatible blocks").row_indexes.is_empty());
+  // incompatible blocks are refused
+  let mut other = b.clone();
+  other.subcase = 2;
+  assert_eq!(a.delta(&other).err(), Some(MergeIncompatible::SubcaseMismatch));
+  let mut other = b.clone();
+  other.col_indexes.remove(&ty);
+  assert!(matches!(
+    a.delta(&other),
+    Err(MergeIncompatible::ColumnConflict { .. })
+  ));
+  // naturals can go negative
+  let mut n = a.clone();
+  n.data = Some(DMatrix::from_row_slice(2, 2, &[5usize, 1, 1, 1]).into());
+  let mut m = b.clone();
+  m.data = Some(DMatrix::from_row_slice(2, 2, &[9usize, 9, 7, 1]).into());
+  let d = n.delta(&m).expect("compatible blocks");
+  assert_eq!(d.get(gp(2), tx), Some(F06Number::Integer(-2)));
+}
+
+#[test]
+fn test_raw_blocks() {
+  let path = example_path("scnastran", "SB-ALL-ELEM-TEST_scnas_postexport.f06");
+  // off by default
+  let f06 = OnePassParser::parse_file(&path).expect("parse failed");
+  assert!(f06.raw_blocks.is_empty());
+  let settings = ParserSettings {
+    keep_raw_blocks: true,
+    ..Default::default()
+  };
+  let f06 = OnePassParser::parse_file_with(&path, Flavour::default(), settings)
+    .expect("parse failed");
+  // one per potential header
+  assert_eq!(f06.raw_blocks.len(), f06.potential_headers.len());
+  for raw in f06.raw_blocks.iter() {
+    assert!(f06.potential_headers.iter().any(
+      |ph| ph.start == raw.line_range.0 && ph.text == raw.header
+    ));
+    assert!(raw.line_range.0 <= raw.line_range.1);
+    assert!(raw.lines.last().is_none_or(|l| !l.trim().is_empty()));
+  }
+  // the triangle forces are kept whole, up to the page break
+  let forces = f06.raw_blocks.iter()
+    .find(|r| r.header.contains("FORCES IN TRIANGULAR") && r.subcase == 91)
+    .expect("no raw block for the triangle forces");
+  let rows = forces.lines.iter()
+    .filter_map(|l| l.split_whitespace().next())
+    .filter(|w| w.parse::<usize>().is_ok())
+    .count();
+  assert_eq!(rows, 4);
+  assert_eq!(
+    forces.line_range.1 - forces.line_range.0,
+    forces.lines.len()
+  );
+}
+
+#[test]
+fn test_phase_tolerance() {
+  // the short way around, across 0/360
+  assert_eq!(phase_difference_deg(359.0, 1.0), 2.0);
+  assert_eq!(phase_difference_deg(1.0, 359.0), 2.0);
+  assert_eq!(phase_difference_deg(-179.0, 179.0), 2.0);
+  assert_eq!(phase_difference_deg(720.0, 0.0), 0.0);
+  assert_eq!(phase_difference_deg(0.0, 180.0), 180.0);
+  assert!(phase_difference_deg(f64::NAN, 0.0).is_nan());
+  let crit = Criteria {
+    ratio: Some(1.01),
+    phase_tolerance_deg: Some(5.0),
+    ..Default::default()
+  };
+  // close phases near the wrap pass, far ones get flagged
+  assert!(crit.check_polar((1.0, 358.0), (1.0, 2.0)).is_none());
+  assert!(crit.check_polar((1.0, 0.0), (1.0, -4.5)).is_none());
+  assert_eq!(
+    crit.check_polar((1.0, 355.0), (1.0, 6.0)),
+    Some(FlagReason::Phase { abs_difference_deg: 11.0, max_tolerance_deg: 5.0 })
+  );
+  assert!(crit.check_polar((1.0, 90.0), (1.0, 270.0)).is_some());
+  // magnitudes use the usual criteria, and go first
+  assert!(matches!(
+    crit.check_polar((1.0, 0.0), (2.0, 180.0)),
+    Some(FlagReason::Ratio { .. })
+  ));
+  // without a phase tolerance, phases are ignored
+  let mags_only = Criteria { phase_tolerance_deg: None, ..crit };
+  assert!(mags_only.check_polar((1.0, 0.0), (1.0, 180.0)).is_none());
+  assert_eq!(
+    mags_only.or_from(&crit).phase_tolerance_deg,
+    crit.phase_tolerance_deg
+  );
+  // NaN phases are NaNs
+  assert_eq!(
+    crit.check_polar((1.0, f64::NAN), (1.0, 0.0)),
+    Some(FlagReason::NaN)
+  );
+  // and the tolerance carries through to file diffs
+  let file = |re: f64, im: f64| {
+    let mut f06 = F06File::new();
+    f06.insert_block(complex_block(re, im));
+    return f06;
+  };
+  let settings = DiffSettings { criteria: crit, ..Default::default() };
+  let diff = F06Diff::compare(&settings, &file(1.0, 0.0), &file(-1.0, 0.0));
+  let flags = diff.compared.values().flatten().collect::<Vec<_>>();
+  assert_eq!(flags.len(), 1);
+  assert!(matches!(flags[0].reason, FlagReason::Phase { .. }));
+}
+
+#[test]
+fn test_disjunction_behaviour() {
+  // one-column displacements, grid points 1..=n, all ones
+  let file = |n: usize, solver: Solver| {
+    let gids = (1..=n).collect::<Vec<_>>();
+    let mut f06 = F06File::new();
+    f06.flavour.solver = Some(solver);
+    f06.insert_block(grid_block(&gids, &[DOF_TX], &vec![1.0; n]));
+    return f06;
+  };
+  let a = file(3, Solver::Mystran);
+  let b = file(5, Solver::Simcenter);
+  let br = BlockRef::new(Subcase(1), BlockType::Displacements);
+  let count = |dxn: DisjunctionBehaviour| {
+    let settings = DiffSettings {
+      criteria: Criteria { difference: Some(0.1), ..Default::default() },
+      dxn_behaviour: Some(dxn),
+      ..Default::default()
+    };
+    return F06Diff::compare(&settings, &a, &b).compared[&br].len();
+  };
+  // the two extra rows are left out, compared to zero, or flagged outright
+  assert_eq!(count(DisjunctionBehaviour::Skip), 0);
+  assert_eq!(count(DisjunctionBehaviour::AssumeZeroes), 2);
+  assert_eq!(count(DisjunctionBehaviour::Flag), 2);
+  // the default depends on whether the solvers differ
+  let mut settings = DiffSettings { dxn_behaviour: None, ..Default::default() };
+  assert_eq!(
+    settings.resolve_dxn_behaviour(&a, &b),
+    DisjunctionBehaviour::Skip
+  );
+  settings.dxn_behaviour = None;
+  assert_eq!(
+    settings.resolve_dxn_behaviour(&a, &a),
+    DisjunctionBehaviour::AssumeZeroes
+  );
+  // and an explicit choice is kept
+  settings.dxn_behaviour = Some(DisjunctionBehaviour::Flag);
+  assert_eq!(
+    settings.resolve_dxn_behaviour(&a, &b),
+    DisjunctionBehaviour::Flag
+  );
+}
+
+#[test]
+fn test_insert_block_merge() {
+  // one-column displacements for some grid points, all with the same value
+  let tx: NasIndex = Dof::try_from(1).unwrap().into();
+  let block = |subcase: usize, gids: &[usize], x: f64| FinalBlock {
+    subcase,
+    ..grid_block(gids, &[DOF_TX], &vec![x; gids.len()])
+  };
+  let br = BlockRef::new(Subcase(1), BlockType::Displacements);
+  let g = |gid: usize| GridPointRef { gid };
+  // inserting just appends, in order
+  let mut f06 = F06File::new();
+  f06.insert_block(block(1, &[1, 2], 1.0));
+  f06.insert_block(block(1, &[3], 2.0));
+  f06.insert_block(block(2, &[1], 3.0));
+  assert_eq!(f06.blocks.len(), 2);
+  assert_eq!(f06.blocks[&br].len(), 2);
+  assert_eq!(f06.blocks[&br][1].get(g(3), tx), Some(2.0.into()));
+  assert_eq!(f06.all_blocks(false).count(), 3);
+  assert_eq!(f06.all_blocks(true).count(), 1);
+  // merging joins the rows, leaving other subcases alone
+  assert_eq!(f06.merge_blocks(true), 1);
+  assert_eq!(f06.blocks[&br].len(), 1);
+  let merged = &f06.blocks[&br][0];
+  assert_eq!(merged.row_indexes.len(), 3);
+  assert_eq!(merged.get(g(1), tx), Some(1.0.into()));
+  assert_eq!(merged.get(g(3), tx), Some(2.0.into()));
+  assert_eq!(f06.all_blocks(true).count(), 2);
+  assert_eq!(f06.merge_report().len(), 1);
+  // conflicting rows prevent clean merges...
+  let mut f06 = F06File::new();
+  f06.insert_block(block(1, &[1, 2], 1.0));
+  f06.insert_block(block(1, &[2, 3], 2.0));
+  assert_eq!(f06.merge_blocks(true), 0);
+  assert_eq!(f06.blocks[&br].len(), 2);
+  // ...and in unclean ones, the block inserted last wins
+  assert_eq!(f06.merge_blocks(false), 1);
+  let merged = &f06.blocks[&br][0];
+  assert_eq!(merged.row_indexes.len(), 3);
+  assert_eq!(merged.get(g(1), tx), Some(1.0.into()));
+  assert_eq!(merged.get(g(2), tx), Some(2.0.into()));
+  let report = &f06.merge_report()[0];
+  assert_eq!(report.skipped, [g(2).into()].into());
+  assert_eq!(report.conflicts.len(), 1);
+  // blocks with different columns are left alone
+  let mut f06 = F06File::new();
+  let mut other = block(1, &[4], 1.0);
+  other.rename_column(tx, Dof::try_from(2).unwrap().into());
+  f06.insert_block(block(1, &[1], 1.0));
+  f06.insert_block(other);
+  assert_eq!(f06.merge_blocks(false), 0);
+  assert_eq!(f06.blocks[&br].len(), 2);
+}
+
+#[test]
+fn test_compare_intersection() {
+  use crate::blocks::indexing::PlateStressField as PSF;
+  // one-row quad stress files, with the same value in every column
+  let file = |cols: &[PSF]| {
+    let mut f06 = F06File::new();
+    f06.insert_block(quad_block(cols, &vec![1.0; cols.len()]));
+    return f06;
+  };
+  let a = file(&[PSF::NormalX, PSF::VonMises]);
+  let b = file(&[PSF::NormalX, PSF::Major, PSF::Minor, PSF::VonMises]);
+  let br = BlockRef::new(Subcase(1), BlockType::QuadStresses);
+  // by default, the whole block is left out
+  let mut settings = DiffSettings {
+    criteria: Criteria { difference: Some(0.1), ..Default::default() },
+    ..Default::default()
+  };
+  let diff = F06Diff::compare(&settings, &a, &b);
+  assert!(!diff.compared.contains_key(&br));
+  assert!(matches!(
+    diff.not_compared.get(&br),
+    Some(NonCompareReason::NotCompatible(
+      IncompatibilityReason::DifferentColumns
+    ))
+  ));
+  // in intersection mode, the common columns are compared
+  settings.compare_mode = CompareMode::Intersection;
+  let diff = F06Diff::compare(&settings, &a, &b);
+  assert!(diff.not_compared.is_empty());
+  assert_eq!(diff.compared.get(&br).map(Vec::len), Some(0));
+  let dropped = &diff.dropped_columns[&br];
+  assert!(dropped.only_in_a.is_empty());
+  assert_eq!(
+    dropped.only_in_b,
+    [PSF::Major.into(), PSF::Minor.into()].into()
+  );
+  // but blocks with no common columns are still incompatible
+  let c = file(&[PSF::ShearXY]);
+  let diff = F06Diff::compare(&settings, &a, &c);
+  assert!(diff.not_compared.contains_key(&br));
+  assert!(diff.dropped_columns.is_empty());
+}
+
+#[test]
+fn test_extraction_threshold() {
+  use crate::blocks::indexing::PlateStressField as PSF;
+  let path = example_path("mystran", "SB-ALL-ELEM-TEST.F06");
+  let mut f06 = OnePassParser::parse_file(&path).unwrap();
+  f06.merge_blocks(true);
+  f06.sort_all_blocks();
+  let vm: NasIndex = PSF::VonMises.into();
+  let mut extraction = Extraction {
+    block_types: Specifier::List(vec![BlockType::QuadStresses]),
+    cols: Specifier::List(vec![vm]),
+    ..Default::default()
+  };
+  let all = extraction.lookup(&f06).collect::<Vec<_>>();
+  // keep the rows above the median von Mises stress
+  let mut values = all.iter()
+    .map(|ix| f64::from(ix.get_from(&f06).unwrap()))
+    .collect::<Vec<_>>();
+  values.sort_by(f64::total_cmp);
+  let threshold = values[values.len() / 2];
+  extraction.value_threshold = Some(threshold);
+  let high = extraction.lookup(&f06).collect::<Vec<_>>();
+  assert!(!high.is_empty() && high.len() < all.len());
+  assert!(high.iter().all(
+    |ix| f64::from(ix.get_from(&f06).unwrap()).abs() > threshold
+  ));
+  let blocks = extraction.blockify(&f06);
+  let rows = blocks.iter().map(|b| b.row_indexes.len()).sum::<usize>();
+  assert_eq!(rows, high.len());
+  // rows are kept whole if any selected column passes
+  extraction.cols = Specifier::All;
+  let wide = extraction.lookup(&f06).collect::<Vec<_>>();
+  let ncols = PSF::all().len();
+  assert!(wide.len() >= high.len() * ncols);
+  // and nothing is kept above the maximum
+  extraction.value_threshold = values.last().copied();
+  extraction.cols = Specifier::List(vec![vm]);
+  assert_eq!(extraction.lookup(&f06).count(), 0);
+}
+
+#[test]
+fn test_content_fingerprint() {
+  let path = example_path("mystran", "SB-BUSH-01-OFFSET-2b.F06");
+  let mut f06 = OnePassParser::parse_file(&path).unwrap();
+  f06.merge_blocks(true);
+  let fp = f06.content_fingerprint();
+  // fingerprints are meant to be stored, so they can't drift across builds
+  assert_eq!(fp, 0x1b86_72d5_7792_8ce5);
+  // changes the first value of the displacements
+  let change = |f06: &F06File, factor: f64, offset: f64| {
+    let mut f06 = f06.clone();
+    let block = f06.all_blocks_mut(false)
+      .find(|b| b.block_type == BlockType::Displacements)
+      .expect("no displacements");
+    if let Some(FinalDMat::Reals(ref mut m)) = block.data {
+      m[(0, 0)] = m[(0, 0)] * factor + offset;
+    }
+    return f06.content_fingerprint();
+  };
+  // the same results from elsewhere, laid out differently, hash the same
+  let mut same = f06.clone();
+  same.filename = Some("other.f06".to_owned());
+  same.warnings.clear();
+  same.all_blocks_mut(false).for_each(|b| {
+    b.line_range = None;
+    let first = *b.row_indexes.keys().next().unwrap();
+    let last = *b.row_indexes.keys().last().unwrap();
+    b.swap_rows(first, last);
+  });
+  assert_eq!(same.content_fingerprint(), fp);
+  // noise past the rounding doesn't change it, but actual changes do
+  assert_eq!(change(&f06, 1.0 + 1e-13, 0.0), fp);
+  assert_ne!(change(&f06, 1.0, 1.0), fp);
+  assert_ne!(F06File::new().content_fingerprint(), fp);
+  // the same results at different steps differ
+  let at = |x: f64| {
+    let mut f06 = F06File::new();
+    f06.insert_block(FinalBlock {
+      step: Some(OutputStep::Frequency(x)),
+      ..grid_block(&[1], &[DOF_TX], &[1.0])
+    });
+    return f06.content_fingerprint();
+  };
+  assert_eq!(at(10.0), at(10.0));
+  assert_ne!(at(10.0), at(20.0));
+  let time = FinalBlock {
+    step: Some(OutputStep::Time(10.0)),
+    ..grid_block(&[1], &[DOF_TX], &[1.0])
+  };
+  let unstepped = grid_block(&[1], &[DOF_TX], &[1.0]);
+  assert_ne!(time.content_fingerprint(), unstepped.content_fingerprint());
+}
+
+#[test]
+fn test_load_info() {
+  let examples = examples_dir();
+  let parse = |path: PathBuf| {
+    let mut f06 = OnePassParser::parse_file(path).expect("parse failed");
+    f06.merge_blocks(true);
+    return f06;
+  };
+  let a = parse(examples.join("mystran").join("SB-ALL-ELEM-TEST.F06"));
+  let mut b = parse(
+    examples.join("scnastran").join("SB-ALL-ELEM-TEST_scnas_postexport.f06")
+  );
+  for f06 in [&a, &b] {
+    let sets = f06.load_info.iter()
+      .map(|(sc, li)| (*sc, li.load_set))
+      .collect::<Vec<_>>();
+    assert_eq!(sets, vec![(Subcase(91), 1991), (Subcase(92), 1992)]);
+  }
+  // a subcase without SUBCASE is the first
+  let bush = parse(examples.join("mystran").join("SB-BUSH-01-OFFSET-2b.F06"));
+  assert_eq!(bush.load_info.get(&Subcase(1)).map(|li| li.load_set), Some(1));
+  // scales come from echoed load combinations
+  let text = "\
+SUBCASE 3
+  LOAD = 10
+  OLOAD = ALL
+ BEGIN BULK
+LOAD,10,2.5,1.0,11
+ENDDATA
+";
+  let hint = Flavour { solver: Some(Solver::Mystran), soltype: None };
+  let f06 = OnePassParser::parse_bufread_hinted(text.as_bytes(), hint)
+    .unwrap();
+  assert_eq!(
+    f06.load_info.get(&Subcase(3)),
+    Some(&LoadInfo { load_set: 10, scale: Some(2.5) })
+  );
+  // and a diff picks up differing loads
+  let settings = DiffSettings::default();
+  assert!(F06Diff::compare(&settings, &a, &b).load_mismatches.is_empty());
+  b.load_info.get_mut(&Subcase(92)).unwrap().load_set = 2992;
+  let mismatches = F06Diff::compare(&settings, &a, &b).load_mismatches;
+  assert_eq!(mismatches.len(), 1);
+  assert_eq!(mismatches[0].subcase, Subcase(92));
+  assert_eq!(mismatches[0].second.load_set, 2992);
+  // going by the subcase map
+  let settings = DiffSettings {
+    subcase_map: Some("91=92,92=91".parse().unwrap()),
+    ..Default::default()
+  };
+  let mismatches = F06Diff::compare(&settings, &a, &b).load_mismatches;
+  assert_eq!(mismatches.len(), 2);
+}
+
+#[test]
+fn test_compare_metric_location() {
+  let tx: NasIndex = Dof::try_from(1).unwrap().into();
+  let ty: NasIndex = Dof::try_from(2).unwrap().into();
+  let gp = |gid: usize| NasIndex::from(GridPointRef { gid });
+  // five grid points, two columns, rows given out of order
+  let block = |rows: &[(usize, [f64; 2])]| {
+    let gids = rows.iter().map(|r| r.0).collect::<Vec<_>>();
+    let vals = rows.iter().flat_map(|r| r.1).collect::<Vec<_>>();
+    return grid_block(&gids, &[DOF_TX, DOF_TY], &vals);
+  };
+  let reference = block(&[
+    (5, [5.0, 0.5]), (1, [1.0, 0.1]), (3, [3.0, 0.3]),
+    (2, [2.0, 0.2]), (4, [4.0, 0.4])
+  ]);
+  // grid point 4 is way off in Ty, the others are a bit off
+  let testing = block(&[
+    (1, [1.1, 0.1]), (2, [2.0, 0.21]), (3, [3.0, 0.3]),
+    (4, [4.0, 9.4]), (5, [5.0, 0.5])
+  ]);
+  for metric in ColumnCompareMetric::all() {
+    let (x, row) = metric.compute_located(&reference, &testing, ty)
+      .expect("no value");
+    assert_eq!(row, gp(4), "{}", metric);
+    assert_eq!(Some(x), metric.compute(&reference, &testing, ty));
+  }
+  let mad = ColumnCompareMetric::MaximumAbsoluteDifference;
+  let (x, row) = mad.compute_located(&reference, &testing, tx).unwrap();
+  assert!((x - 0.1).abs() < 1e-12);
+  assert_eq!(row, gp(1));
+  // skipping the outlier's values moves the location
+  let opts = MetricOptions { unset_threshold: Some(9.0), ..Default::default() };
+  let (_, row) = mad.compute_located_with(&reference, &testing, ty, &opts)
+    .unwrap();
+  assert_eq!(row, gp(2));
+  // and there's nothing to locate without common rows
+  let lone = block(&[(6, [1.0, 1.0])]);
+  assert_eq!(mad.compute_located(&reference, &lone, tx), None);
+}
+
+#[test]
+fn test_etype_overrides() {
+  use std::str::FromStr;
+  let reals = concat!(
+    "  -5.000000E-01   1.000000E+00   2.000000E+00   3.000000E-01",
+    "    10.0000    2.000000E+00   1.000000E+00   1.700000E+00"
+  );
+  let lines = [
+    concat!(
+      "                         S T R E S S E S   I N   Q U A D R I L A T",
+      " E R A L   E L E M E N T S   ( Q U A D 8 )"
+    ).to_string(),
+    concat!(
+      "  ELEMENT              FIBER            STRESSES IN ELEMENT COORD ",
+      "SYSTEM         PRINCIPAL STRESSES (ZERO SHEAR)"
+    ).to_string(),
+    concat!(
+      "    ID      GRID-ID   DISTANCE        NORMAL-X      NORMAL-Y     ",
+      "SHEAR-XY      ANGLE        MAJOR         MINOR       VON MISES"
+    ).to_string(),
+    format!("0       7    CEN/4 {}", reals),
+    format!("                  {}", reals),
+    "1    SOME TITLE                    JANUARY  12, 2024  SIMCENTER NASTRAN"
+      .to_string()
+  ];
+  let etypes = |settings: ParserSettings| {
+    let mut parser = OnePassParser::new();
+    parser.hint_flavour(Flavour {
+      solver: Some(Solver::Simcenter),
+      soltype: None
+    });
+    parser.apply_settings(settings);
+    for line in lines.iter() {
+      parser.consume(line);
+    }
+    return parser.finish()
+      .all_blocks(true)
+      .find(|b| b.block_type == BlockType::QuadStresses)
+      .expect("no quad stresses block")
+      .row_indexes
+      .keys()
+      .map(|ri| match ri {
+        NasIndex::ElementSidedPoint(esp) => esp.element.etype,
+        _ => panic!("unexpected row index {}", ri)
+      })
+      .collect::<Vec<_>>();
+  };
+  // the header says QUAD8, but the rows are of a QUAD4
+  let found = etypes(ParserSettings::default());
+  assert!(!found.is_empty());
+  assert!(found.iter().all(|et| *et == Some(ElementType::Quad8)));
+  let settings = ParserSettings {
+    etype_overrides: vec![
+      EtypeOverride::from_str("elements (quad8)=quad4").unwrap()
+    ],
+    ..Default::default()
+  };
+  let found = etypes(settings);
+  assert!(!found.is_empty());
+  assert!(found.iter().all(|et| *et == Some(ElementType::Quad4)));
+  assert!(EtypeOverride::from_str("QUADX").is_err());
+  assert!(EtypeOverride::from_str("QUADX=NOTANELEMENT").is_err());
+}
+
+#[test]
+fn test_row_line_range() {
+  let path = example_path("scnastran", "many_quads_scnas_postexport.f06");
+  let text = std::fs::read_to_string(&path).unwrap();
+  let lines = text.lines().collect::<Vec<_>>();
+  let mut f06 = OnePassParser::parse_file(&path).unwrap();
+  // find a block split across pages, and where each page starts
+  let (br, pages) = f06.blocks.iter()
+    .find(|(_, bs)| bs.len() > 1)
+    .map(|(br, bs)| (*br, bs.clone()))
+    .expect("no block split across pages");
+  let firsts = pages.iter()
+    .map(|b| *b.row_indexes.keys().next().unwrap())
+    .collect::<Vec<_>>();
+  for (page, row) in pages.iter().zip(firsts.iter()) {
+    assert!(page.line_range.is_some());
+    assert_eq!(f06.row_line_range(&br, row), page.line_range);
+  }
+  // merged blocks keep a range spanning all of their pages
+  f06.merge_blocks(true);
+  let start = pages.iter().filter_map(|b| b.line_range).map(|r| r.0).min();
+  let end = pages.iter().filter_map(|b| b.line_range).map(|r| r.1).max();
+  for row in firsts.iter() {
+    let range = f06.row_line_range(&br, row);
+    assert_eq!(range, start.zip(end));
+  }
+  let (start, end) = start.zip(end).unwrap();
+  assert!(start <= lines.len() && start < end);
+  // rows not in the file have no range
+  let missing: NasIndex = GridPointRef { gid: usize::MAX }.into();
+  assert_eq!(f06.row_line_range(&br, &missing), None);
+}
+
+#[test]
+fn test_subcase_accessors() {
+  let block = |subcase: usize, bt: BlockType, gid: usize| FinalBlock {
+    block_type: bt,
+    subcase,
+    ..grid_block(&[gid], &[DOF_TX], &[1.0])
+  };
+  let mut f06 = F06File::new();
+  f06.insert_block(block(3, BlockType::Displacements, 1));
+  f06.insert_block(block(3, BlockType::SpcForces, 1));
+  f06.insert_block(block(3, BlockType::SpcForces, 2));
+  f06.insert_block(block(7, BlockType::Displacements, 1));
+  assert_eq!(f06.subcases().collect::<Vec<_>>(), [Subcase(3), Subcase(7)]);
+  assert_eq!(f06.blocks_in(Subcase(3), false).count(), 3);
+  assert_eq!(f06.blocks_in(Subcase(7), false).count(), 1);
+  assert_eq!(f06.blocks_of(Subcase(3), BlockType::SpcForces).len(), 2);
+  assert!(f06.blocks_of(Subcase(5), BlockType::SpcForces).is_empty());
+  assert!(f06.unique_block(Subcase(3), BlockType::Displacements).is_some());
+  assert!(f06.unique_block(Subcase(3), BlockType::SpcForces).is_none());
+  // it's a bare number everywhere else
+  assert_eq!(Subcase::from(4), Subcase(4));
+  assert_eq!(usize::from(Subcase(4)), 4);
+  assert_eq!(" 12 ".parse::<Subcase>(), Ok(Subcase(12)));
+  assert!("mode 1".parse::<Subcase>().is_err());
+  let map: SubcaseMap = "3=7".parse().unwrap();
+  assert_eq!(
+    map.resolve(&f06, &f06),
+    BTreeMap::from([(Subcase(3), Subcase(7))])
+  );
+  // the second file's own subcase 3 is left out instead of colliding
+  let settings = DiffSettings {
+    subcase_map: Some(map),
+    ..Default::default()
+  };
+  let diff = F06Diff::compare(&settings, &f06, &f06);
+  let disp = |sc: usize| BlockRef::new(Subcase(sc), BlockType::Displacements);
+  assert!(diff.compared.contains_key(&disp(3)));
+  assert!(matches!(
+    diff.not_compared.get(&disp(7)),
+    Some(NonCompareReason::NoCounterpart(_))
+  ));
+  assert!("1=5,2=5".parse::<SubcaseMap>().is_err());
+  // extractions pick subcases by it
+  let extraction = Extraction {
+    subcases: Specifier::List(vec![Subcase(7)]),
+    ..Default::default()
+  };
+  let indexes = extraction.lookup(&f06).collect::<Vec<_>>();
+  assert_eq!(indexes.len(), 1);
+  assert_eq!(indexes[0].block_ref.subcase, Subcase(7));
+}
+
+#[test]
+fn test_connector_forces() {
+  let lines = [
+    concat!(
+      "                           F O R C E S   I N   W E L D   E L E M E N",
+      " T S   ( C W E L D )"
+    ),
+    concat!(
+      "    ELEMENT         BEND-MOMENT END-A            BEND-MOMENT END-B",
+      "                - SHEAR -               AXIAL"
+    ),
+    concat!(
+      "      ID.         PLANE 1       PLANE 2        PLANE 1       PLANE 2",
+      "        PLANE 1       PLANE 2         FORCE          TORQUE"
+    ),
+    concat!(
+      "0       101    1.000000E+01   2.000000E+00   3.000000E+00",
+      "   4.000000E+00   5.000000E+00   6.000000E+00   7.000000E+00",
+      "   8.000000E+00"
+    ),
+    "1    SOME TITLE                    JANUARY  12, 2024  SIMCENTER NASTRAN",
+    concat!(
+      "                         F O R C E S   I N   F A S T E N E R   E L E M",
+      " E N T S   ( C F A S T )"
+    ),
+    concat!(
+      "                  ELEMENT-ID         FORCE-X       FORCE-Y",
+      "       FORCE-Z      MOMENT-X      MOMENT-Y      MOMENT-Z"
+    ),
+    concat!(
+      "0                       201   1.000000E+00   2.000000E+00",
+      "   3.000000E+00   4.000000E+00   5.000000E+00   6.000000E+00"
+    ),
+    "1    SOME TITLE                    JANUARY  12, 2024  SIMCENTER NASTRAN"
+  ];
+  let mut parser = OnePassParser::new();
+  parser.hint_flavour(Flavour {
+    solver: Some(Solver::Simcenter),
+    soltype: None
+  });
+  for line in lines {
+    parser.consume(line);
+  }
+  let mut file = parser.finish();
+  // the CWELD and CFAST tables have different columns, so they stay apart.
+  assert_eq!(file.merge_blocks(true), 0);
+  let weld_block = file.all_blocks(true)
+    .find(|b| b.block_type == BlockType::ConnectorForces)
+    .expect("no connector forces block");
+  let fast_block = file.all_blocks(true)
+    .find(|b| b.block_type == BlockType::FastenerForces)
+    .expect("no fastener forces block");
+  let weld = ElementRef { eid: 101, etype: Some(ElementType::Weld) };
+  let fast = ElementRef { eid: 201, etype: Some(ElementType::Fast) };
+  assert_eq!(weld_block.row_indexes.len(), 1);
+  assert_eq!(fast_block.row_indexes.len(), 1);
+  let get = |eref: ElementRef, col: ConnectorForceField| {
+    let x = weld_block.get(eref, col).expect("missing connector value");
+    return f64::from(x);
+  };
+  assert_eq!(get(weld, ConnectorForceField::MomentB2), 4.0);
+  assert_eq!(get(weld, ConnectorForceField::AxialForce), 7.0);
+  assert_eq!(get(weld, ConnectorForceField::Torque), 8.0);
+  for (i, x) in [1.0, 2.0, 3.0, 4.0, 5.0, 6.0].into_iter().enumerate() {
+    let dof = Dof::try_from(i + 1).unwrap();
+    assert_eq!(fast_block.get(fast, dof).map(f64::from), Some(x));
+  }
+}
+
+#[test]
+fn test_assert_f06_matches() {
+  use crate::testing::*;
+  let examples = examples_dir();
+  let bush = examples.join("mystran").join("SB-BUSH-01-OFFSET-2b.F06");
+  let quads = examples.join("scnastran")
+    .join("many_quads_scnas_postexport.f06");
+  let crit = Criteria { ratio: Some(1.01), ..Default::default() };
+  assert!(assert_f06_matches(&bush, &bush, &crit).is_ok());
+  // entirely different models: blocks without counterparts are flagged
+  let flags = assert_f06_matches(&bush, &quads, &crit).unwrap_err();
+  assert!(flags.iter().any(|f| matches!(f, Flag::NotCompared { .. })));
+  let flags = assert_f06_matches(&bush, examples.join("nope.f06"), &crit)
+    .unwrap_err();
+  assert!(matches!(flags.as_slice(), [Flag::Unparseable { .. }]));
+}
+
+#[test]
+fn test_stateful_decoder_pages() {
+  let banner = concat!(
+    "1    SOME TITLE                              JANUARY  22, 2024  ",
+    "SIMCENTER NASTRAN  2/11/21   PAGE     4"
+  );
+  let header = concat!(
+    "                         S T R E S S E S   I N   T R I A N G U L A R",
+    "   E L E M E N T S   ( C T R I A 3 )"
+  );
+  let cols = [
+    concat!(
+      "  ELEMENT      FIBER               STRESSES IN ELEMENT COORD SYSTEM",
+      "             PRINCIPAL STRESSES (ZERO SHEAR)"
+    ),
+    concat!(
+      "    ID.       DISTANCE           NORMAL-X       NORMAL-Y      SHEAR-XY",
+      "       ANGLE         MAJOR           MINOR        VON MISES"
+    )
+  ];
+  let row = |lead: &str, z: &str, x: &str| format!(
+    "{}{}      {}   1.000000E+00   0.000000E+00     0.0000    {}   {}   {}",
+    lead, z, x, x, "1.000000E+00", x
+  );
+  let mut lines = vec![banner.to_owned(), header.to_owned()];
+  lines.extend(cols.map(String::from));
+  lines.push(row("0      21", "   -6.250000E-02", "2.000000E+00"));
+  // the second line of element 21 goes on the next page
+  lines.extend([banner.to_owned(), header.to_owned()]);
+  lines.extend(cols.map(String::from));
+  lines.push(row("          ", "    6.250000E-02", "3.000000E+00"));
+  lines.push(row("0      22", "   -6.250000E-02", "4.000000E+00"));
+  lines.push(row("          ", "    6.250000E-02", "5.000000E+00"));
+  lines.push(banner.to_owned());
+  let mut parser = OnePassParser::new();
+  parser.hint_flavour(Flavour {
+    solver: Some(Solver::Simcenter),
+    soltype: None
+  });
+  for line in lines.iter() {
+    parser.consume(line);
+  }
+  let mut f06 = parser.finish();
+  f06.merge_blocks(true);
+  let block = f06.all_blocks(true)
+    .find(|b| b.block_type == BlockType::TriaStresses)
+    .expect("no tria stresses block");
+  assert_eq!(block.row_indexes.len(), 4);
+  let top = ElementSidedPoint {
+    element: ElementRef { eid: 21, etype: Some(ElementType::Tria3) },
+    point: ElementPoint::Anywhere,
+    side: ElementSide::Top
+  };
+  let nx = block.get(top, PlateStressField::NormalX).map(f64::from);
+  assert_eq!(nx, Some(3.0));
+}
+
+#[test]
+fn test_output_context() {
+  let cases = [
+    (
+      "         OUTPUT FOR SUBCASE        3",
+      ParserResponse::Subcase(3)
+    ),
+    (
+      "         OUTPUT FOR EIGENVECTOR        2",
+      ParserResponse::Mode(2)
+    ),
+    (
+      "         OUTPUT FOR LOAD STEP   5.000000E-01",
+      ParserResponse::Step(OutputStep::LoadStep(0.5))
+    ),
+    (
+      "         OUTPUT FOR TIME   1.200000E+00",
+      ParserResponse::Step(OutputStep::Time(1.2))
+    ),
+    (
+      "         OUTPUT FOR FREQUENCY   2.500000E+01",
+      ParserResponse::Step(OutputStep::Frequency(25.0))
+    ),
+  ];
+  for (line, expected) in cases {
+    let mut parser = OnePassParser::new();
+    parser.hint_flavour(Flavour {
+      solver: Some(Solver::Mystran),
+      soltype: None
+    });
+    let response = parser.consume(line);
+    let (got, want) = (format!("{:?}", response), format!("{:?}", expected));
+    assert_eq!(got, want, "{}", line);
+  }
+  // blocks remember their step and don't merge across steps
+  let banner = concat!(
+    "1    SOME TITLE                              JANUARY  22, 2024  ",
+    "SIMCENTER NASTRAN  2/11/21   PAGE     4"
+  );
+  let header = "                D I S P L A C E M E N T   V E C T O R";
+  let cols = concat!(
+    "      POINT ID.   TYPE          T1             T2             T3",
+    "             R1             R2             R3"
+  );
+  let row = concat!(
+    "             1      G      1.000000E+00   0.0            0.0",
+    "            0.0            0.0            0.0"
+  );
+  let mut parser = OnePassParser::new();
+  parser.hint_flavour(Flavour {
+    solver: Some(Solver::Simcenter),
+    soltype: None
+  });
+  for step in ["1.0", "2.0"] {
+    for line in [
+      banner.to_string(),
+      format!("      OUTPUT FOR LOAD STEP {}", step),
+      header.to_string(),
+      cols.to_string(),
+      row.to_string(),
+    ] {
+      parser.consume(&line);
+    }
+  }
+  parser.consume(banner);
+  let mut f06 = parser.finish();
+  f06.merge_blocks(true);
+  let steps: Vec<_> = f06.all_blocks(false)
+    .filter(|b| b.block_type == BlockType::Displacements)
+    .map(|b| b.step)
+    .collect();
+  assert_eq!(
+    steps,
+    vec![Some(OutputStep::LoadStep(1.0)), Some(OutputStep::LoadStep(2.0))]
+  );
+  // each step is under a key of its own, so both blocks are unique
+  let at = |x: f64| BlockRef::new(Subcase(1), BlockType::Displacements)
+    .at_step(Some(OutputStep::LoadStep(x)));
+  assert_eq!(f06.all_blocks(true).count(), 2);
+  assert_eq!(f06.blocks.get(&at(2.0)).map(Vec::len), Some(1));
+  assert!(f06.blocks_of(Subcase(1), BlockType::Displacements).is_empty());
+  // and diffs pair them by step
+  let diff = F06Diff::compare(&DiffSettings::default(), &f06, &f06);
+  assert!(diff.not_compared.is_empty());
+  assert!(diff.compared.contains_key(&at(1.0)));
+  assert!(diff.compared.contains_key(&at(2.0)));
+}
+
+#[test]
+fn test_beam_forces() {
+  let beam = |eid: usize| ElementRef { eid, etype: Some(ElementType::Beam) };
+  let at = |eid: usize, station: BeamStation| ElementStation {
+    element: beam(eid),
+    station
+  };
+  // mystran: one line per element, bar-like
+  let lines = [
+    concat!(
+      "                  E L E M E N T   E N G I N E E R I N G   ",
+      "F O R C E S"
+    ),
+    "                    F O R   E L E M E N T   T Y P E   B E A M",
+    concat!(
+      "         Element       Bend-Moment End A           Bend-Moment End B",
+      "              - Shear -              Axial         Torque"
+    ),
+    concat!(
+      "            ID       Plane 1       Plane 2       Plane 1       Plane 2",
+      "      Plane 1       Plane 2        Force"
+    ),
+    concat!(
+      "            11  1.000000E+00  2.000000E+00  3.000000E+00  4.000000E+00",
+      "  5.000000E+00  6.000000E+00  7.000000E+00  8.000000E+00"
+    ),
+    "",
+  ];
+  let mut parser = OnePassParser::new();
+  parser.hint_flavour(Flavour {
+    solver: Some(Solver::Mystran),
+    soltype: None
+  });
+  for line in lines {
+    parser.consume(line);
+  }
+  let file = parser.finish();
+  let block = file.all_blocks(true)
+    .find(|b| b.block_type == BlockType::BeamForces)
+    .expect("no mystran beam forces block");
+  assert_eq!(block.row_indexes.len(), 2);
+  let get = |es: ElementStation, col: BeamForceField| {
+    return block.get(es, col).map(f64::from);
+  };
+  let (a, b) = (at(11, BeamStation::EndA), at(11, BeamStation::EndB));
+  assert_eq!(get(a, BeamForceField::BendMoment2), Some(2.0));
+  assert_eq!(get(b, BeamForceField::BendMoment1), Some(3.0));
+  assert_eq!(get(b, BeamForceField::StationDistance), Some(1.0));
+  assert_eq!(get(b, BeamForceField::Shear2), Some(6.0));
+  assert_eq!(get(a, BeamForceField::TotalTorque), Some(8.0));
+  // simcenter: element lines, then a line per station, across pages
+  let banner = concat!(
+    "1    SOME TITLE                              JANUARY  22, 2024  ",
+    "SIMCENTER NASTRAN  2/11/21   PAGE     4"
+  );
+  let header = concat!(
+    "                 F O R C E S   I N   B E A M   E L E M E N T S",
+    "        ( C B E A M )"
+  );
+  let cols = concat!(
+    "   ELEMENT-ID  GRID   LENGTH    PLANE 1       PLANE 2        PLANE 1",
+    "       PLANE 2        FORCE          TORQUE         TORQUE"
+  );
+  let row = |grid: usize, dist: &str, m1: &str| format!(
+    "             {:>5}   {}   {}   0.0            0.0            0.0{}",
+    grid,
+    dist,
+    m1,
+    "            1.000000E+00   2.000000E+00   0.0"
+  );
+  let lines = [
+    banner.to_string(),
+    header.to_string(),
+    cols.to_string(),
+    "0                    21".to_string(),
+    row(1, "0.000", "1.000000E+01"),
+    row(0, "0.500", "2.000000E+01"),
+    banner.to_string(),
+    header.to_string(),
+    cols.to_string(),
+    row(2, "1.000", "3.000000E+01"),
+    "0                    22".to_string(),
+    row(2, "0.000", "4.000000E+01"),
+    banner.to_string(),
+  ];
+  let mut parser = OnePassParser::new();
+  parser.hint_flavour(Flavour {
+    solver: Some(Solver::Simcenter),
+    soltype: None
+  });
+  for line in lines.iter() {
+    parser.consume(line);
+  }
+  let mut file = parser.finish();
+  file.merge_blocks(true);
+  let block = file.all_blocks(true)
+    .find(|b| b.block_type == BlockType::BeamForces)
+    .expect("no simcenter beam forces block");
+  assert_eq!(block.row_indexes.len(), 4);
+  let get = |es: ElementStation, col: BeamForceField| {
+    return block.get(es, col).map(f64::from);
+  };
+  let m1 = BeamForceField::BendMoment1;
+  assert_eq!(get(at(21, BeamStation::EndA), m1), Some(10.0));
+  assert_eq!(get(at(21, BeamStation::Intermediate(1)), m1), Some(20.0));
+  assert_eq!(get(at(21, BeamStation::EndB), m1), Some(30.0));
+  assert_eq!(get(at(22, BeamStation::EndA), m1), Some(40.0));
+  assert_eq!(
+    get(at(21, BeamStation::EndB), BeamForceField::TotalTorque),
+    Some(2.0)
+  );
+}
+
+#[test]
+fn test_solid_stresses() {
+  let point = |name: &str, x: &str| [
+    format!(
+      "0{:>20}  X  {}  XY   2.000000E+00   A   3.000000E+00  {}",
+      name,
+      x,
+      "LX 0.71 0.00-0.71  -4.000000E+00    5.000000E+00"
+    ),
+    format!(
+      "{:>21}  Y  -1.000000E+00  YZ   0.0            B  -2.000000E+00  {}",
+      "",
+      "LY 0.00 1.00 0.00"
+    ),
+    format!(
+      "{:>21}  Z   6.000000E+00  ZX   7.000000E+00   C   8.000000E+00  {}",
+      "",
+      "LZ 0.71 0.00 0.71"
+    ),
+  ];
+  let mut lines = vec![
+    concat!(
+      "1    SOME TITLE                              JANUARY  22, 2024  ",
+      "SIMCENTER NASTRAN  2/11/21   PAGE     4"
+    ).to_string(),
+    concat!(
+      "            S T R E S S E S   I N    H E X A H E D R O N   S O L I D",
+      "   E L E M E N T S   ( H E X A )"
+    ).to_string(),
+    concat!(
+      "               CORNER        ------CENTER AND CORNER POINT STRESSES",
+      "---------       DIR.  COSINES       MEAN"
+    ).to_string(),
+    concat!(
+      "  ELEMENT-ID    GRID-ID       NORMAL              SHEAR",
+      "             PRINCIPAL       -A-  -B-  -C-     PRESSURE       VON MISES"
+    ).to_string(),
+    "0        31           0GRID CS  8 GP".to_string(),
+  ];
+  lines.extend(point("CENTER", "1.000000E+00"));
+  lines.extend(point("101", "9.000000E+00"));
+  lines.push(lines[0].clone());
+  let mut parser = OnePassParser::new();
+  parser.hint_flavour(Flavour {
+    solver: Some(Solver::Simcenter),
+    soltype: None
+  });
+  for line in lines.iter() {
+    parser.consume(line);
+  }
+  let file = parser.finish();
+  let block = file.all_blocks(true)
+    .find(|b| b.block_type == BlockType::SolidStresses)
+    .expect("no solid stresses block");
+  assert_eq!(block.row_indexes.len(), 2);
+  let element = ElementRef { eid: 31, etype: Some(ElementType::Hexa) };
+  let centre = PointInElement { element, point: ElementPoint::Centroid };
+  let corner = PointInElement {
+    element,
+    point: ElementPoint::Corner(101.into())
+  };
+  let get = |pie: PointInElement, col: SolidStressField| {
+    return block.get(pie, col).map(f64::from);
+  };
+  assert_eq!(get(centre, SolidStressField::NormalX), Some(1.0));
+  assert_eq!(get(corner, SolidStressField::NormalX), Some(9.0));
+  assert_eq!(get(centre, SolidStressField::NormalY), Some(-1.0));
+  assert_eq!(get(centre, SolidStressField::ShearZX), Some(7.0));
+  assert_eq!(get(centre, SolidStressField::PrincipalB), Some(-2.0));
+  assert_eq!(get(centre, SolidStressField::CosineCX), Some(-0.71));
+  assert_eq!(get(centre, SolidStressField::CosineBY), Some(1.0));
+  assert_eq!(get(centre, SolidStressField::CosineCZ), Some(0.71));
+  assert_eq!(get(corner, SolidStressField::MeanPressure), Some(-4.0));
+  assert_eq!(get(corner, SolidStressField::VonMises), Some(5.0));
+}
+
+#[test]
+fn test_complex_displacements() {
+  let banner = concat!(
+    "1    SOME TITLE                              JANUARY  22, 2024  ",
+    "SIMCENTER NASTRAN  2/11/21   PAGE     4"
+  );
+  let table = |freq: &str, form: &str, second: &str| [
+    banner.to_string(),
+    "0                                                           SUBCASE 1"
+      .to_string(),
+    format!("      FREQUENCY =  {}", freq),
+    concat!(
+      "                   C O M P L E X   D I S P L A C E M E N T",
+      "   V E C T O R"
+    ).to_string(),
+    format!("                              {}", form),
+    concat!(
+      "      POINT ID.   TYPE          T1             T2             T3",
+      "             R1             R2             R3"
+    ).to_string(),
+    concat!(
+      "0            7      G      2.000000E+00   0.0            0.0",
+      "            0.0            0.0            0.0"
+    ).to_string(),
+    format!(
+      "                           {}   0.0            0.0{}",
+      second,
+      "            0.0            0.0            0.0"
+    ),
+  ];
+  let mut lines = Vec::new();
+  lines.extend(table("1.000000E+01", "(REAL/IMAGINARY)", "3.000000E+00"));
+  lines.extend(table("2.000000E+01", "(MAGNITUDE/PHASE)", "9.000000E+01"));
+  lines.push(banner.to_string());
+  let mut parser = OnePassParser::new();
+  parser.hint_flavour(Flavour {
+    solver: Some(Solver::Simcenter),
+    soltype: None
+  });
+  for line in lines.iter() {
+    parser.consume(line);
+  }
+  let file = parser.finish();
+  let blocks = file.all_blocks(false)
+    .filter(|b| b.block_type == BlockType::ComplexDisplacements)
+    .collect::<Vec<_>>();
+  assert_eq!(blocks.len(), 2);
+  let grid: GridPointRef = 7.into();
+  let tx = Dof::try_from(1).unwrap();
+  let ri = blocks[0];
+  assert_eq!(ri.step, Some(OutputStep::Frequency(10.0)));
+  assert_eq!(ri.complex_form, Some(ComplexForm::RealImaginary));
+  let z = ri.get_complex(grid, tx).expect("no real/imaginary value");
+  assert_eq!((z.re, z.im), (2.0, 3.0));
+  let mp = blocks[1];
+  assert_eq!(mp.step, Some(OutputStep::Frequency(20.0)));
+  assert_eq!(mp.complex_form, Some(ComplexForm::MagnitudePhase));
+  let z = mp.get_complex(grid, tx).expect("no magnitude/phase value");
+  assert!(z.re.abs() < 1e-12 && (z.im - 2.0).abs() < 1e-12);
+  let ty = Dof::try_from(2).unwrap();
+  assert_eq!(mp.get_complex(grid, ty).map(|z| z.norm()), Some(0.0));
+}
+
+#[test]
+fn test_complex_diff() {
+  let tx = Dof::try_from(1).unwrap();
+  let part = |part| NasIndex::from(ComplexDof { dof: tx, part });
+  let flags = |crit: Criteria, a: &FinalBlock, b: &FinalBlock| {
+    return DataDiffer::new(crit, DisjunctionBehaviour::Skip)
+      .compare(a, b)
+      .unwrap()
+      .collect::<Vec<_>>();
+  };
+  let crit = Criteria {
+    ratio: Some(1.01),
+    phase_tolerance_deg: Some(5.0),
+    ..Default::default()
+  };
+  // same magnitude, 3 degrees apart: fine, though the parts differ a lot
+  let a = complex_block(1.0, 0.0);
+  let (sin, cos) = 3.0_f64.to_radians().sin_cos();
+  let close = complex_block(cos, sin);
+  assert!(flags(crit, &a, &close).is_empty());
+  // a quarter turn apart: a single phase flag, on the real column
+  let found = flags(crit, &a, &complex_block(0.0, 1.0));
+  assert_eq!(found.len(), 1);
+  assert!(matches!(found[0].reason, FlagReason::Phase { .. }));
+  assert_eq!(found[0].values.col, part(ComplexPart::Real));
+  // without a phase tolerance, the parts are compared one by one
+  let by_part = Criteria { phase_tolerance_deg: None, ..crit };
+  assert!(!flags(by_part, &a, &close).is_empty());
+}
+
+#[test]
+fn test_grid_point_and_element_ids() {
+  let path = example_path("mystran", "SB-ALL-ELEM-TEST.F06");
+  let mut f06 = OnePassParser::parse_file(path).expect("parse failed");
+  f06.merge_blocks(true);
+  let gids = f06.grid_point_ids();
+  assert!(gids.contains(&1011.into()));
+  let in_91 = f06.grid_point_ids_in(Subcase(91));
+  assert!(!in_91.is_empty() && in_91.is_subset(&gids));
+  assert!(f06.grid_point_ids_in(Subcase(12345)).is_empty());
+  let bar = ElementRef { eid: 1112, etype: Some(ElementType::Bar) };
+  assert!(f06.element_ids().contains(&bar));
+  assert!(f06.element_ids_in(Subcase(91)).contains(&bar));
+  let by_type = f06.element_ids_by_type();
+  assert!(by_type.get(&ElementType::Bar).is_some_and(|s| s.contains(&1112)));
+  assert!(by_type.values().all(|s| !s.is_empty()));
+}
+
+#[test]
+fn test_parse_progress() {
+  use std::io::Cursor;
+  let text = "\n".repeat(25) + "MYSTRAN\n";
+  let mut seen: Vec<usize> = Vec::new();
+  OnePassParser::parse_bufread_with_progress(
+    Cursor::new(text.as_bytes()),
+    Flavour::default(),
+    ParserSettings::default(),
+    10,
+    |n| seen.push(n)
+  ).expect("parse failed");
+  assert_eq!(seen, vec![10, 20]);
+  let mut calls = 0;
+  OnePassParser::parse_bufread_with_progress(
+    Cursor::new(text.as_bytes()),
+    Flavour::default(),
+    ParserSettings::default(),
+    0,
+    |_| calls += 1
+  ).expect("parse failed");
+  assert_eq!(calls, 0);
+}
+
+#[test]
+fn test_bulk_echo_end() {
+  let examples = examples_dir().join("scnastran");
+  let parse = |name: &str| {
+    let mut f06 = OnePassParser::parse_file(examples.join(name))
+      .expect("parse failed");
+    f06.merge_blocks(true);
+    return f06;
+  };
+  // echoed, across a page header
+  let f06 = parse("SB-ALL-ELEM-TEST_scnas_postexport.f06");
+  assert_eq!(f06.connectivity.len(), 27);
+  assert_eq!(f06.all_blocks(false).count(), 32);
+  // not echoed at all
+  let f06 = parse("SB-BUSH-01-OFFSET-2b_scnas_postexport.f06");
+  assert!(f06.connectivity.is_empty());
+  assert_eq!(f06.all_blocks(false).count(), 7);
+  // card-like lines after the echo aren't connectivity
+  let text = "\
+      BEGIN BULK
+0                             INPUT BULK DATA ENTRY COUNT =          58
+ *** USER INFORMATION MESSAGE 5293 (SSG3A)
+CBAR    5       1       1       2
+";
+  let hint = Flavour { solver: Some(Solver::Simcenter), soltype: None };
+  let f06 = OnePassParser::parse_bufread_hinted(text.as_bytes(), hint)
+    .unwrap();
+  assert!(f06.connectivity.is_empty());
+}
+
+#[test]
+fn test_complex_displacement_frequencies() {
+  // a SORT1 table over two frequencies, the first across two pages
+  let page = |n: usize, freq: &str, grids: &[(usize, f64, f64)]| {
+    let mut text = format!(
+      concat!(
+        "1    FREQUENCY RESPONSE                      JANUARY  22, 2024  ",
+        "SIMCENTER NASTRAN  2/11/21   PAGE    {}\n",
+        "\n",
+        "0                                                            ",
+        "SUBCASE 1\n",
+        "      FREQUENCY =  {}\n",
+        "                   C O M P L E X   D I S P L A C E M E N T   ",
+        "V E C T O R\n",
+        "                              (REAL/IMAGINARY)\n",
+        " \n",
+        "      POINT ID.   TYPE          T1             T2             T3",
+        "             R1             R2             R3\n"
+      ),
+      n,
+      freq
+    );
+    for (gid, re, im) in grids {
+      text += &format!(
+        "0{:>13}      G     {:>13.6E}   0.0            0.0{}\n",
+        gid,
+        re,
+        "            0.0            0.0            0.0"
+      );
+      text += &format!(
+        "                          {:>13.6E}   0.0            0.0{}\n",
+        im,
+        "            0.0            0.0            0.0"
+      );
+    }
+    return text;
+  };
+  let text = [
+    page(10, "1.000000E+01", &[(1, 1.0, 0.5), (2, 2.0, 1.0)]),
+    page(11, "1.000000E+01", &[(3, 3.0, 1.5)]),
+    page(12, "2.000000E+01", &[(1, -1.0, 0.25), (3, -3.0, 0.75)])
+  ].concat();
+  let hint = Flavour { solver: Some(Solver::Simcenter), soltype: None };
+  let mut f06 = OnePassParser::parse_bufread_hinted(text.as_bytes(), hint)
+    .expect("parse failed");
+  f06.merge_blocks(true);
+  let at = |x: f64| BlockRef::new(Subcase(1), BlockType::ComplexDisplacements)
+    .at_step(Some(OutputStep::Frequency(x)));
+  assert_eq!(f06.blocks.len(), 2);
+  let [first, second] = [10.0, 20.0].map(|x| match f06.blocks.get(&at(x)) {
+    Some(v) if v.len() == 1 => &v[0],
+    _ => panic!("no single block at {} Hz", x)
+  });
+  assert_eq!(first.row_indexes.len(), 3);
+  let grid: GridPointRef = 3.into();
+  let tx = Dof::try_from(1).unwrap();
+  let z = first.get_complex(grid, tx).expect("no value at 10 Hz");
+  assert_eq!((z.re, z.im), (3.0, 1.5));
+  let z = second.get_complex(grid, tx).expect("no value at 20 Hz");
+  assert_eq!((z.re, z.im), (-3.0, 0.75));
+}
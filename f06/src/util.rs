@@ -4,10 +4,15 @@
 use std::cell::Cell;
 use std::collections::BTreeMap;
 use std::fmt::Write;
+use std::ops::RangeInclusive;
+use log::warn;
 use serde::{Serialize, Deserialize};
 
 use crate::elements::ElementType;
 
+/// The form feed character, which some files use to break pages.
+pub(crate) const FORM_FEED: char = '\x0c';
+
 /// Words that we can find in a spaced block header to make us suspicious.
 pub(crate) const SUS_WORDS: &[&str] = &[
   "ELEMENT",
@@ -24,6 +29,7 @@ pub(crate) const SUS_WORDS: &[&str] = &[
   "MPC",
   "GRID",
   "DISPLACEMENT",
+  "EIGENVECTOR",
   "APPLIED",
   "LOAD",
   "TEMPERATURE",
@@ -49,18 +55,120 @@ pub(crate) const BAD_WORDS: &[&str] = &[
   "GENERATOR"
 ];
 
+/// Element cards whose echo we read connectivity from, along with the
+/// positions of their grid IDs (counting the card name as zero).
+pub(crate) const CONNECTIVITY_CARDS: &[(&str, &[usize])] = &[
+  ("CROD", &[3, 4]),
+  ("CONROD", &[2, 3]),
+  ("CBAR", &[3, 4]),
+  ("CBEAM", &[3, 4]),
+  ("CBUSH", &[3, 4]),
+  ("CELAS1", &[3, 5]),
+  ("CTRIA3", &[3, 4, 5]),
+  ("CQUAD4", &[3, 4, 5, 6]),
+];
+
+/// Element cards whose echo we read property IDs from. In all of them, the
+/// property ID comes right after the element ID.
+pub(crate) const PROPERTY_CARDS: &[&str] = &[
+  "CROD", "CBAR", "CBEAM", "CBUSH", "CELAS1", "CTRIA3", "CQUAD4"
+];
+
+/// Splits an echoed bulk data line into its fields, dropping a leading line
+/// count (as in numbered echoes). Returns the card name, in uppercase and
+/// without the large-field asterisk, and the fields (card name included).
+fn bulk_card_fields(line: &str) -> Option<(String, Vec<&str>)> {
+  let mut tokens = line
+    .split(|c: char| c == ',' || c.is_whitespace())
+    .filter(|s| !s.is_empty())
+    .collect::<Vec<_>>();
+  if tokens.first()?.parse::<usize>().is_ok() {
+    tokens.remove(0);
+  }
+  let card = tokens.first()?.trim_end_matches('*').to_uppercase();
+  return Some((card, tokens));
+}
+
+/// Returns whether a line could be part of a bulk data echo: a card, a
+/// continuation, a comment, a blank or Simcenter's column ruler.
+pub(crate) fn bulk_echo_line(line: &str) -> bool {
+  let trimmed = line.trim_start();
+  if trimmed.is_empty() || trimmed.starts_with(['$', '+', '.']) {
+    return true;
+  }
+  // large-field continuations, but not "***" messages
+  if trimmed.starts_with('*') && !trimmed.starts_with("**") {
+    return true;
+  }
+  return bulk_card_fields(line).is_some_and(|(card, _)| {
+    return card.len() <= 8
+      && card.starts_with(|c: char| c.is_ascii_alphabetic())
+      && card.chars().all(|c| c.is_ascii_alphanumeric());
+  });
+}
+
+/// Reads an element's ID and grid IDs from an echoed bulk data line, if it's
+/// a known element card. Small-field and free-field (comma-separated) cards
+/// are understood, as is a leading line count (as in numbered echoes).
+pub(crate) fn connectivity_card(line: &str) -> Option<(usize, Vec<usize>)> {
+  let (card, tokens) = bulk_card_fields(line)?;
+  let (_, positions) = CONNECTIVITY_CARDS.iter().find(|(c, _)| *c == card)?;
+  let eid = tokens.get(1)?.parse::<usize>().ok()?;
+  let grids = positions.iter()
+    .filter_map(|i| tokens.get(*i)?.parse::<usize>().ok())
+    .filter(|g| *g != 0)
+    .collect::<Vec<_>>();
+  if grids.is_empty() {
+    return None;
+  }
+  return Some((eid, grids));
+}
+
+/// Reads an element's ID and property ID from an echoed bulk data line, if
+/// it's a known element card. Understands the same formats as
+/// `connectivity_card`. A blank property ID (which defaults to the element
+/// ID) isn't read.
+pub(crate) fn property_card(line: &str) -> Option<(usize, usize)> {
+  let (card, tokens) = bulk_card_fields(line)?;
+  if !PROPERTY_CARDS.contains(&card.as_str()) {
+    return None;
+  }
+  let eid = tokens.get(1)?.parse::<usize>().ok()?;
+  let pid = tokens.get(2)?.parse::<usize>().ok()?;
+  return Some((eid, pid));
+}
+
+/// Reads a load set ID and its overall scale factor from an echoed LOAD bulk
+/// data card (a load combination). Understands the same formats as
+/// `connectivity_card`.
+pub(crate) fn load_card(line: &str) -> Option<(usize, f64)> {
+  let (card, tokens) = bulk_card_fields(line)?;
+  if card != "LOAD" {
+    return None;
+  }
+  let sid = tokens.get(1)?.parse::<usize>().ok()?;
+  let scale = tokens.get(2)
+    .and_then(|s| s.parse::<f64>().ok().or_else(|| decode_nasfloat(s)))?;
+  return Some((sid, scale));
+}
+
 /// Decodes a Nastran-format floating point number. Hyper-lenient and doesn't
-/// require pulling a whole regex library.
+/// require pulling a whole regex library. Never panics, whatever the input.
 pub(crate) fn decode_nasfloat(s: &str) -> Option<f64> {
-  // mantissa start/end, exponent start/end
+  // mantissa start/end, exponent start/end (byte offsets)
   let mut ixs: [usize; 4] = [0, 0, 0, 0];
   // 0-1 = looking for mantissa start/end, 2-3 = looking for exponent start/end
   let step: Cell<usize> = 0.into();
-  let mut mark = |i| { ixs[step.get()] = i; step.replace(step.get() + 1); };
-  let mut seen_chars: usize = 0;
-  for (i, c) in s.chars().enumerate() {
-    seen_chars += 1;
-    match (step.get() % 2, c.is_numeric() || c == '.', c == '+' || c == '-') {
+  let mut mark = |i| {
+    if let Some(ix) = ixs.get_mut(step.get()) {
+      *ix = i;
+      step.replace(step.get() + 1);
+    }
+  };
+  for (i, c) in s.char_indices() {
+    let numeric = c.is_ascii_digit() || c == '.';
+    let sign = c == '+' || c == '-';
+    match (step.get() % 2, numeric, sign) {
       // looking for number start. nothing yet. keep looking.
       (0, false, false) => continue,
       // looking for number start, found something. mark it and look for end.
@@ -71,30 +179,28 @@ pub(crate) fn decode_nasfloat(s: &str) -> Option<f64> {
       (1, false, false) => mark(i),
       // looking for number end, saw sign. mark end, mark start.
       (1, _, true) => { mark(i); mark(i); },
-      // should be unreachable
-      _ => panic!("unreachable branch 1 in decoding nasfloat \"{}\"", s)
+      // can't happen, but don't take chances with bad input
+      _ => return None
     };
     if step.get() > 3 { break; }
   }
   // handle empty string
-  if seen_chars == 0 {
+  if s.is_empty() {
     return None;
   }
   // handle end at end-of-string
   if step.get() % 2 == 1 {
-    mark(seen_chars);
+    mark(s.len());
   }
-  let mantissa = || s[ixs[0]..ixs[1]].parse::<f64>().ok();
-  let exponent = || s[ixs[2]..ixs[3]].parse::<i32>().ok();
-  match step.get() {
-    // never found mantissa
-    0 => return None,
+  let mantissa = || s.get(ixs[0]..ixs[1])?.parse::<f64>().ok();
+  let exponent = || s.get(ixs[2]..ixs[3])?.parse::<i32>().ok();
+  return match step.get() {
     // only found a mantissa
-    2 => return mantissa(),
+    2 => mantissa(),
     // found mantissa and exponent
-    4 => return Some(mantissa()? * 10.0_f64.powi(exponent()?)),
-    // should be unreachable
-    _ => panic!("unreachable branch 2 in returning nasfloat \"{}\"", s)
+    4 => Some(mantissa()? * 10.0_f64.powi(exponent()?)),
+    // never found a mantissa, or something odd
+    _ => None
   };
 }
 
@@ -125,8 +231,12 @@ impl<'s> LineField<'s> {
     if s.len() == 1 {
       return Self::Character(s.chars().nth(0).unwrap());
     }
+    // element names may come in parentheses and with the card's C prefix,
+    // but must otherwise match whole, lest QUADRILATERAL be taken for QUADR.
+    let name = s.trim_matches(|c| c == '(' || c == ')');
     for cand in ElementType::all() {
-      if s.contains(cand.name()) {
+      let card = name.strip_prefix('C').unwrap_or("");
+      if name == cand.name() || card == cand.name() {
         return Self::ElementType(*cand);
       }
     }
@@ -186,6 +296,74 @@ pub(crate) fn lax_reals<const N: usize>(line: &str) -> Option<[f64; N]> {
   }
 }
 
+/// How a data line in a table whose rows can wrap over two lines is read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum RowLine {
+  /// The line begins a new row. Contains the integers (IDs) found before the
+  /// first real in the line.
+  Leading(Vec<usize>),
+  /// The line has no IDs and continues the previous row.
+  Continuation,
+  /// The line doesn't carry enough data to be either.
+  NotData
+}
+
+/// Classifies a line in a table whose rows can wrap over two lines. Only
+/// integers that come before the first real count as IDs, so integer-looking
+/// values amongst the data don't turn a continuation into a new row. The
+/// number of reals must also be within the expected range for each kind of
+/// line, so totals and other stray lines aren't taken for data. A negative
+/// ID makes for a line that isn't data, with a warning.
+pub(crate) fn row_line(
+  line: &str,
+  lead_reals: RangeInclusive<usize>,
+  cont_reals: RangeInclusive<usize>
+) -> RowLine {
+  let mut ids: Vec<usize> = Vec::new();
+  let mut reals: usize = 0;
+  for field in line_breakdown(line) {
+    match field {
+      LineField::Integer(i) if reals == 0 => match usize::try_from(i) {
+        Ok(id) => ids.push(id),
+        Err(_) => {
+          warn!("negative ID {} in a data line: {}", i, line.trim());
+          return RowLine::NotData;
+        }
+      },
+      LineField::Real(_) => reals += 1,
+      _ => continue
+    };
+  }
+  if reals == 0 {
+    return RowLine::NotData;
+  }
+  if ids.is_empty() && cont_reals.contains(&reals) {
+    return RowLine::Continuation;
+  }
+  if !ids.is_empty() && lead_reals.contains(&reals) {
+    return RowLine::Leading(ids);
+  }
+  return RowLine::NotData;
+}
+
+/// Finds the ID of a line in a table with a single ID per row, whether it
+/// comes before the data (as in most solvers) or after it. When it comes
+/// first, the last integer before the reals is taken, so a carriage control
+/// character is skipped.
+pub(crate) fn row_id(line: &str) -> Option<isize> {
+  let mut before: Option<isize> = None;
+  let mut seen_real = false;
+  for field in line_breakdown(line) {
+    match field {
+      LineField::Integer(i) if !seen_real => before = Some(i),
+      LineField::Integer(i) => return before.or(Some(i)),
+      LineField::Real(_) => seen_real = true,
+      _ => continue
+    };
+  }
+  return before;
+}
+
 /// Gets the N-th integer in a line.
 pub(crate) fn nth_integer(line: &str, n: usize) -> Option<isize> {
   return line_breakdown(line)
@@ -250,8 +428,9 @@ pub(crate) fn int_pattern(line: &str) -> BTreeMap<usize, Vec<f64>> {
   for field in line_breakdown(line) {
     match field {
       LineField::Integer(i) => {
+        // negative integers can't be IDs, so they break the pattern
         flush(&mut res, &mut current_nat);
-        current_nat = Some((i as usize, Vec::new()));
+        current_nat = usize::try_from(i).ok().map(|n| (n, Vec::new()));
       },
       LineField::Real(x) => {
         if let Some((_, ref mut v)) = current_nat {
@@ -301,7 +480,7 @@ pub(crate) fn unspace(line: &str) -> Option<String> {
         // not spaced. but have we seen a lot?
         if cap > 20 {
           // we've seen enough, this is fine. drop the extra chars tho
-          stop_at = 0.max(stop_at - 2);
+          stop_at = stop_at.saturating_sub(2);
           break;
         } else {
           // nah, we've seen it too soon.
@@ -409,6 +588,17 @@ impl PotentialHeader {
 
   /// Merges this potential header with another, if possible.
   pub fn try_merge(self, other: Self) -> Result<Self, (Self, Self)> {
+    return self.try_merge_within(other, 0);
+  }
+
+  /// Merges this potential header with another, if possible, bridging up to
+  /// a number of lines between them (such as the blank line some solvers put
+  /// between the lines of a header). Bridged lines count towards the span.
+  pub fn try_merge_within(
+    self,
+    other: Self,
+    max_gap: usize
+  ) -> Result<Self, (Self, Self)> {
     // put them in order
     let (mut first, second) = if self.start <= other.start {
       (self, other)
@@ -416,10 +606,11 @@ impl PotentialHeader {
       (other, self)
     };
     // check if the ranges work glued together
-    if first.lines().last().unwrap() == (second.lines().nth(0).unwrap() - 1) {
+    let end = first.start + first.span;
+    if second.start >= end && second.start - end <= max_gap {
       first.text.push(' ');
       first.text.push_str(&second.text);
-      first.span += second.span;
+      first.span = second.start + second.span - first.start;
       return Ok(first);
     }
     return Err((first, second));
@@ -4,8 +4,10 @@
 #![warn(missing_docs)]
 #![warn(clippy::missing_docs_in_private_items)]
 
+use std::collections::{BTreeMap, BTreeSet};
+use std::collections::btree_map::Entry;
 use std::error::Error;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, BufReader, BufWriter, Write};
 use std::path::PathBuf;
 
@@ -16,6 +18,12 @@ use f06::prelude::*;
 use nas_csv::from_f06::templates::all_converters;
 use nas_csv::prelude::*;
 
+/// A CSV output, along with the last header written to it.
+type Output = (
+  csv::Writer<BufWriter<Box<dyn Write>>>,
+  Option<(&'static RowHeader, CsvBlockId)>
+);
+
 /// The arguments passed to the converter.
 #[derive(Clone, Debug, Parser)]
 #[command(
@@ -67,6 +75,17 @@ struct Cli {
   /// If absent, no element type filter is applied.
   #[arg(short = 't', long = "etypes", num_args = 0.., value_delimiter = ',')]
   etypes: Vec<ElementType>,
+  /// Property ID filter.
+  ///
+  /// If a record has an element ID, only output those whose element has one
+  /// of the specified property IDs. Requires the F06 to have a bulk data
+  /// echo; elements missing from it are dropped.
+  ///
+  /// Can be specified more than once, or comma-separated.
+  ///
+  /// If absent, no property ID filter is applied.
+  #[arg(long = "pids", num_args = 0.., value_delimiter = ',')]
+  pids: Vec<usize>,
   /// Subcase filter.
   ///
   /// If a record has subcase ID, only output those that contain the
@@ -77,6 +96,26 @@ struct Cli {
   /// If absent, no subcase filter is applied.
   #[arg(short = 's', long = "subcases", num_args = 0.., value_delimiter = ',')]
   subcases: Vec<usize>,
+  /// Only output records in subcases whose labels contain this text,
+  /// ignoring case.
+  ///
+  /// Can be specified more than once. The subcases found are added to those
+  /// given with -s, and it's an error if a label matches no subcase.
+  #[arg(long = "subcase-label", value_name = "LABEL")]
+  subcase_labels: Vec<String>,
+  /// Give a subcase a label, in the form "SUBCASE=LABEL", replacing the one
+  /// in the file (if any).
+  ///
+  /// Can be specified more than once, and applies before --subcase-label
+  /// and --label-column.
+  #[arg(long = "relabel", value_name = "SUBCASE=LABEL")]
+  relabel: Vec<SubcaseRelabel>,
+  /// Element point filter.
+  ///
+  /// For records at points within elements (such as plate stresses), only
+  /// output centroid or corner ones.
+  #[arg(long = "points", default_value = "all")]
+  points: PointFilter,
   /// Enable writing CSV headers.
   ///
   /// Be warned, they're written every time there's a change.
@@ -91,36 +130,241 @@ struct Cli {
   /// Formatting options.
   #[command(flatten)]
   fmtr: CsvFormatting,
+  /// Flavour hints, for files whose solver banner is missing.
+  #[command(flatten)]
+  flavour: Flavour,
+  /// Parser settings.
+  #[command(flatten)]
+  parser: ParserSettings,
+  /// Write one CSV file per subcase instead of a single output.
+  ///
+  /// Files are named subcase_001.csv and so forth, and records without a
+  /// subcase go to subcase_none.csv. They're written to the directory given
+  /// by -o, or to the current directory if it is absent.
+  #[arg(long = "split-by-subcase")]
+  split_by_subcase: bool,
+  /// Reorder the columns of a CSV block by header name.
+  ///
+  /// Takes the form "block:col1,col2,..." and can be specified more than
+  /// once. Columns not listed are dropped, and listing a column a record does
+  /// not have is an error.
+  #[arg(long = "reorder", verbatim_doc_comment)]
+  reorder: Vec<ColumnOrder>,
+  /// Only write the columns of these degrees of freedom.
+  ///
+  /// Applies to blocks with DOF columns, such as displacements; other blocks
+  /// are unaffected. Takes t1,t2,t3,r1,r2,r3 (or tx..rz), comma-separated.
+  /// A --reorder for a block takes precedence over this.
+  #[arg(long = "dofs", num_args = 0.., value_delimiter = ',')]
+  dofs: Vec<Dof>,
+  /// Append the grid IDs of each record's element as extra columns.
+  ///
+  /// Requires the F06 to have a bulk data echo. Records without an element,
+  /// or whose element isn't in the echo, get blank node columns.
+  #[arg(long = "connectivity")]
+  connectivity: bool,
+  /// Append the property ID of each record's element as an extra column.
+  ///
+  /// Requires the F06 to have a bulk data echo. Records without an element,
+  /// or whose element isn't in the echo, get a blank PID column. It goes
+  /// before the node columns of --connectivity.
+  #[arg(long = "pid-column")]
+  pid_column: bool,
+  /// Append the label of each record's subcase as an extra column.
+  ///
+  /// Records without a subcase, or whose subcase has no label, get a blank
+  /// label column. It goes after the PID column of --pid-column and before
+  /// the node columns of --connectivity.
+  ///
+  /// Results for steps of a subcase (load steps, times or frequencies)
+  /// always get a Step column, right after this one.
+  #[arg(long = "label-column")]
+  label_column: bool,
+  /// Only write the records for the rows of these data.
+  ///
+  /// Takes a path to a JSON file holding a list of datum indexes, such as
+  /// the positions flagged by a diff. Other filters still apply.
+  #[arg(long = "flagged")]
+  flagged: Option<PathBuf>,
+  /// Drop columns that are blank or zero in every written record of a CSV
+  /// block.
+  ///
+  /// This takes an extra pass over the records, and applies after --reorder
+  /// and --dofs. Keep in mind that a column of real zeroes is dropped too.
+  #[arg(long = "trim-blanks")]
+  trim_blanks: bool,
+  /// Write the reals of a column as integers if they're all integral in
+  /// every written record of its CSV block, such as IDs that came as reals.
+  ///
+  /// This takes an extra pass over the records, like --trim-blanks. Blanks
+  /// don't stop a column from being written as integers.
+  #[arg(
+    long = "int-like",
+    conflicts_with_all = ["metrics", "envelope", "sql"]
+  )]
+  int_like: bool,
+  /// Write a placeholder record for every subcase and CSV block with no
+  /// records, so joins across blocks and files line up.
+  ///
+  /// Placeholders are blank except for their subcase, and go after the
+  /// other records of their block. The grid is made of the subcases in the
+  /// file (or those given with -s) and the blocks given with -b (or all of
+  /// them). Filters can leave a block empty, which also gets a placeholder.
+  #[arg(
+    long = "fill-missing",
+    conflicts_with_all = ["metrics", "envelope", "sql"]
+  )]
+  fill_missing: bool,
+  /// Also write a JSON file describing the columns of every kind of record
+  /// that can be written for the blocks in the file: their headers, what
+  /// they hold (e.g. the type of index of value columns) and units, if known.
+  ///
+  /// Includes the columns appended after the converted ones (such as PID,
+  /// Label, Step and the nodes). Can't be combined with the options that
+  /// drop or move columns around.
+  #[arg(
+    long = "schema-out",
+    value_name = "PATH",
+    conflicts_with_all = [
+      "reorder", "dofs", "trim_blanks", "metrics", "envelope", "sql"
+    ]
+  )]
+  schema_out: Option<PathBuf>,
+  /// Write per-column metrics instead of records.
+  ///
+  /// Writes one row per subcase, block type and column, with the minimum,
+  /// maximum, average and standard deviation of that column. The subcase
+  /// and block filters still apply; the other filters don't.
+  #[arg(
+    long = "metrics",
+    conflicts_with_all = [
+      "split_by_subcase", "reorder", "connectivity", "pid_column",
+      "label_column", "trim_blanks"
+    ]
+  )]
+  metrics: bool,
+  /// Write the envelope of a column instead of records.
+  ///
+  /// Takes a column name as displayed (ignoring case), like "VON MISES". For
+  /// every row of each block type with that column, writes the subcase where
+  /// it's largest in magnitude, and the value there. The block filter still
+  /// applies; the other filters don't.
+  #[arg(
+    long = "envelope",
+    value_name = "COLUMN",
+    conflicts_with_all = [
+      "split_by_subcase", "reorder", "connectivity", "pid_column",
+      "label_column", "trim_blanks", "metrics"
+    ]
+  )]
+  envelope: Option<String>,
+  /// Write the records as an SQL script instead of a CSV.
+  ///
+  /// The script creates one table per CSV block, with columns named after
+  /// the headers, and inserts the records that pass the filters. Load it
+  /// into SQLite with "sqlite3 results.db < results.sql".
+  #[arg(
+    long = "sql",
+    conflicts_with_all = [
+      "split_by_subcase", "reorder", "dofs", "connectivity", "pid_column",
+      "label_column", "trim_blanks", "metrics", "envelope"
+    ]
+  )]
+  sql: bool,
+  /// Write the records into a SQLite database instead of a CSV.
+  ///
+  /// Writes the same tables as --sql, replacing them if the database has
+  /// them already, and leaves other tables alone.
+  #[cfg(feature = "sqlite")]
+  #[arg(
+    long = "sqlite",
+    value_name = "DB",
+    conflicts_with_all = [
+      "output", "split_by_subcase", "reorder", "dofs", "connectivity",
+      "pid_column", "label_column", "trim_blanks", "int_like", "fill_missing",
+      "schema_out", "metrics", "envelope", "sql"
+    ]
+  )]
+  sqlite: Option<PathBuf>,
   /// Output extra/debug info while parsing and converting.
   #[arg(short = 'v', long = "verbose", verbatim_doc_comment)]
   verbose: bool,
-  /// The name of the input F06 file.
+  /// List every known block type, the CSV block it's written to and its
+  /// column headers, then exit.
+  #[arg(long = "list-blocks")]
+  list_blocks: bool,
+  /// The name of the input F06 file, or punch file if it ends in .pch.
   ///
   /// If -, reads from standard input.
-  input: PathBuf,
+  #[arg(required_unless_present = "list_blocks")]
+  input: Option<PathBuf>,
+}
+
+/// Prints out every known block type along with its CSV conversion, if any.
+fn list_blocks() {
+  let converters = all_converters();
+  for bt in BlockType::all() {
+    println!("{} ({}):", bt.short_name(), bt.desc());
+    if let Some(conv) = converters.get(bt) {
+      let bid = conv.output_block_id;
+      println!(
+        "  CSV block {} ({}, -b {})",
+        usize::from(bid),
+        bid.name(),
+        bid.shorthand()
+      );
+      for header in conv.headers {
+        println!("  columns: {}", header.join(", "));
+      }
+    } else {
+      println!("  no CSV converter");
+    }
+  }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
   // init cli stuff
-  let args = Cli::parse();
+  let mut args = Cli::parse();
   let log_level = if args.verbose {
     LevelFilter::Debug
   } else {
     LevelFilter::Info
   };
   env_logger::builder().filter_level(log_level).init();
+  if args.list_blocks {
+    list_blocks();
+    return Ok(());
+  }
+  if let Err(e) = args.fmtr.check_delimiter(args.delim) {
+    error!("Bad formatting options: {}!", e);
+    std::process::exit(1);
+  }
+  let input = args.input.clone().expect("clap should require an input");
+  let progress = |n: usize| info!("Read {} lines...", n);
   // parse the file
-  let mut f06: F06File = if args.input.as_os_str().eq_ignore_ascii_case("-") {
-    OnePassParser::parse_bufread(BufReader::new(io::stdin()))?
-  } else if args.input.is_file() {
-    if let Some(bn) = args.input.file_name() {
+  let mut f06: F06File = if input.as_os_str().eq_ignore_ascii_case("-") {
+    OnePassParser::parse_bufread_with_progress(
+      BufReader::new(io::stdin()),
+      args.flavour,
+      args.parser.clone(),
+      PROGRESS_INTERVAL,
+      progress
+    ).unwrap_or_else(|e| parse_failed(e))
+  } else if input.is_file() {
+    if let Some(bn) = input.file_name() {
       if let Some(sbn) = bn.to_str() {
         info!("Parsing {}...", sbn);
       }
     } else {
       info!("Parsing...");
     }
-    OnePassParser::parse_file(&args.input)?
+    parse_any_file_with_progress(
+      &input,
+      args.flavour,
+      args.parser.clone(),
+      PROGRESS_INTERVAL,
+      progress
+    ).unwrap_or_else(|e| parse_failed(e))
   } else {
     error!("Provided path either does not exist or is not a file!");
     std::process::exit(1);
@@ -129,41 +373,205 @@ fn main() -> Result<(), Box<dyn Error>> {
   f06.merge_potential_headers();
   f06.sort_all_blocks();
   info!("Done parsing.");
-  // init the csv writer
-  let output: BufWriter<Box<dyn Write>> = BufWriter::new(
-    if let Some(ref op) = args.output {
-      Box::new(File::create(op)?)
-    } else {
-      Box::new(io::stdout())
+  f06.relabel_subcases(args.relabel.iter());
+  // turn subcase labels into subcase IDs
+  for label in args.subcase_labels.iter() {
+    let found = f06.subcases_labelled(label);
+    if found.is_empty() {
+      error!("No subcase has a label containing \"{}\"!", label);
+      std::process::exit(1);
+    }
+    args.subcases.extend(found.into_iter().map(usize::from));
+  }
+  // tell the user what we won't be converting
+  if args.verbose {
+    for bt in unconverted_block_types(&f06, &all_converters()) {
+      debug!("No CSV converter exists yet for {}.", bt);
     }
-  );
+    for (kind, phs) in f06.unsupported_report() {
+      debug!("Found {} unsupported header(s) with {}:", phs.len(), kind);
+      for ph in phs {
+        debug!("  - line {}: \"{}\"", ph.start, ph.text);
+      }
+    }
+  }
+  // read the flagged data, if any
+  let flagged: Vec<DatumIndex> = if let Some(ref p) = args.flagged {
+    serde_json::from_reader(BufReader::new(File::open(p)?))?
+  } else {
+    Vec::new()
+  };
+  if args.flagged.is_some() && flagged.is_empty() {
+    warn!("The flagged data list is empty, so nothing will be written.");
+    return Ok(());
+  }
+  // init the csv writers
   let delim_byte: u8 = args.delim.try_into()
     .expect("Delimiter must not be a special character1");
   let term = if args.crlf { Terminator::CRLF } else { Terminator::default() };
-  let mut wtr = csv::WriterBuilder::new()
+  // results for several steps of a subcase need telling apart
+  let has_steps = f06.blocks.keys().any(|br| br.step.is_some());
+  let mk_writer = |w: Box<dyn Write>| csv::WriterBuilder::new()
     .delimiter(delim_byte)
     .terminator(term)
-    .from_writer(output);
-  /// Filter only if there is at least one in the filter.
-  fn lax_filter<T: PartialEq>(v: &[T], x: &Option<T>) -> bool {
-    return v.is_empty()
-      || x.is_none()
-      || x.as_ref().is_some_and(|k| v.contains(k));
+    .flexible(
+      !args.reorder.is_empty()
+        || !args.dofs.is_empty()
+        || args.connectivity
+        || args.pid_column
+        || args.label_column
+        || has_steps
+        || args.trim_blanks
+    )
+    .from_writer(BufWriter::new(w));
+  // outputs are keyed by subcase when splitting, else there's just the one
+  let mut outputs: BTreeMap<Option<usize>, Output> = BTreeMap::new();
+  let out_dir = args.output.clone().unwrap_or(PathBuf::from("."));
+  if args.split_by_subcase {
+    fs::create_dir_all(&out_dir)?;
+  } else {
+    let output: Box<dyn Write> = if let Some(ref op) = args.output {
+      Box::new(File::create(op)?)
+    } else {
+      Box::new(io::stdout())
+    };
+    outputs.insert(None, (mk_writer(output), None));
+  }
+  // metrics are a different shape entirely
+  if args.metrics {
+    if let Some((wtr, _)) = outputs.get_mut(&None) {
+      info!("Writing column metrics...");
+      write_metrics(&f06, &args, wtr)?;
+      wtr.flush()?;
+    }
+    info!("All done.");
+    return Ok(());
+  }
+  // and so is the envelope
+  if let Some(ref name) = args.envelope {
+    if let Some((wtr, _)) = outputs.get_mut(&None) {
+      info!("Writing the envelope of {}...", name);
+      write_envelope(&f06, &args, name, wtr)?;
+      wtr.flush()?;
+    }
+    info!("All done.");
+    return Ok(());
   }
   // should we write a record?
-  let should_write = |r: &CsvRecord, a: &Cli| -> bool {
-    let f_blocks = lax_filter(&a.csv_blocks, &Some(r.block_id));
-    let f_gids = lax_filter(&a.gids, &r.gid);
-    let f_eids = lax_filter(&a.eids, &r.eid);
-    let f_etypes = lax_filter(&a.etypes, &r.etype);
-    let f_subcases = lax_filter(&a.subcases, &r.subcase);
-    return f_gids && f_eids && f_etypes && f_subcases && f_blocks;
+  let filter = RecordFilter::new()
+    .blocks(args.csv_blocks.iter().copied())
+    .gids(args.gids.iter().copied())
+    .eids(args.eids.iter().copied())
+    .etypes(args.etypes.iter().copied())
+    .subcases(args.subcases.iter().copied())
+    .pids(&f06.properties, args.pids.iter().copied())
+    .points(args.points)
+    .datums(flagged);
+  // and so is a database
+  #[cfg(feature = "sqlite")]
+  if let Some(ref db) = args.sqlite {
+    info!("Writing to {}...", db.display());
+    write_sqlite(
+      db,
+      to_records(&f06, &all_converters()).filter(|rec| filter.matches(rec))
+    )?;
+    info!("All done.");
+    return Ok(());
+  }
+  // an SQL script is yet another shape
+  if args.sql {
+    if let Some((wtr, _)) = outputs.remove(&None) {
+      info!("Writing SQL...");
+      let mut out = wtr.into_inner().map_err(|e| e.into_error())?;
+      write_sql(
+        &mut out,
+        to_records(&f06, &all_converters()).filter(|rec| filter.matches(rec))
+      )?;
+      out.flush()?;
+    }
+    info!("All done.");
+    return Ok(());
+  }
+  let converters = all_converters();
+  // which DOF columns to keep, if restricted
+  let dof_sel = if args.dofs.is_empty() {
+    None
+  } else {
+    Some(DofSelection::new(args.dofs.iter().copied()))
+  };
+  // the headers and fields of a record, after reordering and DOF selection
+  let rec_headers = |rec: &CsvRecord| -> Result<Vec<&str>, ColumnOrderError> {
+    let order = args.reorder.iter().find(|o| o.block_id == rec.block_id);
+    return Ok(if let Some(o) = order {
+      o.headers(rec)?
+    } else if let Some(ref ds) = dof_sel {
+      ds.headers(rec, &converters)
+    } else {
+      rec.header_as_iter().collect()
+    });
+  };
+  let rec_fields = |rec: &CsvRecord| -> Result<Vec<_>, ColumnOrderError> {
+    let order = args.reorder.iter().find(|o| o.block_id == rec.block_id);
+    return Ok(if let Some(o) = order {
+      o.fields(rec)?
+    } else if let Some(ref ds) = dof_sel {
+      ds.fields(rec, &converters)
+    } else {
+      rec.clone().to_fields().collect()
+    });
+  };
+  // find the columns worth keeping in each block, if trimming
+  let mut kept_cols: BTreeMap<CsvBlockId, Vec<bool>> = BTreeMap::new();
+  if args.trim_blanks {
+    for rec in to_records(&f06, &converters) {
+      if !filter.matches(&rec) {
+        continue;
+      }
+      let kept = kept_cols.entry(rec.block_id).or_default();
+      for (i, field) in rec_fields(&rec)?.iter().enumerate() {
+        // the block ID goes first, and always stays
+        let used = i == 0 || !args.fmtr.is_blank_or_zero(field);
+        match kept.get_mut(i) {
+          Some(k) => *k |= used,
+          None => kept.push(used)
+        };
+      }
+    }
+  }
+  // find the columns whose reals are all integral in each block, if asked
+  let mut int_cols: BTreeMap<CsvBlockId, Vec<bool>> = BTreeMap::new();
+  if args.int_like {
+    for rec in to_records(&f06, &converters) {
+      if !filter.matches(&rec) {
+        continue;
+      }
+      let ints = int_cols.entry(rec.block_id).or_default();
+      for (i, field) in rec_fields(&rec)?.iter().enumerate() {
+        let int = args.fmtr.is_int_like(field);
+        match ints.get_mut(i) {
+          Some(k) => *k &= int,
+          None => ints.push(int)
+        };
+      }
+    }
+  }
+  let to_ints = |rec: &CsvRecord, fields: Vec<CsvField>| -> Vec<CsvField> {
+    let Some(ints) = int_cols.get(&rec.block_id) else {
+      return fields;
+    };
+    return fields.into_iter()
+      .enumerate()
+      .map(|(i, f)| match ints.get(i) {
+        Some(true) => args.fmtr.to_int_like(f),
+        _ => f
+      })
+      .collect();
   };
   // determine padding
   let largest: Option<usize> = if args.fmtr.align != Alignment::None {
     to_records(&f06, &all_converters())
       .filter_map(|rec| {
-        if should_write(&rec, &args) && rec.block_id != CsvBlockId::Metadata {
+        if filter.matches(&rec) && rec.block_id != CsvBlockId::Metadata {
           let h = if args.headers {
             rec.header_as_iter().map(|f| f.len()).max()
           } else {
@@ -203,26 +611,266 @@ fn main() -> Result<(), Box<dyn Error>> {
       return s.to_owned();
     }
   };
+  // the property ID column to append, if any
+  if (args.pid_column || !args.pids.is_empty()) && f06.properties.is_empty() {
+    warn!("No element property IDs found, is the bulk data echoed?");
+  }
+  let pid_headers: &[&str] = if args.pid_column { &["PID"] } else { &[] };
+  let pid_fields = |r: &CsvRecord| -> Vec<CsvField> {
+    if !args.pid_column || r.block_id == CsvBlockId::Metadata {
+      return Vec::new();
+    }
+    let pid = r.eid.and_then(|e| f06.properties.get(&e));
+    return vec![pid.map_or(CsvField::Blank, |p| CsvField::Natural(*p))];
+  };
+  // the subcase label column to append, if any
+  let label_headers: &[&str] = if args.label_column { &["Label"] } else { &[] };
+  let label_fields = |r: &CsvRecord| -> Vec<CsvField> {
+    if !args.label_column || r.block_id == CsvBlockId::Metadata {
+      return Vec::new();
+    }
+    let label = r.subcase.and_then(|sc| f06.subcase_label(sc.into()));
+    return vec![label.map_or(CsvField::Blank, |l| CsvField::String(l.into()))];
+  };
+  // the step column to append, if there are steps
+  let step_headers: &[&str] = if has_steps { &["Step"] } else { &[] };
+  let step_fields = |r: &CsvRecord| -> Vec<CsvField> {
+    if !has_steps || r.block_id == CsvBlockId::Metadata {
+      return Vec::new();
+    }
+    return vec![r.step.map_or(CsvField::Blank, |s| CsvField::Real(s.value()))];
+  };
+  // number of node columns to append
+  let nnodes = if args.connectivity {
+    if f06.connectivity.is_empty() {
+      warn!("No element connectivity found, is the bulk data echoed?");
+    }
+    f06.connectivity.values().map(Vec::len).max().unwrap_or(0)
+  } else {
+    0
+  };
+  let node_headers = (1..=nnodes)
+    .map(|i| format!("Node {}", i))
+    .collect::<Vec<_>>();
+  let node_fields = |r: &CsvRecord| -> Vec<CsvField> {
+    if r.block_id == CsvBlockId::Metadata {
+      return Vec::new();
+    }
+    let nodes = r.eid.and_then(|e| f06.connectivity.get(&e));
+    return (0..nnodes)
+      .map(|i| nodes.and_then(|v| v.get(i)).map_or(
+        CsvField::Blank,
+        |g| CsvField::Natural(*g)
+      ))
+      .collect();
+  };
+  // describe the columns, if asked
+  if let Some(ref p) = args.schema_out {
+    let block_types = f06.all_blocks(false)
+      .map(|b| b.block_type)
+      .filter(|bt| wants_block_type(&args, &converters, *bt))
+      .collect::<BTreeSet<_>>();
+    let mut schema = block_types.iter()
+      .filter_map(|bt| converters.get(bt))
+      .map(BlockConverter::schema)
+      .collect::<Vec<_>>();
+    for conv in schema.iter_mut() {
+      pid_headers.iter().for_each(|h| conv.append(h, "PROPERTY ID"));
+      label_headers.iter().for_each(|h| conv.append(h, "SUBCASE LABEL"));
+      step_headers.iter().for_each(|h| conv.append(h, "STEP"));
+      node_headers.iter().for_each(|h| conv.append(h, "GRID ID"));
+    }
+    info!("Writing the column schema to {}...", p.display());
+    let file = BufWriter::new(File::create(p)?);
+    serde_json::to_writer_pretty(file, &schema)?;
+  }
+  // the records to write, with placeholders if filling in missing ones
+  let filtered = to_records(&f06, &converters)
+    .filter(|rec| filter.matches(rec));
+  let records: Box<dyn Iterator<Item = CsvRecord>> = if args.fill_missing {
+    let block_ids = if args.csv_blocks.is_empty() {
+      CsvBlockId::all()
+        .iter()
+        .copied()
+        .filter(|b| *b != CsvBlockId::Metadata)
+        .collect()
+    } else {
+      args.csv_blocks.clone()
+    };
+    let subcases = f06.subcases()
+      .map(usize::from)
+      .filter(|sc| args.subcases.is_empty() || args.subcases.contains(sc))
+      .collect::<BTreeSet<_>>();
+    let filled = fill_missing(filtered, &block_ids, &subcases, &converters);
+    Box::new(filled.into_iter())
+  } else {
+    Box::new(filtered)
+  };
   // write blocks
   info!("Writing CSV records...");
-  let mut last_header: Option<(&RowHeader, CsvBlockId)> = None;
-  for rec in to_records(&f06, &all_converters()) {
-    if should_write(&rec, &args) {
-      if args.headers {
-        let cur_header = &rec.headers;
-        let cur_bid = rec.block_id;
-        let was_none = last_header.is_none();
-        last_header = last_header.or(Some((cur_header, cur_bid)));
-        if last_header != Some((cur_header, cur_bid)) || was_none {
-          // header change
-          last_header = Some((cur_header, cur_bid));
-          wtr.write_record(rec.header_as_iter().map(pad))?;
+  for rec in records {
+    let key = if args.split_by_subcase { rec.subcase } else { None };
+    let (wtr, last_header) = match outputs.entry(key) {
+      Entry::Occupied(e) => e.into_mut(),
+      Entry::Vacant(e) => {
+        let name = match key {
+          Some(sc) => format!("subcase_{:03}.csv", sc),
+          None => "subcase_none.csv".to_owned()
+        };
+        info!("Writing to {}...", name);
+        let file = File::create(out_dir.join(name))?;
+        e.insert((mk_writer(Box::new(file)), None))
+      }
+    };
+    if args.headers {
+      let cur = Some((rec.headers, rec.block_id));
+      if *last_header != cur {
+        // header change
+        *last_header = cur;
+        let kept = kept_cols.get(&rec.block_id);
+        let mut headers = trim_cols(rec_headers(&rec)?, kept)
+          .into_iter()
+          .map(pad)
+          .collect::<Vec<_>>();
+        if rec.block_id != CsvBlockId::Metadata {
+          headers.extend(pid_headers.iter().map(|h| pad(h)));
+          headers.extend(label_headers.iter().map(|h| pad(h)));
+          headers.extend(step_headers.iter().map(|h| pad(h)));
+          headers.extend(node_headers.iter().map(|h| pad(h)));
         }
+        wtr.write_record(headers)?;
       }
-      wtr.write_record(rec.to_fields().map(|f| pad(&args.fmtr.to_string(f))))?;
     }
+    let kept = kept_cols.get(&rec.block_id);
+    let mut fields = trim_cols(to_ints(&rec, rec_fields(&rec)?), kept);
+    fields.extend(pid_fields(&rec));
+    fields.extend(label_fields(&rec));
+    fields.extend(step_fields(&rec));
+    fields.extend(node_fields(&rec));
+    wtr.write_record(
+      fields.into_iter().map(|f| pad(&args.fmtr.to_string(f)))
+    )?;
+  }
+  for (wtr, _) in outputs.values_mut() {
+    wtr.flush()?;
   }
   info!("All done.");
   // done
   return Ok(());
 }
+
+/// Writes a row of single-column metrics per subcase, block type and column.
+fn write_metrics(
+  f06: &F06File,
+  args: &Cli,
+  wtr: &mut csv::Writer<BufWriter<Box<dyn Write>>>
+) -> Result<(), Box<dyn Error>> {
+  let converters = all_converters();
+  let opts = MetricOptions {
+    epsilon: 0.0,
+    unset_threshold: args.fmtr.blank_unset
+  };
+  if args.headers {
+    let mut headers = vec!["Subcase", "Block", "Column"];
+    headers.extend(SingleColumnMetric::all().iter().map(|m| m.short_name()));
+    wtr.write_record(headers)?;
+  }
+  for block in f06.all_blocks(true) {
+    if !args.subcases.is_empty() && !args.subcases.contains(&block.subcase) {
+      continue;
+    }
+    if !wants_block_type(args, &converters, block.block_type) {
+      continue;
+    }
+    for col in block.col_indexes.keys() {
+      let mut fields = vec![
+        block.subcase.to_string(),
+        block.block_type.short_name().to_owned(),
+        col.to_string()
+      ];
+      fields.extend(SingleColumnMetric::all().iter().map(|m| {
+        let field = m.compute_with(block, *col, &opts)
+          .map_or(CsvField::Blank, CsvField::Real);
+        return args.fmtr.to_string(field);
+      }));
+      wtr.write_record(fields)?;
+    }
+  }
+  return Ok(());
+}
+
+/// Writes the governing subcase and value of a column, for each row of every
+/// block type that has it.
+fn write_envelope(
+  f06: &F06File,
+  args: &Cli,
+  name: &str,
+  wtr: &mut csv::Writer<BufWriter<Box<dyn Write>>>
+) -> Result<(), Box<dyn Error>> {
+  let converters = all_converters();
+  if args.headers {
+    wtr.write_record(["Block", "Row", "Column", "Subcase", "Value"])?;
+  }
+  let mut found = false;
+  for bt in f06.block_types() {
+    if !wants_block_type(args, &converters, bt) {
+      continue;
+    }
+    let cols = f06.block_search(Some(bt), None, false)
+      .flat_map(|b| b.col_indexes.keys())
+      .filter(|c| c.to_string().eq_ignore_ascii_case(name))
+      .copied()
+      .collect::<BTreeSet<_>>();
+    for col in cols {
+      found = true;
+      for (row, (subcase, x)) in f06.envelope(bt, col) {
+        wtr.write_record([
+          bt.short_name().to_owned(),
+          row.to_string(),
+          col.to_string(),
+          subcase.to_string(),
+          args.fmtr.to_string(CsvField::Real(x))
+        ])?;
+      }
+    }
+  }
+  if !found {
+    warn!("No block has a column named \"{}\".", name);
+  }
+  return Ok(());
+}
+
+/// Returns whether the blocks of a type pass the CSV block filter.
+fn wants_block_type(
+  args: &Cli,
+  converters: &BTreeMap<BlockType, BlockConverter>,
+  bt: BlockType
+) -> bool {
+  if args.csv_blocks.is_empty() {
+    return true;
+  }
+  let bid = converters.get(&bt).map(|c| c.output_block_id);
+  return bid.is_some_and(|b| args.csv_blocks.contains(&b));
+}
+
+/// Drops the columns of a record not marked as kept. Columns past the end of
+/// the marks, or all of them if there are no marks, are kept.
+fn trim_cols<T>(v: Vec<T>, kept: Option<&Vec<bool>>) -> Vec<T> {
+  return match kept {
+    Some(kept) => v.into_iter()
+      .enumerate()
+      .filter(|(i, _)| kept.get(*i).copied().unwrap_or(true))
+      .map(|(_, x)| x)
+      .collect(),
+    None => v
+  };
+}
+
+/// Logs why the input couldn't be parsed, then exits.
+fn parse_failed(e: ParseError) -> ! {
+  error!("Could not parse the input: {}.", e);
+  if let Some(hint) = e.hint() {
+    error!("{}", hint);
+  }
+  std::process::exit(1);
+}
@@ -8,8 +8,9 @@
 #![allow(clippy::needless_return)] // i'll never forgive rust for this
 
 use std::collections::BTreeSet;
+use std::fs;
 use std::io::{self, BufReader};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::Parser;
 use log::{LevelFilter, info, error, warn};
@@ -29,10 +30,44 @@ struct Cli {
   #[clap(default_value_t = 10)]
   #[arg(short = 'p')]
   print_max_flags: isize,
+  /// Instead of a report, print how many values get flagged at a series of
+  /// tolerances (absolute differences and ratios alike), then exit.
+  #[arg(long)]
+  sweep: bool,
+  /// Instead of a report, compare the potential headers (tables that were
+  /// not decoded) of both files, printing those unique to each and the
+  /// common ones, then exit.
+  #[arg(long)]
+  potential_headers: bool,
+  /// Print the N most severe flagged values across all blocks before the
+  /// per-block report.
+  #[arg(long, value_name = "N")]
+  top: Option<usize>,
+  /// Start from the criteria conventional for a kind of result. Criteria
+  /// given explicitly take precedence over the preset's.
+  #[arg(long = "preset")]
+  preset: Option<CriteriaPreset>,
+  /// Print N lines of each file before and after every flagged position
+  /// reported in detail (see -p). They're centred on the line of the block
+  /// that mentions the row's element and grid point IDs, or on the block's
+  /// first line if there's none. Needs both files on disk.
+  #[arg(long = "context", value_name = "N")]
+  context: Option<usize>,
+  /// Only compare blocks in the subcase whose label (in the first file)
+  /// contains this text, ignoring case. It must match exactly one subcase.
+  #[arg(long = "subcase-label", conflicts_with = "only_subcase")]
+  subcase_label: Option<String>,
   /// The settings for the differ.
   #[command(flatten)]
   settings: DiffSettings,
-  /// Path to the first file.
+  /// Flavour hints, for files whose solver banner is missing.
+  #[command(flatten)]
+  flavour: Flavour,
+  /// Parser settings.
+  #[command(flatten)]
+  parser: ParserSettings,
+  /// Path to the first file. Files ending in .pch are read as punch files,
+  /// and files ending in .csv as MYSTRAN self-check CSVs.
   first: PathBuf,
   /// Path to the second file. Set to "-" to read from stdin.
   second: PathBuf
@@ -40,16 +75,22 @@ struct Cli {
 
 fn main() -> io::Result<()> {
   // init cli stuff
-  let args = Cli::parse();
+  let mut args = Cli::parse();
   let log_level = if args.verbose {
     LevelFilter::Debug
   } else {
     LevelFilter::Info
   };
   env_logger::builder().filter_level(log_level).init();
+  // fill in the preset's criteria
+  if let Some(preset) = args.preset {
+    let crit = args.settings.criteria.or_from(&preset.criteria());
+    args.settings.criteria = crit;
+  }
   // check for no ratio and no difference
   let crit = &args.settings.criteria;
-  if crit.ratio.is_none() && crit.difference.is_none() {
+  let numeric = !args.sweep && !args.potential_headers;
+  if crit.ratio.is_none() && crit.difference.is_none() && numeric {
     warn!("You didn't specify a max difference nor a max ratio.");
     warn!("You'll likely get no useful results, number-wise.");
   }
@@ -62,14 +103,19 @@ fn main() -> io::Result<()> {
     } else {
       info!("Loading first file...");
     }
-    OnePassParser::parse_file(&args.first)?
+    parse_any_file(&args.first, args.flavour, args.parser.clone())
+      .unwrap_or_else(|e| parse_failed(e))
   } else {
     error!("Second path either does not exist or is not a file!");
     std::process::exit(1);
   };
   // parse the second file
   let mut second = if args.second.as_os_str().eq_ignore_ascii_case("-") {
-    let mut f = OnePassParser::parse_bufread(BufReader::new(io::stdin()))?;
+    let mut f = OnePassParser::parse_bufread_with(
+      BufReader::new(io::stdin()),
+      args.flavour,
+      args.parser.clone()
+    ).unwrap_or_else(|e| parse_failed(e));
     f.filename = Some("<stdin>".to_string());
     f
   } else if args.second.is_file() {
@@ -80,7 +126,8 @@ fn main() -> io::Result<()> {
     } else {
       info!("Loading first file...");
     }
-    OnePassParser::parse_file(&args.second)?
+    parse_any_file(&args.second, args.flavour, args.parser.clone())
+      .unwrap_or_else(|e| parse_failed(e))
   } else {
     error!("Second path either does not exist or is not a file!");
     std::process::exit(1);
@@ -96,6 +143,41 @@ fn main() -> io::Result<()> {
     b.merge_potential_headers();
     b.sort_all_blocks();
   }
+  // turn the subcase label into a subcase ID
+  if let Some(ref label) = args.subcase_label {
+    match first.subcases_labelled(label).as_slice() {
+      [sc] => {
+        info!("Subcase {} has a label containing \"{}\".", sc, label);
+        args.settings.only_subcase = Some(*sc);
+      },
+      [] => {
+        error!("No subcase has a label containing \"{}\"!", label);
+        std::process::exit(1);
+      },
+      many => {
+        error!(
+          "Subcases {:?} all have labels containing \"{}\"!",
+          many,
+          label
+        );
+        std::process::exit(1);
+      }
+    };
+  }
+  // pick what to do with rows unique to either file
+  let explicit_dxn = args.settings.dxn_behaviour.is_some();
+  let dxn = args.settings.resolve_dxn_behaviour(&first, &second);
+  if !explicit_dxn && dxn == DisjunctionBehaviour::Skip {
+    info!("Files come from different solvers, skipping rows unique to one.");
+  }
+  if args.sweep {
+    sweep(&args.settings, &first, &second);
+    return Ok(());
+  }
+  if args.potential_headers {
+    compare_potential_headers(&first, &second);
+    return Ok(());
+  }
   // generate the diff
   info!("Generating diff...");
   let diff = F06Diff::compare(&args.settings, &first, &second);
@@ -115,6 +197,7 @@ fn main() -> io::Result<()> {
       solver_name(&second)
     );
   }
+  info!("{}- Rows unique to one file: {};", INDENT, dxn);
   // number of blocks
   let nb1 = first.all_blocks(false).count();
   let nb1u = first.all_blocks(true).count();
@@ -147,6 +230,16 @@ fn main() -> io::Result<()> {
   };
   countwarn(first.warnings.len(), second.warnings.len(), "Warnings");
   countwarn(first.fatal_errors.len(), second.fatal_errors.len(), "Fatals");
+  // applied loads
+  for mismatch in diff.load_mismatches.iter() {
+    warn!(
+      "{}- Loads differ in subcase {}: first has {}, second has {}!",
+      INDENT,
+      mismatch.subcase,
+      mismatch.first,
+      mismatch.second
+    );
+  }
   // filenames similarity
   let mut fnwarn: Option<&str> = None;
   if fn1.eq_ignore_ascii_case(&fn2) {
@@ -171,12 +264,39 @@ fn main() -> io::Result<()> {
       "respectively"
     );
   }
+  // read the files again for context, if asked to
+  let subcase_pairs = args.settings.subcase_map.clone()
+    .unwrap_or_default()
+    .resolve(&first, &second);
+  let (lines1, lines2) = if args.context.is_some() {
+    (read_lines(&args.first), read_lines(&args.second))
+  } else {
+    (None, None)
+  };
   // make file padding
   let mkpad = |a: &str, b: &str| {
     " ".repeat((b.len() as isize - a.len() as isize).max(0) as usize)
   };
   let pad1 = mkpad(&fn1, &fn2);
   let pad2 = mkpad(&fn2, &fn1);
+  // list the worst flags first
+  if let Some(n) = args.top.filter(|n| *n > 0) {
+    let ranked = diff.ranked();
+    info!("Most severe flags ({} of {}):", n.min(ranked.len()), ranked.len());
+    for (br, flag) in ranked.into_iter().take(n) {
+      info!(
+        "{}- {}, {}, {}: {} | {} ({}, severity {:.3e})",
+        INDENT,
+        br,
+        flag.values.row,
+        flag.values.col,
+        flag.values.val_a,
+        flag.values.val_b,
+        flag.reason,
+        flag.severity()
+      );
+    }
+  }
   // list not compared blocks
   if !diff.not_compared.is_empty() {
     info!("Blocks that could not be compared:");
@@ -205,6 +325,24 @@ fn main() -> io::Result<()> {
       br.subcase,
       br.block_type.desc().to_lowercase()
     );
+    if let Some(dropped) = diff.dropped_columns.get(br) {
+      let names = |cols: &BTreeSet<NasIndex>| cols.iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+      let sides = [(&dropped.only_in_a, &fn1), (&dropped.only_in_b, &fn2)];
+      for (cols, name) in sides {
+        if !cols.is_empty() {
+          info!(
+            "{}{}- Columns only in {} were dropped: {};",
+            INDENT,
+            INDENT,
+            name,
+            names(cols)
+          );
+        }
+      }
+    }
     if flags.is_empty() {
       info!("{}{}- No values flagged.", INDENT, INDENT);
     } else {
@@ -298,8 +436,247 @@ fn main() -> io::Result<()> {
           INDENT,
           flag.reason
         );
+        if let Some(n) = args.context {
+          let subcase2 = subcase_pairs.get(&br.subcase)
+            .copied()
+            .unwrap_or(br.subcase);
+          let br2 = BlockRef { subcase: subcase2, ..*br };
+          let sides = [
+            (&first, br, &lines1, &fn1),
+            (&second, &br2, &lines2, &fn2)
+          ];
+          for (f, br, lines, name) in sides {
+            let row = &flag.values.row;
+            let range = f.row_line_range(br, row);
+            let Some((lines, range)) = lines.as_ref().zip(range) else {
+              continue;
+            };
+            info!(
+              "{}{}{}{}- Context in {}:",
+              INDENT,
+              INDENT,
+              INDENT,
+              INDENT,
+              name
+            );
+            for (i, text) in context_lines(lines, range, row, n) {
+              info!(
+                "{}{}{}{}{}{:>7}: {}",
+                INDENT,
+                INDENT,
+                INDENT,
+                INDENT,
+                INDENT,
+                i,
+                text
+              );
+            }
+          }
+        }
+      }
+    }
+  }
+  // with a single block pair selected, show the values side by side
+  let only = (args.settings.only_block, args.settings.only_subcase);
+  if let (Some(block_type), Some(subcase)) = only {
+    let subcase_b = subcase_pairs
+      .get(&subcase)
+      .copied()
+      .unwrap_or(subcase);
+    let get_one = |f: &F06File, name: &str, subcase: Subcase| {
+      let found = f.blocks.iter()
+        .filter(|(br, _)| br.subcase == subcase && br.block_type == block_type)
+        .flat_map(|(_, v)| v)
+        .collect::<Vec<_>>();
+      return match found.as_slice() {
+        [b] => {
+          let mut b = (*b).clone();
+          if args.settings.derive_von_mises {
+            b.derive_von_mises();
+          }
+          if args.settings.canonicalize_sides {
+            b.canonicalize_sides();
+          }
+          if let Some(how) = args.settings.normalize_modes {
+            b.normalize_mode_shape(how);
+          }
+          Some(b)
+        },
+        [] => {
+          warn!("No {} block in subcase {} of {}.", block_type, subcase, name);
+          None
+        },
+        many => {
+          warn!(
+            "{} {} blocks in subcase {} of {}, can't pick one to show:",
+            many.len(),
+            block_type,
+            subcase,
+            name
+          );
+          for b in many {
+            if let Some((start, end)) = b.line_range {
+              let br = b.block_ref();
+              warn!("{}- {} (lines {} to {})", INDENT, br, start, end);
+            } else {
+              warn!("{}- {}", INDENT, b.block_ref());
+            }
+          }
+          None
+        }
+      };
+    };
+    let pair = (
+      get_one(&first, &fn1, subcase),
+      get_one(&second, &fn2, subcase_b)
+    );
+    if let (Some(mut ba), Some(mut bb)) = pair {
+      for alias in args.settings.column_aliases.iter() {
+        alias.apply(&mut ba, &mut bb);
+      }
+      info!("Values side by side ({} | {}):", fn1, fn2);
+      let show = |b: &FinalBlock, r: &NasIndex, c: &NasIndex| {
+        b.get(*r, *c).map(|x| x.to_string()).unwrap_or("-".to_owned())
+      };
+      let rows = ba.row_indexes.keys()
+        .chain(bb.row_indexes.keys())
+        .collect::<BTreeSet<_>>();
+      for row in rows {
+        info!("{}- {}:", INDENT, row);
+        for col in ba.col_indexes.keys() {
+          info!(
+            "{}{}- {}: {} | {}",
+            INDENT,
+            INDENT,
+            col,
+            show(&ba, row, col),
+            show(&bb, row, col)
+          );
+        }
       }
     }
   }
   return Ok(());
 }
+
+/// Reads the lines of a file, for printing context. Returns None (with a
+/// warning) if it can't be read, like when it came from standard input.
+fn read_lines(path: &Path) -> Option<Vec<String>> {
+  if path.as_os_str().eq_ignore_ascii_case("-") {
+    warn!("Can't print context from standard input.");
+    return None;
+  }
+  return match fs::read(path) {
+    Ok(bytes) => Some(
+      String::from_utf8_lossy(&bytes).lines().map(String::from).collect()
+    ),
+    Err(e) => {
+      warn!("Couldn't read {} for context: {}.", path.display(), e);
+      None
+    }
+  };
+}
+
+/// Picks the lines to print around a row: N before and after the first line
+/// in the block's (one-based) line range that has the row's element ID, and
+/// then its grid point ID, as whole words. If neither is found, the block's
+/// first line is used.
+fn context_lines<'a>(
+  lines: &'a [String],
+  range: (usize, usize),
+  row: &NasIndex,
+  n: usize
+) -> Vec<(usize, &'a str)> {
+  let (start, end) = (range.0.max(1), range.1.min(lines.len()));
+  let has = |i: &usize, id: &str| lines[*i - 1]
+    .split_whitespace()
+    .any(|w| w == id);
+  let ids = [
+    row.element_id().map(|e| e.eid),
+    row.grid_point_id().map(|g| g.gid)
+  ];
+  let mut at = start;
+  for id in ids.into_iter().flatten() {
+    let id = id.to_string();
+    if let Some(i) = (at..=end).find(|i| has(i, &id)) {
+      at = i;
+    }
+  }
+  let first = at.saturating_sub(n).max(1);
+  let last = (at + n).min(lines.len());
+  return (first..=last).map(|i| (i, lines[i - 1].as_str())).collect();
+}
+
+/// Decades covered by the tolerance sweep, from 1 down to 1e-(N-1).
+const SWEEP_DECADES: i32 = 10;
+
+/// Re-runs the comparison with a geometric series of tolerances, printing
+/// the number of flagged values at each.
+fn sweep(settings: &DiffSettings, first: &F06File, second: &F06File) {
+  let mut settings = settings.clone();
+  settings.max_flags = Some(0);
+  let count_with = |difference: Option<f64>, ratio: Option<f64>| {
+    let mut s = settings.clone();
+    s.criteria.difference = difference;
+    s.criteria.ratio = ratio;
+    let diff = F06Diff::compare(&s, first, second);
+    let cells = diff.compared.keys()
+      .flat_map(|br| first.blocks.get(br).into_iter().flatten())
+      .map(|b| b.row_indexes.len() * b.col_indexes.len())
+      .sum::<usize>();
+    let flags = diff.compared.values().map(Vec::len).sum::<usize>();
+    return (flags, cells);
+  };
+  info!("Tolerance sweep (flagged values out of compared values):");
+  for k in 0..SWEEP_DECADES {
+    let tol = 10f64.powi(-k);
+    let (by_diff, cells) = count_with(Some(tol), None);
+    let (by_ratio, _) = count_with(None, Some(1.0 + tol));
+    let pct = |n: usize| if cells == 0 { 100.0 } else {
+      100.0 * (cells - n.min(cells)) as f64 / cells as f64
+    };
+    info!(
+      "{}- {:.0e}: difference flags {} ({:.2}% agree), ratio flags {} \
+        ({:.2}% agree)",
+      INDENT,
+      tol,
+      by_diff,
+      pct(by_diff),
+      by_ratio,
+      pct(by_ratio)
+    );
+  }
+}
+
+/// Prints the potential headers unique to each file, then the common ones.
+fn compare_potential_headers(first: &F06File, second: &F06File) {
+  let pha = first.potential_header_texts();
+  let phb = second.potential_header_texts();
+  let list = |title: &str, texts: Vec<&&str>| {
+    info!("{} ({}):", title, texts.len());
+    for text in texts {
+      info!("{}- {}", INDENT, text);
+    }
+  };
+  list(
+    "Potential headers only in the first file",
+    pha.difference(&phb).collect()
+  );
+  list(
+    "Potential headers only in the second file",
+    phb.difference(&pha).collect()
+  );
+  list(
+    "Potential headers in both files",
+    pha.intersection(&phb).collect()
+  );
+}
+
+/// Logs why the input couldn't be parsed, then exits.
+fn parse_failed(e: ParseError) -> ! {
+  error!("Could not parse the input: {}.", e);
+  if let Some(hint) = e.hint() {
+    error!("{}", hint);
+  }
+  std::process::exit(1);
+}
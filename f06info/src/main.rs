@@ -3,11 +3,16 @@
 #![allow(clippy::needless_return)] // i'll never forgive rust for this
 #![allow(dead_code)] // temporary
 
-use std::collections::BTreeMap;
-use std::io::{self, BufReader};
+mod query;
+#[cfg(test)]
+mod tests;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, BufReader, Write};
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use f06::prelude::*;
 use f06::util::PotentialHeader;
 use log::{LevelFilter, info, error};
@@ -21,7 +26,62 @@ struct Cli {
   /// Output extra/debug info while parsing.
   #[arg(short, long)]
   verbose: bool,
-  /// File path (set to "-" to read from standard input).
+  /// Print the minimum, maximum and average of each column, per block type,
+  /// across all subcases.
+  #[arg(long)]
+  stats: bool,
+  /// In --stats, take values smaller in magnitude than this as zero.
+  #[arg(long, default_value_t = 0.0)]
+  epsilon: f64,
+  /// Run the built-in physical consistency checks on each subcase, such as
+  /// applied loads against SPC forces and grid point force balances.
+  #[arg(long)]
+  checks: bool,
+  /// Relative tolerance for --checks, against the largest value involved.
+  #[arg(long, default_value_t = DEFAULT_CHECK_TOLERANCE)]
+  check_tolerance: f64,
+  /// Print the original file lines of a block, given its type and subcase,
+  /// then exit. Blocks are not merged when doing this.
+  #[arg(
+    long = "extract-raw",
+    num_args = 2,
+    value_names = ["BLOCK_TYPE", "SUBCASE"]
+  )]
+  extract_raw: Option<Vec<String>>,
+  /// Print everything in the file (blocks, warnings, fatals, flavour,
+  /// potential headers and so on) as pretty JSON to standard output, then
+  /// exit. Blocks are sorted by subcase and type, and their values are
+  /// written row-major next to their row and column labels. Blocks are
+  /// merged first unless -M is given.
+  #[arg(long)]
+  dump_json: bool,
+  /// Read queries from standard input and answer them until EOF, instead of
+  /// printing the usual summary. Type "help" for the grammar. Blocks are
+  /// merged first unless -M is given.
+  #[arg(long)]
+  query: bool,
+  /// Merge potential headers separated by up to this many lines, such as
+  /// headers split by a blank line.
+  #[arg(long, default_value_t = 0)]
+  header_gap: usize,
+  /// Only keep the blocks in subcases whose labels contain this text,
+  /// ignoring case. Can be specified more than once. Does not apply to
+  /// --extract-raw, which takes a subcase of its own.
+  #[arg(long = "subcase-label", value_name = "LABEL")]
+  subcase_labels: Vec<String>,
+  /// Give a subcase a label, in the form "SUBCASE=LABEL", replacing the one
+  /// in the file (if any). Can be specified more than once, and applies
+  /// before --subcase-label.
+  #[arg(long = "relabel", value_name = "SUBCASE=LABEL")]
+  relabel: Vec<SubcaseRelabel>,
+  /// Flavour hints, for files whose solver banner is missing.
+  #[command(flatten)]
+  flavour: Flavour,
+  /// Parser settings.
+  #[command(flatten)]
+  parser: ParserSettings,
+  /// File path (set to "-" to read from standard input). Files ending in
+  /// .pch are read as punch files.
   file: PathBuf
 }
 
@@ -38,7 +98,11 @@ fn main() -> io::Result<()> {
   env_logger::builder().filter_level(log_level).init();
   // parse the file
   let mut f06: F06File = if args.file.as_os_str().eq_ignore_ascii_case("-") {
-    OnePassParser::parse_bufread(BufReader::new(io::stdin()))?
+    OnePassParser::parse_bufread_with(
+      BufReader::new(io::stdin()),
+      args.flavour,
+      args.parser.clone()
+    ).unwrap_or_else(|e| parse_failed(e))
   } else if args.file.is_file() {
     if let Some(bn) = args.file.file_name() {
       if let Some(sbn) = bn.to_str() {
@@ -47,17 +111,69 @@ fn main() -> io::Result<()> {
     } else {
       info!("Parsing...");
     }
-    OnePassParser::parse_file(&args.file)?
+    parse_any_file(&args.file, args.flavour, args.parser.clone())
+      .unwrap_or_else(|e| parse_failed(e))
   } else {
     error!("Provided path either does not exist or is not a file!");
     std::process::exit(1);
   };
+  // raw extraction happens before merging, which discards line ranges
+  if let Some(ref spec) = args.extract_raw {
+    extract_raw(&f06, &args.file, spec);
+    return Ok(());
+  }
+  f06.relabel_subcases(args.relabel.iter());
+  // drop the subcases whose labels weren't asked for
+  if !args.subcase_labels.is_empty() {
+    let mut keep: BTreeSet<Subcase> = BTreeSet::new();
+    for label in args.subcase_labels.iter() {
+      let found = f06.subcases_labelled(label);
+      if found.is_empty() {
+        error!("No subcase has a label containing \"{}\"!", label);
+        std::process::exit(1);
+      }
+      keep.extend(found);
+    }
+    f06.blocks.retain(|br, _| keep.contains(&br.subcase));
+  }
+  // dump everything, if that's all that's wanted
+  if args.dump_json {
+    if !args.no_merge {
+      f06.merge_blocks(true);
+      f06.merge_potential_headers_within(args.header_gap);
+    }
+    f06.sort_all_blocks();
+    let dump = F06Dump::from(&f06);
+    let mut out = io::stdout().lock();
+    serde_json::to_writer_pretty(&mut out, &dump)?;
+    writeln!(out)?;
+    return Ok(());
+  }
+  // answer queries, if that's all that's wanted
+  if args.query {
+    if args.file.as_os_str().eq_ignore_ascii_case("-") {
+      error!("Queries are read from standard input, so the file can't be!");
+      std::process::exit(1);
+    }
+    if !args.no_merge {
+      f06.merge_blocks(true);
+    }
+    f06.sort_all_blocks();
+    info!("Done parsing, reading queries (try \"help\").");
+    return query::run(&f06);
+  }
   // print block & merge info
   info!("Done parsing.");
   let solver_name = f06.flavour.solver.map_or("unknown", |s| s.name());
   let soltype = f06.flavour.soltype.map_or("unknown", |st| st.name());
   info!("Solver is {}.", solver_name);
   info!("Analysis type is {}.", soltype);
+  if let Some(ref version) = f06.solver_version {
+    info!("Solver version is {}.", version);
+  }
+  if let Some(ref stamp) = f06.run_timestamp {
+    info!("Run timestamp is {}.", stamp);
+  }
   // print warnings
   if f06.warnings.is_empty() {
     info!("No warnings found.");
@@ -95,23 +211,36 @@ fn main() -> io::Result<()> {
     };
     info!("Supported blocks found:");
     for subcase in f06.subcases() {
-      info!("{}- Subcase {}:", INDENT, subcase);
-      for block in f06.block_search(None, Some(subcase), false) {
+      if let Some(label) = f06.subcase_label(subcase) {
+        info!("{}- Subcase {} ({}):", INDENT, subcase, label);
+      } else {
+        info!("{}- Subcase {}:", INDENT, subcase);
+      }
+      for block in f06.blocks_in(subcase, false) {
+        let at = block.step.map(|s| format!(" at {}", s)).unwrap_or_default();
         info!(
-          "{}{}- {}: {} rows, {} columns",
+          "{}{}- {}{}: {} rows, {} columns",
           INDENT,
           INDENT,
           block.block_type,
+          at,
           block.row_indexes.len(),
           block.col_indexes.len()
         );
       }
     }
+    info!("Content fingerprint is {:016x}.", f06.content_fingerprint());
+    if args.stats {
+      print_stats(&f06, args.epsilon);
+    }
+    if args.checks {
+      print_checks(&f06, args.check_tolerance);
+    }
   }
   if f06.potential_headers.is_empty() {
     info!("No potential headers for unsupported blocks were found.");
   } else {
-    f06.merge_potential_headers();
+    f06.merge_potential_headers_within(args.header_gap);
     info!("Some potential headers for unsupported blocks were found:");
     let mut headers = f06.potential_headers
       .iter()
@@ -138,3 +267,144 @@ fn main() -> io::Result<()> {
   }
   return Ok(());
 }
+
+/// Prints the original lines of every block matching a (type, subcase) spec.
+fn extract_raw(f06: &F06File, path: &PathBuf, spec: &[String]) {
+  let block_type = match BlockType::from_str(&spec[0], true) {
+    Ok(bt) => bt,
+    Err(e) => {
+      error!("Bad block type \"{}\": {}", spec[0], e);
+      std::process::exit(1);
+    }
+  };
+  let subcase = match spec[1].parse::<Subcase>() {
+    Ok(sc) => sc,
+    Err(e) => {
+      error!("Bad subcase \"{}\": {}", spec[1], e);
+      std::process::exit(1);
+    }
+  };
+  if path.as_os_str().eq_ignore_ascii_case("-") {
+    error!("Raw extraction needs a file, not standard input!");
+    std::process::exit(1);
+  }
+  let bytes = match fs::read(path) {
+    Ok(b) => b,
+    Err(e) => {
+      error!("Could not re-read the file: {}", e);
+      std::process::exit(1);
+    }
+  };
+  let text = String::from_utf8_lossy(&bytes);
+  let lines = text.lines().collect::<Vec<_>>();
+  let block_ref = BlockRef::new(subcase, block_type);
+  let ranges = f06.blocks.get(&block_ref)
+    .into_iter()
+    .flatten()
+    .filter_map(|b| b.line_range)
+    .collect::<Vec<_>>();
+  if ranges.is_empty() {
+    error!("No {} block with a known line range in subcase {}.",
+      block_type, subcase);
+    std::process::exit(1);
+  }
+  for range in ranges {
+    for line in raw_lines(&lines, range) {
+      println!("{}", line);
+    }
+  }
+}
+
+/// Picks the lines in a block's line range, which is one-based and includes
+/// both ends. Ranges going past the end of the file are cut short.
+fn raw_lines<'a>(lines: &'a [&'a str], range: (usize, usize)) -> &'a [&'a str] {
+  let start = range.0.saturating_sub(1).min(lines.len());
+  let end = range.1.clamp(start, lines.len());
+  return &lines[start..end];
+}
+
+/// Prints per-column statistics for each block type, across all subcases.
+/// Values smaller in magnitude than epsilon are taken as zero.
+fn print_stats(f06: &F06File, epsilon: f64) {
+  info!("Column statistics (across all subcases):");
+  for bt in f06.block_types() {
+    let blocks = f06.block_search(Some(bt), None, false).collect::<Vec<_>>();
+    let cols = blocks.iter()
+      .flat_map(|b| b.col_indexes.keys().copied())
+      .collect::<BTreeSet<_>>();
+    info!("{}- {}:", INDENT, bt);
+    for col in cols {
+      let metric = |m: SingleColumnMetric| blocks.iter()
+        .filter_map(move |b| m.compute_with_epsilon(b, col, epsilon));
+      let min = metric(SingleColumnMetric::Mininum)
+        .min_by(|a, b| a.total_cmp(b));
+      let max = metric(SingleColumnMetric::Maximum)
+        .max_by(|a, b| a.total_cmp(b));
+      // weigh each block's average by how many values it has in the column
+      let mut count: usize = 0;
+      let mut total: f64 = 0.0;
+      for b in blocks.iter() {
+        let avg = SingleColumnMetric::Average
+          .compute_with_epsilon(b, col, epsilon);
+        if let Some(avg) = avg {
+          let n = b.row_indexes.keys()
+            .filter(|r| b.get(**r, col).is_some())
+            .count();
+          count += n;
+          total += avg * n as f64;
+        }
+      }
+      if let (Some(min), Some(max), true) = (min, max, count > 0) {
+        info!(
+          "{}{}- {}: min {:.6e}, max {:.6e}, avg {:.6e}",
+          INDENT,
+          INDENT,
+          col,
+          min,
+          max,
+          total / count as f64
+        );
+      } else {
+        info!("{}{}- {}: no values", INDENT, INDENT, col);
+      }
+    }
+  }
+}
+
+/// Runs the consistency checks and prints their outcomes per subcase.
+fn print_checks(f06: &F06File, tolerance: f64) {
+  let results = f06.consistency_checks(tolerance);
+  if results.is_empty() {
+    info!("No consistency checks could be run, the blocks they need are \
+      missing.");
+    return;
+  }
+  let failed = results.iter().filter(|r| !r.passed).count();
+  info!(
+    "Consistency checks ({} run, {} failed, relative tolerance {:e}):",
+    results.len(),
+    failed,
+    tolerance
+  );
+  for r in results {
+    info!(
+      "{}- Subcase {}, {}: {} (residual {:.6e}, {:.3e} of {:.6e})",
+      INDENT,
+      r.subcase,
+      r.kind,
+      if r.passed { "pass" } else { "FAIL" },
+      r.residual,
+      r.relative(),
+      r.scale
+    );
+  }
+}
+
+/// Logs why the input couldn't be parsed, then exits.
+fn parse_failed(e: ParseError) -> ! {
+  error!("Could not parse the input: {}.", e);
+  if let Some(hint) = e.hint() {
+    error!("{}", hint);
+  }
+  std::process::exit(1);
+}
@@ -0,0 +1,312 @@
+//! This module implements the `--query` mode, a minimal query language to
+//! poke at a parsed file without re-running the program. Queries are read from
+//! standard input, one per line, until EOF:
+//!
+//! - `blocks` lists the blocks, with their subcases, steps and sizes;
+//! - `get BLOCK [SUBCASE] [FILTER...]` prints the values in a block;
+//! - `stats BLOCK [SUBCASE] [FILTER...]` prints the minimum, maximum, average
+//!   and standard deviation of each column in a block;
+//! - `help` prints a summary of this, and `quit` or `exit` stop early.
+//!
+//! BLOCK is the short name of a block type (see `f06csv --list-blocks`), or
+//! any unambiguous prefix of one, ignoring case; so `DISP` is displacements.
+//! Without a SUBCASE, every subcase is used. The filters narrow things down:
+//!
+//! - `grid=ID` keeps the rows of a grid point;
+//! - `elem=ID` keeps the rows of an element;
+//! - `col=NAME` keeps a column by its name, ignoring case, such as `col=TX`
+//!   or `col="VON MISES"` (quotes group words together). DOF columns can
+//!   also be named like in `--dofs`, such as `col=T1`.
+//!
+//! A filter given more than once keeps anything matching any of its values.
+//! Queries that select no block, or name a column no block has, are errors.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use clap::ValueEnum;
+use f06::prelude::*;
+
+/// A summary of the grammar, printed by `help`.
+const HELP: &str = "\
+queries:
+  blocks                                list the blocks
+  get BLOCK [SUBCASE] [FILTER...]       print values
+  stats BLOCK [SUBCASE] [FILTER...]     print column statistics
+  help                                  print this
+  quit | exit                           stop
+filters (repeatable):
+  grid=ID  elem=ID  col=NAME  (quote names with spaces: col=\"VON MISES\")
+BLOCK is a block type short name, or an unambiguous prefix of one.";
+
+/// Splits a query into words, keeping double-quoted parts together.
+fn tokenize(line: &str) -> Vec<String> {
+  let mut tokens: Vec<String> = Vec::new();
+  let mut current = String::new();
+  let mut quoted = false;
+  let mut started = false;
+  for c in line.chars() {
+    match c {
+      '"' => {
+        quoted = !quoted;
+        started = true;
+      },
+      c if c.is_whitespace() && !quoted => {
+        if started {
+          tokens.push(std::mem::take(&mut current));
+          started = false;
+        }
+      },
+      c => {
+        current.push(c);
+        started = true;
+      }
+    };
+  }
+  if started {
+    tokens.push(current);
+  }
+  return tokens;
+}
+
+/// Finds a block type by its short name or an unambiguous prefix of it.
+fn find_block_type(name: &str) -> Result<BlockType, String> {
+  if let Ok(bt) = BlockType::from_str(name, true) {
+    return Ok(bt);
+  }
+  let lc = name.to_lowercase();
+  let candidates = BlockType::all()
+    .iter()
+    .filter(|bt| bt.short_name().to_lowercase().starts_with(&lc))
+    .collect::<Vec<_>>();
+  return match candidates.as_slice() {
+    [bt] => Ok(**bt),
+    [] => Err(format!("unknown block type \"{}\"", name)),
+    _ => Err(format!(
+      "ambiguous block type \"{}\" (could be {})",
+      name,
+      candidates.iter()
+        .map(|bt| bt.short_name())
+        .collect::<Vec<_>>()
+        .join(", ")
+    ))
+  };
+}
+
+/// The blocks, rows and columns a query is about.
+struct Selection {
+  /// The type of block.
+  block_type: BlockType,
+  /// The subcase, if restricted to one.
+  subcase: Option<Subcase>,
+  /// Grid point IDs to keep rows of.
+  grids: Vec<usize>,
+  /// Element IDs to keep rows of.
+  elems: Vec<usize>,
+  /// Names of columns to keep.
+  cols: Vec<String>,
+  /// The columns to keep that named a DOF, and which one.
+  dofs: Vec<(String, Dof)>
+}
+
+impl Selection {
+  /// Parses the arguments to a query.
+  fn parse(args: &[String]) -> Result<Self, String> {
+    let (first, rest) = args.split_first()
+      .ok_or("missing a block type".to_owned())?;
+    let mut sel = Self {
+      block_type: find_block_type(first)?,
+      subcase: None,
+      grids: Vec::new(),
+      elems: Vec::new(),
+      cols: Vec::new(),
+      dofs: Vec::new()
+    };
+    let id = |v: &str| v.parse::<usize>()
+      .map_err(|e| format!("bad ID \"{}\": {}", v, e));
+    for (i, arg) in rest.iter().enumerate() {
+      match arg.split_once('=') {
+        Some(("grid", v)) => sel.grids.push(id(v)?),
+        Some(("elem", v)) => sel.elems.push(id(v)?),
+        Some(("col", v)) => match <Dof as ValueEnum>::from_str(v, true) {
+          Ok(dof) => sel.dofs.push((v.to_owned(), dof)),
+          Err(_) => sel.cols.push(v.to_owned())
+        },
+        Some((k, _)) => return Err(format!("unknown filter \"{}\"", k)),
+        None if i == 0 => sel.subcase = Some(
+          arg.parse().map_err(|e| format!("bad subcase \"{}\": {}", arg, e))?
+        ),
+        None => return Err(format!("unexpected \"{}\"", arg)),
+      };
+    }
+    return Ok(sel);
+  }
+
+  /// Returns whether a row passes the filters.
+  fn wants_row(&self, row: &NasIndex) -> bool {
+    let grid_ok = self.grids.is_empty() || row.grid_point_id()
+      .is_some_and(|g| self.grids.contains(&g.gid));
+    let elem_ok = self.elems.is_empty() || row.element_id()
+      .is_some_and(|e| self.elems.contains(&e.eid));
+    return grid_ok && elem_ok;
+  }
+
+  /// Returns the column filters a column matches, by the name they were
+  /// given with.
+  fn col_matches<'a>(
+    &'a self,
+    col: &'a NasIndex
+  ) -> impl Iterator<Item = &'a str> + 'a {
+    let name = col.to_string();
+    let dof = match col {
+      NasIndex::Dof(d) => Some(*d),
+      NasIndex::ComplexDof(cd) => Some(cd.dof),
+      _ => None
+    };
+    let by_name = self.cols.iter()
+      .filter(move |c| c.eq_ignore_ascii_case(&name))
+      .map(String::as_str);
+    let by_dof = self.dofs.iter()
+      .filter(move |(_, d)| Some(*d) == dof)
+      .map(|(c, _)| c.as_str());
+    return by_name.chain(by_dof);
+  }
+
+  /// Returns whether a column passes the filters.
+  fn wants_col(&self, col: &NasIndex) -> bool {
+    return (self.cols.is_empty() && self.dofs.is_empty())
+      || self.col_matches(col).next().is_some();
+  }
+
+  /// Returns trimmed-down copies of the selected blocks, or an error saying
+  /// what matched nothing.
+  fn blocks(&self, f06: &F06File) -> Result<Vec<FinalBlock>, String> {
+    let found = f06.block_search(Some(self.block_type), self.subcase, false)
+      .collect::<Vec<_>>();
+    if found.is_empty() {
+      return Err(match self.subcase {
+        Some(sc) => format!(
+          "no {} block in subcase {}",
+          self.block_type.short_name(),
+          sc
+        ),
+        None => format!("no {} block", self.block_type.short_name())
+      });
+    }
+    let named = self.cols.iter().chain(self.dofs.iter().map(|(c, _)| c));
+    for name in named {
+      let exists = found.iter()
+        .flat_map(|b| b.col_indexes.keys())
+        .any(|c| self.col_matches(c).any(|m| m == name));
+      if !exists {
+        return Err(format!(
+          "no column \"{}\" in {} blocks",
+          name,
+          self.block_type.short_name()
+        ));
+      }
+    }
+    return Ok(found.into_iter()
+      .map(|b| {
+        let mut b = b.clone();
+        b.row_indexes.retain(|r, _| self.wants_row(r));
+        b.col_indexes.retain(|c, _| self.wants_col(c));
+        return b;
+      })
+      .collect());
+  }
+}
+
+/// Formats a value, with reals in scientific notation.
+fn fmt_value(value: F06Number) -> String {
+  return match value {
+    F06Number::Real(x) => format!("{:.6e}", x),
+    other => other.to_string()
+  };
+}
+
+/// Prints the values of the selected blocks.
+fn get(f06: &F06File, sel: &Selection) -> Result<(), String> {
+  for block in sel.blocks(f06)? {
+    println!("{}:", block.block_ref());
+    for row in block.row_indexes.keys() {
+      let values = block.col_indexes.keys()
+        .filter_map(|c| block.get(*row, *c).map(|v| (c, fmt_value(v))))
+        .map(|(c, v)| format!("{} = {}", c, v))
+        .collect::<Vec<_>>();
+      println!("  {}: {}", row, values.join(", "));
+    }
+  }
+  return Ok(());
+}
+
+/// Prints column statistics for the selected blocks.
+fn stats(f06: &F06File, sel: &Selection) -> Result<(), String> {
+  for block in sel.blocks(f06)? {
+    println!("{}:", block.block_ref());
+    for col in block.col_indexes.keys() {
+      let metrics = SingleColumnMetric::all()
+        .iter()
+        .map(|m| match m.compute(&block, *col) {
+          Some(x) => format!("{} {:.6e}", m.short_name(), x),
+          None => format!("{} -", m.short_name())
+        })
+        .collect::<Vec<_>>();
+      println!("  {}: {}", col, metrics.join(", "));
+    }
+  }
+  return Ok(());
+}
+
+/// Runs a single query, returning false if it asks to stop.
+fn run_query(f06: &F06File, line: &str) -> bool {
+  let tokens = tokenize(line);
+  let Some((command, args)) = tokens.split_first() else {
+    return true;
+  };
+  let result = match command.to_lowercase().as_str() {
+    "quit" | "exit" => return false,
+    "help" => {
+      println!("{}", HELP);
+      Ok(())
+    },
+    "blocks" => {
+      for block in f06.all_blocks(false) {
+        println!(
+          "{}: {} rows, {} columns",
+          block.block_ref(),
+          block.row_indexes.len(),
+          block.col_indexes.len()
+        );
+      }
+      Ok(())
+    },
+    "get" => Selection::parse(args).and_then(|sel| get(f06, &sel)),
+    "stats" => Selection::parse(args).and_then(|sel| stats(f06, &sel)),
+    other => Err(format!("unknown query \"{}\" (try \"help\")", other))
+  };
+  if let Err(e) = result {
+    println!("error: {}", e);
+  }
+  return true;
+}
+
+/// Reads queries from standard input and answers them until EOF.
+pub(crate) fn run(f06: &F06File) -> io::Result<()> {
+  let stdin = io::stdin();
+  let interactive = stdin.is_terminal();
+  let prompt = || -> io::Result<()> {
+    if interactive {
+      print!("> ");
+      io::stdout().flush()?;
+    }
+    return Ok(());
+  };
+  prompt()?;
+  for line in stdin.lock().lines() {
+    if !run_query(f06, &line?) {
+      break;
+    }
+    prompt()?;
+  }
+  return Ok(());
+}
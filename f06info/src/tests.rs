@@ -0,0 +1,13 @@
+use crate::raw_lines;
+
+#[test]
+fn test_raw_lines() {
+  let lines = ["one", "two", "three", "four", "five"];
+  // both ends are included
+  assert_eq!(raw_lines(&lines, (2, 4)), ["two", "three", "four"]);
+  assert_eq!(raw_lines(&lines, (1, 1)), ["one"]);
+  assert_eq!(raw_lines(&lines, (1, 5)), lines);
+  // ranges past the end are cut short
+  assert_eq!(raw_lines(&lines, (4, 9)), ["four", "five"]);
+  assert!(raw_lines(&lines, (7, 9)).is_empty());
+}
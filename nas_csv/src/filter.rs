@@ -0,0 +1,184 @@
+//! This module implements a reusable filter for CSV records, so that tools
+//! built on this library can narrow down their output the same way `f06csv`
+//! does.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+
+use f06::prelude::*;
+
+use crate::layout::*;
+
+/// A predicate on the value of a named column.
+type ValuePredicate = Box<dyn Fn(&CsvField) -> bool>;
+
+/// A filter for CSV records, built up with its builder-style methods.
+///
+/// The ID filters are lax: an empty list lets everything through, and a
+/// record that lacks the attribute (e.g. a grid point ID filter on an element
+/// record) passes that filter.
+#[derive(Default)]
+pub struct RecordFilter {
+  /// CSV blocks to keep.
+  blocks: Vec<CsvBlockId>,
+  /// Grid point IDs to keep.
+  gids: Vec<usize>,
+  /// Element IDs to keep.
+  eids: Vec<usize>,
+  /// Element types to keep.
+  etypes: Vec<ElementType>,
+  /// Subcases to keep.
+  subcases: Vec<usize>,
+  /// Property IDs to keep.
+  pids: Vec<usize>,
+  /// The property ID of each element, for the property ID filter.
+  properties: BTreeMap<usize, usize>,
+  /// Which points within elements to keep.
+  points: PointFilter,
+  /// Columns (by header name) a record must have.
+  columns: Vec<String>,
+  /// Predicates on the values of columns (by header name).
+  values: Vec<(String, ValuePredicate)>,
+  /// Rows (by block and row index) to keep.
+  rows: BTreeSet<(BlockRef, NasIndex)>
+}
+
+impl Debug for RecordFilter {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return f.debug_struct("RecordFilter")
+      .field("blocks", &self.blocks)
+      .field("gids", &self.gids)
+      .field("eids", &self.eids)
+      .field("etypes", &self.etypes)
+      .field("subcases", &self.subcases)
+      .field("pids", &self.pids)
+      .field("points", &self.points)
+      .field("columns", &self.columns)
+      .field("values", &self.values.iter().map(|v| &v.0).collect::<Vec<_>>())
+      .field("rows", &self.rows)
+      .finish();
+  }
+}
+
+/// Filter only if there is at least one in the filter.
+fn lax_filter<T: PartialEq>(v: &[T], x: &Option<T>) -> bool {
+  return v.is_empty()
+    || x.is_none()
+    || x.as_ref().is_some_and(|k| v.contains(k));
+}
+
+/// Returns the position of a column in a record, by header name.
+fn column_position(rec: &CsvRecord, column: &str) -> Option<usize> {
+  return rec.headers.iter().position(|h| h.eq_ignore_ascii_case(column));
+}
+
+impl RecordFilter {
+  /// Creates a filter that lets every record through.
+  pub fn new() -> Self {
+    return Self::default();
+  }
+
+  /// Only keep records in these CSV blocks.
+  pub fn blocks<I: IntoIterator<Item = CsvBlockId>>(mut self, it: I) -> Self {
+    self.blocks.extend(it);
+    return self;
+  }
+
+  /// Only keep records at these grid points.
+  pub fn gids<I: IntoIterator<Item = usize>>(mut self, it: I) -> Self {
+    self.gids.extend(it);
+    return self;
+  }
+
+  /// Only keep records for these elements.
+  pub fn eids<I: IntoIterator<Item = usize>>(mut self, it: I) -> Self {
+    self.eids.extend(it);
+    return self;
+  }
+
+  /// Only keep records for these element types.
+  pub fn etypes<I: IntoIterator<Item = ElementType>>(mut self, it: I) -> Self {
+    self.etypes.extend(it);
+    return self;
+  }
+
+  /// Only keep records in these subcases.
+  pub fn subcases<I: IntoIterator<Item = usize>>(mut self, it: I) -> Self {
+    self.subcases.extend(it);
+    return self;
+  }
+
+  /// Only keep records for elements with these property IDs, going by a map
+  /// of element IDs to property IDs (like `F06File::properties`). Elements
+  /// missing from the map are dropped, but records without an element pass.
+  pub fn pids<I: IntoIterator<Item = usize>>(
+    mut self,
+    properties: &BTreeMap<usize, usize>,
+    it: I
+  ) -> Self {
+    self.pids.extend(it);
+    self.properties.extend(properties);
+    return self;
+  }
+
+  /// Only keep records at these kinds of points within elements.
+  pub fn points(mut self, points: PointFilter) -> Self {
+    self.points = points;
+    return self;
+  }
+
+  /// Only keep records that have a column with this header (ignoring case).
+  pub fn column(mut self, name: &str) -> Self {
+    self.columns.push(name.to_owned());
+    return self;
+  }
+
+  /// Only keep records that have a column with this header (ignoring case)
+  /// whose value satisfies a predicate.
+  pub fn value<F>(mut self, name: &str, pred: F) -> Self
+    where F: Fn(&CsvField) -> bool + 'static {
+    self.values.push((name.to_owned(), Box::new(pred)));
+    return self;
+  }
+
+  /// Only keep records for the rows these data are in, such as the ones
+  /// flagged by a diff. Unlike the ID filters, this one isn't lax: records
+  /// that don't come from a block row are dropped.
+  pub fn datums<I: IntoIterator<Item = DatumIndex>>(mut self, it: I) -> Self {
+    self.rows.extend(it.into_iter().map(|dxi| (dxi.block_ref, dxi.row)));
+    return self;
+  }
+
+  /// Returns whether a record passes this filter.
+  pub fn matches(&self, rec: &CsvRecord) -> bool {
+    let ids = lax_filter(&self.blocks, &Some(rec.block_id))
+      && lax_filter(&self.gids, &rec.gid)
+      && lax_filter(&self.eids, &rec.eid)
+      && lax_filter(&self.etypes, &rec.etype)
+      && lax_filter(&self.subcases, &rec.subcase)
+      && (self.pids.is_empty() || rec.eid.is_none_or(
+        |e| self.properties.get(&e).is_some_and(|p| self.pids.contains(p))
+      ))
+      && self.points.accepts(rec);
+    if !ids {
+      return false;
+    }
+    if !self.rows.is_empty() {
+      let key = match (rec.subcase, rec.block_type, rec.row) {
+        (Some(sc), Some(bt), Some(row)) => {
+          (BlockRef::new(sc.into(), bt).at_step(rec.step), row)
+        },
+        _ => return false
+      };
+      if !self.rows.contains(&key) {
+        return false;
+      }
+    }
+    let has_columns = self.columns.iter()
+      .all(|c| column_position(rec, c).is_some());
+    let values_ok = self.values.iter().all(|(c, pred)| {
+      return column_position(rec, c).is_some_and(|i| pred(&rec.fields[i]));
+    });
+    return has_columns && values_ok;
+  }
+}
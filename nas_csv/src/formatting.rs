@@ -3,6 +3,7 @@
 use std::fmt::{Display, Write};
 
 use clap::{Args, ValueEnum};
+use f06::prelude::is_unset;
 use f06::util::fmt_f64;
 use serde::{Deserialize, Serialize};
 
@@ -133,27 +134,133 @@ pub struct CsvFormatting {
   pub blanks: BlankDisplay,
   /// Alignment setting for values such that commas line up.
   #[arg(long = "align", default_value = "none")]
-  pub align: Alignment
+  pub align: Alignment,
+  /// Write reals in plain decimals when their magnitude is below this and at
+  /// least its reciprocal (or zero), and in scientific notation otherwise.
+  ///
+  /// If absent, the notation is the same for all values.
+  #[arg(long = "sci-threshold", verbatim_doc_comment)]
+  #[serde(default)]
+  pub scientific_threshold: Option<f64>,
+  /// Write reals at least this large in magnitude as blanks, since Nastran
+  /// uses them to mean "unset" (e.g. in margins of safety).
+  ///
+  /// If given without a value, 1.0E+37 is used.
+  #[arg(
+    long = "blank-unset",
+    require_equals = true,
+    num_args = 0..=1,
+    default_missing_value = "1.0E+37",
+    verbatim_doc_comment
+  )]
+  #[serde(default)]
+  pub blank_unset: Option<f64>,
+  /// The decimal separator to write reals with, such as "," for spreadsheets
+  /// in locales that use it. Must differ from the field delimiter.
+  ///
+  /// If absent, a dot is used.
+  #[arg(long = "decimal", verbatim_doc_comment)]
+  #[serde(default)]
+  pub decimal: Option<char>
 }
 
 impl CsvFormatting {
+  /// Writes out a real according to this format, picking the notation by the
+  /// scientific threshold if there is one.
+  fn fmt_real<W: Write>(&self, f: &mut W, x: f64) -> std::fmt::Result {
+    if let Some(sep) = self.decimal.filter(|c| *c != '.') {
+      let mut buf = String::new();
+      Self { decimal: None, ..*self }.fmt_real(&mut buf, x)?;
+      return f.write_str(&buf.replace('.', sep.encode_utf8(&mut [0; 4])));
+    }
+    if let Some(t) = self.scientific_threshold {
+      let mag = x.abs();
+      let plain = mag == 0.0 || (mag < t && mag >= t.recip());
+      let reals = FloatFormat { no_scientific: plain, ..self.reals };
+      return reals.fmt_f64(f, x);
+    }
+    return self.reals.fmt_f64(f, x);
+  }
+
+  /// Checks that reals written with this format can't be mistaken for
+  /// multiple fields, given the field delimiter.
+  pub fn check_delimiter(&self, delim: char) -> Result<(), String> {
+    let sep = self.decimal.unwrap_or('.');
+    if sep == delim {
+      return Err(format!(
+        "the decimal separator and the delimiter are both '{}'",
+        sep
+      ));
+    }
+    if sep.is_ascii_digit() || matches!(sep, '+' | '-' | 'e' | 'E') {
+      return Err(format!("'{}' can't be a decimal separator", sep));
+    }
+    return Ok(());
+  }
+
   /// Writes out a CSV field according to this format.
   pub fn fmt<W: Write>(&self, fld: &CsvField, f: &mut W) -> std::fmt::Result {
     return match fld {
       CsvField::Blank => write!(f, "{}", self.blanks.fmt_str()),
-      CsvField::Real(x) => self.reals.fmt_f64(f, *x),
+      CsvField::Real(x) if is_unset(*x, self.blank_unset) => {
+        write!(f, "{}", self.blanks.fmt_str())
+      },
+      CsvField::Real(x) => self.fmt_real(f, *x),
       _ => write!(f, "{}", fld)
     }
   }
 
+  /// Returns whether a field is written as a blank or is a zero, which is
+  /// what `--trim-blanks` looks for.
+  pub fn is_blank_or_zero(&self, field: &CsvField) -> bool {
+    return match field {
+      CsvField::Blank => true,
+      CsvField::Integer(i) => *i == 0,
+      CsvField::Natural(n) => *n == 0,
+      CsvField::Real(x) => *x == 0.0 || is_unset(*x, self.blank_unset),
+      CsvField::String(s) => s.is_empty(),
+      CsvField::ElementType(_) => false
+    };
+  }
+
+  /// Returns whether a field lets its column be written as integers, which is
+  /// what `--int-like` looks for. Blanks and unset reals don't get in the
+  /// way, but strings and reals with a fractional part do.
+  pub fn is_int_like(&self, field: &CsvField) -> bool {
+    return match field {
+      CsvField::Blank => true,
+      CsvField::Integer(_) | CsvField::Natural(_) => true,
+      CsvField::Real(x) if is_unset(*x, self.blank_unset) => true,
+      CsvField::Real(x) => {
+        x.fract() == 0.0 && x.abs() <= isize::MAX as f64
+      },
+      CsvField::String(_) | CsvField::ElementType(_) => false
+    };
+  }
+
+  /// Turns an integral real into an integer field, leaving anything else
+  /// (including unset reals, which are written as blanks) as-is.
+  pub fn to_int_like(&self, field: CsvField) -> CsvField {
+    return match field {
+      CsvField::Real(x) if is_unset(x, self.blank_unset) => field,
+      CsvField::Real(x) if self.is_int_like(&field) => {
+        CsvField::Integer(x as isize)
+      },
+      _ => field
+    };
+  }
+
   /// Turns a CSV field into a string using this formatter.
   pub fn to_string(&self, field: CsvField) -> String {
     return match field {
       CsvField::Blank => self.blanks.fmt_str().to_owned(),
+      CsvField::Real(x) if is_unset(x, self.blank_unset) => {
+        self.blanks.fmt_str().to_owned()
+      },
       CsvField::Real(x) => {
         let mut buf = String::new();
         // Bypass format_args!() to avoid write_str with zero-length strs
-        self.reals.fmt_f64(&mut buf, x)
+        self.fmt_real(&mut buf, x)
           .expect("a Display implementation returned an error unexpectedly");
         buf
       },
@@ -1,8 +1,9 @@
 //! This module contains types and subroutines to produce CSV-ready data from
 //! parsed F06 files.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Display;
+use std::iter::Peekable;
 
 use f06::prelude::*;
 use log::error;
@@ -144,6 +145,103 @@ impl ColumnGenerator {
       }
     });
   }
+
+  /// Returns the column index whose values this generator outputs, if any.
+  pub fn column(&self) -> Option<NasIndex> {
+    return match self {
+      Self::ColumnValue(col) => Some(*col),
+      Self::WithDefault(g, _) => g.column(),
+      _ => None
+    };
+  }
+
+  /// Returns a short, upper-case description of what this generator outputs:
+  /// the index type name for column values, and the kind of value otherwise.
+  pub fn kind(&self) -> &'static str {
+    return match self {
+      Self::Blank => "BLANK",
+      Self::ColumnValue(col) => col.type_name(),
+      Self::ConstantField(_) => "CONSTANT",
+      Self::GridId => "GRID ID",
+      Self::ElementId => "ELEMENT ID",
+      Self::ElementType => "ELEMENT TYPE",
+      Self::RowIndexFn(_) => "ROW INDEX",
+      Self::BlockShortName => "BLOCK SHORT NAME",
+      Self::BlockLongName => "BLOCK LONG NAME",
+      Self::SolTypeNumber => "SOLUTION TYPE NUMBER",
+      Self::SolTypeName => "SOLUTION TYPE NAME",
+      Self::SolverName => "SOLVER NAME",
+      Self::Subcase => "SUBCASE",
+      Self::ConstantNumber(_) => "CONSTANT",
+      Self::ConstantString(_) => "CONSTANT",
+      Self::WithDefault(g, _) => g.kind()
+    };
+  }
+
+  /// Returns the DOF whose column this generator outputs, if any.
+  pub fn dof(&self) -> Option<Dof> {
+    return match self {
+      Self::ColumnValue(NasIndex::Dof(dof)) => Some(*dof),
+      Self::WithDefault(g, _) => g.dof(),
+      _ => None
+    };
+  }
+}
+
+/// Returns the units of the values in a column, if they're known regardless
+/// of the model's own units.
+pub fn column_units(col: NasIndex) -> Option<&'static str> {
+  return match col {
+    NasIndex::PlateStressField(PlateStressField::Angle) => Some("deg"),
+    NasIndex::PlateStrainField(PlateStrainField(PlateStressField::Angle)) => {
+      Some("deg")
+    },
+    _ => None
+  };
+}
+
+/// Describes a column of the records made by a converter.
+#[derive(Clone, Debug, Serialize)]
+pub struct ColumnSchema {
+  /// The position of the column in the record, counting the block ID
+  /// column as zero.
+  pub position: usize,
+  /// The column's header.
+  pub header: String,
+  /// What the column holds (see `ColumnGenerator::kind`).
+  pub kind: &'static str,
+  /// The column index whose values it holds, if any, as displayed.
+  pub index: Option<String>,
+  /// The units of its values, if known.
+  pub units: Option<&'static str>
+}
+
+/// Describes the records made by a converter, for consumers that want to
+/// build typed schemas instead of going by the headers alone.
+#[derive(Clone, Debug, Serialize)]
+pub struct ConverterSchema {
+  /// The block type that's converted.
+  pub block_type: BlockType,
+  /// The CSV block it's converted into.
+  pub csv_block: CsvBlockId,
+  /// The columns of each kind of record made per row of the block.
+  pub records: Vec<Vec<ColumnSchema>>
+}
+
+impl ConverterSchema {
+  /// Appends a column to every kind of record, such as one that's written
+  /// after the converted fields.
+  pub fn append(&mut self, header: &str, kind: &'static str) {
+    for cols in self.records.iter_mut() {
+      cols.push(ColumnSchema {
+        position: cols.len() + 1,
+        header: header.to_owned(),
+        kind,
+        index: None,
+        units: None
+      });
+    }
+  }
 }
 
 /// A template to convert an F06 block into a series of CSV records.
@@ -161,6 +259,41 @@ pub struct BlockConverter {
 }
 
 impl BlockConverter {
+  /// Returns the positions of a record's fields that hold DOF columns, along
+  /// with their DOFs. The record must have been produced by this converter.
+  pub fn dof_columns(&self, rec: &CsvRecord) -> Vec<(usize, Dof)> {
+    let irow = self.headers.iter().position(|h| h == rec.headers);
+    return irow.and_then(|i| self.generators.get(i))
+      .map(|gens| gens.iter()
+        .enumerate()
+        .filter_map(|(i, g)| g.dof().map(|d| (i, d)))
+        .collect())
+      .unwrap_or_default();
+  }
+
+  /// Describes the columns of the records this produces.
+  pub fn schema(&self) -> ConverterSchema {
+    let records = self.generators.iter()
+      .zip(self.headers.iter())
+      .map(|(gens, headers)| gens.iter()
+        .zip(headers.iter())
+        .enumerate()
+        .map(|(i, (g, h))| ColumnSchema {
+          position: i + 1,
+          header: (*h).to_owned(),
+          kind: g.kind(),
+          index: g.column().map(|c| c.to_string()),
+          units: g.column().and_then(column_units)
+        })
+        .collect())
+      .collect();
+    return ConverterSchema {
+      block_type: self.input_block_type,
+      csv_block: self.output_block_id,
+      records
+    };
+  }
+
   /// Begins conversion of a block into an iterator of CSV records. Need to
   /// know the file flavour though. Fields that cause an error when converting
   /// will issue an error log and turn into "<ERROR>" fields.
@@ -247,8 +380,10 @@ impl BlockConverter {
           eid,
           etype,
           subcase,
+          step: block.step,
           fields,
-          headers
+          headers,
+          row: Some(*row)
         }
       })
     }));
@@ -269,6 +404,8 @@ pub fn zeroth_block(
   let vvk: Vec<(&'static str, Option<String>)> = vec![
     ("Solver", file.flavour.solver.map(ts)),
     ("Solution", file.flavour.soltype.map(ts)),
+    ("Solver version", file.solver_version.clone()),
+    ("Run timestamp", file.run_timestamp.clone()),
     ("Filename", file.filename.clone()),
     ("#Subcases", Some(file.subcases().count().to_string())),
     ("#Warnings", Some(file.warnings.len().to_string())),
@@ -285,6 +422,7 @@ pub fn zeroth_block(
     eid: None,
     etype: None,
     subcase: None,
+    step: None,
     fields: [
       CsvField::String(k.to_owned()),
       CsvField::String(v.unwrap_or(U.to_owned())),
@@ -300,7 +438,8 @@ pub fn zeroth_block(
     headers: &[
       "Key", "Value", HBLANK, HBLANK, HBLANK,
       HBLANK, HBLANK, HBLANK, HBLANK, HBLANK
-    ]
+    ],
+    row: None
   })
 }
 
@@ -328,3 +467,111 @@ pub fn to_records<'s>(
     ).flatten();
   return zeroth.chain(blocks.flatten());
 }
+
+/// Makes a placeholder record for a CSV block in a subcase with no data. It
+/// has the headers of the first converter to that block, and blank fields
+/// except for the subcase. Returns None if no converter makes that block.
+pub fn placeholder_record(
+  block_id: CsvBlockId,
+  subcase: usize,
+  converters: &BTreeMap<BlockType, BlockConverter>
+) -> Option<CsvRecord> {
+  let headers = converters.values()
+    .find(|c| c.output_block_id == block_id)
+    .and_then(|c| c.headers.first())?;
+  let mut fields: [CsvField; NAS_CSV_COLS-1] =
+    std::array::from_fn(|_| CsvField::Blank);
+  for (field, header) in fields.iter_mut().zip(headers.iter()) {
+    if *header == "Subcase" {
+      *field = CsvField::Natural(subcase);
+    }
+  }
+  return Some(CsvRecord {
+    block_id,
+    block_type: None,
+    gid: None,
+    eid: None,
+    etype: None,
+    subcase: Some(subcase),
+    step: None,
+    fields,
+    headers,
+    row: None
+  });
+}
+
+/// Adds placeholder records (see `placeholder_record`) for every pair of CSV
+/// block and subcase that has no records, so that every block has a row for
+/// every subcase. The records must be grouped by CSV block, like the ones
+/// from `to_records`; placeholders go after the last record of their block,
+/// by subcase, or where that block would be if it has no records at all.
+pub fn fill_missing<I: IntoIterator<Item = CsvRecord>>(
+  records: I,
+  block_ids: &[CsvBlockId],
+  subcases: &BTreeSet<usize>,
+  converters: &BTreeMap<BlockType, BlockConverter>
+) -> Vec<CsvRecord> {
+  let mut records = records.into_iter().collect::<Vec<_>>();
+  let found = records.iter()
+    .filter_map(|r| r.subcase.map(|sc| (r.block_id, sc)))
+    .collect::<BTreeSet<_>>();
+  for block_id in block_ids.iter().collect::<BTreeSet<_>>() {
+    let placeholders = subcases.iter()
+      .filter(|sc| !found.contains(&(*block_id, **sc)))
+      .filter_map(|sc| placeholder_record(*block_id, *sc, converters))
+      .collect::<Vec<_>>();
+    let at = records.iter()
+      .rposition(|r| r.block_id == *block_id)
+      .map(|i| i + 1)
+      .or_else(|| records.iter().position(|r| r.block_id > *block_id))
+      .unwrap_or(records.len());
+    records.splice(at..at, placeholders);
+  }
+  return records;
+}
+
+/// Returns the block types present in a file for which there is no converter.
+pub fn unconverted_block_types(
+  file: &F06File,
+  converters: &BTreeMap<BlockType, BlockConverter>
+) -> BTreeSet<BlockType> {
+  return file.block_types()
+    .filter(|bt| !converters.contains_key(bt))
+    .collect();
+}
+
+/// Groups an ordered record iterator (such as the one from `to_records`) into
+/// contiguous runs sharing a CSV block ID and subcase. Records without a
+/// subcase (such as the zeroth block's) are grouped under subcase zero.
+pub struct RecordGroups<I: Iterator<Item = CsvRecord>> {
+  /// The underlying records.
+  inner: Peekable<I>
+}
+
+impl<I: Iterator<Item = CsvRecord>> RecordGroups<I> {
+  /// Wraps a record iterator.
+  pub fn new(records: I) -> Self {
+    return Self { inner: records.peekable() };
+  }
+
+  /// The grouping key of a record.
+  fn key(rec: &CsvRecord) -> (CsvBlockId, usize) {
+    return (rec.block_id, rec.subcase.unwrap_or(0));
+  }
+}
+
+impl<I: Iterator<Item = CsvRecord>> Iterator for RecordGroups<I> {
+  type Item = (CsvBlockId, usize, Vec<CsvRecord>);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let first = self.inner.next()?;
+    let (block_id, subcase) = Self::key(&first);
+    let mut group = vec![first];
+    while let Some(rec) = self.inner.next_if(
+      |r| Self::key(r) == (block_id, subcase)
+    ) {
+      group.push(rec);
+    }
+    return Some((block_id, subcase, group));
+  }
+}
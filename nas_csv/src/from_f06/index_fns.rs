@@ -36,6 +36,7 @@ fn util_eref(index: NasIndex) -> Result<ElementRef, ConversionError> {
       _ => return bad_col_type(index)
     },
     NasIndex::ElementSidedPoint(esp) => esp.element,
+    NasIndex::ElementStation(es) => es.element,
     _ => return bad_col_type(index)
   });
 }
@@ -70,3 +71,28 @@ pub fn ixfn_fo(index: NasIndex) -> Result<CsvField, ConversionError> {
     return Err(ConversionError::BadColIndexType(index));
   }
 }
+
+/// Extracts the side (Z1 for bottom, Z2 for top) of an element-sided point.
+pub fn ixfn_side(index: NasIndex) -> Result<CsvField, ConversionError> {
+  if let NasIndex::ElementSidedPoint(esp) = index {
+    return Ok(match esp.side {
+      ElementSide::Bottom => "Z1".to_owned(),
+      ElementSide::Top => "Z2".to_owned(),
+    }.into());
+  } else {
+    return Err(ConversionError::BadRowIndexType(index));
+  }
+}
+
+/// Extracts the station (End A, End B or Station n) of a beam row.
+pub fn ixfn_station(index: NasIndex) -> Result<CsvField, ConversionError> {
+  if let NasIndex::ElementStation(es) = index {
+    return Ok(match es.station {
+      BeamStation::EndA => "End A".to_owned(),
+      BeamStation::Intermediate(n) => format!("Station {}", n),
+      BeamStation::EndB => "End B".to_owned(),
+    }.into());
+  } else {
+    return Err(ConversionError::BadRowIndexType(index));
+  }
+}
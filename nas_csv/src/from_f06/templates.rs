@@ -104,6 +104,11 @@ const END_A: ColumnGenerator = ColumnGenerator::ConstantString("End A");
 /// Generator for End A columns.
 const END_B: ColumnGenerator = ColumnGenerator::ConstantString("End B");
 
+/// Generator for the side (Z1/Z2) of plate element rows.
+const SIDE: ColumnGenerator = ColumnGenerator::RowIndexFn(
+  &(ixfn_side as IndexFn)
+);
+
 /// Generator that always produces a blank.
 const BLANK: ColumnGenerator = ColumnGenerator::Blank;
 
@@ -132,8 +137,11 @@ pub const ALL_CONVERTERS: &[BlockConverter] = &[
   CT_FORCES_TRIA,
   CT_FORCES_ROD,
   CT_FORCES_BAR,
+  CT_FORCES_BEAM,
   CT_FORCES_ELAS1,
   CT_FORCES_BUSH,
+  CT_FORCES_CONNECTOR,
+  CT_FORCES_FASTENER,
   // applied forces
   CT_APPLIED_FORCES,
   // spc forces
@@ -215,13 +223,13 @@ pub const CT_STRESSES_QUAD: BlockConverter = BlockConverter {
       [],
       [],
       [ShearXY,],
-      [BLANK, BLANK,],
+      [SIDE, BLANK,],
     )
   ],
   headers: &[
     [
       "EID (QUAD4)", "Subcase", "GID", "FibreDistance",
-      "NormalX", "NormalY", HBLANK, "ShearXY", HBLANK, HBLANK
+      "NormalX", "NormalY", HBLANK, "ShearXY", "Side", HBLANK
     ]
   ]
 };
@@ -234,7 +242,7 @@ pub const CT_STRESSES_TRIA: BlockConverter = BlockConverter {
   headers: &[
     [
       "EID (TRIA3)", "Subcase", "GID", "FibreDistance",
-      "NormalX", "NormalY", HBLANK, "ShearXY", HBLANK, HBLANK
+      "NormalX", "NormalY", HBLANK, "ShearXY", "Side", HBLANK
     ]
   ]
 };
@@ -348,7 +356,7 @@ pub const CT_STRESSES_ELAS1: BlockConverter = BlockConverter {
 
 /// Header for bush stresses.
 pub const BUSH_STRESSES_HEADER: [&str; 10] = [
-  "EID (BUSH)", "Subcase", HBLANK, HBLANK, "Sx", "Sy", "Sx", "Mx", "My", "Mz"
+  "EID (BUSH)", "Subcase", HBLANK, HBLANK, "Sx", "Sy", "Sz", "Mx", "My", "Mz"
 ];
 
 /// Conversion template for BUSH stresses;
@@ -395,7 +403,7 @@ pub const CT_STRAINS_QUAD: BlockConverter = BlockConverter {
       [],
       [],
       [ShearXY,],
-      [BLANK, BLANK,],
+      [SIDE, BLANK,],
     )
   ],
   headers: CT_STRESSES_QUAD.headers
@@ -633,6 +641,36 @@ pub const CT_FORCES_BAR: BlockConverter = BlockConverter {
   headers: &[BAR_FORCES_HEADER, BAR_FORCES_HEADER]
 };
 
+/// Conversion template for beam forces, one row per station. The warping
+/// torque is left out, as there's no column for it.
+pub const CT_FORCES_BEAM: BlockConverter = BlockConverter {
+  input_block_type: BlockType::BeamForces,
+  output_block_id: CsvBlockId::EngForces,
+  generators: &[
+    cols!(
+      BeamForceField,
+      [
+        ColumnGenerator::ElementId,
+        ColumnGenerator::Subcase,
+      ],
+      [],
+      [StationDistance,],
+      [ColumnGenerator::RowIndexFn(&(ixfn_station as IndexFn)),],
+      BeamForceField,
+      [],
+      [],
+      [AxialForce, Shear1, Shear2, BendMoment1, BendMoment2, TotalTorque,],
+      [],
+    )
+  ],
+  headers: &[
+    [
+      "EID (BEAM)", "Subcase", "Dist", "Station", "Axial", "S1", "S2", "M1",
+      "M2", "Torque"
+    ]
+  ]
+};
+
 /// Conversion template for ELAS1 forces.
 pub const CT_FORCES_ELAS1: BlockConverter = BlockConverter {
   input_block_type: BlockType::Elas1Forces,
@@ -669,6 +707,55 @@ pub const CT_FORCES_BUSH: BlockConverter = BlockConverter {
   ]
 };
 
+/// Header for connector forces. It appears twice.
+const CONNECTOR_FORCES_HEADER: [&str; 10] = [
+  "EID (CONN)", "Subcase", "GID", "End", "Axial", "S1", "S2", "M1", "M2",
+  "Torque"
+];
+
+/// Conversion template for CWELD connector forces.
+pub const CT_FORCES_CONNECTOR: BlockConverter = BlockConverter {
+  input_block_type: BlockType::ConnectorForces,
+  output_block_id: CsvBlockId::EngForces,
+  generators: &[
+    cols!(
+      ConnectorForceField,
+      [
+        ColumnGenerator::ElementId,
+        ColumnGenerator::Subcase,
+        ZERO,
+        END_A,
+      ],
+      [],
+      [AxialForce, Shear1, Shear2, MomentA1, MomentA2, Torque,],
+      [],
+    ),
+    cols!(
+      ConnectorForceField,
+      [
+        ColumnGenerator::ElementId,
+        ColumnGenerator::Subcase,
+        ZERO,
+        END_B,
+      ],
+      [],
+      [AxialForce, Shear1, Shear2, MomentB1, MomentB2, Torque,],
+      [],
+    )
+  ],
+  headers: &[CONNECTOR_FORCES_HEADER, CONNECTOR_FORCES_HEADER]
+};
+
+/// Conversion template for CFAST fastener forces.
+pub const CT_FORCES_FASTENER: BlockConverter = BlockConverter {
+  input_block_type: BlockType::FastenerForces,
+  output_block_id: CsvBlockId::EngForces,
+  generators: CT_STRESSES_BUSH.generators,
+  headers: &[
+    ["EID (FAST)", "Subcase", HBLANK, HBLANK, "Fx", "Fy", "Fz", "Mx", "My", "Mz"]
+  ]
+};
+
 /// Conversion template for the load vector.
 pub const CT_APPLIED_FORCES: BlockConverter = BlockConverter {
   input_block_type: BlockType::AppliedForces,
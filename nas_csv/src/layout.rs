@@ -2,7 +2,9 @@
 
 #![allow(clippy::needless_return)] // i'll never forgive rust for this
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Display;
+use std::str::FromStr;
 
 use clap::builder::PossibleValue;
 use clap::ValueEnum;
@@ -10,34 +12,43 @@ use f06::prelude::*;
 use f06::util::fmt_f64;
 use serde::{Serialize, Deserialize};
 
+use crate::from_f06::BlockConverter;
+
 /// Number of fields in a fixed-form CSV record.
 pub const NAS_CSV_COLS: usize = 11;
 
 /// Type that holds the headers for a row.
 pub type RowHeader = [&'static str; NAS_CSV_COLS-1];
 
-/// CSV block IDs based on their content.]
+/// CSV block IDs based on their content.
+///
+/// The discriminants are the numbers written in the first column of every
+/// record and accepted by `-b`, so they're stable: a new block takes the next
+/// free number and existing ones are never renumbered. To add one, give it a
+/// number here, add it to `all()`, and point its converters at it through
+/// their `output_block_id`.
 #[derive(
   Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord
 )]
 #[non_exhaustive]
+#[repr(usize)]
 pub enum CsvBlockId {
   /// The 0-block: general solution info; subcase IDs, solution types, etc.
-  Metadata,
+  Metadata = 0,
   /// The 1-block: displacements.
-  Displacements,
+  Displacements = 1,
   /// The 2-block: stresses.
-  Stresses,
+  Stresses = 2,
   /// The 3-block: strains.
-  Strains,
+  Strains = 3,
   /// The 4-block: element engineering forces.
-  EngForces,
+  EngForces = 4,
   /// The 5-block: grid point force balance.
-  GridPointForces,
+  GridPointForces = 5,
   /// The 6-block: applied forces.
-  AppliedForces,
+  AppliedForces = 6,
   /// The 7-block: forces of single-point constraint.
-  SpcForces,
+  SpcForces = 7,
 }
 
 // this impl allow numerical shorthands
@@ -68,6 +79,11 @@ impl CsvBlockId {
     ];
   }
 
+  /// Returns the stable number of this block ID.
+  pub const fn number(&self) -> usize {
+    return *self as usize;
+  }
+
   /// Returns a constant name for this block ID.
   pub const fn name(&self) -> &'static str {
     return match self {
@@ -122,16 +138,7 @@ impl Display for CsvBlockId {
 
 impl From<CsvBlockId> for usize {
   fn from(value: CsvBlockId) -> Self {
-    return match value {
-      CsvBlockId::Metadata => 0,
-      CsvBlockId::Displacements => 1,
-      CsvBlockId::Stresses => 2,
-      CsvBlockId::Strains => 3,
-      CsvBlockId::EngForces => 4,
-      CsvBlockId::GridPointForces => 5,
-      CsvBlockId::AppliedForces => 6,
-      CsvBlockId::SpcForces => 7,
-    };
+    return value.number();
   }
 }
 
@@ -145,17 +152,10 @@ impl TryFrom<usize> for CsvBlockId {
   type Error = ();
 
   fn try_from(value: usize) -> Result<Self, Self::Error> {
-    return Ok(match value {
-      0 => CsvBlockId::Metadata,
-      1 => CsvBlockId::Displacements,
-      2 => CsvBlockId::Stresses,
-      3 => CsvBlockId::Strains,
-      4 => CsvBlockId::EngForces,
-      5 => CsvBlockId::GridPointForces,
-      6 => CsvBlockId::AppliedForces,
-      7 => CsvBlockId::SpcForces,
-      _ => return Err(())
-    });
+    return Self::all().iter()
+      .find(|bid| bid.number() == value)
+      .copied()
+      .ok_or(());
   }
 }
 
@@ -217,10 +217,14 @@ pub struct CsvRecord {
   pub etype: Option<ElementType>,
   /// If this record relates to a subcase, its ID.
   pub subcase: Option<usize>,
+  /// If this record is for a step of its subcase, that step.
+  pub step: Option<OutputStep>,
   /// The remaining ten fields.
   pub fields: [CsvField; NAS_CSV_COLS-1],
   /// The headers for the ten fields.
-  pub headers: &'static RowHeader
+  pub headers: &'static RowHeader,
+  /// The row index in the originating block, if there's one.
+  pub row: Option<NasIndex>
 }
 
 impl CsvRecord {
@@ -230,9 +234,216 @@ impl CsvRecord {
   }
 
   /// Returns this block's headers as eleven strings.
-  pub fn header_as_iter(&self) -> impl Iterator<Item = &str> {
+  pub fn header_as_iter(&self) -> impl Iterator<Item = &'static str> {
     return [self.block_id.name()].into_iter().chain(
       self.headers.iter().copied()
     );
   }
 }
+
+/// Which points within elements to keep records for.
+#[derive(
+  Copy, Clone, Debug, Serialize, Deserialize, ValueEnum, PartialEq, Eq, Default
+)]
+#[clap(rename_all = "snake_case")]
+pub enum PointFilter {
+  /// Only keep element centroid records (including whole-element records,
+  /// such as MYSTRAN's for triangles).
+  Centroid,
+  /// Only keep records at grid points in elements (corners and midpoints).
+  Corner,
+  /// Keep all records.
+  #[default]
+  All
+}
+
+impl PointFilter {
+  /// Returns whether a record passes the filter. Records not at a point
+  /// within an element always pass.
+  pub fn accepts(&self, rec: &CsvRecord) -> bool {
+    let point = match rec.row.and_then(|r| r.element_point()) {
+      Some(p) => p,
+      None => return true
+    };
+    return match self {
+      Self::Centroid => matches!(
+        point,
+        ElementPoint::Centroid | ElementPoint::Anywhere
+      ),
+      Self::Corner => matches!(
+        point,
+        ElementPoint::Corner(_) | ElementPoint::Midpoint(_)
+      ),
+      Self::All => true,
+    };
+  }
+}
+
+/// An error that can come up when parsing or applying a column order.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ColumnOrderError {
+  /// The spec was not of the form "block:col1,col2,...".
+  BadSpec(String),
+  /// The block in the spec is not a known CSV block.
+  UnknownBlock(String),
+  /// A requested column is not among the record's headers.
+  NoSuchColumn {
+    /// The CSV block the order is for.
+    block_id: CsvBlockId,
+    /// The requested column name.
+    column: String
+  }
+}
+
+impl Display for ColumnOrderError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return match self {
+      Self::BadSpec(s) => write!(
+        f,
+        "bad column order \"{}\", expected block:col1,col2,...",
+        s
+      ),
+      Self::UnknownBlock(s) => write!(f, "unknown CSV block \"{}\"", s),
+      Self::NoSuchColumn { block_id, column } => write!(
+        f,
+        "no column named \"{}\" in a {} record",
+        column,
+        block_id
+      ),
+    };
+  }
+}
+
+impl std::error::Error for ColumnOrderError {}
+
+/// A column order for the records of a CSV block, given by header names.
+///
+/// Columns not named in the order are dropped. The block ID column always
+/// stays in front.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ColumnOrder {
+  /// The CSV block this order applies to.
+  pub block_id: CsvBlockId,
+  /// The header names, in the order they should be written.
+  pub columns: Vec<String>
+}
+
+impl ColumnOrder {
+  /// Returns the header positions for this order in a record, or an error if
+  /// a requested column is not present.
+  fn positions(
+    &self,
+    rec: &CsvRecord
+  ) -> Result<Vec<usize>, ColumnOrderError> {
+    return self.columns.iter().map(|col| {
+      return rec.headers.iter()
+        .position(|h| h.eq_ignore_ascii_case(col))
+        .ok_or_else(|| ColumnOrderError::NoSuchColumn {
+          block_id: self.block_id,
+          column: col.clone()
+        });
+    }).collect();
+  }
+
+  /// Returns the headers of a record in this order, block ID first.
+  pub fn headers(
+    &self,
+    rec: &CsvRecord
+  ) -> Result<Vec<&'static str>, ColumnOrderError> {
+    let mut v = vec![rec.block_id.name()];
+    v.extend(self.positions(rec)?.into_iter().map(|i| rec.headers[i]));
+    return Ok(v);
+  }
+
+  /// Returns the fields of a record in this order, block ID first.
+  pub fn fields(
+    &self,
+    rec: &CsvRecord
+  ) -> Result<Vec<CsvField>, ColumnOrderError> {
+    let mut v = vec![CsvField::from(rec.block_id)];
+    v.extend(
+      self.positions(rec)?.into_iter().map(|i| rec.fields[i].clone())
+    );
+    return Ok(v);
+  }
+}
+
+impl FromStr for ColumnOrder {
+  type Err = ColumnOrderError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let bad = || ColumnOrderError::BadSpec(s.to_owned());
+    let (block, cols) = s.split_once(':').ok_or_else(bad)?;
+    let block_id = CsvBlockId::from_str(block.trim(), true)
+      .map_err(|_| ColumnOrderError::UnknownBlock(block.trim().to_owned()))?;
+    let columns = cols.split(',')
+      .map(|c| c.trim().to_owned())
+      .collect::<Vec<_>>();
+    if columns.iter().any(String::is_empty) {
+      return Err(bad());
+    }
+    return Ok(Self { block_id, columns });
+  }
+}
+
+/// A selection of degrees of freedom for records with DOF columns.
+///
+/// Columns for the other DOFs are dropped. Records without DOF columns are
+/// left as they are.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DofSelection {
+  /// The DOFs to keep.
+  pub dofs: BTreeSet<Dof>
+}
+
+impl DofSelection {
+  /// Creates a selection that keeps these DOFs.
+  pub fn new(dofs: impl IntoIterator<Item = Dof>) -> Self {
+    return Self { dofs: dofs.into_iter().collect() };
+  }
+
+  /// Returns the positions of the fields of a record that are kept.
+  fn positions(
+    &self,
+    rec: &CsvRecord,
+    converters: &BTreeMap<BlockType, BlockConverter>
+  ) -> Vec<usize> {
+    let dropped = rec.block_type
+      .and_then(|bt| converters.get(&bt))
+      .map(|conv| conv.dof_columns(rec))
+      .unwrap_or_default()
+      .into_iter()
+      .filter(|(_, dof)| !self.dofs.contains(dof))
+      .map(|(i, _)| i)
+      .collect::<BTreeSet<_>>();
+    return (0..rec.fields.len()).filter(|i| !dropped.contains(i)).collect();
+  }
+
+  /// Returns the headers of a record with this selection, block ID first.
+  pub fn headers(
+    &self,
+    rec: &CsvRecord,
+    converters: &BTreeMap<BlockType, BlockConverter>
+  ) -> Vec<&'static str> {
+    let mut v = vec![rec.block_id.name()];
+    v.extend(
+      self.positions(rec, converters).into_iter().map(|i| rec.headers[i])
+    );
+    return v;
+  }
+
+  /// Returns the fields of a record with this selection, block ID first.
+  pub fn fields(
+    &self,
+    rec: &CsvRecord,
+    converters: &BTreeMap<BlockType, BlockConverter>
+  ) -> Vec<CsvField> {
+    let mut v = vec![CsvField::from(rec.block_id)];
+    v.extend(
+      self.positions(rec, converters)
+        .into_iter()
+        .map(|i| rec.fields[i].clone())
+    );
+    return v;
+  }
+}
@@ -6,13 +6,20 @@
 #![warn(missing_docs)]
 #![warn(clippy::missing_docs_in_private_items)]
 
+pub mod filter;
 pub mod formatting;
 pub mod from_f06;
 pub mod layout;
+pub mod sql;
 
 /// Imports the most relevant exports from the library.
 pub mod prelude {
+  pub use super::filter::*;
   pub use super::formatting::*;
   pub use super::from_f06::*;
   pub use super::layout::*;
+  pub use super::sql::*;
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,238 @@
+//! This module implements writing CSV records out as an SQL script, with one
+//! table per CSV block, so they can be loaded into a database and queried
+//! there; for SQLite, that's `sqlite3 results.db < results.sql`.
+//!
+//! Each table is named after its CSV block, and has a column for each header
+//! found in its records, in the order they're first found. Records in the
+//! same block may come from templates with different headers (like stresses
+//! of different element types), so each one only fills in the columns it has
+//! and leaves the rest as NULLs, as are blanks. Element ID columns (such as
+//! "EID (QUAD4)") all go into a single "EID" column, with the element type in
+//! an "Element" column. Results for steps of a subcase (load steps, times or
+//! frequencies) have the step's value in a "Step" column. Columns are
+//! declared with the widest type among their values (integers, then reals,
+//! then text).
+//!
+//! With the `sqlite` feature, the same tables can also be written straight
+//! into a SQLite database.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::from_f06::HBLANK;
+use crate::prelude::*;
+
+/// The type a column is declared with, from narrowest to widest.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum SqlType {
+  /// Nothing but blanks, so no declared type.
+  Untyped,
+  /// Integers.
+  Integer,
+  /// Reals (and integers).
+  Real,
+  /// Text (and anything else).
+  Text
+}
+
+impl SqlType {
+  /// Returns the type a field needs.
+  fn of(field: &CsvField) -> Self {
+    return match field {
+      CsvField::Blank => Self::Untyped,
+      CsvField::Integer(_) | CsvField::Natural(_) => Self::Integer,
+      CsvField::Real(_) => Self::Real,
+      CsvField::String(_) | CsvField::ElementType(_) => Self::Text,
+    };
+  }
+
+  /// Returns the type's name, as written after a column name.
+  fn suffix(&self) -> &'static str {
+    return match self {
+      Self::Untyped => "",
+      Self::Integer => " INTEGER",
+      Self::Real => " REAL",
+      Self::Text => " TEXT",
+    };
+  }
+}
+
+/// Quotes an SQL identifier, such as a table or column name.
+fn quote_ident(s: &str) -> String {
+  return format!("\"{}\"", s.replace('"', "\"\""));
+}
+
+/// Quotes an SQL string literal.
+fn quote_str(s: &str) -> String {
+  return format!("'{}'", s.replace('\'', "''"));
+}
+
+/// Writes a field as an SQL literal. Blanks and non-finite reals are NULLs,
+/// and reals are written in full precision.
+fn literal(field: &CsvField) -> String {
+  return match field {
+    CsvField::Blank => "NULL".to_owned(),
+    CsvField::Integer(i) => i.to_string(),
+    CsvField::Natural(n) => n.to_string(),
+    CsvField::Real(x) if x.is_finite() => format!("{:e}", x),
+    CsvField::Real(_) => "NULL".to_owned(),
+    CsvField::String(s) => quote_str(s),
+    CsvField::ElementType(et) => quote_str(&et.to_string()),
+  };
+}
+
+/// The name of the element type column.
+const ELEMENT_COLUMN: &str = "Element";
+
+/// The name of the output step column.
+const STEP_COLUMN: &str = "Step";
+
+/// Returns the columns a record fills in, and their values.
+fn sql_fields(rec: &CsvRecord) -> Vec<(&'static str, CsvField)> {
+  let mut fields = rec.headers.iter()
+    .zip(rec.fields.iter())
+    .filter(|(h, _)| **h != HBLANK)
+    .map(|(h, f)| (if h.starts_with("EID (") { "EID" } else { *h }, f.clone()))
+    .collect::<Vec<_>>();
+  if let Some(et) = rec.etype {
+    if fields.iter().all(|(h, _)| *h != ELEMENT_COLUMN) {
+      fields.push((ELEMENT_COLUMN, CsvField::ElementType(et)));
+    }
+  }
+  if let Some(step) = rec.step {
+    if fields.iter().all(|(h, _)| *h != STEP_COLUMN) {
+      fields.push((STEP_COLUMN, CsvField::Real(step.value())));
+    }
+  }
+  return fields;
+}
+
+/// A column of a table: its name and type.
+struct SqlColumn {
+  /// The column name.
+  name: &'static str,
+  /// The declared type.
+  sql_type: SqlType
+}
+
+/// Works out the columns of a table from its records.
+fn columns(records: &[CsvRecord]) -> Vec<SqlColumn> {
+  let mut cols: Vec<SqlColumn> = Vec::new();
+  for rec in records {
+    for (name, field) in sql_fields(rec) {
+      let sql_type = SqlType::of(&field);
+      match cols.iter_mut().find(|c| c.name == name) {
+        Some(col) => col.sql_type = col.sql_type.max(sql_type),
+        None => cols.push(SqlColumn { name, sql_type })
+      };
+    }
+  }
+  return cols;
+}
+
+/// Groups records by CSV block, as each block gets its own table.
+fn tables<I: IntoIterator<Item = CsvRecord>>(
+  records: I
+) -> BTreeMap<CsvBlockId, Vec<CsvRecord>> {
+  let mut tables: BTreeMap<CsvBlockId, Vec<CsvRecord>> = BTreeMap::new();
+  for rec in records {
+    tables.entry(rec.block_id).or_default().push(rec);
+  }
+  return tables;
+}
+
+/// Returns the statement that creates a table with some columns.
+fn create_table(table: &str, cols: &[SqlColumn]) -> String {
+  let decls = cols.iter()
+    .map(|c| format!("{}{}", quote_ident(c.name), c.sql_type.suffix()))
+    .collect::<Vec<_>>();
+  return format!("CREATE TABLE {} ({});", table, decls.join(", "));
+}
+
+/// Writes records as an SQL script that (re)creates one table per CSV block
+/// and fills them in, all in a single transaction.
+pub fn write_sql<W: Write, I: IntoIterator<Item = CsvRecord>>(
+  w: &mut W,
+  records: I
+) -> io::Result<()> {
+  writeln!(w, "BEGIN TRANSACTION;")?;
+  for (block_id, recs) in tables(records).iter() {
+    let table = quote_ident(block_id.name());
+    let cols = columns(recs);
+    if cols.is_empty() {
+      continue;
+    }
+    writeln!(w, "DROP TABLE IF EXISTS {};", table)?;
+    writeln!(w, "{}", create_table(&table, &cols))?;
+    for rec in recs {
+      let (names, values): (Vec<_>, Vec<_>) = sql_fields(rec)
+        .into_iter()
+        .map(|(h, f)| (quote_ident(h), literal(&f)))
+        .unzip();
+      writeln!(
+        w,
+        "INSERT INTO {} ({}) VALUES ({});",
+        table,
+        names.join(", "),
+        values.join(", ")
+      )?;
+    }
+  }
+  writeln!(w, "COMMIT;")?;
+  return Ok(());
+}
+
+/// Converts a field to a SQLite value. Blanks and non-finite reals are NULLs.
+#[cfg(feature = "sqlite")]
+fn sqlite_value(field: &CsvField) -> rusqlite::types::Value {
+  use rusqlite::types::Value;
+  return match field {
+    CsvField::Blank => Value::Null,
+    CsvField::Integer(i) => Value::Integer(*i as i64),
+    CsvField::Natural(n) => match i64::try_from(*n) {
+      Ok(i) => Value::Integer(i),
+      Err(_) => Value::Real(*n as f64)
+    },
+    CsvField::Real(x) if x.is_finite() => Value::Real(*x),
+    CsvField::Real(_) => Value::Null,
+    CsvField::String(s) => Value::Text(s.clone()),
+    CsvField::ElementType(et) => Value::Text(et.to_string()),
+  };
+}
+
+/// Writes records into a SQLite database, (re)creating one table per CSV
+/// block and filling them in, all in a single transaction. Tables for other
+/// CSV blocks are left alone.
+#[cfg(feature = "sqlite")]
+pub fn write_sqlite<P, I>(path: P, records: I) -> rusqlite::Result<()>
+  where P: AsRef<std::path::Path>, I: IntoIterator<Item = CsvRecord> {
+  let mut conn = rusqlite::Connection::open(path)?;
+  let tx = conn.transaction()?;
+  for (block_id, recs) in tables(records).iter() {
+    let table = quote_ident(block_id.name());
+    let cols = columns(recs);
+    if cols.is_empty() {
+      continue;
+    }
+    tx.execute(&format!("DROP TABLE IF EXISTS {};", table), [])?;
+    tx.execute(&create_table(&table, &cols), [])?;
+    for rec in recs {
+      let (names, values): (Vec<_>, Vec<_>) = sql_fields(rec)
+        .into_iter()
+        .map(|(h, f)| (quote_ident(h), sqlite_value(&f)))
+        .unzip();
+      let params = (1..=values.len())
+        .map(|i| format!("?{}", i))
+        .collect::<Vec<_>>();
+      let query = format!(
+        "INSERT INTO {} ({}) VALUES ({});",
+        table,
+        names.join(", "),
+        params.join(", ")
+      );
+      tx.prepare_cached(&query)?
+        .execute(rusqlite::params_from_iter(values))?;
+    }
+  }
+  return tx.commit();
+}
@@ -0,0 +1,561 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use f06::prelude::*;
+use nalgebra::DMatrix;
+
+use crate::prelude::*;
+use crate::prelude::templates::*;
+
+/// Returns the directory with the example files.
+fn examples_dir() -> PathBuf {
+  return PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..").join("examples");
+}
+
+/// Returns the path to an example file, given its solver's directory.
+fn example_path(solver: &str, name: &str) -> PathBuf {
+  return examples_dir().join(solver).join(name);
+}
+
+/// Builds a quad stresses block with both sides of a single element centroid.
+fn sided_quad_block() -> FinalBlock {
+  let elem = ElementRef { eid: 7, etype: Some(ElementType::Quad4) };
+  let row_indexes: BTreeMap<NasIndex, usize> = [
+    ElementSide::Bottom,
+    ElementSide::Top
+  ].into_iter().enumerate().map(|(i, side)| {
+    let esp = ElementSidedPoint {
+      element: elem,
+      point: ElementPoint::Centroid,
+      side
+    };
+    return (esp.into(), i);
+  }).collect();
+  let col_indexes: BTreeMap<NasIndex, usize> = PlateStressField::all()
+    .iter()
+    .enumerate()
+    .map(|(i, c)| (NasIndex::from(*c), i))
+    .collect();
+  let ncols = col_indexes.len();
+  let data = DMatrix::<f64>::from_fn(2, ncols, |r, c| (r * ncols + c) as f64);
+  return FinalBlock {
+    line_range: None,
+    block_type: BlockType::QuadStresses,
+    subcase: 1,
+    step: None,
+    complex_form: None,
+    row_indexes,
+    col_indexes,
+    data: Some(data.into())
+  };
+}
+
+/// Returns plain formatting settings: default reals and blanks, no alignment,
+/// no scientific threshold or unset values, and a dot for decimals.
+fn fmtr() -> CsvFormatting {
+  return CsvFormatting {
+    reals: FloatFormat::default(),
+    blanks: BlankDisplay::default(),
+    align: Alignment::None,
+    scientific_threshold: None,
+    blank_unset: None,
+    decimal: None
+  };
+}
+
+#[test]
+fn test_plate_stress_sides() {
+  let block = sided_quad_block();
+  let flavour = Flavour::default();
+  let records: Vec<CsvRecord> = CT_STRESSES_QUAD
+    .convert_block(&block, &flavour)
+    .expect("conversion failed")
+    .collect();
+  assert_eq!(records.len(), 2);
+  let iside = records[0].headers.iter()
+    .position(|h| *h == "Side")
+    .expect("no side column");
+  let sides: Vec<&CsvField> = records.iter().map(|r| &r.fields[iside]).collect();
+  assert!(sides.contains(&&CsvField::String("Z1".to_owned())));
+  assert!(sides.contains(&&CsvField::String("Z2".to_owned())));
+  assert_ne!(sides[0], sides[1]);
+}
+
+#[test]
+fn test_column_order() {
+  let block = sided_quad_block();
+  let flavour = Flavour::default();
+  let rec = CT_STRESSES_QUAD
+    .convert_block(&block, &flavour)
+    .expect("conversion failed")
+    .next()
+    .expect("no records");
+  let order: ColumnOrder = "stress:side,Subcase".parse().expect("bad spec");
+  assert_eq!(order.block_id, CsvBlockId::Stresses);
+  let headers = order.headers(&rec).expect("headers failed");
+  assert_eq!(headers, vec!["Stresses", "Side", "Subcase"]);
+  let fields = order.fields(&rec).expect("fields failed");
+  assert_eq!(fields.len(), 3);
+  assert_eq!(fields[2], CsvField::Natural(1));
+  let extra: ColumnOrder = "stress:Side,Nope".parse().expect("bad spec");
+  assert!(extra.fields(&rec).is_err());
+  assert!("stress".parse::<ColumnOrder>().is_err());
+  assert!("nope:Side".parse::<ColumnOrder>().is_err());
+}
+
+#[test]
+fn test_record_groups() {
+  let flavour = Flavour::default();
+  let block = sided_quad_block();
+  let mut other = sided_quad_block();
+  other.subcase = 2;
+  let records = CT_STRESSES_QUAD
+    .convert_block(&block, &flavour)
+    .expect("conversion failed")
+    .chain(CT_STRESSES_QUAD.convert_block(&other, &flavour).unwrap())
+    .chain(CT_STRESSES_QUAD.convert_block(&block, &flavour).unwrap());
+  let groups = RecordGroups::new(records).collect::<Vec<_>>();
+  let keys = groups.iter()
+    .map(|(bid, sc, recs)| (*bid, *sc, recs.len()))
+    .collect::<Vec<_>>();
+  assert_eq!(keys, vec![
+    (CsvBlockId::Stresses, 1, 2),
+    (CsvBlockId::Stresses, 2, 2),
+    (CsvBlockId::Stresses, 1, 2)
+  ]);
+  assert!(groups.iter().all(
+    |(_, sc, recs)| recs.iter().all(|r| r.subcase == Some(*sc))
+  ));
+}
+
+#[test]
+fn test_record_filter() {
+  let block = sided_quad_block();
+  let flavour = Flavour::default();
+  let records: Vec<CsvRecord> = CT_STRESSES_QUAD
+    .convert_block(&block, &flavour)
+    .expect("conversion failed")
+    .collect();
+  let count = |f: &RecordFilter| {
+    return records.iter().filter(|r| f.matches(r)).count();
+  };
+  // empty filters let everything through
+  assert_eq!(count(&RecordFilter::new()), 2);
+  // lax: grid point filters don't apply to records without one
+  assert_eq!(count(&RecordFilter::new().gids([99])), 2);
+  // ID filters
+  assert_eq!(count(&RecordFilter::new().eids([7])), 2);
+  assert_eq!(count(&RecordFilter::new().eids([8])), 0);
+  assert_eq!(count(&RecordFilter::new().subcases([2])), 0);
+  assert_eq!(count(&RecordFilter::new().blocks([CsvBlockId::Strains])), 0);
+  assert_eq!(count(&RecordFilter::new().etypes([ElementType::Quad4])), 2);
+  assert_eq!(count(&RecordFilter::new().points(PointFilter::Corner)), 0);
+  // columns and values
+  assert_eq!(count(&RecordFilter::new().column("side")), 2);
+  assert_eq!(count(&RecordFilter::new().column("nope")), 0);
+  let top = |f: &CsvField| *f == CsvField::String("Z2".to_owned());
+  assert_eq!(count(&RecordFilter::new().value("Side", top)), 1);
+  // combinations must all hold
+  let both = RecordFilter::new()
+    .eids([7])
+    .blocks([CsvBlockId::Stresses])
+    .value("Side", top);
+  assert_eq!(count(&both), 1);
+  let clash = RecordFilter::new().eids([7]).subcases([3]);
+  assert_eq!(count(&clash), 0);
+  // property IDs go through the element's
+  let properties = BTreeMap::from([(7, 12)]);
+  assert_eq!(count(&RecordFilter::new().pids(&properties, [12])), 2);
+  assert_eq!(count(&RecordFilter::new().pids(&properties, [13])), 0);
+  assert_eq!(count(&RecordFilter::new().pids(&BTreeMap::new(), [12])), 0);
+  // data, as flagged by a diff, select their rows
+  let bottom = *block.row_indexes.keys().next().unwrap();
+  let datum = |subcase: usize| DatumIndex {
+    block_ref: BlockRef::new(Subcase(subcase), BlockType::QuadStresses),
+    row: bottom,
+    col: PlateStressField::NormalX.into()
+  };
+  assert_eq!(count(&RecordFilter::new().datums([datum(1)])), 1);
+  assert_eq!(count(&RecordFilter::new().datums([datum(2)])), 0);
+}
+
+#[test]
+fn test_dof_selection() {
+  let flavour = Flavour::default();
+  let disp = FinalBlock {
+    line_range: None,
+    block_type: BlockType::Displacements,
+    subcase: 1,
+    step: None,
+    complex_form: None,
+    row_indexes: [(GridPointRef { gid: 3 }.into(), 0)].into_iter().collect(),
+    col_indexes: Dof::all()
+      .iter()
+      .enumerate()
+      .map(|(i, d)| (NasIndex::from(*d), i))
+      .collect(),
+    data: Some(DMatrix::<f64>::from_fn(1, SIXDOF, |_, c| c as f64).into())
+  };
+  let converters = all_converters();
+  let sel = DofSelection::new([DOF_TZ, DOF_RX]);
+  let rec = CT_DISPLACEMENTS
+    .convert_block(&disp, &flavour)
+    .expect("conversion failed")
+    .next()
+    .expect("no records");
+  let headers = sel.headers(&rec, &converters);
+  assert_eq!(headers[..5], ["Displacements", "GID", "Subcase", "Tz", "Rx"]);
+  let fields = sel.fields(&rec, &converters);
+  assert_eq!(fields.len(), headers.len());
+  assert_eq!(fields[3], CsvField::Real(2.0));
+  assert_eq!(fields[4], CsvField::Real(3.0));
+  // blocks without DOF columns are unaffected
+  let block = sided_quad_block();
+  let rec = CT_STRESSES_QUAD
+    .convert_block(&block, &flavour)
+    .expect("conversion failed")
+    .next()
+    .expect("no records");
+  assert!(sel.headers(&rec, &converters).into_iter().eq(rec.header_as_iter()));
+}
+
+#[test]
+fn test_block_ids_stable() {
+  // these are written to files and passed to -b, so they must never change
+  let numbers = CsvBlockId::all().iter()
+    .map(|bid| (bid.shorthand(), usize::from(*bid)))
+    .collect::<Vec<_>>();
+  assert_eq!(numbers[..8], [
+    ("meta", 0),
+    ("disp", 1),
+    ("stress", 2),
+    ("strain", 3),
+    ("engfor", 4),
+    ("gpforce", 5),
+    ("load", 6),
+    ("spcfor", 7)
+  ]);
+  for bid in CsvBlockId::all() {
+    assert_eq!(CsvBlockId::try_from(bid.number()), Ok(*bid));
+    assert!(bid.aliases().contains(&bid.number().to_string().as_str()));
+  }
+  let unique = CsvBlockId::all().iter()
+    .map(|bid| bid.number())
+    .collect::<BTreeSet<_>>();
+  assert_eq!(unique.len(), CsvBlockId::all().len());
+  // every converter writes to a registered block
+  for conv in all_converters().values() {
+    assert!(CsvBlockId::all().contains(&conv.output_block_id));
+  }
+}
+
+#[test]
+fn test_blank_or_zero() {
+  let block = sided_quad_block();
+  let flavour = Flavour::default();
+  let fmtr = CsvFormatting { blank_unset: Some(1.0e37), ..fmtr() };
+  let records: Vec<CsvRecord> = CT_STRESSES_QUAD
+    .convert_block(&block, &flavour)
+    .expect("conversion failed")
+    .collect();
+  for rec in records {
+    for (h, f) in rec.headers.iter().zip(rec.fields.iter()) {
+      // template padding is always blank, and the data never is
+      if *h == "<UNUSED>" {
+        assert!(fmtr.is_blank_or_zero(f));
+      } else if h.starts_with("EID") {
+        assert!(!fmtr.is_blank_or_zero(f));
+      }
+    }
+  }
+  assert!(fmtr.is_blank_or_zero(&CsvField::Real(0.0)));
+  assert!(fmtr.is_blank_or_zero(&CsvField::Real(2.0e37)));
+  assert!(fmtr.is_blank_or_zero(&CsvField::Natural(0)));
+  assert!(!fmtr.is_blank_or_zero(&CsvField::Real(-1.0e-30)));
+  assert!(!fmtr.is_blank_or_zero(&CsvField::String("Z1".to_owned())));
+}
+
+#[test]
+fn test_int_like() {
+  let fmtr = CsvFormatting { blank_unset: Some(1.0e37), ..fmtr() };
+  assert!(fmtr.is_int_like(&CsvField::Real(100.0)));
+  assert!(fmtr.is_int_like(&CsvField::Real(-3.0)));
+  assert!(fmtr.is_int_like(&CsvField::Real(2.0e37)));
+  assert!(fmtr.is_int_like(&CsvField::Blank));
+  assert!(!fmtr.is_int_like(&CsvField::Real(0.5)));
+  assert!(!fmtr.is_int_like(&CsvField::Real(f64::NAN)));
+  assert!(!fmtr.is_int_like(&CsvField::String("End A".to_owned())));
+  let id = fmtr.to_int_like(CsvField::Real(100.0));
+  assert_eq!(id, CsvField::Integer(100));
+  assert_eq!(fmtr.to_string(id), "100");
+  // unset reals still come out blank
+  let unset = fmtr.to_int_like(CsvField::Real(2.0e37));
+  assert_eq!(fmtr.to_string(unset), fmtr.blanks.fmt_str());
+  assert_eq!(fmtr.to_int_like(CsvField::Real(0.5)), CsvField::Real(0.5));
+}
+
+#[test]
+fn test_force_balance_order() {
+  let gp = GridPointRef { gid: 3 };
+  let elem = |eid: usize| ForceOrigin::Element {
+    elem: ElementRef { eid, etype: Some(ElementType::Quad4) }
+  };
+  // deliberately out of order
+  let origins = [
+    elem(20),
+    ForceOrigin::MultiPointConstraint,
+    elem(5),
+    ForceOrigin::Load,
+    ForceOrigin::SinglePointConstraint
+  ];
+  let row_indexes: BTreeMap<NasIndex, usize> = origins.iter()
+    .enumerate()
+    .map(|(i, fo)| {
+      let gpfo = GridPointForceOrigin { grid_point: gp, force_origin: *fo };
+      return (gpfo.into(), i);
+    })
+    .collect();
+  let col_indexes: BTreeMap<NasIndex, usize> = Dof::all().iter()
+    .enumerate()
+    .map(|(i, d)| (NasIndex::from(*d), i))
+    .collect();
+  let block = FinalBlock {
+    line_range: None,
+    block_type: BlockType::GridPointForceBalance,
+    subcase: 1,
+    step: None,
+    complex_form: None,
+    row_indexes,
+    col_indexes,
+    data: Some(DMatrix::<f64>::zeros(origins.len(), SIXDOF).into())
+  };
+  let records: Vec<CsvRecord> = CT_GPFORCEBALANCE
+    .convert_block(&block, &Flavour::default())
+    .expect("conversion failed")
+    .collect();
+  let col = |name: &str| records[0].headers.iter()
+    .position(|h| *h == name)
+    .expect("missing column");
+  let (ieid, itype) = (col("EID"), col("TYPE"));
+  let rows = records.iter()
+    .map(|r| (r.fields[itype].to_string(), r.fields[ieid].to_string()))
+    .collect::<Vec<_>>();
+  let expected = [
+    ("APPLIED", "0"),
+    ("SPC", "0"),
+    ("MPC", "0"),
+    ("QUAD4", "5"),
+    ("QUAD4", "20")
+  ].map(|(t, e)| (t.to_owned(), e.to_owned()));
+  assert_eq!(rows, expected);
+}
+
+#[test]
+fn test_decimal_separator() {
+  let fmtr = CsvFormatting {
+    scientific_threshold: Some(1.0e3),
+    decimal: Some(','),
+    ..fmtr()
+  };
+  assert_eq!(fmtr.to_string(CsvField::Real(-1.5e-7)), "-1,500000E-07");
+  assert_eq!(fmtr.to_string(CsvField::Real(2.25)), "+2,250000");
+  let mut buf = String::new();
+  fmtr.fmt(&CsvField::Real(2.25), &mut buf).unwrap();
+  assert_eq!(buf, "+2,250000");
+  // other fields are left alone
+  assert_eq!(fmtr.to_string(CsvField::String("1.5".to_owned())), "1.5");
+  assert_eq!(fmtr.to_string(CsvField::Natural(15)), "15");
+  // the delimiter can't be the separator
+  assert!(fmtr.check_delimiter(';').is_ok());
+  assert!(fmtr.check_delimiter(',').is_err());
+  let dot = CsvFormatting { decimal: None, ..fmtr };
+  assert!(dot.check_delimiter(',').is_ok());
+  assert!(dot.check_delimiter('.').is_err());
+  assert!(CsvFormatting { decimal: Some('e'), ..fmtr }
+    .check_delimiter(';')
+    .is_err());
+}
+
+#[test]
+fn test_write_sql() {
+  let block = sided_quad_block();
+  let flavour = Flavour::default();
+  let mut records: Vec<CsvRecord> = CT_STRESSES_QUAD
+    .convert_block(&block, &flavour)
+    .expect("conversion failed")
+    .collect();
+  // quotes in text must be escaped
+  let mut quoted = records[0].clone();
+  let iside = quoted.headers.iter()
+    .position(|h| *h == "Side")
+    .expect("no side column");
+  quoted.fields[iside] = CsvField::String("it's".to_owned());
+  records.push(quoted);
+  // results for a step of the subcase carry its value
+  let mut stepped = records[0].clone();
+  stepped.step = Some(OutputStep::Time(0.25));
+  records.push(stepped);
+  let mut out: Vec<u8> = Vec::new();
+  write_sql(&mut out, records).expect("writing failed");
+  let sql = String::from_utf8(out).expect("not UTF-8");
+  let lines = sql.lines().collect::<Vec<_>>();
+  assert_eq!(lines.first(), Some(&"BEGIN TRANSACTION;"));
+  assert_eq!(lines.last(), Some(&"COMMIT;"));
+  let create = lines.iter()
+    .find(|l| l.starts_with("CREATE TABLE \"Stresses\""))
+    .expect("no table created");
+  // element IDs go in a single column, and the element type in another
+  assert!(create.contains("\"EID\" INTEGER"));
+  assert!(create.contains("\"Element\" TEXT"));
+  assert!(create.contains("\"Side\" TEXT"));
+  assert!(create.contains("\"Step\" REAL"));
+  assert!(!create.contains("UNUSED"));
+  let inserts = lines.iter()
+    .filter(|l| l.starts_with("INSERT INTO \"Stresses\""))
+    .collect::<Vec<_>>();
+  assert_eq!(inserts.len(), 4);
+  assert!(inserts.iter().all(|l| l.contains("'QUAD4'")));
+  assert!(inserts[2].contains("'it''s'"));
+  assert!(!inserts[2].contains("\"Step\""));
+  assert!(inserts[3].contains("\"Step\") VALUES"));
+  assert!(inserts[3].ends_with(", 2.5e-1);"));
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_write_sqlite() {
+  let block = sided_quad_block();
+  let flavour = Flavour::default();
+  let records: Vec<CsvRecord> = CT_STRESSES_QUAD
+    .convert_block(&block, &flavour)
+    .expect("conversion failed")
+    .collect();
+  let nrecs = records.len();
+  let path = std::env::temp_dir()
+    .join(format!("nas_csv_test_{}.db", std::process::id()));
+  // writing twice replaces the table instead of appending to it
+  write_sqlite(&path, records.clone()).expect("writing failed");
+  write_sqlite(&path, records).expect("writing again failed");
+  let conn = rusqlite::Connection::open(&path).expect("can't open database");
+  let count: usize = conn
+    .query_row("SELECT COUNT(*) FROM \"Stresses\"", [], |r| r.get(0))
+    .expect("no stresses table");
+  assert_eq!(count, nrecs);
+  let (eid, etype): (i64, String) = conn
+    .query_row(
+      "SELECT \"EID\", \"Element\" FROM \"Stresses\" LIMIT 1",
+      [],
+      |r| Ok((r.get(0)?, r.get(1)?))
+    )
+    .expect("no typed columns");
+  assert!(eid > 0);
+  assert_eq!(etype, "QUAD4");
+  drop(conn);
+  std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn test_fill_missing() {
+  let block = sided_quad_block();
+  let flavour = Flavour::default();
+  let converters = all_converters();
+  let records: Vec<CsvRecord> = CT_STRESSES_QUAD
+    .convert_block(&block, &flavour)
+    .expect("conversion failed")
+    .collect();
+  let nrecs = records.len();
+  let sc = block.subcase;
+  let subcases: BTreeSet<usize> = [sc, sc + 1].into_iter().collect();
+  let block_ids = [CsvBlockId::Stresses, CsvBlockId::Displacements];
+  let filled = fill_missing(records, &block_ids, &subcases, &converters);
+  // one placeholder for the other stresses subcase, two for displacements
+  assert_eq!(filled.len(), nrecs + 3);
+  // displacements come before stresses
+  let disps = filled[..2].iter()
+    .map(|r| (r.block_id, r.subcase))
+    .collect::<Vec<_>>();
+  assert_eq!(disps, vec![
+    (CsvBlockId::Displacements, Some(sc)),
+    (CsvBlockId::Displacements, Some(sc + 1))
+  ]);
+  let placeholder = filled.last().expect("no records");
+  assert_eq!(placeholder.block_id, CsvBlockId::Stresses);
+  assert_eq!(placeholder.subcase, Some(sc + 1));
+  assert_eq!(placeholder.row, None);
+  let isc = placeholder.headers.iter()
+    .position(|h| *h == "Subcase")
+    .expect("no subcase column");
+  for (i, field) in placeholder.fields.iter().enumerate() {
+    if i == isc {
+      assert_eq!(*field, CsvField::Natural(sc + 1));
+    } else {
+      assert_eq!(*field, CsvField::Blank);
+    }
+  }
+}
+
+#[test]
+fn test_converter_schema() {
+  let schema = CT_STRESSES_QUAD.schema();
+  assert_eq!(schema.block_type, BlockType::QuadStresses);
+  assert_eq!(schema.records.len(), CT_STRESSES_QUAD.headers.len());
+  let cols = &schema.records[0];
+  assert_eq!(cols.len(), NAS_CSV_COLS - 1);
+  assert_eq!(cols[0].position, 1);
+  assert_eq!(cols[0].kind, "ELEMENT ID");
+  assert_eq!(cols[1].kind, "SUBCASE");
+  let nx = cols.iter()
+    .find(|c| c.header == "NormalX")
+    .expect("no normal-x column");
+  assert_eq!(nx.kind, "PLATE STRESS FIELD");
+  assert_eq!(nx.index.as_deref(), Some("NORMAL-X"));
+  assert_eq!(nx.units, None);
+  let angle: NasIndex = PlateStrainField(PlateStressField::Angle).into();
+  assert_eq!(column_units(angle), Some("deg"));
+  // appended columns go after the converted ones, in every kind of record
+  let mut schema = schema;
+  schema.append("Step", "STEP");
+  for cols in schema.records.iter() {
+    let last = cols.last().expect("no columns");
+    assert_eq!(last.position, cols.len());
+    assert_eq!(last.header, "Step");
+    assert_eq!(last.kind, "STEP");
+  }
+}
+
+#[test]
+fn test_point_filter_examples() {
+  let converters = all_converters();
+  let count = |path: PathBuf, points: PointFilter| {
+    let mut f06 = OnePassParser::parse_file(path).expect("parse failed");
+    f06.merge_blocks(true);
+    let filter = RecordFilter::new()
+      .blocks([CsvBlockId::Stresses])
+      .etypes([ElementType::Quad4, ElementType::Tria3])
+      .points(points);
+    return to_records(&f06, &converters)
+      .filter(|r| filter.matches(r))
+      .count();
+  };
+  // MYSTRAN's triangles and quads only have whole-element stresses
+  let mystran = example_path("mystran", "SB-ALL-ELEM-TEST.F06");
+  let all = count(mystran.clone(), PointFilter::All);
+  assert!(all > 0);
+  assert_eq!(count(mystran.clone(), PointFilter::Centroid), all);
+  assert_eq!(count(mystran, PointFilter::Corner), 0);
+  // and so do Simcenter's quads without corner output
+  let simcenter = example_path(
+    "scnastran",
+    "SB-ALL-ELEM-TEST_scnas_postexport.f06"
+  );
+  let all = count(simcenter.clone(), PointFilter::All);
+  assert!(all > 0);
+  assert_eq!(count(simcenter.clone(), PointFilter::Centroid), all);
+  assert_eq!(count(simcenter, PointFilter::Corner), 0);
+  // but with it, each quad has a centroid and four corners, on both sides
+  let quads = example_path("scnastran", "many_quads_scnas_postexport.f06");
+  let cen = count(quads.clone(), PointFilter::Centroid);
+  let corner = count(quads, PointFilter::Corner);
+  assert!(cen > 0);
+  assert_eq!(corner, 4 * cen);
+}
@@ -5,7 +5,7 @@
 use std::collections::BTreeMap;
 use std::collections::VecDeque;
 use std::path::PathBuf;
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::thread;
@@ -82,7 +82,10 @@ impl AppState {
     let uuid = Uuid::new_v4();
     let critset = NamedCriteria {
       name: format!("critset_{}", self.suite.criteria_sets.len() + 1),
-      criteria: Criteria::default()
+      criteria: Criteria::default(),
+      epsilon: 0.0,
+      grid_weights: BTreeMap::new(),
+      element_weights: BTreeMap::new()
     };
     self.suite.criteria_sets.insert(uuid, critset);
     return uuid;
@@ -213,9 +216,12 @@ impl AppState {
     pick: SolverPick
   ) -> Option<Job> {
     if let Some(solver) = self.get_solver(pick).cloned() {
-      if let Some((deck, res)) = self.get_deck(deck_uuid) {
+      // jobs get the deck with its path resolved, the suite keeps it as is
+      let deck = self.suite.decks.get(&deck_uuid)
+        .map(|d| self.suite.resolved(d));
+      if let (Some(deck), Some((_, res))) = (deck, self.get_deck(deck_uuid)) {
         return Some(Job {
-          deck: deck.clone(),
+          deck,
           pick,
           target: res,
           solver: solver.clone(),
@@ -267,14 +273,51 @@ impl AppState {
     self.runner.job_queue.lock().unwrap().clear();
   }
 
+  /// Cancels the runs in the queue. Jobs already running get to finish, but
+  /// no more are taken from the queue, and the ones left in it go back to
+  /// being ready. This is done in a separate thread to prevent UI locking.
+  pub(crate) fn cancel_queue(&self) {
+    let relaxed = std::sync::atomic::Ordering::Relaxed;
+    self.runner.cancelled.lock().expect("mutex poisoned").store(true, relaxed);
+    let queue = self.runner.job_queue.clone();
+    thread::spawn(move || {
+      let jobs = queue.lock().expect("mutex poisoned")
+        .drain(..)
+        .collect::<Vec<_>>();
+      log::info!("Cancelled {} queued job(s).", jobs.len());
+      for job in jobs {
+        *job.target.lock().expect("mutex poisoned").get_mut(job.pick) =
+          RunState::Ready;
+      }
+    });
+  }
+
+  /// Returns whether there are jobs queued or running.
+  pub(crate) fn is_running(&self) -> bool {
+    let relaxed = std::sync::atomic::Ordering::Relaxed;
+    return self.runner.current_jobs.load(relaxed) > 0
+      || self.runner.job_queue.try_lock().is_ok_and(|q| !q.is_empty());
+  }
+
   /// Spawns threads to run the queue.
   pub(crate) fn run_queue(&self) {
     let relaxed = std::sync::atomic::Ordering::Relaxed;
-    let runner = |queue: Arc<Mutex<VecDeque<Job>>>, mj: Arc<AtomicUsize>| {
+    // a fresh token, as runners of a cancelled run may still be finishing
+    let token = Arc::new(AtomicBool::new(false));
+    *self.runner.cancelled.lock().expect("mutex poisoned") = token.clone();
+    let runner = |
+      queue: Arc<Mutex<VecDeque<Job>>>,
+      mj: Arc<AtomicUsize>,
+      cancelled: Arc<AtomicBool>
+    | {
       let relaxed = std::sync::atomic::Ordering::Relaxed;
       mj.fetch_add(1, relaxed);
       log::debug!("Runner {} spawned!", mj.load(relaxed));
       loop {
+        if cancelled.load(relaxed) {
+          log::debug!("Runner stopping, the queue was cancelled.");
+          break;
+        }
         let job_opt = queue.lock().expect("lock poisoned").pop_front();
         if let Some(job) = job_opt {
           job.run();
@@ -294,9 +337,10 @@ impl AppState {
       if self.runner.current_jobs.load(relaxed) < nt {
         let queue = self.runner.job_queue.clone();
         let job_count = self.runner.current_jobs.clone();
+        let cancelled = token.clone();
         thread::Builder::new()
           .name(format!("job_runner_{}", jn+1))
-          .spawn(move || runner(queue, job_count))
+          .spawn(move || runner(queue, job_count, cancelled))
           .expect("failed to spawn runner thread");
       }
     }
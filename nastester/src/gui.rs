@@ -9,8 +9,8 @@ use std::path::PathBuf;
 use std::str::FromStr;
 
 use egui::{
-  Align, Color32, ComboBox, Context, DragValue, FontFamily, Id, Layout,
-  RichText, TextStyle, Ui, Visuals, WidgetText
+  Align, Button, Color32, ComboBox, Context, DragValue, FontFamily, Id,
+  Layout, RichText, TextStyle, Ui, Visuals, WidgetText
 };
 use egui_extras::{Column, TableBuilder};
 use f06::blocks::types::BlockType;
@@ -54,7 +54,10 @@ pub(crate) struct StaticFields {
   /// Highlight flagged values?
   highlight_flagged: bool,
   /// Display column metrics when looking at extraction results?
-  show_col_metrics: bool
+  show_col_metrics: bool,
+  /// Combine column metrics across subcases?
+  #[serde(default)]
+  aggregate_metrics: bool
 }
 
 impl Default for StaticFields {
@@ -65,6 +68,7 @@ impl Default for StaticFields {
       extractions_only: false,
       highlight_flagged: true,
       show_col_metrics: false,
+      aggregate_metrics: false,
     };
   }
 }
@@ -126,6 +130,7 @@ impl Gui {
       }
     }
     if let Some(ref p) = self.suite_file {
+      self.state.suite.set_file(p);
       let file = File::create(p)?;
       let mut writer = BufWriter::new(file);
       serde_json::to_writer_pretty(&mut writer, &self.state.suite)?;
@@ -176,6 +181,7 @@ impl Gui {
       let file = File::open(p)?;
       let reader = BufReader::new(file);
       self.state.suite = serde_json::from_reader(reader)?;
+      self.state.suite.set_file(p);
       log::info!("Loaded suite from {}.", p.display());
       return Ok(true);
     }
@@ -609,6 +615,19 @@ impl Gui {
           self.state.enqueue_solver(SolverPick::Testing);
           self.state.run_queue();
         }
+        ui.separator();
+        let running = self.state.is_running();
+        if ui.add_enabled(running, Button::new("Cancel")).clicked() {
+          self.state.cancel_queue();
+          ui.close_menu();
+        }
+        ui.horizontal(|ui| {
+          ui.label("Max concurrent jobs (0 for one per CPU):");
+          ui.add(
+            DragValue::new(&mut self.state.runner.max_jobs)
+              .clamp_range(0..=256)
+          );
+        });
       });
       // advanced stuff
       ui.menu_button("Advanced", |ui| {
@@ -633,7 +652,7 @@ impl Gui {
         #[cfg(debug_assertions)]
         {
           let guidebug = if ctx.debug_on_hover() { "ON" } else { "OFF" };
-          if ui.button(&format!("GUI debug {}", guidebug)).clicked() {
+          if ui.button(format!("GUI debug {}", guidebug)).clicked() {
             ctx.set_debug_on_hover(!ctx.debug_on_hover());
           }
         }
@@ -645,7 +664,7 @@ impl Gui {
   fn view_decks(&mut self, ctx: &Context) {
     // one per deck
     let deck_data = self.state.decks_by_name()
-      .map(|(u, d, r)| (u, d.clone(), r))
+      .map(|(u, d, r)| (u, self.state.suite.resolved(d), r))
       .collect::<Vec<_>>();
     egui::CentralPanel::default().show(ctx, |ui| {
       self.show_menu(ctx, ui);
@@ -753,7 +772,11 @@ impl Gui {
                   match (&h.ref_f06, &h.test_f06) {
                     (RunState::Finished(_), RunState::Finished(_)) => {
                       row.col(|ui| {
-                        ui.label(format!("{} values", h.num_flagged()));
+                        ui.label(format!(
+                          "{} values (score {})",
+                          h.num_flagged(),
+                          h.flag_score()
+                        ));
                       });
                     },
                     _ => {
@@ -879,7 +902,7 @@ impl Gui {
                 ].into_iter().max().unwrap() + 1;
                 let est_height = max_exn_lens as f32 * item_height;
                 body.row(est_height, |mut row| {
-                  row.col(|ui| { ui.label(&i.to_string()); });
+                  row.col(|ui| { ui.label(i.to_string()); });
                   row.col(|ui| {
                     self.combo_specifier(
                       ui,
@@ -1017,6 +1040,9 @@ impl Gui {
           .column(Column::auto().resizable(true))
           .column(Column::auto().resizable(true))
           .column(Column::auto().resizable(true))
+          .column(Column::auto().resizable(true))
+          .column(Column::auto().resizable(true))
+          .column(Column::auto())
           .column(Column::auto())
           .column(Column::auto())
           .column(Column::auto())
@@ -1025,9 +1051,12 @@ impl Gui {
             header.col(|ui| { ui.heading("Criteria set name"); });
             header.col(|ui| { ui.heading("Max absolute difference"); });
             header.col(|ui| { ui.heading("Max absolute ratio"); });
+            header.col(|ui| { ui.heading("Ratio floor"); });
+            header.col(|ui| { ui.heading("Metrics zero below"); });
             header.col(|ui| { ui.heading("Flag NaN"); });
             header.col(|ui| { ui.heading("Flag infinities"); });
             header.col(|ui| { ui.heading("Flag if signs differ"); });
+            header.col(|ui| { ui.heading("Skip unset (1E+37)"); });
             header.col(|ui| { ui.heading("Actions"); });
           })
           .body(|body| {
@@ -1060,6 +1089,16 @@ impl Gui {
               row.col(|ui| {
                 disableable_number(ui, &mut critset.criteria.ratio);
               });
+              // ratio floor
+              row.col(|ui| {
+                let floor = &mut critset.criteria.ratio_floor;
+                ui.add(DragValue::new(floor).speed(0.1));
+              });
+              // metrics epsilon
+              row.col(|ui| {
+                let eps = &mut critset.epsilon;
+                ui.add(DragValue::new(eps).speed(1e-6));
+              });
               // flag NaNs
               row.col(|ui| {
                 ui.vertical_centered(|ui| {
@@ -1078,6 +1117,16 @@ impl Gui {
                   ui.checkbox(&mut critset.criteria.sig, "");
                 });
               });
+              // skip unset values
+              row.col(|ui| {
+                ui.vertical_centered(|ui| {
+                  let unset = &mut critset.criteria.unset_threshold;
+                  let mut skip = unset.is_some();
+                  if ui.checkbox(&mut skip, "").changed() {
+                    *unset = skip.then_some(UNSET_SENTINEL);
+                  }
+                });
+              });
               // delete action
               row.col(|ui| {
                 if ui.button("Delete").clicked() {
@@ -1188,16 +1237,29 @@ impl Gui {
       warn!("tried to view results with no deck!");
       return;
     };
-    // block ref option to string
-    let obref_str = |o: &Option<BlockRef>| -> String {
+    // block ref option to string, with the subcase label if there's one
+    let obref_str = |o: &Option<BlockRef>, res: &DeckResults| -> String {
       if let Some(bref) = o {
-        return format!("Subcase {}, {}", bref.subcase, bref.block_type);
+        if let Some(label) = res.subcase_label(bref.subcase) {
+          return format!(
+            "Subcase {} ({}), {}",
+            bref.subcase,
+            label,
+            bref.block_type
+          );
+        }
+        return bref.to_string();
       } else {
         return "Results summary".to_string();
       }
     };
     // show block in column
     let formatter = FloatFormat::default();
+    let fmt_x = |x: f64| -> String {
+      let mut fbuf = String::new();
+      formatter.fmt_f64(&mut fbuf, x).ok();
+      return fbuf;
+    };
     let block_table = |
       ui: &mut Ui,
       block: &FinalBlock,
@@ -1303,11 +1365,12 @@ impl Gui {
         // blockref picker
         let bref = &mut sf.block_ref;
         ComboBox::from_id_source("res_block_picker")
-          .selected_text(obref_str(bref))
+          .selected_text(obref_str(bref, &res))
           .show_ui(ui, |ui| {
-            ui.selectable_value(bref, None, obref_str(&None));
+            ui.selectable_value(bref, None, obref_str(&None, &res));
             for cand in res.all_block_refs() {
-              ui.selectable_value(bref, Some(cand), obref_str(&Some(cand)));
+              let text = obref_str(&Some(cand), &res);
+              ui.selectable_value(bref, Some(cand), text);
             }
           });
         // limit to extracted
@@ -1325,6 +1388,11 @@ impl Gui {
           &mut sf.show_col_metrics,
           "Show column metrics"
         );
+        // aggregate metrics
+        ui.checkbox(
+          &mut sf.aggregate_metrics,
+          "Aggregate metrics across subcases"
+        );
       });
       if let Some(bref) = sf.block_ref {
         // show chosen block
@@ -1347,6 +1415,73 @@ impl Gui {
             );
           }
         });
+      } else if sf.show_col_metrics {
+        // show metrics, either per block or combined across subcases
+        let mut rows: Vec<[String; 4]> = Vec::new();
+        if sf.aggregate_metrics {
+          for ((pick, bt, col, m), x) in res.aggregate_col_metrics() {
+            let metric = format!("{} ({:?})", m.short_name(), pick);
+            rows.push([bt.to_string(), col.to_string(), metric, fmt_x(x)]);
+          }
+          for ((bt, col, m), x) in res.aggregate_col_compares() {
+            let metric = m.short_name().to_string();
+            rows.push([bt.to_string(), col.to_string(), metric, fmt_x(x)]);
+          }
+        } else {
+          for er in res.extractions.iter() {
+            for ((pick, bref, col, m), x) in er.col_metrics.iter() {
+              if let Some(x) = x {
+                rows.push([
+                  bref.to_string(),
+                  col.to_string(),
+                  format!("{} ({:?})", m.short_name(), pick),
+                  fmt_x(*x)
+                ]);
+              }
+            }
+            for ((bref, col, m), x) in er.col_compares.iter() {
+              if let Some(x) = x {
+                // point out where the largest deviation is
+                let mad = ColumnCompareMetric::MaximumAbsoluteDifference;
+                let worst = er.worst_rows.get(&(*bref, *col))
+                  .filter(|_| *m == mad);
+                let value = match worst {
+                  Some(row) => format!("{} (at {})", fmt_x(*x), row),
+                  None => fmt_x(*x)
+                };
+                rows.push([
+                  bref.to_string(),
+                  col.to_string(),
+                  m.short_name().to_string(),
+                  value
+                ]);
+              }
+            }
+          }
+        }
+        let heading_height = ui.text_style_height(&TextStyle::Heading);
+        let dy = ui.spacing().item_spacing.y;
+        let body_height = ui.text_style_height(&TextStyle::Body) + dy;
+        TableBuilder::new(ui)
+          .vscroll(true)
+          .auto_shrink(false)
+          .striped(true)
+          .cell_layout(Layout::left_to_right(Align::Center))
+          .columns(Column::auto().resizable(true), 4)
+          .header(heading_height, |mut header| {
+            header.col(|ui| { ui.heading("Block"); });
+            header.col(|ui| { ui.heading("Column"); });
+            header.col(|ui| { ui.heading("Metric"); });
+            header.col(|ui| { ui.heading("Value"); });
+          })
+          .body(|body| {
+            body.rows(body_height, rows.len(), |mut row| {
+              let fields = rows[row.index()].clone();
+              for field in fields {
+                row.col(|ui| { ui.label(field); });
+              }
+            });
+          });
       };
     });
   }
@@ -16,6 +16,9 @@ pub(crate) mod results;
 pub(crate) mod running;
 pub(crate) mod suite;
 
+#[cfg(test)]
+mod tests;
+
 #[cfg(debug_assertions)]
 /// Default log level for debug builds.
 const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Debug;
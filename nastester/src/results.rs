@@ -21,7 +21,7 @@ pub(crate) enum RunState {
   /// Running
   Running,
   /// Finished, F06 file present.
-  Finished(F06File),
+  Finished(Box<F06File>),
   /// Run failed, contains error.
   Error(String)
 }
@@ -29,210 +29,98 @@ pub(crate) enum RunState {
 impl<T: ToString> From<Result<F06File, T>> for RunState {
   fn from(value: Result<F06File, T>) -> Self {
     return match value {
-      Ok(f) => Self::Finished(f),
+      Ok(f) => Self::Finished(Box::new(f)),
       Err(e) => Self::Error(e.to_string()),
     }
   }
 }
 
-/// Single-column metrics (such as min, max, mean).
-#[derive(
-  Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord
-)]
-pub(crate) enum SingleColumnMetric {
-  /// Minimum of a column.
-  Mininum,
-  /// Maximum of a column.
-  Maximum,
-  /// Average value of a column.
-  Average,
-  /// Standard deviation of a column.
-  StandardDeviation
-}
-
-impl SingleColumnMetric {
-  /// Returns all the currently-implemented single-column metrics.
-  pub(crate) const fn all() -> &'static [Self] {
-    return &[
-      Self::Mininum,
-      Self::Maximum,
-      Self::Average,
-      Self::StandardDeviation,
-    ];
-  }
+/// Index to get a single-column metric.
+pub(crate) type SingleColumnMetricIndex = (
+  SolverPick,
+  BlockRef,
+  NasIndex,
+  SingleColumnMetric
+);
 
-  /// Returns a short name for this metric.
-  pub(crate) const fn short_name(&self) -> &'static str {
-    return match self {
-      Self::Mininum => "min",
-      Self::Maximum => "max",
-      Self::Average => "avg",
-      Self::StandardDeviation => "sd",
-    };
-  }
+/// Index to get a column-compare metric.
+pub(crate) type ColumnCompareMetricIndex = (
+  BlockRef,
+  NasIndex,
+  ColumnCompareMetric
+);
 
-  /// Returns a long name for this metric.
-  pub(crate) const fn long_name(&self) -> &'static str {
-    return match self {
-      Self::Mininum => "minimum",
-      Self::Maximum => "maximum",
-      Self::Average => "average",
-      Self::StandardDeviation => "standard deviation",
-    };
-  }
+/// Index to get a single-column metric aggregated across subcases.
+pub(crate) type AggregateSingleIndex = (
+  SolverPick,
+  BlockType,
+  NasIndex,
+  SingleColumnMetric
+);
 
-  /// Computes this metric over a block and columns.
-  pub(crate) fn compute(
-    &self,
-    block: &FinalBlock,
-    col: NasIndex
-  ) -> Option<f64> {
-    let nums = block.row_indexes.keys()
-      .filter_map(|r| block.get(*r, col))
-      .map(f64::from);
-    match self {
-      Self::Mininum => {
-        return nums.min_by(|a, b| a.total_cmp(b));
-      },
-      Self::Maximum => {
-        return nums.max_by(|a, b| a.total_cmp(b));
-      },
-      Self::Average => {
-        let mut count: usize = 0;
-        let mut total: f64 = 0.0;
-        for num in nums {
-          count += 1;
-          total += num;
-        }
-        if count > 0 {
-          return Some(total/count as f64);
-        } else {
-          return None;
-        }
-      },
-      Self::StandardDeviation => {
-        let avg = Self::Average.compute(block, col)?;
-        let mut count: usize = 0;
-        let mut total_qm: f64 = 0.0;
-        for num in nums {
-          count += 1;
-          total_qm += (avg - num).powi(2);
-        }
-        if count > 0 {
-          return Some(f64::sqrt(total_qm/count as f64));
-        } else {
-          return None;
-        }
-      },
-    }
-  }
-}
+/// Index to get a column-compare metric aggregated across subcases.
+pub(crate) type AggregateCompareIndex = (
+  BlockType,
+  NasIndex,
+  ColumnCompareMetric
+);
 
-/// Column-compare metrics (like the RMSD).
-#[derive(
-  Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord
-)]
-pub(crate) enum ColumnCompareMetric {
-  /// Max absolute deviation.
-  MaximumAbsoluteDifference,
-  /// Average absolute deviation.
-  AverageAbsoluteDifference,
-  /// Root mean square deviation.
-  RootMeanSquareDeviation
+/// How the values of a metric are combined across subcases.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Combination {
+  /// Take the smallest.
+  Minimum,
+  /// Take the largest, i.e. the worst for deviations.
+  Maximum,
+  /// Take the mean.
+  Mean
 }
 
-impl ColumnCompareMetric {
-  /// Returns all the currently-implemented single-column metrics.
-  pub(crate) const fn all() -> &'static [Self] {
-    return &[
-      Self::MaximumAbsoluteDifference,
-      Self::AverageAbsoluteDifference,
-      Self::RootMeanSquareDeviation
-    ];
+impl Combination {
+  /// Returns how a single-column metric is combined.
+  const fn of_single(metric: SingleColumnMetric) -> Self {
+    return match metric {
+      SingleColumnMetric::Mininum => Self::Minimum,
+      SingleColumnMetric::Maximum => Self::Maximum,
+      SingleColumnMetric::Average => Self::Mean,
+      SingleColumnMetric::StandardDeviation => Self::Maximum,
+    };
   }
 
-  /// Returns a short name for this metric.
-  pub(crate) const fn short_name(&self) -> &'static str {
-    return match self {
-      Self::MaximumAbsoluteDifference => "max-abs-diff",
-      Self::AverageAbsoluteDifference => "avg-abs-diff",
-      Self::RootMeanSquareDeviation => "rmsd"
+  /// Returns how a column-compare metric is combined.
+  const fn of_compare(metric: ColumnCompareMetric) -> Self {
+    return match metric {
+      ColumnCompareMetric::MaximumAbsoluteDifference => Self::Maximum,
+      ColumnCompareMetric::AverageAbsoluteDifference => Self::Mean,
+      ColumnCompareMetric::RootMeanSquareDeviation => Self::Maximum,
     };
   }
 
-  /// Returns a long name for this metric.
-  pub(crate) const fn long_name(&self) -> &'static str {
+  /// Combines a list of values.
+  fn apply(&self, vals: &[f64]) -> Option<f64> {
     return match self {
-      Self::MaximumAbsoluteDifference => "maximum absolute deviation",
-      Self::AverageAbsoluteDifference => "average absolute deviation",
-      Self::RootMeanSquareDeviation => "root mean square deviation",
+      Self::Minimum => vals.iter().copied().min_by(|a, b| a.total_cmp(b)),
+      Self::Maximum => vals.iter().copied().max_by(|a, b| a.total_cmp(b)),
+      Self::Mean if vals.is_empty() => None,
+      Self::Mean => Some(vals.iter().sum::<f64>() / vals.len() as f64),
     };
   }
+}
 
-  /// Computes this metric over a block and columns.
-  pub(crate) fn compute(
-    &self,
-    ref_block: &FinalBlock,
-    test_block: &FinalBlock,
-    col: NasIndex
-  ) -> Option<f64> {
-    let nums = ref_block.row_indexes.keys().filter_map(|r| {
-      if let Some(rval) = ref_block.get(*r, col) {
-        if let Some(tval) = test_block.get(*r, col) {
-          return Some((f64::from(rval), f64::from(tval)));
-        }
-      }
-      return None;
-    });
-    match self {
-      Self::MaximumAbsoluteDifference => {
-        return nums.map(|(r, t)| (r - t).abs()).max_by(|a, b| a.total_cmp(b));
-      },
-      Self::AverageAbsoluteDifference => {
-        let mut count: usize = 0;
-        let mut total: f64 = 0.0;
-        for (r, t) in nums {
-          count += 1;
-          total += (r - t).abs();
-        }
-        if count > 0 {
-          return Some(total/count as f64);
-        } else {
-          return None;
-        }
-      },
-      Self::RootMeanSquareDeviation => {
-        let mut count: usize = 0;
-        let mut total: f64 = 0.0;
-        for (r, t) in nums {
-          count += 1;
-          total += (r - t).powi(2);
-        }
-        if count > 0 {
-          return Some(f64::sqrt(total/count as f64));
-        } else {
-          return None;
-        }
-      },
-    }
+/// Groups metric values by an aggregate key, then combines each group.
+fn aggregate<K: Ord>(
+  values: impl Iterator<Item = (K, f64)>,
+  how: impl Fn(&K) -> Combination
+) -> BTreeMap<K, f64> {
+  let mut groups: BTreeMap<K, Vec<f64>> = BTreeMap::new();
+  for (k, v) in values {
+    groups.entry(k).or_default().push(v);
   }
+  return groups.into_iter()
+    .filter_map(|(k, vals)| how(&k).apply(&vals).map(|x| (k, x)))
+    .collect();
 }
 
-/// Index to get a single-column metric.
-pub(crate) type SingleColumnMetricIndex = (
-  SolverPick,
-  BlockRef,
-  NasIndex,
-  SingleColumnMetric
-);
-
-/// Index to get a column-compare metric.
-pub(crate) type ColumnCompareMetricIndex = (
-  BlockRef,
-  NasIndex,
-  ColumnCompareMetric
-);
-
 /// This structure holds extraction results: blocks and flagged indexes.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub(crate) struct ExtractionResults {
@@ -244,12 +132,17 @@ pub(crate) struct ExtractionResults {
   pub(crate) blocks_test: Vec<FinalBlock>,
   /// The flagged indices.
   pub(crate) flagged: Option<BTreeSet<DatumIndex>>,
+  /// The sum of the weights of the flagged indices.
+  pub(crate) flag_score: f64,
   /// The extracted indices.
   pub(crate) extracted: BTreeSet<DatumIndex>,
   /// Single-column metrics.
   pub(crate) col_metrics: BTreeMap<SingleColumnMetricIndex, Option<f64>>,
   /// Column-compare metrics.
-  pub(crate) col_compares: BTreeMap<ColumnCompareMetricIndex, Option<f64>>
+  pub(crate) col_compares: BTreeMap<ColumnCompareMetricIndex, Option<f64>>,
+  /// The row where each column deviates the most between the solvers.
+  #[serde(default)]
+  pub(crate) worst_rows: BTreeMap<(BlockRef, NasIndex), NasIndex>
 }
 
 impl ExtractionResults {
@@ -277,8 +170,8 @@ impl ExtractionResults {
       .flat_map(|p| self.blocks_of(*p).iter().map(|b| b.block_ref()));
   }
 
-  /// Updates the single-column metrics.
-  pub(crate) fn update_single_col_metrics(&mut self) {
+  /// Updates the single-column metrics with the given options.
+  pub(crate) fn update_single_col_metrics(&mut self, opts: &MetricOptions) {
     let indices = SolverPick::all().iter()
       .flat_map(|p| self.blocks_of(*p).iter().flat_map(
         move |b| b.col_indexes.keys().map(move |ci| (*p, b, *ci))
@@ -289,28 +182,35 @@ impl ExtractionResults {
     let mut new_scm: BTreeMap<_, Option<f64>> = BTreeMap::new();
     for (pick, block, col, metric) in indices {
       let true_index = (pick, block.block_ref(), col, metric);
-      let value = metric.compute(block, col);
+      let value = metric.compute_with(block, col, opts);
       new_scm.insert(true_index, value);
     }
     mem::swap(&mut self.col_metrics, &mut new_scm);
   }
 
-  /// Updates the column-compare metrics.
-  pub(crate) fn update_col_compare_metrics(&mut self) {
+  /// Updates the column-compare metrics with the given options.
+  pub(crate) fn update_col_compare_metrics(&mut self, opts: &MetricOptions) {
     let brs: BTreeSet<_> = self.block_refs().collect();
     let mut new_ccm: BTreeMap<_, Option<f64>> = BTreeMap::new();
+    let mut new_worst: BTreeMap<_, NasIndex> = BTreeMap::new();
     for block_ref in brs {
       if let (Some(r), Some(t)) = self.block_pair(block_ref) {
         for col in r.col_indexes.keys() {
+          let located = ColumnCompareMetric::MaximumAbsoluteDifference
+            .compute_located_with(r, t, *col, opts);
+          if let Some((_, row)) = located {
+            new_worst.insert((block_ref, *col), row);
+          }
           for metric in ColumnCompareMetric::all() {
             let true_index = (block_ref, *col, *metric);
-            let value = metric.compute(r, t, *col);
+            let value = metric.compute_with(r, t, *col, opts);
             new_ccm.insert(true_index, value);
           }
         }
       }
     }
     mem::swap(&mut self.col_compares, &mut new_ccm);
+    mem::swap(&mut self.worst_rows, &mut new_worst);
   }
 }
 
@@ -350,6 +250,7 @@ impl DeckResults {
   pub(crate) fn clear_flags(&mut self) {
     for res in self.extractions.iter_mut() {
       res.flagged = None;
+      res.flag_score = 0.0;
     }
     self.flagged.clear();
   }
@@ -359,11 +260,13 @@ impl DeckResults {
     *self.get_mut(pick) = RunState::Ready;
     for res in self.extractions.iter_mut() {
       res.flagged = None;
+      res.flag_score = 0.0;
       match pick {
         SolverPick::Reference => &mut res.blocks_ref,
         SolverPick::Testing => &mut res.blocks_test,
       }.clear();
       res.col_compares.clear();
+      res.worst_rows.clear();
       res.col_metrics.retain(|k, _| k.0 != pick);
     }
   }
@@ -383,17 +286,26 @@ impl DeckResults {
           blocks_ref: exn.blockify(r),
           blocks_test: exn.blockify(t),
           flagged: None,
+          flag_score: 0.0,
           extracted: BTreeSet::new(),
           col_metrics: BTreeMap::new(),
           col_compares: BTreeMap::new(),
+          worst_rows: BTreeMap::new(),
         };
         // get extracted indices
         res.extracted.extend(exn.lookup(r));
         res.extracted.extend(exn.lookup(t));
         // recompute metrics
-        res.update_single_col_metrics();
-        res.update_col_compare_metrics();
-        if let Some(critset) = crit_uuid.and_then(|u| crit_sets.get(&u)) {
+        let critset = crit_uuid.and_then(|u| crit_sets.get(&u));
+        let opts = critset.map_or(MetricOptions::default(), |cs| {
+          return MetricOptions {
+            epsilon: cs.epsilon,
+            unset_threshold: cs.criteria.unset_threshold
+          };
+        });
+        res.update_single_col_metrics(&opts);
+        res.update_col_compare_metrics(&opts);
+        if let Some(critset) = critset {
           let in_ref = exn.lookup(r).collect::<BTreeSet<_>>();
           let in_test = exn.lookup(t).collect::<BTreeSet<_>>();
           let in_either = in_ref
@@ -408,24 +320,43 @@ impl DeckResults {
           if exn.dxn == DisjunctionBehaviour::Flag {
             flagged.extend(dxn);
           }
+          // zero-weight values are left out of the comparison
+          let in_either = in_either.into_iter()
+            .filter(|ix| critset.weight_of(ix) != 0.0);
+          flagged.retain(|ix| critset.weight_of(ix) != 0.0);
+          // a value missing from either file is handled according to the
+          // extraction's disjunction behaviour instead of being zeroed.
           let get = |f: &F06File, ix: &DatumIndex| -> Option<F06Number> {
-            let v = ix.get_from(f);
-            if v.is_err() && exn.dxn == DisjunctionBehaviour::AssumeZeroes {
-              return Some(0.0.into());
-            } else {
-              return Some(v.unwrap());
-            }
+            return match ix.get_from(f) {
+              Ok(v) => Some(v),
+              Err(e) => {
+                log::debug!("missing value for {:?}: {}", ix, e);
+                match exn.dxn {
+                  DisjunctionBehaviour::AssumeZeroes => Some(0.0.into()),
+                  DisjunctionBehaviour::Skip => None,
+                  DisjunctionBehaviour::Flag => None,
+                }
+              }
+            };
           };
           for ix in in_either {
             let val_ref = get(r, &ix);
             let val_test = get(t, &ix);
-            if let (Some(rv), Some(tv)) = (val_ref, val_test) {
-              if critset.criteria.check(rv.into(), tv.into()).is_some() {
-                flagged.insert(ix);
+            match (val_ref, val_test) {
+              (Some(rv), Some(tv)) => {
+                if critset.criteria.check(rv.into(), tv.into()).is_some() {
+                  flagged.insert(ix);
+                }
+              },
+              _ => {
+                if exn.dxn == DisjunctionBehaviour::Flag {
+                  flagged.insert(ix);
+                }
               }
             }
           }
           self.flagged.extend(flagged.iter().copied());
+          res.flag_score = flagged.iter().map(|ix| critset.weight_of(ix)).sum();
           res.flagged = Some(flagged);
         }
         self.extracted.extend(res.extracted.iter().copied());
@@ -434,6 +365,18 @@ impl DeckResults {
     }
   }
 
+  /// Returns the label of a subcase, from the reference run if it has one
+  /// and from the test run otherwise.
+  pub(crate) fn subcase_label(&self, subcase: Subcase) -> Option<&str> {
+    return SolverPick::all().iter().find_map(|pick| {
+      if let RunState::Finished(f) = self.get(*pick) {
+        return f.subcase_label(subcase);
+      } else {
+        return None;
+      }
+    });
+  }
+
   /// Returns all block refs in the results set.
   pub(crate) fn all_block_refs(&self) -> Vec<BlockRef> {
     let mut v: Vec<BlockRef> = Vec::new();
@@ -447,6 +390,41 @@ impl DeckResults {
     return v;
   }
 
+  /// Combines the single-column metrics of all extractions across subcases,
+  /// so there's one value per solver, block type, column and metric. Minima
+  /// and maxima stay so, averages are averaged, and deviations take the
+  /// largest.
+  pub(crate) fn aggregate_col_metrics(
+    &self
+  ) -> BTreeMap<AggregateSingleIndex, f64> {
+    let values = self.extractions.iter()
+      .flat_map(|er| er.col_metrics.iter())
+      .filter_map(|((pick, bref, col, metric), v)| {
+        return v.map(|x| ((*pick, bref.block_type, *col, *metric), x));
+      });
+    return aggregate(values, |k| Combination::of_single(k.3));
+  }
+
+  /// Combines the column-compare metrics of all extractions across subcases,
+  /// so there's one value per block type, column and metric. The maximum
+  /// and RMS deviations take the largest, and the average one is averaged.
+  pub(crate) fn aggregate_col_compares(
+    &self
+  ) -> BTreeMap<AggregateCompareIndex, f64> {
+    let values = self.extractions.iter()
+      .flat_map(|er| er.col_compares.iter())
+      .filter_map(|((bref, col, metric), v)| {
+        return v.map(|x| ((bref.block_type, *col, *metric), x));
+      });
+    return aggregate(values, |k| Combination::of_compare(k.2));
+  }
+
+  /// Returns the weighted flag score, i.e. the sum of the weights of all
+  /// flagged values.
+  pub(crate) fn flag_score(&self) -> f64 {
+    return self.extractions.iter().map(|er| er.flag_score).sum();
+  }
+
   /// Returns the total number of flagged values.
   pub(crate) fn num_flagged(&self) -> usize {
     return self.extractions.iter()
@@ -6,7 +6,7 @@ use core::fmt::Display;
 use std::ffi::OsStr;
 use std::fs::File;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
 use std::sync::{Arc, Mutex};
 
 use f06::prelude::*;
@@ -163,7 +163,8 @@ impl RunnableSolver {
         (true, false) => lower,
         (false, true) => upper,
       };
-      let mut file = f06::parser::OnePassParser::parse_file(f06path)?;
+      let mut file = f06::parser::OnePassParser::parse_file(&f06path)
+        .map_err(|e| RunError::UnreadableF06(f06path.clone(), Box::new(e)))?;
       file.merge_blocks(true);
       file.sort_all_blocks();
       return Ok(file);
@@ -271,7 +272,13 @@ pub(crate) struct Runner {
   /// Max concurrent jobs. If zero, auto-detect.
   pub(crate) max_jobs: usize,
   /// Current number of jobs running.
-  pub(crate) current_jobs: Arc<AtomicUsize>
+  pub(crate) current_jobs: Arc<AtomicUsize>,
+  /// The cancellation token of the latest run. Set to make its runners stop
+  /// taking jobs from the queue; checked between jobs, so the ones already
+  /// running get to finish. Each run gets a fresh one, so starting a run
+  /// can't revive the runners of a cancelled one.
+  #[serde(skip)]
+  pub(crate) cancelled: Mutex<Arc<AtomicBool>>
 }
 
 impl Runner {
@@ -2,7 +2,7 @@
 //! criteria.
 
 use std::collections::BTreeMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use f06::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -42,7 +42,29 @@ pub(crate) struct NamedCriteria {
   /// The name for this criteria set.
   pub(crate) name: String,
   /// The actual number comparison criteria.
-  pub(crate) criteria: Criteria
+  pub(crate) criteria: Criteria,
+  /// Values smaller in magnitude than this are taken as zero in metrics.
+  #[serde(default)]
+  pub(crate) epsilon: f64,
+  /// Weights for values at specific grid points, by ID. Defaults to 1.
+  #[serde(default)]
+  pub(crate) grid_weights: BTreeMap<usize, f64>,
+  /// Weights for values at specific elements, by ID. Defaults to 1, and
+  /// takes precedence over grid point weights.
+  #[serde(default)]
+  pub(crate) element_weights: BTreeMap<usize, f64>
+}
+
+impl NamedCriteria {
+  /// Returns the weight of a datum, going by its row. A weight of zero means
+  /// the datum is excluded from comparison.
+  pub(crate) fn weight_of(&self, ix: &DatumIndex) -> f64 {
+    let by_element = ix.row.element_id()
+      .and_then(|e| self.element_weights.get(&e.eid));
+    let by_grid = ix.row.grid_point_id()
+      .and_then(|g| self.grid_weights.get(&g.gid));
+    return by_element.or(by_grid).copied().unwrap_or(1.0);
+  }
 }
 
 /// This is a test suite. It contains decks and criteria sets.
@@ -51,5 +73,53 @@ pub(crate) struct Suite {
   /// The test decks to run.
   pub(crate) decks: BTreeMap<Uuid, Deck>,
   /// The named criteria sets.
-  pub(crate) criteria_sets: BTreeMap<Uuid, NamedCriteria>
+  pub(crate) criteria_sets: BTreeMap<Uuid, NamedCriteria>,
+  /// Directory against which relative deck paths are resolved. If absent,
+  /// the directory containing the suite file is used.
+  #[serde(default)]
+  pub(crate) base_dir: Option<PathBuf>,
+  /// The file the suite was loaded from or last saved to. It's not saved
+  /// along with the suite, which can be moved around with its decks.
+  #[serde(skip)]
+  pub(crate) suite_file: Option<PathBuf>
+}
+
+impl Suite {
+  /// Returns the directory relative deck paths are resolved against. A
+  /// relative `base_dir` is itself taken relative to the suite file.
+  fn base(&self) -> PathBuf {
+    let suite_dir = self.suite_file.as_deref()
+      .and_then(Path::parent)
+      .unwrap_or(Path::new(""));
+    return match self.base_dir {
+      Some(ref bd) => suite_dir.join(bd),
+      None => suite_dir.to_path_buf(),
+    };
+  }
+
+  /// Returns a copy of a deck with its path resolved, ready to be run.
+  pub(crate) fn resolved(&self, deck: &Deck) -> Deck {
+    let mut deck = deck.clone();
+    if deck.in_file.is_relative() {
+      deck.in_file = self.base().join(&deck.in_file);
+    }
+    return deck;
+  }
+
+  /// Sets the file the suite was loaded from or is about to be saved to.
+  /// Relative deck paths are kept as they are, unless the suite is moving to
+  /// another directory, in which case they're resolved first so they keep
+  /// pointing at the same files.
+  pub(crate) fn set_file(&mut self, suite_file: &Path) {
+    let old_dir = self.suite_file.as_deref().and_then(Path::parent);
+    if old_dir.is_some_and(|d| Some(d) != suite_file.parent()) {
+      let base = self.base();
+      for deck in self.decks.values_mut() {
+        if deck.in_file.is_relative() {
+          deck.in_file = base.join(&deck.in_file);
+        }
+      }
+    }
+    self.suite_file = Some(suite_file.to_path_buf());
+  }
 }
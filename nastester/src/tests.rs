@@ -0,0 +1,54 @@
+use std::path::{Path, PathBuf};
+
+use uuid::Uuid;
+
+use crate::suite::*;
+
+/// Builds a suite with a single deck, returning it and the deck's UUID.
+fn one_deck_suite(in_file: &str) -> (Suite, Uuid) {
+  let mut suite = Suite::default();
+  let uuid = Uuid::new_v4();
+  let deck = Deck { in_file: PathBuf::from(in_file), extractions: Vec::new() };
+  suite.decks.insert(uuid, deck);
+  return (suite, uuid);
+}
+
+#[test]
+fn test_deck_paths() {
+  let (mut suite, uuid) = one_deck_suite("models/sub/model.bdf");
+  let suite_file = Path::new("/work/suites/regression.nts");
+  suite.set_file(suite_file);
+  let resolved = |suite: &Suite| suite.resolved(&suite.decks[&uuid]).in_file;
+  // decks in subdirectories resolve against the suite's directory...
+  assert_eq!(
+    resolved(&suite),
+    PathBuf::from("/work/suites/models/sub/model.bdf")
+  );
+  // ...but are saved as they were
+  let saved = serde_json::to_string(&suite).unwrap();
+  assert!(saved.contains("\"models/sub/model.bdf\""));
+  assert!(!saved.contains("/work/suites"));
+  let mut loaded: Suite = serde_json::from_str(&saved).unwrap();
+  loaded.set_file(Path::new("/elsewhere/regression.nts"));
+  assert_eq!(
+    resolved(&loaded),
+    PathBuf::from("/elsewhere/models/sub/model.bdf")
+  );
+  // a relative base directory is taken relative to the suite file
+  suite.base_dir = Some(PathBuf::from("../inputs"));
+  assert_eq!(
+    resolved(&suite),
+    PathBuf::from("/work/suites/../inputs/models/sub/model.bdf")
+  );
+  // saving somewhere else keeps pointing at the same file
+  suite.set_file(Path::new("/other/place/copy.nts"));
+  assert_eq!(
+    suite.decks[&uuid].in_file,
+    PathBuf::from("/work/suites/../inputs/models/sub/model.bdf")
+  );
+  // absolute paths are left alone
+  let (mut suite, abs_uuid) = one_deck_suite("/abs/model.bdf");
+  suite.set_file(suite_file);
+  let deck = suite.resolved(&suite.decks[&abs_uuid]);
+  assert_eq!(deck.in_file, PathBuf::from("/abs/model.bdf"));
+}